@@ -17,10 +17,11 @@ fn main() {
     env::set_var("LIBCLANG_PATH", "/opt/llvm-5.0.0/lib64/");
 
     pkg_config::Config::new()
-        .atleast_version("0.7.13")
+        .atleast_version("0.8.6")
         .probe("libzfs")
         .unwrap();
     println!("cargo:rustc-link-lib=zpool");
+    println!("cargo:rustc-link-lib=zfs_core");
 
     // Skip building if bindings already exist.
     // If you want to rebuild, delete the bindings file.
@@ -35,6 +36,8 @@ fn main() {
         .whitelist_type("vdev_stat_t")
         .whitelist_var("ZPOOL_MAXPROPLEN")
         .whitelist_var("ZPOOL_CONFIG_POOL_NAME")
+        .whitelist_var("ZPOOL_CONFIG_POOL_STATE")
+        .whitelist_var("ZPOOL_CONFIG_POOL_TXG")
         .whitelist_var("ZPOOL_CONFIG_TYPE")
         .whitelist_var("ZPOOL_CONFIG_VDEV_TREE")
         .whitelist_var("ZPOOL_CONFIG_CHILDREN")
@@ -58,17 +61,72 @@ fn main() {
         .whitelist_var("VDEV_TYPE_FILE")
         .whitelist_var("VDEV_TYPE_MISSING")
         .whitelist_var("VDEV_TYPE_HOLE")
+        .whitelist_var("VDEV_TYPE_INDIRECT")
         .whitelist_var("VDEV_TYPE_SPARE")
         .whitelist_var("VDEV_TYPE_LOG")
         .whitelist_var("VDEV_TYPE_L2CACHE")
         .whitelist_var("ZPROP_VALUE")
         .whitelist_var("ZFS_MAXPROPLEN")
         .whitelist_var("ZFS_MAX_DATASET_NAME_LEN")
+        .whitelist_var("ZFS_DIFF_PARSEABLE")
+        .whitelist_var("ZFS_DIFF_TIMESTAMP")
+        .whitelist_var("ZFS_DIFF_CLASSIFY")
+        .whitelist_var("ZPROP_INVAL")
+        .whitelist_var("MS_FORCE")
+        .whitelist_var("MS_DETACH")
+        .whitelist_var("ZPOOL_CONFIG_SCAN_STATS")
+        .whitelist_var("ZPOOL_CONFIG_NPARITY")
+        .whitelist_var("ZPOOL_CONFIG_ID")
+        .whitelist_var("ZPOOL_CONFIG_REMOVAL_STATS")
+        .whitelist_var("ZPOOL_CONFIG_CHECKPOINT_STATS")
+        .whitelist_var("ZFS_IMPORT_CHECKPOINT")
+        .whitelist_var("SPA_VERSION_FEATURES")
+        .whitelist_var("ZPOOL_HIST_RECORD")
+        .whitelist_var("ZPOOL_HIST_TIME")
+        .whitelist_var("ZPOOL_HIST_CMD")
+        .whitelist_var("ZPOOL_HIST_WHO")
+        .whitelist_var("ZPOOL_HIST_HOST")
+        .whitelist_var("ZPOOL_HIST_TXG")
+        .whitelist_var("ZPOOL_HIST_INT_EVENT")
+        .whitelist_var("ZPOOL_HIST_INT_STR")
+        .whitelist_var("ZPOOL_HIST_INT_NAME")
+        .whitelist_var("ZPOOL_HIST_IOCTL")
+        .whitelist_var("ZPOOL_HIST_DSNAME")
+        .whitelist_var("ZEVENT_NONE")
+        .whitelist_var("ZEVENT_NONBLOCK")
+        .whitelist_var("ZPOOL_LOAD_POLICY")
+        .whitelist_var("ZPOOL_LOAD_REWIND_POLICY")
+        .whitelist_var("ZPOOL_NO_REWIND")
+        .whitelist_var("ZPOOL_TRY_REWIND")
+        .whitelist_var("ZPOOL_DO_REWIND")
+        .whitelist_var("ZPOOL_EXTREME_REWIND")
+        .whitelist_var("ZFS_IMPORT_NORMAL")
+        .whitelist_var("ZFS_IMPORT_ANY_HOST")
+        .whitelist_var("ZPOOL_CONFIG_ERRCOUNT")
+        .whitelist_var("ZPOOL_ERR_DATASET")
+        .whitelist_var("ZPOOL_ERR_OBJECT")
+        .whitelist_var("ZPOOL_CONFIG_ASHIFT")
+        .whitelist_var("ZPOOL_CONFIG_ASIZE")
+        .whitelist_var("ZPOOL_CONFIG_METASLAB_SHIFT")
+        .whitelist_var("ZPOOL_CONFIG_ALLOCATION_BIAS")
+        .whitelist_var("VDEV_ALLOC_BIAS_LOG")
+        .whitelist_var("VDEV_ALLOC_BIAS_SPECIAL")
+        .whitelist_var("VDEV_ALLOC_BIAS_DEDUP")
         .whitelist_type("zpool_prop_t")
         .constified_enum_module("zpool_prop_t")
         .whitelist_type("pool_state_t")
         .constified_enum_module("pool_state")
         .bitfield_enum("zfs_type_t")
+        .whitelist_type("zfs_userquota_prop_t")
+        .whitelist_type("zfs_error_t")
+        .whitelist_type("pool_scan_func_t")
+        .whitelist_type("pool_scrub_cmd_t")
+        .whitelist_type("pool_trim_func_t")
+        .whitelist_type("trimflags_t")
+        .whitelist_type("pool_initialize_func_t")
+        .whitelist_type("zpool_status_t")
+        .whitelist_type("zpool_errata_t")
+        .whitelist_type("lzc_send_flags")
         .opaque_type("libzfs_handle_t")
         .blacklist_type("nvlist_t")
         .blacklist_type("nvlist")
@@ -105,9 +163,79 @@ fn main() {
         .whitelist_function("zfs_prop_to_name")
         .whitelist_function("zfs_validate_name")
         .whitelist_function("zprop_free_list")
+        .whitelist_function("zfs_get_handle")
+        .whitelist_function("zfs_show_diffs")
+        .whitelist_function("zfs_prop_set")
+        .whitelist_function("zfs_prop_set_list")
+        .whitelist_function("zfs_prop_inherit")
+        .whitelist_function("zfs_prop_get_numeric")
+        .whitelist_function("zfs_prop_get_recvd")
+        .whitelist_function("get_system_hostid")
+        .whitelist_function("zfs_nicestrtonum")
+        .whitelist_function("zfs_prop_get_userquota_int")
+        .whitelist_function("zfs_userspace")
+        .whitelist_function("libzfs_core_init")
+        .whitelist_function("libzfs_core_fini")
+        .whitelist_function("lzc_channel_program")
+        .whitelist_function("zfs_crypto_load_key")
+        .whitelist_function("libzfs_errno")
+        .whitelist_function("zfs_crypto_unload_key")
+        .whitelist_function("zfs_crypto_rewrap")
+        .whitelist_function("zpool_expand_proplist")
+        .whitelist_function("zpool_prop_to_name")
+        .whitelist_function("zpool_prop_get_feature")
+        .whitelist_function("zfs_name_to_prop")
+        .whitelist_function("zfs_is_mounted")
+        .whitelist_function("zfs_mount")
+        .whitelist_function("zfs_unmount")
+        .whitelist_function("lzc_destroy_snaps")
+        .whitelist_function("zfs_get_fsacl")
+        .whitelist_function("zfs_set_fsacl")
+        .whitelist_function("zpool_create")
+        .whitelist_function("zpool_destroy")
+        .whitelist_function("zpool_add")
+        .whitelist_function("zpool_vdev_remove")
+        .whitelist_function("zpool_vdev_remove_cancel")
+        .whitelist_function("zpool_vdev_attach")
+        .whitelist_function("zpool_vdev_detach")
+        .whitelist_function("zpool_scan")
+        .whitelist_function("zpool_trim")
+        .whitelist_function("zpool_initialize")
+        .whitelist_function("zpool_checkpoint")
+        .whitelist_function("zpool_discard_checkpoint")
+        .whitelist_function("zpool_import_props")
+        .whitelist_function("zpool_upgrade")
+        .whitelist_function("zpool_set_prop")
+        .whitelist_function("zpool_refresh_stats")
+        .whitelist_function("zpool_reguid")
+        .whitelist_function("zpool_reopen_one")
+        .whitelist_function("zpool_in_use")
+        .whitelist_function("zpool_clear_label")
+        .whitelist_function("zpool_get_history")
+        .whitelist_function("zpool_events_next")
+        .whitelist_function("zpool_get_features")
+        .whitelist_function("zpool_enable_datasets")
+        .whitelist_function("zpool_export_force")
+        .whitelist_function("zpool_get_status")
+        .whitelist_function("zpool_get_errlog")
+        .whitelist_function("zpool_obj_to_path")
+        .whitelist_function("libzfs_error_action")
+        .whitelist_function("lzc_snapshot")
+        .whitelist_function("lzc_bookmark")
+        .whitelist_function("lzc_hold")
+        .whitelist_function("lzc_release")
+        .whitelist_function("lzc_get_holds")
+        .whitelist_function("lzc_send")
+        .whitelist_function("lzc_receive")
+        .whitelist_function("lzc_exists")
+        .whitelist_function("lzc_rollback")
+        .whitelist_function("zfs_iter_dependents")
+        .whitelist_function("zfs_iter_snapshots")
+        .whitelist_function("zfs_get_pool_name")
+        .whitelist_function("zfs_get_clones_nvl")
         .clang_arg("-I/usr/lib/gcc/x86_64-redhat-linux/4.8.2/include/")
-        .clang_arg("-I/usr/src/zfs-0.7.13/lib/libspl/include/")
-        .clang_arg("-I/usr/src/zfs-0.7.13/include/")
+        .clang_arg("-I/usr/src/zfs-0.8.6/lib/libspl/include/")
+        .clang_arg("-I/usr/src/zfs-0.8.6/include/")
         .generate()
         .expect("Unable to generate bindings");
 
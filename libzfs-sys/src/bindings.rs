@@ -84,6 +84,8 @@ where
 pub const ZFS_MAX_DATASET_NAME_LEN: u32 = 256;
 pub const ZPROP_VALUE: &'static [u8; 6usize] = b"value\0";
 pub const ZPOOL_CONFIG_POOL_NAME: &'static [u8; 5usize] = b"name\0";
+pub const ZPOOL_CONFIG_POOL_STATE: &'static [u8; 6usize] = b"state\0";
+pub const ZPOOL_CONFIG_POOL_TXG: &'static [u8; 4usize] = b"txg\0";
 pub const ZPOOL_CONFIG_VDEV_TREE: &'static [u8; 10usize] = b"vdev_tree\0";
 pub const ZPOOL_CONFIG_TYPE: &'static [u8; 5usize] = b"type\0";
 pub const ZPOOL_CONFIG_CHILDREN: &'static [u8; 9usize] = b"children\0";
@@ -107,11 +109,61 @@ pub const VDEV_TYPE_DISK: &'static [u8; 5usize] = b"disk\0";
 pub const VDEV_TYPE_FILE: &'static [u8; 5usize] = b"file\0";
 pub const VDEV_TYPE_MISSING: &'static [u8; 8usize] = b"missing\0";
 pub const VDEV_TYPE_HOLE: &'static [u8; 5usize] = b"hole\0";
+pub const VDEV_TYPE_INDIRECT: &'static [u8; 9usize] = b"indirect\0";
 pub const VDEV_TYPE_SPARE: &'static [u8; 6usize] = b"spare\0";
 pub const VDEV_TYPE_LOG: &'static [u8; 4usize] = b"log\0";
 pub const VDEV_TYPE_L2CACHE: &'static [u8; 8usize] = b"l2cache\0";
 pub const ZFS_MAXPROPLEN: u32 = 4096;
 pub const ZPOOL_MAXPROPLEN: u32 = 4096;
+pub const lzc_send_flags_LZC_SEND_FLAG_EMBED_DATA: lzc_send_flags = 1;
+pub const lzc_send_flags_LZC_SEND_FLAG_LARGE_BLOCK: lzc_send_flags = 2;
+pub const lzc_send_flags_LZC_SEND_FLAG_COMPRESS: lzc_send_flags = 4;
+pub const lzc_send_flags_LZC_SEND_FLAG_RAW: lzc_send_flags = 8;
+pub type lzc_send_flags = u32;
+pub const ZPOOL_CONFIG_ALLOCATION_BIAS: &'static [u8; 11usize] = b"alloc_bias\0";
+pub const VDEV_ALLOC_BIAS_LOG: &'static [u8; 4usize] = b"log\0";
+pub const VDEV_ALLOC_BIAS_SPECIAL: &'static [u8; 8usize] = b"special\0";
+pub const VDEV_ALLOC_BIAS_DEDUP: &'static [u8; 6usize] = b"dedup\0";
+pub const ZPOOL_CONFIG_ASHIFT: &'static [u8; 7usize] = b"ashift\0";
+pub const ZPOOL_CONFIG_ASIZE: &'static [u8; 6usize] = b"asize\0";
+pub const ZPOOL_CONFIG_METASLAB_SHIFT: &'static [u8; 15usize] = b"metaslab_shift\0";
+pub const ZPOOL_ERR_DATASET: &'static [u8; 8usize] = b"dataset\0";
+pub const ZPOOL_ERR_OBJECT: &'static [u8; 7usize] = b"object\0";
+pub const ZPOOL_CONFIG_ERRCOUNT: &'static [u8; 12usize] = b"error_count\0";
+pub const ZPOOL_LOAD_POLICY: &'static [u8; 12usize] = b"load-policy\0";
+pub const ZPOOL_LOAD_REWIND_POLICY: &'static [u8; 19usize] = b"load-rewind-policy\0";
+pub const ZPOOL_NO_REWIND: u32 = 1;
+pub const ZPOOL_TRY_REWIND: u32 = 4;
+pub const ZPOOL_DO_REWIND: u32 = 8;
+pub const ZPOOL_EXTREME_REWIND: u32 = 16;
+pub const ZFS_IMPORT_NORMAL: u32 = 0;
+pub const ZFS_IMPORT_ANY_HOST: u32 = 2;
+pub const ZEVENT_NONE: u32 = 0;
+pub const ZEVENT_NONBLOCK: u32 = 1;
+pub const ZPOOL_HIST_RECORD: &'static [u8; 15usize] = b"history record\0";
+pub const ZPOOL_HIST_TIME: &'static [u8; 13usize] = b"history time\0";
+pub const ZPOOL_HIST_CMD: &'static [u8; 16usize] = b"history command\0";
+pub const ZPOOL_HIST_WHO: &'static [u8; 12usize] = b"history who\0";
+pub const ZPOOL_HIST_HOST: &'static [u8; 17usize] = b"history hostname\0";
+pub const ZPOOL_HIST_TXG: &'static [u8; 12usize] = b"history txg\0";
+pub const ZPOOL_HIST_INT_EVENT: &'static [u8; 23usize] = b"history internal event\0";
+pub const ZPOOL_HIST_INT_STR: &'static [u8; 21usize] = b"history internal str\0";
+pub const ZPOOL_HIST_INT_NAME: &'static [u8; 14usize] = b"internal_name\0";
+pub const ZPOOL_HIST_IOCTL: &'static [u8; 6usize] = b"ioctl\0";
+pub const ZPOOL_HIST_DSNAME: &'static [u8; 7usize] = b"dsname\0";
+pub const SPA_VERSION_FEATURES: u32 = 5000;
+pub const ZPOOL_CONFIG_CHECKPOINT_STATS: &'static [u8; 17usize] = b"checkpoint_stats\0";
+pub const ZFS_IMPORT_CHECKPOINT: u32 = 128;
+pub const ZPOOL_CONFIG_REMOVAL_STATS: &'static [u8; 14usize] = b"removal_stats\0";
+pub const ZPOOL_CONFIG_NPARITY: &'static [u8; 8usize] = b"nparity\0";
+pub const ZPOOL_CONFIG_ID: &'static [u8; 3usize] = b"id\0";
+pub const ZPOOL_CONFIG_SCAN_STATS: &'static [u8; 11usize] = b"scan_stats\0";
+pub const MS_FORCE: u32 = 1;
+pub const MS_DETACH: u32 = 2;
+pub const ZPROP_INVAL: i32 = -1;
+pub const ZFS_DIFF_PARSEABLE: u32 = 1;
+pub const ZFS_DIFF_TIMESTAMP: u32 = 2;
+pub const ZFS_DIFF_CLASSIFY: u32 = 4;
 pub mod boolean {
     pub type Type = u32;
     pub const B_FALSE: Type = 0;
@@ -257,8 +309,33 @@ pub const zfs_prop_t_ZFS_PROP_REDUNDANT_METADATA: zfs_prop_t = 78;
 pub const zfs_prop_t_ZFS_PROP_OVERLAY: zfs_prop_t = 79;
 pub const zfs_prop_t_ZFS_PROP_PREV_SNAP: zfs_prop_t = 80;
 pub const zfs_prop_t_ZFS_PROP_RECEIVE_RESUME_TOKEN: zfs_prop_t = 81;
-pub const zfs_prop_t_ZFS_NUM_PROPS: zfs_prop_t = 82;
+pub const zfs_prop_t_ZFS_PROP_REMAPTXG: zfs_prop_t = 82;
+pub const zfs_prop_t_ZFS_PROP_SPECIAL_SMALL_BLOCKS: zfs_prop_t = 83;
+pub const zfs_prop_t_ZFS_PROP_ENCRYPTION: zfs_prop_t = 84;
+pub const zfs_prop_t_ZFS_PROP_KEYLOCATION: zfs_prop_t = 85;
+pub const zfs_prop_t_ZFS_PROP_KEYFORMAT: zfs_prop_t = 86;
+pub const zfs_prop_t_ZFS_PROP_PBKDF2_SALT: zfs_prop_t = 87;
+pub const zfs_prop_t_ZFS_PROP_PBKDF2_ITERS: zfs_prop_t = 88;
+pub const zfs_prop_t_ZFS_PROP_ENCRYPTION_ROOT: zfs_prop_t = 89;
+pub const zfs_prop_t_ZFS_PROP_KEY_GUID: zfs_prop_t = 90;
+pub const zfs_prop_t_ZFS_PROP_KEYSTATUS: zfs_prop_t = 91;
+pub const zfs_prop_t_ZFS_PROP_IVSET_GUID: zfs_prop_t = 92;
+pub const zfs_prop_t_ZFS_NUM_PROPS: zfs_prop_t = 93;
 pub type zfs_prop_t = i32;
+pub const zfs_userquota_prop_t_ZFS_PROP_USERUSED: zfs_userquota_prop_t = 0;
+pub const zfs_userquota_prop_t_ZFS_PROP_USERQUOTA: zfs_userquota_prop_t = 1;
+pub const zfs_userquota_prop_t_ZFS_PROP_GROUPUSED: zfs_userquota_prop_t = 2;
+pub const zfs_userquota_prop_t_ZFS_PROP_GROUPQUOTA: zfs_userquota_prop_t = 3;
+pub const zfs_userquota_prop_t_ZFS_PROP_USEROBJUSED: zfs_userquota_prop_t = 4;
+pub const zfs_userquota_prop_t_ZFS_PROP_USEROBJQUOTA: zfs_userquota_prop_t = 5;
+pub const zfs_userquota_prop_t_ZFS_PROP_GROUPOBJUSED: zfs_userquota_prop_t = 6;
+pub const zfs_userquota_prop_t_ZFS_PROP_GROUPOBJQUOTA: zfs_userquota_prop_t = 7;
+pub const zfs_userquota_prop_t_ZFS_PROP_PROJECTUSED: zfs_userquota_prop_t = 8;
+pub const zfs_userquota_prop_t_ZFS_PROP_PROJECTQUOTA: zfs_userquota_prop_t = 9;
+pub const zfs_userquota_prop_t_ZFS_PROP_PROJECTOBJUSED: zfs_userquota_prop_t = 10;
+pub const zfs_userquota_prop_t_ZFS_PROP_PROJECTOBJQUOTA: zfs_userquota_prop_t = 11;
+pub const zfs_userquota_prop_t_ZFS_NUM_USERQUOTA_PROPS: zfs_userquota_prop_t = 12;
+pub type zfs_userquota_prop_t = u32;
 pub mod zpool_prop_t {
     pub type Type = u32;
     pub const ZPOOL_PROP_NAME: Type = 0;
@@ -290,7 +367,10 @@ pub mod zpool_prop_t {
     pub const ZPOOL_PROP_TNAME: Type = 26;
     pub const ZPOOL_PROP_MAXDNODESIZE: Type = 27;
     pub const ZPOOL_PROP_MULTIHOST: Type = 28;
-    pub const ZPOOL_NUM_PROPS: Type = 29;
+    pub const ZPOOL_PROP_CHECKPOINT: Type = 29;
+    pub const ZPOOL_PROP_LOAD_GUID: Type = 30;
+    pub const ZPOOL_PROP_AUTOTRIM: Type = 31;
+    pub const ZPOOL_NUM_PROPS: Type = 32;
 }
 pub const zprop_source_t_ZPROP_SRC_NONE: zprop_source_t = 1;
 pub const zprop_source_t_ZPROP_SRC_DEFAULT: zprop_source_t = 2;
@@ -333,6 +413,98 @@ pub const vdev_aux_VDEV_AUX_EXTERNAL_PERSIST: vdev_aux = 17;
 pub const vdev_aux_VDEV_AUX_ACTIVE: vdev_aux = 18;
 pub type vdev_aux = u32;
 pub use self::vdev_aux as vdev_aux_t;
+pub const zfs_error_EZFS_SUCCESS: zfs_error = 0;
+pub const zfs_error_EZFS_NOMEM: zfs_error = 2000;
+pub const zfs_error_EZFS_BADPROP: zfs_error = 2001;
+pub const zfs_error_EZFS_PROPREADONLY: zfs_error = 2002;
+pub const zfs_error_EZFS_PROPTYPE: zfs_error = 2003;
+pub const zfs_error_EZFS_PROPNONINHERIT: zfs_error = 2004;
+pub const zfs_error_EZFS_PROPSPACE: zfs_error = 2005;
+pub const zfs_error_EZFS_BADTYPE: zfs_error = 2006;
+pub const zfs_error_EZFS_BUSY: zfs_error = 2007;
+pub const zfs_error_EZFS_EXISTS: zfs_error = 2008;
+pub const zfs_error_EZFS_NOENT: zfs_error = 2009;
+pub const zfs_error_EZFS_BADSTREAM: zfs_error = 2010;
+pub const zfs_error_EZFS_DSREADONLY: zfs_error = 2011;
+pub const zfs_error_EZFS_VOLTOOBIG: zfs_error = 2012;
+pub const zfs_error_EZFS_INVALIDNAME: zfs_error = 2013;
+pub const zfs_error_EZFS_BADRESTORE: zfs_error = 2014;
+pub const zfs_error_EZFS_BADBACKUP: zfs_error = 2015;
+pub const zfs_error_EZFS_BADTARGET: zfs_error = 2016;
+pub const zfs_error_EZFS_NODEVICE: zfs_error = 2017;
+pub const zfs_error_EZFS_BADDEV: zfs_error = 2018;
+pub const zfs_error_EZFS_NOREPLICAS: zfs_error = 2019;
+pub const zfs_error_EZFS_RESILVERING: zfs_error = 2020;
+pub const zfs_error_EZFS_BADVERSION: zfs_error = 2021;
+pub const zfs_error_EZFS_POOLUNAVAIL: zfs_error = 2022;
+pub const zfs_error_EZFS_DEVOVERFLOW: zfs_error = 2023;
+pub const zfs_error_EZFS_BADPATH: zfs_error = 2024;
+pub const zfs_error_EZFS_CROSSTARGET: zfs_error = 2025;
+pub const zfs_error_EZFS_ZONED: zfs_error = 2026;
+pub const zfs_error_EZFS_MOUNTFAILED: zfs_error = 2027;
+pub const zfs_error_EZFS_UMOUNTFAILED: zfs_error = 2028;
+pub const zfs_error_EZFS_UNSHARENFSFAILED: zfs_error = 2029;
+pub const zfs_error_EZFS_SHARENFSFAILED: zfs_error = 2030;
+pub const zfs_error_EZFS_PERM: zfs_error = 2031;
+pub const zfs_error_EZFS_NOSPC: zfs_error = 2032;
+pub const zfs_error_EZFS_FAULT: zfs_error = 2033;
+pub const zfs_error_EZFS_IO: zfs_error = 2034;
+pub const zfs_error_EZFS_INTR: zfs_error = 2035;
+pub const zfs_error_EZFS_ISSPARE: zfs_error = 2036;
+pub const zfs_error_EZFS_INVALCONFIG: zfs_error = 2037;
+pub const zfs_error_EZFS_RECURSIVE: zfs_error = 2038;
+pub const zfs_error_EZFS_NOHISTORY: zfs_error = 2039;
+pub const zfs_error_EZFS_POOLPROPS: zfs_error = 2040;
+pub const zfs_error_EZFS_POOL_NOTSUP: zfs_error = 2041;
+pub const zfs_error_EZFS_POOL_INVALARG: zfs_error = 2042;
+pub const zfs_error_EZFS_NAMETOOLONG: zfs_error = 2043;
+pub const zfs_error_EZFS_OPENFAILED: zfs_error = 2044;
+pub const zfs_error_EZFS_NOCAP: zfs_error = 2045;
+pub const zfs_error_EZFS_LABELFAILED: zfs_error = 2046;
+pub const zfs_error_EZFS_BADWHO: zfs_error = 2047;
+pub const zfs_error_EZFS_BADPERM: zfs_error = 2048;
+pub const zfs_error_EZFS_BADPERMSET: zfs_error = 2049;
+pub const zfs_error_EZFS_NODELEGATION: zfs_error = 2050;
+pub const zfs_error_EZFS_UNSHARESMBFAILED: zfs_error = 2051;
+pub const zfs_error_EZFS_SHARESMBFAILED: zfs_error = 2052;
+pub const zfs_error_EZFS_BADCACHE: zfs_error = 2053;
+pub const zfs_error_EZFS_ISL2CACHE: zfs_error = 2054;
+pub const zfs_error_EZFS_VDEVNOTSUP: zfs_error = 2055;
+pub const zfs_error_EZFS_NOTSUP: zfs_error = 2056;
+pub const zfs_error_EZFS_ACTIVE_SPARE: zfs_error = 2057;
+pub const zfs_error_EZFS_UNPLAYED_LOGS: zfs_error = 2058;
+pub const zfs_error_EZFS_REFTAG_RELE: zfs_error = 2059;
+pub const zfs_error_EZFS_REFTAG_HOLD: zfs_error = 2060;
+pub const zfs_error_EZFS_TAGTOOLONG: zfs_error = 2061;
+pub const zfs_error_EZFS_PIPEFAILED: zfs_error = 2062;
+pub const zfs_error_EZFS_THREADCREATEFAILED: zfs_error = 2063;
+pub const zfs_error_EZFS_POSTSPLIT_ONLINE: zfs_error = 2064;
+pub const zfs_error_EZFS_SCRUBBING: zfs_error = 2065;
+pub const zfs_error_EZFS_NO_SCRUB: zfs_error = 2066;
+pub const zfs_error_EZFS_DIFF: zfs_error = 2067;
+pub const zfs_error_EZFS_DIFFDATA: zfs_error = 2068;
+pub const zfs_error_EZFS_POOLREADONLY: zfs_error = 2069;
+pub const zfs_error_EZFS_SCRUB_PAUSED: zfs_error = 2070;
+pub const zfs_error_EZFS_ACTIVE_POOL: zfs_error = 2071;
+pub const zfs_error_EZFS_CRYPTOFAILED: zfs_error = 2072;
+pub const zfs_error_EZFS_NO_PENDING: zfs_error = 2073;
+pub const zfs_error_EZFS_CHECKPOINT_EXISTS: zfs_error = 2074;
+pub const zfs_error_EZFS_DISCARDING_CHECKPOINT: zfs_error = 2075;
+pub const zfs_error_EZFS_NO_CHECKPOINT: zfs_error = 2076;
+pub const zfs_error_EZFS_DEVRM_IN_PROGRESS: zfs_error = 2077;
+pub const zfs_error_EZFS_VDEV_TOO_BIG: zfs_error = 2078;
+pub const zfs_error_EZFS_IOC_NOTSUPPORTED: zfs_error = 2079;
+pub const zfs_error_EZFS_TOOMANY: zfs_error = 2080;
+pub const zfs_error_EZFS_INITIALIZING: zfs_error = 2081;
+pub const zfs_error_EZFS_NO_INITIALIZE: zfs_error = 2082;
+pub const zfs_error_EZFS_WRONG_PARENT: zfs_error = 2083;
+pub const zfs_error_EZFS_TRIMMING: zfs_error = 2084;
+pub const zfs_error_EZFS_NO_TRIM: zfs_error = 2085;
+pub const zfs_error_EZFS_TRIM_NOTSUP: zfs_error = 2086;
+pub const zfs_error_EZFS_NO_RESILVER_DEFER: zfs_error = 2087;
+pub const zfs_error_EZFS_UNKNOWN: zfs_error = 2088;
+pub type zfs_error = u32;
+pub use self::zfs_error as zfs_error_t;
 pub mod pool_state {
     pub type Type = u32;
     pub const POOL_STATE_ACTIVE: Type = 0;
@@ -361,16 +533,30 @@ pub struct vdev_stat {
     pub vs_read_errors: u64,
     pub vs_write_errors: u64,
     pub vs_checksum_errors: u64,
+    pub vs_initialize_errors: u64,
     pub vs_self_healed: u64,
     pub vs_scan_removing: u64,
     pub vs_scan_processed: u64,
     pub vs_fragmentation: u64,
+    pub vs_initialize_bytes_done: u64,
+    pub vs_initialize_bytes_est: u64,
+    pub vs_initialize_state: u64,
+    pub vs_initialize_action_time: u64,
+    pub vs_checkpoint_space: u64,
+    pub vs_resilver_deferred: u64,
+    pub vs_slow_ios: u64,
+    pub vs_trim_errors: u64,
+    pub vs_trim_notsup: u64,
+    pub vs_trim_bytes_done: u64,
+    pub vs_trim_bytes_est: u64,
+    pub vs_trim_state: u64,
+    pub vs_trim_action_time: u64,
 }
 #[test]
 fn bindgen_test_layout_vdev_stat() {
     assert_eq!(
         ::std::mem::size_of::<vdev_stat>(),
-        216usize,
+        328usize,
         concat!("Size of: ", stringify!(vdev_stat))
     );
     assert_eq!(
@@ -509,8 +695,18 @@ fn bindgen_test_layout_vdev_stat() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_self_healed as *const _ as usize },
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_initialize_errors as *const _ as usize },
         184usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(vdev_stat),
+            "::",
+            stringify!(vs_initialize_errors)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_self_healed as *const _ as usize },
+        192usize,
         concat!(
             "Offset of field: ",
             stringify!(vdev_stat),
@@ -520,7 +716,7 @@ fn bindgen_test_layout_vdev_stat() {
     );
     assert_eq!(
         unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_scan_removing as *const _ as usize },
-        192usize,
+        200usize,
         concat!(
             "Offset of field: ",
             stringify!(vdev_stat),
@@ -530,7 +726,7 @@ fn bindgen_test_layout_vdev_stat() {
     );
     assert_eq!(
         unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_scan_processed as *const _ as usize },
-        200usize,
+        208usize,
         concat!(
             "Offset of field: ",
             stringify!(vdev_stat),
@@ -540,7 +736,7 @@ fn bindgen_test_layout_vdev_stat() {
     );
     assert_eq!(
         unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_fragmentation as *const _ as usize },
-        208usize,
+        216usize,
         concat!(
             "Offset of field: ",
             stringify!(vdev_stat),
@@ -548,6 +744,136 @@ fn bindgen_test_layout_vdev_stat() {
             stringify!(vs_fragmentation)
         )
     );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_initialize_bytes_done as *const _ as usize },
+        224usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(vdev_stat),
+            "::",
+            stringify!(vs_initialize_bytes_done)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_initialize_bytes_est as *const _ as usize },
+        232usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(vdev_stat),
+            "::",
+            stringify!(vs_initialize_bytes_est)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_initialize_state as *const _ as usize },
+        240usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(vdev_stat),
+            "::",
+            stringify!(vs_initialize_state)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_initialize_action_time as *const _ as usize },
+        248usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(vdev_stat),
+            "::",
+            stringify!(vs_initialize_action_time)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_checkpoint_space as *const _ as usize },
+        256usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(vdev_stat),
+            "::",
+            stringify!(vs_checkpoint_space)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_resilver_deferred as *const _ as usize },
+        264usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(vdev_stat),
+            "::",
+            stringify!(vs_resilver_deferred)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_slow_ios as *const _ as usize },
+        272usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(vdev_stat),
+            "::",
+            stringify!(vs_slow_ios)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_trim_errors as *const _ as usize },
+        280usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(vdev_stat),
+            "::",
+            stringify!(vs_trim_errors)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_trim_notsup as *const _ as usize },
+        288usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(vdev_stat),
+            "::",
+            stringify!(vs_trim_notsup)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_trim_bytes_done as *const _ as usize },
+        296usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(vdev_stat),
+            "::",
+            stringify!(vs_trim_bytes_done)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_trim_bytes_est as *const _ as usize },
+        304usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(vdev_stat),
+            "::",
+            stringify!(vs_trim_bytes_est)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_trim_state as *const _ as usize },
+        312usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(vdev_stat),
+            "::",
+            stringify!(vs_trim_state)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<vdev_stat>())).vs_trim_action_time as *const _ as usize },
+        320usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(vdev_stat),
+            "::",
+            stringify!(vs_trim_action_time)
+        )
+    );
 }
 pub type vdev_stat_t = vdev_stat;
 extern "C" {
@@ -751,13 +1077,13 @@ pub struct importargs {
     pub guid: u64,
     pub cachefile: *mut ::std::os::raw::c_char,
     pub _bitfield_1: __BindgenBitfieldUnit<[u8; 1usize], u8>,
-    pub __bindgen_padding_0: [u8; 7usize],
+    pub policy: *mut nvlist_t,
 }
 #[test]
 fn bindgen_test_layout_importargs() {
     assert_eq!(
         ::std::mem::size_of::<importargs>(),
-        48usize,
+        56usize,
         concat!("Size of: ", stringify!(importargs))
     );
     assert_eq!(
@@ -815,6 +1141,16 @@ fn bindgen_test_layout_importargs() {
             stringify!(cachefile)
         )
     );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<importargs>())).policy as *const _ as usize },
+        48usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(importargs),
+            "::",
+            stringify!(policy)
+        )
+    );
 }
 impl importargs {
     #[inline]
@@ -1062,6 +1398,495 @@ extern "C" {
         arg2: boolean_t,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn zfs_get_handle(arg1: *mut zfs_handle_t) -> *mut libzfs_handle_t;
+}
+extern "C" {
+    pub fn zfs_show_diffs(
+        arg1: *mut zfs_handle_t,
+        arg2: ::std::os::raw::c_int,
+        arg3: *mut ::std::os::raw::c_char,
+        arg4: *mut ::std::os::raw::c_char,
+        arg5: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_prop_set(
+        arg1: *mut zfs_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_prop_set_list(arg1: *mut zfs_handle_t, arg2: *mut nvlist_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_prop_inherit(
+        arg1: *mut zfs_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: boolean_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_prop_get_numeric(
+        arg1: *mut zfs_handle_t,
+        arg2: zfs_prop_t,
+        arg3: *mut u64,
+        arg4: *mut zprop_source_t,
+        arg5: *mut ::std::os::raw::c_char,
+        arg6: usize,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_prop_get_recvd(
+        arg1: *mut zfs_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: *mut ::std::os::raw::c_char,
+        arg4: usize,
+        arg5: boolean_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn get_system_hostid() -> ::std::os::raw::c_ulong;
+}
+extern "C" {
+    pub fn zfs_nicestrtonum(
+        arg1: *mut libzfs_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: *mut u64,
+    ) -> ::std::os::raw::c_int;
+}
+pub type uid_t = ::std::os::raw::c_uint;
+pub type zfs_userspace_cb_t = ::std::option::Option<
+    unsafe extern "C" fn(
+        arg: *mut ::std::os::raw::c_void,
+        domain: *const ::std::os::raw::c_char,
+        rid: uid_t,
+        space: u64,
+    ) -> ::std::os::raw::c_int,
+>;
+extern "C" {
+    pub fn zfs_prop_get_userquota_int(
+        zhp: *mut zfs_handle_t,
+        propname: *const ::std::os::raw::c_char,
+        propvalue: *mut u64,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_userspace(
+        arg1: *mut zfs_handle_t,
+        arg2: zfs_userquota_prop_t,
+        arg3: zfs_userspace_cb_t,
+        arg4: *mut ::std::os::raw::c_void,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn libzfs_core_init() -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn libzfs_core_fini();
+}
+extern "C" {
+    pub fn lzc_channel_program(
+        arg1: *const ::std::os::raw::c_char,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: u64,
+        arg4: u64,
+        arg5: *mut nvlist_t,
+        arg6: *mut *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_crypto_load_key(
+        arg1: *mut zfs_handle_t,
+        arg2: boolean_t,
+        arg3: *mut ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn libzfs_errno(arg1: *mut libzfs_handle_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_crypto_unload_key(arg1: *mut zfs_handle_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_crypto_rewrap(
+        arg1: *mut zfs_handle_t,
+        arg2: *mut nvlist_t,
+        arg3: boolean_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_expand_proplist(
+        arg1: *mut zpool_handle_t,
+        arg2: *mut *mut zprop_list_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_prop_to_name(arg1: zpool_prop_t::Type) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn zpool_prop_get_feature(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: *mut ::std::os::raw::c_char,
+        arg4: usize,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_name_to_prop(arg1: *const ::std::os::raw::c_char) -> zfs_prop_t;
+}
+extern "C" {
+    pub fn zfs_is_mounted(
+        arg1: *mut zfs_handle_t,
+        arg2: *mut *mut ::std::os::raw::c_char,
+    ) -> boolean_t;
+}
+extern "C" {
+    pub fn zfs_mount(
+        arg1: *mut zfs_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_unmount(
+        arg1: *mut zfs_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn lzc_destroy_snaps(
+        arg1: *mut nvlist_t,
+        arg2: boolean_t,
+        arg3: *mut *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_get_fsacl(arg1: *mut zfs_handle_t, arg2: *mut *mut nvlist_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_set_fsacl(
+        arg1: *mut zfs_handle_t,
+        arg2: boolean_t,
+        arg3: *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_create(
+        arg1: *mut libzfs_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: *mut nvlist_t,
+        arg4: *mut nvlist_t,
+        arg5: *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_destroy(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_add(arg1: *mut zpool_handle_t, arg2: *mut nvlist_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_vdev_remove(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_vdev_remove_cancel(arg1: *mut zpool_handle_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_vdev_attach(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: *const ::std::os::raw::c_char,
+        arg4: *mut nvlist_t,
+        arg5: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_vdev_detach(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+pub const pool_scan_func_POOL_SCAN_NONE: pool_scan_func = 0;
+pub const pool_scan_func_POOL_SCAN_SCRUB: pool_scan_func = 1;
+pub const pool_scan_func_POOL_SCAN_RESILVER: pool_scan_func = 2;
+pub const pool_scan_func_POOL_SCAN_FUNCS: pool_scan_func = 3;
+pub type pool_scan_func = u32;
+pub use self::pool_scan_func as pool_scan_func_t;
+pub const pool_scrub_cmd_POOL_SCRUB_NORMAL: pool_scrub_cmd = 0;
+pub const pool_scrub_cmd_POOL_SCRUB_PAUSE: pool_scrub_cmd = 1;
+pub const pool_scrub_cmd_POOL_SCRUB_FLAGS_END: pool_scrub_cmd = 2;
+pub type pool_scrub_cmd = u32;
+pub use self::pool_scrub_cmd as pool_scrub_cmd_t;
+extern "C" {
+    pub fn zpool_scan(
+        arg1: *mut zpool_handle_t,
+        arg2: pool_scan_func_t,
+        arg3: pool_scrub_cmd_t,
+    ) -> ::std::os::raw::c_int;
+}
+pub const pool_trim_func_POOL_TRIM_START: pool_trim_func = 0;
+pub const pool_trim_func_POOL_TRIM_CANCEL: pool_trim_func = 1;
+pub const pool_trim_func_POOL_TRIM_SUSPEND: pool_trim_func = 2;
+pub const pool_trim_func_POOL_TRIM_FUNCS: pool_trim_func = 3;
+pub type pool_trim_func = u32;
+pub use self::pool_trim_func as pool_trim_func_t;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct trimflags {
+    pub fullpool: boolean_t,
+    pub secure: boolean_t,
+    pub rate: u64,
+}
+pub type trimflags_t = trimflags;
+extern "C" {
+    pub fn zpool_trim(
+        arg1: *mut zpool_handle_t,
+        arg2: pool_trim_func_t,
+        arg3: *mut nvlist_t,
+        arg4: *mut trimflags_t,
+    ) -> ::std::os::raw::c_int;
+}
+pub const pool_initialize_func_POOL_INITIALIZE_DO: pool_initialize_func = 0;
+pub const pool_initialize_func_POOL_INITIALIZE_CANCEL: pool_initialize_func = 1;
+pub const pool_initialize_func_POOL_INITIALIZE_SUSPEND: pool_initialize_func = 2;
+pub const pool_initialize_func_POOL_INITIALIZE_FUNCS: pool_initialize_func = 3;
+pub type pool_initialize_func = u32;
+pub use self::pool_initialize_func as pool_initialize_func_t;
+extern "C" {
+    pub fn zpool_initialize(
+        arg1: *mut zpool_handle_t,
+        arg2: pool_initialize_func_t,
+        arg3: *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_checkpoint(arg1: *mut zpool_handle_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_discard_checkpoint(arg1: *mut zpool_handle_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_import_props(
+        arg1: *mut libzfs_handle_t,
+        arg2: *mut nvlist_t,
+        arg3: *const ::std::os::raw::c_char,
+        arg4: *mut nvlist_t,
+        arg5: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_upgrade(arg1: *mut zpool_handle_t, arg2: u64) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_set_prop(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_refresh_stats(
+        arg1: *mut zpool_handle_t,
+        arg2: *mut boolean_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_reguid(arg1: *mut zpool_handle_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_reopen_one(
+        arg1: *mut zpool_handle_t,
+        arg2: *mut ::std::os::raw::c_void,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_in_use(
+        arg1: *mut libzfs_handle_t,
+        arg2: ::std::os::raw::c_int,
+        arg3: *mut pool_state_t,
+        arg4: *mut *mut ::std::os::raw::c_char,
+        arg5: *mut boolean_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_clear_label(arg1: ::std::os::raw::c_int) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_get_history(
+        arg1: *mut zpool_handle_t,
+        arg2: *mut *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_events_next(
+        arg1: *mut libzfs_handle_t,
+        arg2: *mut *mut nvlist_t,
+        arg3: *mut ::std::os::raw::c_int,
+        arg4: ::std::os::raw::c_uint,
+        arg5: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_get_features(arg1: *mut zpool_handle_t) -> *mut nvlist_t;
+}
+extern "C" {
+    pub fn zpool_enable_datasets(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_export_force(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+pub const zpool_status_t_ZPOOL_STATUS_CORRUPT_CACHE: zpool_status_t = 0;
+pub const zpool_status_t_ZPOOL_STATUS_MISSING_DEV_R: zpool_status_t = 1;
+pub const zpool_status_t_ZPOOL_STATUS_MISSING_DEV_NR: zpool_status_t = 2;
+pub const zpool_status_t_ZPOOL_STATUS_CORRUPT_LABEL_R: zpool_status_t = 3;
+pub const zpool_status_t_ZPOOL_STATUS_CORRUPT_LABEL_NR: zpool_status_t = 4;
+pub const zpool_status_t_ZPOOL_STATUS_BAD_GUID_SUM: zpool_status_t = 5;
+pub const zpool_status_t_ZPOOL_STATUS_CORRUPT_POOL: zpool_status_t = 6;
+pub const zpool_status_t_ZPOOL_STATUS_CORRUPT_DATA: zpool_status_t = 7;
+pub const zpool_status_t_ZPOOL_STATUS_FAILING_DEV: zpool_status_t = 8;
+pub const zpool_status_t_ZPOOL_STATUS_VERSION_NEWER: zpool_status_t = 9;
+pub const zpool_status_t_ZPOOL_STATUS_HOSTID_MISMATCH: zpool_status_t = 10;
+pub const zpool_status_t_ZPOOL_STATUS_HOSTID_ACTIVE: zpool_status_t = 11;
+pub const zpool_status_t_ZPOOL_STATUS_HOSTID_REQUIRED: zpool_status_t = 12;
+pub const zpool_status_t_ZPOOL_STATUS_IO_FAILURE_WAIT: zpool_status_t = 13;
+pub const zpool_status_t_ZPOOL_STATUS_IO_FAILURE_CONTINUE: zpool_status_t = 14;
+pub const zpool_status_t_ZPOOL_STATUS_IO_FAILURE_MMP: zpool_status_t = 15;
+pub const zpool_status_t_ZPOOL_STATUS_BAD_LOG: zpool_status_t = 16;
+pub const zpool_status_t_ZPOOL_STATUS_ERRATA: zpool_status_t = 17;
+pub const zpool_status_t_ZPOOL_STATUS_UNSUP_FEAT_READ: zpool_status_t = 18;
+pub const zpool_status_t_ZPOOL_STATUS_UNSUP_FEAT_WRITE: zpool_status_t = 19;
+pub const zpool_status_t_ZPOOL_STATUS_FAULTED_DEV_R: zpool_status_t = 20;
+pub const zpool_status_t_ZPOOL_STATUS_FAULTED_DEV_NR: zpool_status_t = 21;
+pub const zpool_status_t_ZPOOL_STATUS_VERSION_OLDER: zpool_status_t = 22;
+pub const zpool_status_t_ZPOOL_STATUS_FEAT_DISABLED: zpool_status_t = 23;
+pub const zpool_status_t_ZPOOL_STATUS_RESILVERING: zpool_status_t = 24;
+pub const zpool_status_t_ZPOOL_STATUS_OFFLINE_DEV: zpool_status_t = 25;
+pub const zpool_status_t_ZPOOL_STATUS_REMOVED_DEV: zpool_status_t = 26;
+pub const zpool_status_t_ZPOOL_STATUS_NON_NATIVE_ASHIFT: zpool_status_t = 27;
+pub const zpool_status_t_ZPOOL_STATUS_OK: zpool_status_t = 28;
+pub type zpool_status_t = u32;
+pub const zpool_errata_ZPOOL_ERRATA_NONE: zpool_errata = 0;
+pub type zpool_errata = u32;
+pub use self::zpool_errata as zpool_errata_t;
+extern "C" {
+    pub fn zpool_get_status(
+        arg1: *mut zpool_handle_t,
+        arg2: *mut *mut ::std::os::raw::c_char,
+        arg3: *mut zpool_errata_t,
+    ) -> zpool_status_t;
+}
+extern "C" {
+    pub fn zpool_get_errlog(
+        arg1: *mut zpool_handle_t,
+        arg2: *mut *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_obj_to_path(
+        arg1: *mut zpool_handle_t,
+        arg2: u64,
+        arg3: u64,
+        arg4: *mut ::std::os::raw::c_char,
+        len: usize,
+    );
+}
+extern "C" {
+    pub fn libzfs_error_action(arg1: *mut libzfs_handle_t) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn lzc_snapshot(
+        arg1: *mut nvlist_t,
+        arg2: *mut nvlist_t,
+        arg3: *mut *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn lzc_bookmark(arg1: *mut nvlist_t, arg2: *mut *mut nvlist_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn lzc_hold(
+        arg1: *mut nvlist_t,
+        arg2: ::std::os::raw::c_int,
+        arg3: *mut *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn lzc_release(arg1: *mut nvlist_t, arg2: *mut *mut nvlist_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn lzc_get_holds(
+        arg1: *const ::std::os::raw::c_char,
+        arg2: *mut *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn lzc_send(
+        arg1: *const ::std::os::raw::c_char,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: ::std::os::raw::c_int,
+        arg4: lzc_send_flags,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn lzc_receive(
+        arg1: *const ::std::os::raw::c_char,
+        arg2: *mut nvlist_t,
+        arg3: *const ::std::os::raw::c_char,
+        arg4: boolean_t,
+        arg5: boolean_t,
+        arg6: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn lzc_exists(arg1: *const ::std::os::raw::c_char) -> boolean_t;
+}
+extern "C" {
+    pub fn lzc_rollback(
+        arg1: *const ::std::os::raw::c_char,
+        arg2: *mut ::std::os::raw::c_char,
+        arg3: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_iter_dependents(
+        arg1: *mut zfs_handle_t,
+        arg2: boolean_t,
+        arg3: zfs_iter_f,
+        arg4: *mut ::std::os::raw::c_void,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_iter_snapshots(
+        arg1: *mut zfs_handle_t,
+        arg2: boolean_t,
+        arg3: zfs_iter_f,
+        arg4: *mut ::std::os::raw::c_void,
+        arg5: u64,
+        arg6: u64,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_get_pool_name(arg1: *const zfs_handle_t) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn zfs_get_clones_nvl(arg1: *mut zfs_handle_t) -> *mut nvlist_t;
+}
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub struct zfs_handle {
@@ -15,7 +15,7 @@
 //! to the src dir. To rebuild bindings run `cargo build`.
 //!
 //! ## ZFS version
-//! These bindings were compiled against ZFS 0.7.13. As `libzfs` is not a stable interface,
+//! These bindings were compiled against ZFS 0.8.6. As `libzfs` is not a stable interface,
 //! they should only be used against this version.
 //!
 //! ## OS
@@ -37,6 +37,18 @@ pub fn zpool_config_vdev_tree() -> String {
     utf8_to_string(ZPOOL_CONFIG_VDEV_TREE)
 }
 
+pub fn zpool_config_pool_name() -> String {
+    utf8_to_string(ZPOOL_CONFIG_POOL_NAME)
+}
+
+pub fn zpool_config_pool_state() -> String {
+    utf8_to_string(ZPOOL_CONFIG_POOL_STATE)
+}
+
+pub fn zpool_config_pool_txg() -> String {
+    utf8_to_string(ZPOOL_CONFIG_POOL_TXG)
+}
+
 pub fn zpool_config_type() -> String {
     utf8_to_string(ZPOOL_CONFIG_TYPE)
 }
@@ -89,10 +101,110 @@ pub fn zprop_value() -> String {
     utf8_to_string(ZPROP_VALUE)
 }
 
+pub fn zpool_config_scan_stats() -> String {
+    utf8_to_string(ZPOOL_CONFIG_SCAN_STATS)
+}
+
+pub fn zpool_config_nparity() -> String {
+    utf8_to_string(ZPOOL_CONFIG_NPARITY)
+}
+
+pub fn zpool_config_id() -> String {
+    utf8_to_string(ZPOOL_CONFIG_ID)
+}
+
+pub fn zpool_config_ashift() -> String {
+    utf8_to_string(ZPOOL_CONFIG_ASHIFT)
+}
+
+pub fn zpool_config_asize() -> String {
+    utf8_to_string(ZPOOL_CONFIG_ASIZE)
+}
+
+pub fn zpool_config_metaslab_shift() -> String {
+    utf8_to_string(ZPOOL_CONFIG_METASLAB_SHIFT)
+}
+
+pub fn zpool_config_allocation_bias() -> String {
+    utf8_to_string(ZPOOL_CONFIG_ALLOCATION_BIAS)
+}
+
+pub fn zpool_config_removal_stats() -> String {
+    utf8_to_string(ZPOOL_CONFIG_REMOVAL_STATS)
+}
+
+pub fn zpool_config_errcount() -> String {
+    utf8_to_string(ZPOOL_CONFIG_ERRCOUNT)
+}
+
+pub fn zpool_config_checkpoint_stats() -> String {
+    utf8_to_string(ZPOOL_CONFIG_CHECKPOINT_STATS)
+}
+
 pub fn zpool_config_vdev_stats() -> String {
     utf8_to_string(ZPOOL_CONFIG_VDEV_STATS)
 }
 
+pub fn zpool_hist_record() -> String {
+    utf8_to_string(ZPOOL_HIST_RECORD)
+}
+
+pub fn zpool_hist_time() -> String {
+    utf8_to_string(ZPOOL_HIST_TIME)
+}
+
+pub fn zpool_hist_cmd() -> String {
+    utf8_to_string(ZPOOL_HIST_CMD)
+}
+
+pub fn zpool_hist_who() -> String {
+    utf8_to_string(ZPOOL_HIST_WHO)
+}
+
+pub fn zpool_hist_host() -> String {
+    utf8_to_string(ZPOOL_HIST_HOST)
+}
+
+pub fn zpool_hist_txg() -> String {
+    utf8_to_string(ZPOOL_HIST_TXG)
+}
+
+pub fn zpool_hist_int_event() -> String {
+    utf8_to_string(ZPOOL_HIST_INT_EVENT)
+}
+
+pub fn zpool_hist_int_str() -> String {
+    utf8_to_string(ZPOOL_HIST_INT_STR)
+}
+
+pub fn zpool_hist_int_name() -> String {
+    utf8_to_string(ZPOOL_HIST_INT_NAME)
+}
+
+pub fn zpool_hist_ioctl() -> String {
+    utf8_to_string(ZPOOL_HIST_IOCTL)
+}
+
+pub fn zpool_hist_dsname() -> String {
+    utf8_to_string(ZPOOL_HIST_DSNAME)
+}
+
+pub fn zpool_load_policy() -> String {
+    utf8_to_string(ZPOOL_LOAD_POLICY)
+}
+
+pub fn zpool_load_rewind_policy() -> String {
+    utf8_to_string(ZPOOL_LOAD_REWIND_POLICY)
+}
+
+pub fn zpool_err_dataset() -> String {
+    utf8_to_string(ZPOOL_ERR_DATASET)
+}
+
+pub fn zpool_err_object() -> String {
+    utf8_to_string(ZPOOL_ERR_OBJECT)
+}
+
 pub fn zfs_type_dataset() -> zfs_type_t {
     zfs_type_t::ZFS_TYPE_FILESYSTEM | zfs_type_t::ZFS_TYPE_VOLUME | zfs_type_t::ZFS_TYPE_SNAPSHOT
 }
@@ -105,13 +217,15 @@ pub fn import_args() -> importargs {
         guid: 0,
         cachefile: std::ptr::null_mut(),
         _bitfield_1: importargs::new_bitfield_1(0, 1, 1, 0),
-        __bindgen_padding_0: [0, 0, 0, 0, 0, 0, 0],
+        policy: std::ptr::null_mut(),
     }
 }
 
 /// Converts a `Vec<u64>` to `vdev_stat_t`
+///
+/// Fields missing from `xs`, as from an older kernel module, are zero.
 pub fn to_vdev_stat(mut xs: Vec<u64>) -> vdev_stat_t {
-    xs.shrink_to_fit();
+    xs.resize(std::mem::size_of::<vdev_stat_t>() / 8, 0);
 
     unsafe { std::ptr::read(xs.as_ptr() as *const _) }
 }
@@ -127,7 +241,7 @@ pub fn to_vdev_state(n: u32) -> Option<vdev_state_t> {
 
 /// Converts an `i32` to `Option<zfs_prop_t>`
 pub fn to_zfs_prop_t(n: i32) -> Option<zfs_prop_t> {
-    if n >= -1 && n <= 82 {
+    if n >= -1 && n <= 93 {
         Some(unsafe { std::mem::transmute(n) })
     } else {
         None
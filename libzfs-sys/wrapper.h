@@ -5,3 +5,4 @@
 #define _LARGEFILE64_SOURCE
 
 #include <libzfs_impl.h>
+#include <libzfs_core.h>
@@ -8,7 +8,9 @@ extern crate serde_derive;
 
 use serde_derive::{Deserialize, Serialize};
 
-use std::{error, ffi::IntoStringError, fmt, io::Error, path::PathBuf, result};
+use std::{
+    collections::BTreeMap, error, ffi::IntoStringError, fmt, io::Error, path::PathBuf, result,
+};
 
 #[derive(Debug)]
 pub enum LibZfsError {
@@ -16,6 +18,94 @@ pub enum LibZfsError {
     IntoString(IntoStringError),
     PoolNotFound(Option<String>, Option<u64>),
     ZfsNotFound(String),
+    PropSet(Vec<String>, ZfsError),
+    ZfsBusy(String),
+    Zfs(ZfsError),
+    /// Importing the named pool failed
+    PoolImport {
+        pool: String,
+        source: Box<LibZfsError>,
+    },
+    /// Exporting the named pool failed
+    PoolExport {
+        pool: String,
+        source: Box<LibZfsError>,
+    },
+    /// Reading the named property failed
+    PropLookup {
+        name: String,
+        source: Box<LibZfsError>,
+    },
+    /// An atomic batch, such as destroying several snapshots, was not
+    /// done because these items failed, each with the reason why
+    Batch(BTreeMap<String, Error>),
+}
+
+/// Broad classes of libzfs failures, so callers can
+/// tell them apart without comparing messages
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum ZfsErrorKind {
+    PoolNotFound,
+    DatasetNotFound,
+    DatasetBusy,
+    /// The pool is active on this or another system
+    PoolActive,
+    AlreadyExists,
+    NoSuchProperty,
+    PermissionDenied,
+    /// The pool has suspended I/O after losing access to its devices
+    PoolIoSuspended,
+    NoSpace,
+    ReadOnly,
+    InvalidName,
+    NotSupported,
+    /// A named device is not part of the pool
+    DeviceNotFound,
+    /// A device, or the layout asked for, cannot be used
+    InvalidDevice,
+    Io,
+    Other,
+}
+
+/// An error libzfs reported for the last failed call on a handle
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ZfsError {
+    /// The `zfs_error_t` code
+    pub errno: u32,
+    pub kind: ZfsErrorKind,
+    /// What was being done, such as "cannot import 'tank'"
+    pub action: String,
+    /// Why it failed, such as "pool may be in use from other system"
+    pub description: String,
+}
+
+impl fmt::Display for ZfsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.action.is_empty() {
+            write!(f, "{}", self.description)
+        } else {
+            write!(f, "{}: {}", self.action, self.description)
+        }
+    }
+}
+
+impl LibZfsError {
+    /// The class of failure, looking through any context around it.
+    ///
+    /// Returns `None` for errors that did not come from libzfs,
+    /// such as I/O on the crate's own files.
+    pub fn kind(&self) -> Option<ZfsErrorKind> {
+        match *self {
+            LibZfsError::PoolNotFound(_, _) => Some(ZfsErrorKind::PoolNotFound),
+            LibZfsError::ZfsNotFound(_) => Some(ZfsErrorKind::DatasetNotFound),
+            LibZfsError::ZfsBusy(_) => Some(ZfsErrorKind::DatasetBusy),
+            LibZfsError::Zfs(ref err) | LibZfsError::PropSet(_, ref err) => Some(err.kind),
+            LibZfsError::PoolImport { ref source, .. }
+            | LibZfsError::PoolExport { ref source, .. }
+            | LibZfsError::PropLookup { ref source, .. } => source.kind(),
+            LibZfsError::Io(_) | LibZfsError::IntoString(_) | LibZfsError::Batch(_) => None,
+        }
+    }
 }
 
 impl fmt::Display for LibZfsError {
@@ -36,6 +126,28 @@ impl fmt::Display for LibZfsError {
             LibZfsError::ZfsNotFound(ref err) => {
                 write!(f, "The zfs object {} could not be found", err)
             }
+            LibZfsError::PropSet(ref names, ref err) => {
+                write!(f, "Could not set {}: {}", names.join(", "), err)
+            }
+            LibZfsError::ZfsBusy(ref err) => write!(f, "The zfs object {} is busy", err),
+            LibZfsError::Zfs(ref err) => write!(f, "{}", err),
+            LibZfsError::PoolImport {
+                ref pool,
+                ref source,
+            } => write!(f, "Could not import pool {}: {}", pool, source),
+            LibZfsError::PoolExport {
+                ref pool,
+                ref source,
+            } => write!(f, "Could not export pool {}: {}", pool, source),
+            LibZfsError::PropLookup {
+                ref name,
+                ref source,
+            } => write!(f, "Could not read property {}: {}", name, source),
+            LibZfsError::Batch(ref errs) => {
+                let xs: Vec<_> = errs.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+
+                write!(f, "Batch failed, nothing was done: {}", xs.join(", "))
+            }
         }
     }
 }
@@ -47,6 +159,13 @@ impl error::Error for LibZfsError {
             LibZfsError::IntoString(ref err) => Some(err),
             LibZfsError::PoolNotFound(_, _) => None,
             LibZfsError::ZfsNotFound(_) => None,
+            LibZfsError::PropSet(_, _) => None,
+            LibZfsError::ZfsBusy(_) => None,
+            LibZfsError::Zfs(_) => None,
+            LibZfsError::Batch(_) => None,
+            LibZfsError::PoolImport { ref source, .. }
+            | LibZfsError::PoolExport { ref source, .. }
+            | LibZfsError::PropLookup { ref source, .. } => Some(source.as_ref()),
         }
     }
 }
@@ -70,17 +189,52 @@ pub enum VDev {
     Mirror {
         children: Vec<VDev>,
         is_log: Option<bool>,
+        /// Its place among the top-level vdevs, if it is one
+        id: Option<u64>,
     },
     RaidZ {
         children: Vec<VDev>,
+        /// How many devices' worth of parity it keeps
+        parity: Option<u64>,
+        /// Its place among the top-level vdevs
+        id: Option<u64>,
+    },
+    /// A distributed-spare raid group, with its layout when the config has it
+    Draid {
+        children: Vec<VDev>,
+        parity: Option<u64>,
+        data_disks: Option<u64>,
+        /// The number of distributed spares
+        spares: Option<u64>,
+        groups: Option<u64>,
+        /// Its place among the top-level vdevs
+        id: Option<u64>,
     },
     Replacing {
         children: Vec<VDev>,
     },
+    /// A device and the hot spare standing in for it
+    Spare {
+        children: Vec<VDev>,
+    },
+    /// The pool's vdev tree.
+    ///
+    /// `children` lists every top-level vdev, logs and allocation class
+    /// vdevs included. `logs`, `special` and `dedup` repeat those of each
+    /// class so they can be listed without checking each child.
     Root {
         children: Vec<VDev>,
         spares: Vec<VDev>,
         cache: Vec<VDev>,
+        /// Separate intent log vdevs, also in `children`
+        #[serde(default)]
+        logs: Vec<VDev>,
+        /// Top-level vdevs of the special allocation class, also in `children`
+        #[serde(default)]
+        special: Vec<VDev>,
+        /// Top-level vdevs of the dedup allocation class, also in `children`
+        #[serde(default)]
+        dedup: Vec<VDev>,
     },
     Disk {
         guid: Option<u64>,
@@ -90,15 +244,284 @@ pub enum VDev {
         phys_path: Option<String>,
         whole_disk: Option<bool>,
         is_log: Option<bool>,
+        read_errors: Option<u64>,
+        write_errors: Option<u64>,
+        checksum_errors: Option<u64>,
     },
     File {
         guid: Option<u64>,
         state: String,
         path: PathBuf,
         is_log: Option<bool>,
+        read_errors: Option<u64>,
+        write_errors: Option<u64>,
+        checksum_errors: Option<u64>,
+    },
+    /// A spare spread across a dRAID's children, such as `draid2-0-0`
+    DraidSpare {
+        guid: Option<u64>,
+        state: String,
+        name: String,
+    },
+    /// A cache device described by its own label
+    L2Cache {
+        guid: Option<u64>,
+        path: Option<PathBuf>,
+    },
+    /// Holds the place of a removed log device
+    Hole {
+        guid: Option<u64>,
+    },
+    /// A removed top-level vdev whose data was remapped
+    Indirect {
+        guid: Option<u64>,
+    },
+    /// A device the config expects but could not be opened
+    Missing {
+        guid: Option<u64>,
     },
 }
 
+impl VDev {
+    /// The read, write and checksum error counts of a disk or file vdev.
+    pub fn error_counts(&self) -> Option<(u64, u64, u64)> {
+        match *self {
+            VDev::Disk {
+                read_errors: Some(r),
+                write_errors: Some(w),
+                checksum_errors: Some(c),
+                ..
+            }
+            | VDev::File {
+                read_errors: Some(r),
+                write_errors: Some(w),
+                checksum_errors: Some(c),
+                ..
+            } => Some((r, w, c)),
+            _ => None,
+        }
+    }
+    /// How `zpool status` names this vdev, given its place among its siblings.
+    ///
+    /// Top-level vdevs are numbered by their `id` instead, so that logs and
+    /// allocation class vdevs keep the numbers they have among all of them.
+    /// A raidz vdev without a recorded parity is taken to be raidz1.
+    fn label(&self, index: usize) -> String {
+        match *self {
+            VDev::Mirror { id, .. } => format!("mirror-{}", id.unwrap_or(index as u64)),
+            VDev::RaidZ { parity, id, .. } => format!(
+                "raidz{}-{}",
+                parity.unwrap_or(1),
+                id.unwrap_or(index as u64)
+            ),
+            VDev::Draid {
+                parity: Some(p),
+                id,
+                ..
+            } => format!("draid{}-{}", p, id.unwrap_or(index as u64)),
+            VDev::Draid { id, .. } => format!("draid-{}", id.unwrap_or(index as u64)),
+            VDev::Replacing { .. } => format!("replacing-{}", index),
+            VDev::Spare { .. } => format!("spare-{}", index),
+            VDev::Root { .. } => "root".to_string(),
+            VDev::Disk { ref path, .. } | VDev::File { ref path, .. } => path.display().to_string(),
+            VDev::L2Cache {
+                path: Some(ref path),
+                ..
+            } => path.display().to_string(),
+            VDev::DraidSpare { ref name, .. } => name.clone(),
+            VDev::Hole { .. } => "hole".to_string(),
+            VDev::Indirect { .. } => format!("indirect-{}", index),
+            VDev::L2Cache { guid, .. } | VDev::Missing { guid } => match guid {
+                Some(x) => x.to_string(),
+                None => "-".to_string(),
+            },
+        }
+    }
+    fn state(&self) -> Option<&str> {
+        match *self {
+            VDev::Disk { ref state, .. }
+            | VDev::File { ref state, .. }
+            | VDev::DraidSpare { ref state, .. } => Some(state),
+            VDev::Missing { .. } => Some("UNAVAIL"),
+            _ => None,
+        }
+    }
+    fn tree_rows(&self, label: String, depth: usize, rows: &mut Vec<TreeRow>) {
+        fn group(xs: &[VDev], depth: usize, rows: &mut Vec<TreeRow>) {
+            for (i, x) in xs.iter().enumerate() {
+                x.tree_rows(x.label(i), depth, rows);
+            }
+        }
+
+        match *self {
+            VDev::Root {
+                ref children,
+                ref spares,
+                ref cache,
+                ref logs,
+                ref special,
+                ref dedup,
+            } => {
+                let data: Vec<VDev> = children
+                    .iter()
+                    .filter(|x| !logs.contains(x) && !special.contains(x) && !dedup.contains(x))
+                    .cloned()
+                    .collect();
+
+                rows.push(TreeRow::new(depth, label));
+                group(&data, depth + 1, rows);
+
+                let sections = [
+                    ("logs", logs),
+                    ("dedup", dedup),
+                    ("special", special),
+                    ("cache", cache),
+                    ("spares", spares),
+                ];
+
+                for &(name, xs) in &sections {
+                    if !xs.is_empty() {
+                        rows.push(TreeRow::new(depth, name.to_string()));
+                        group(xs, depth + 1, rows);
+                    }
+                }
+            }
+            // `zpool status` leaves out placeholders for removed vdevs
+            VDev::Hole { .. } | VDev::Indirect { .. } => {}
+            VDev::Mirror { ref children, .. }
+            | VDev::RaidZ { ref children, .. }
+            | VDev::Draid { ref children, .. }
+            | VDev::Replacing { ref children }
+            | VDev::Spare { ref children } => {
+                rows.push(TreeRow::new(depth, label));
+                group(children, depth + 1, rows);
+            }
+            _ => rows.push(TreeRow {
+                depth,
+                label,
+                state: self.state().map(|x| x.to_string()),
+                errors: self.error_counts(),
+            }),
+        }
+    }
+    /// Renders the tree the way `zpool status -P` does, one vdev per line
+    /// indented under its parent, with the state and error counts of
+    /// each device. The root vdev is shown as `name`.
+    pub fn render_tree(&self, name: &str) -> String {
+        let mut rows = vec![TreeRow {
+            depth: 0,
+            label: "NAME".to_string(),
+            state: Some("STATE".to_string()),
+            errors: None,
+        }];
+
+        self.tree_rows(name.to_string(), 0, &mut rows);
+
+        render_rows(&rows, true)
+    }
+}
+
+/// A line of `VDev::render_tree`
+struct TreeRow {
+    depth: usize,
+    label: String,
+    state: Option<String>,
+    errors: Option<(u64, u64, u64)>,
+}
+
+impl TreeRow {
+    fn new(depth: usize, label: String) -> TreeRow {
+        TreeRow {
+            depth,
+            label,
+            state: None,
+            errors: None,
+        }
+    }
+}
+
+/// Lays out rows in columns. With `header`, the first row
+/// is the column titles.
+fn render_rows(rows: &[TreeRow], header: bool) -> String {
+    let width = rows
+        .iter()
+        .map(|x| x.depth * 2 + x.label.len())
+        .max()
+        .unwrap_or(0)
+        + 2;
+
+    let mut out = String::new();
+
+    for (i, x) in rows.iter().enumerate() {
+        let name = format!("{}{}", "  ".repeat(x.depth), x.label);
+        let state = x.state.as_deref().unwrap_or("");
+
+        let counts = match x.errors {
+            _ if header && i == 0 => format!("{:>5} {:>5} {:>5}", "READ", "WRITE", "CKSUM"),
+            Some((r, w, c)) => format!("{:>5} {:>5} {:>5}", r, w, c),
+            None => String::new(),
+        };
+
+        let line = format!("{:<width$}{:<9}{}", name, state, counts, width = width);
+
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+
+    out
+}
+
+impl fmt::Display for VDev {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut rows = vec![];
+
+        self.tree_rows(self.label(0), 0, &mut rows);
+
+        write!(f, "{}", render_rows(&rows, false))
+    }
+}
+
+/// Flat description of a single vdev, see `Zpool::vdev_map`
+#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone)]
+pub struct VDevInfo {
+    pub guid: u64,
+    pub kind: String,
+    pub path: Option<PathBuf>,
+    pub state: String,
+    pub parent: Option<u64>,
+    pub stats: VDevStats,
+    /// Log2 of the sector size, set on top-level vdevs
+    pub ashift: Option<u64>,
+    /// Allocatable bytes, set on top-level vdevs
+    pub asize: Option<u64>,
+    /// Log2 of the metaslab size, set on top-level vdevs
+    pub metaslab_shift: Option<u64>,
+}
+
+/// Space, I/O and error counters of a vdev, from its `vdev_stat_t`
+///
+/// I/O counts and bytes are totals since the pool was imported.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, Clone)]
+pub struct VDevStats {
+    /// Allocated bytes
+    pub alloc: u64,
+    /// Total capacity in bytes
+    pub space: u64,
+    /// Deflated capacity in bytes
+    pub dspace: u64,
+    /// Replaceable device size in bytes
+    pub rsize: u64,
+    /// Expandable device size in bytes
+    pub esize: u64,
+    pub read_ops: u64,
+    pub write_ops: u64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    pub read_errors: u64,
+    pub write_errors: u64,
+    pub checksum_errors: u64,
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone, PartialOrd, Ord)]
 pub struct ZProp {
     pub name: String,
@@ -129,3 +552,543 @@ pub struct Dataset {
     pub kind: String,
     pub props: Vec<ZProp>,
 }
+
+/// An owned snapshot of a pool, see `Zpool::to_info`
+pub type ZpoolInfo = Pool;
+
+/// Every imported pool on a host at a point in time, see `Libzfs::export_state`
+#[derive(Debug, Serialize, PartialEq, Deserialize, Clone)]
+pub struct ZfsState {
+    /// Each with its properties and every dataset in it
+    pub pools: Vec<Pool>,
+}
+
+/// An owned snapshot of a dataset, see `Zfs::to_info`
+pub type ZfsInfo = Dataset;
+
+/// The kind of inode a `ZfsDiff` record refers to
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone, Copy, PartialOrd, Ord)]
+pub enum InodeType {
+    BlockDevice,
+    CharDevice,
+    Directory,
+    Door,
+    Fifo,
+    Symlink,
+    EventPort,
+    Socket,
+    File,
+    Unknown,
+}
+
+/// A single change between two snapshots, as reported by `zfs diff`
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone, PartialOrd, Ord)]
+pub enum ZfsDiff {
+    Added {
+        path: PathBuf,
+        kind: InodeType,
+    },
+    Removed {
+        path: PathBuf,
+        kind: InodeType,
+    },
+    Modified {
+        path: PathBuf,
+        kind: InodeType,
+    },
+    Renamed {
+        from: PathBuf,
+        to: PathBuf,
+        kind: InodeType,
+    },
+}
+
+/// Where a property's effective value comes from
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone, PartialOrd, Ord)]
+pub enum PropSource {
+    None,
+    Default,
+    Temporary,
+    Local,
+    /// Inherited from the named dataset
+    Inherited(String),
+    Received,
+}
+
+/// A property value along with its source
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone)]
+pub struct PropValue {
+    pub value: String,
+    /// The raw value, for numeric properties
+    pub numeric: Option<u64>,
+    pub source: PropSource,
+    /// The received value, if one was set by `zfs receive`
+    pub received: Option<String>,
+}
+
+/// Who a user, group or project quota applies to
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone, Copy, PartialOrd, Ord)]
+pub enum QuotaTarget {
+    User(u32),
+    Group(u32),
+    Project(u32),
+}
+
+/// Space charged to a single user, group or project
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone, PartialOrd, Ord)]
+pub struct UserspaceEntry {
+    /// SMB domain, empty for POSIX ids
+    pub domain: String,
+    pub id: u32,
+    pub space: u64,
+}
+
+/// A pool property as a name, value and source
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone)]
+pub struct PoolProp {
+    pub name: String,
+    pub value: String,
+    pub source: PropSource,
+}
+
+/// The native pool properties, parsed, see `Zpool::all_props`
+///
+/// Sizes are in bytes. Properties that are unset or
+/// unavailable on this pool are `None`.
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone)]
+pub struct PoolProps {
+    pub name: String,
+    pub guid: u64,
+    pub health: String,
+    /// `None` once the pool uses feature flags
+    pub version: Option<u64>,
+    pub size: u64,
+    pub allocated: u64,
+    pub free: u64,
+    pub freeing: u64,
+    pub leaked: u64,
+    pub expandsize: Option<u64>,
+    /// Percent of the pool in use
+    pub capacity: u64,
+    /// Percent of free space that is fragmented
+    pub fragmentation: Option<u64>,
+    /// In hundredths, so 100 is a ratio of 1.00x
+    pub dedupratio: u64,
+    pub dedupditto: u64,
+    pub ashift: u64,
+    pub maxblocksize: u64,
+    pub maxdnodesize: u64,
+    pub altroot: Option<String>,
+    pub bootfs: Option<String>,
+    pub cachefile: Option<String>,
+    pub comment: Option<String>,
+    /// `wait`, `continue` or `panic`
+    pub failmode: String,
+    pub autoexpand: bool,
+    pub autoreplace: bool,
+    pub delegation: bool,
+    pub listsnapshots: bool,
+    pub multihost: bool,
+    pub readonly: bool,
+}
+
+/// A ZFS event, as shown by `zpool events`
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone)]
+pub struct ZEvent {
+    /// Such as `sysevent.fs.zfs.scrub_finish` or `ereport.fs.zfs.checksum`
+    pub class: String,
+    pub eid: u64,
+    /// Seconds since the unix epoch
+    pub time_secs: i64,
+    pub time_nsecs: i64,
+    pub pool: Option<String>,
+    pub pool_guid: Option<u64>,
+    pub vdev_guid: Option<u64>,
+    pub vdev_path: Option<String>,
+}
+
+/// What a `ZEvent` reports, decoded from its class and payload
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone)]
+pub enum ZEventKind {
+    /// `ereport.fs.zfs.checksum`: a block was read with the wrong checksum
+    Checksum {
+        offset: Option<u64>,
+        size: Option<u64>,
+    },
+    /// `ereport.fs.zfs.io`: a read or write failed
+    Io {
+        errno: Option<i32>,
+        offset: Option<u64>,
+        size: Option<u64>,
+    },
+    /// `ereport.fs.zfs.probe_failure`: a device failed to respond after errors
+    ProbeFailure,
+    /// `resource.fs.zfs.statechange`: a vdev changed state,
+    /// given as `vdev_state_t` values
+    StateChange {
+        state: Option<u64>,
+        previous: Option<u64>,
+    },
+    /// Any other `ereport.fs.zfs.` class, by subclass, such as `data`
+    Ereport(String),
+    /// Any other `resource.fs.zfs.` class, by subclass, such as `removed`
+    Resource(String),
+    /// A `sysevent.fs.zfs.` class, by subclass, such as `scrub_finish`
+    Sysevent(String),
+    /// A class outside `fs.zfs`, in full
+    Other(String),
+}
+
+/// Why a pool is not healthy, mirroring `zpool_status_t`
+///
+/// `R` and `Nr` variants differ in whether the pool
+/// has the redundancy to keep going.
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone, Copy)]
+pub enum StatusReason {
+    CorruptCache,
+    MissingDevR,
+    MissingDevNr,
+    CorruptLabelR,
+    CorruptLabelNr,
+    BadGuidSum,
+    CorruptPool,
+    CorruptData,
+    FailingDev,
+    VersionNewer,
+    HostidMismatch,
+    HostidActive,
+    HostidRequired,
+    IoFailureWait,
+    IoFailureContinue,
+    IoFailureMmp,
+    BadLog,
+    Errata,
+    UnsupFeatRead,
+    UnsupFeatWrite,
+    FaultedDevR,
+    FaultedDevNr,
+    VersionOlder,
+    FeatDisabled,
+    Resilvering,
+    OfflineDev,
+    RemovedDev,
+    /// A device was added with a smaller ashift than its sectors need
+    NonNativeAshift,
+    Ok,
+    /// A status this crate does not know of, as libzfs numbers it
+    Unknown(u32),
+}
+
+/// Everything `zpool status` shows, see `Zpool::status`
+#[derive(Debug, PartialEq, Deserialize, Serialize, Clone)]
+pub struct ZpoolStatus {
+    pub name: String,
+    pub guid: u64,
+    pub state: String,
+    pub health: String,
+    pub reason: StatusReason,
+    /// The message to look up for `reason`, such as `ZFS-8000-9P`
+    pub msgid: Option<String>,
+    /// The erratum affecting the pool, when `reason` is `Errata`
+    pub errata: Option<u32>,
+    pub vdev: VDev,
+    pub scan: Option<ScanStatus>,
+    pub removal: Option<RemovalStatus>,
+    pub checkpoint: Option<CheckpointStatus>,
+    /// The number of persistent data errors
+    pub error_count: u64,
+    pub errors: Vec<PersistentError>,
+}
+
+/// An object with a persistent data error, see `Zpool::error_log`
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone, PartialOrd, Ord)]
+pub struct PersistentError {
+    /// The objset id of the damaged dataset
+    pub dataset: u64,
+    pub object: u64,
+    /// A file path if the dataset is mounted, otherwise
+    /// the dataset name and object, such as `pool/fs:<0x1f>`
+    pub path: String,
+}
+
+/// What a pool history record describes
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone)]
+pub enum HistoryEvent {
+    /// A logged command line, such as `zpool create test sdb`
+    Command(String),
+    /// An event logged by ZFS itself, such as `snapshot`
+    Internal { name: String, info: String },
+    /// A logged ioctl, such as `snapshot`
+    Ioctl(String),
+}
+
+/// A single entry of `zpool history`
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone)]
+pub struct HistoryRecord {
+    /// Seconds since the unix epoch
+    pub time: u64,
+    pub event: HistoryEvent,
+    pub txg: Option<u64>,
+    /// The uid that made the change
+    pub who: Option<u32>,
+    pub host: Option<String>,
+    /// The dataset an internal event applies to
+    pub dataset: Option<String>,
+}
+
+/// State of a scan or device removal, mirroring `dsl_scan_state_t`
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone, Copy)]
+pub enum ScanState {
+    None,
+    Scanning,
+    Finished,
+    Canceled,
+}
+
+impl ScanState {
+    pub fn from_raw(n: u64) -> Option<ScanState> {
+        match n {
+            0 => Some(ScanState::None),
+            1 => Some(ScanState::Scanning),
+            2 => Some(ScanState::Finished),
+            3 => Some(ScanState::Canceled),
+            _ => None,
+        }
+    }
+}
+
+/// Progress of a device removal, see `Zpool::removal_status`
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone)]
+pub struct RemovalStatus {
+    pub state: ScanState,
+    /// The guid of the vdev being removed
+    pub vdev: u64,
+    /// Seconds since the unix epoch
+    pub start_time: u64,
+    pub end_time: u64,
+    pub to_copy: u64,
+    pub copied: u64,
+    /// Memory used by the indirect mapping, in bytes
+    pub mapping_memory: u64,
+}
+
+/// State of a pool feature, as in its `feature@` property
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone, Copy)]
+pub enum FeatureState {
+    Disabled,
+    Enabled,
+    Active,
+}
+
+impl FeatureState {
+    pub fn from_name(s: &str) -> Option<FeatureState> {
+        match s {
+            "disabled" => Some(FeatureState::Disabled),
+            "enabled" => Some(FeatureState::Enabled),
+            "active" => Some(FeatureState::Active),
+            _ => None,
+        }
+    }
+}
+
+/// A pool feature flag, see `Zpool::features`
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone)]
+pub struct Feature {
+    /// The short name, such as `large_dnode`
+    pub name: String,
+    /// The full name, such as `org.zfsonlinux:large_dnode`,
+    /// if the feature is enabled
+    pub guid: Option<String>,
+    pub state: FeatureState,
+    /// How many times the feature is in use, if it is enabled
+    pub refcount: Option<u64>,
+}
+
+/// State of a pool checkpoint, mirroring `checkpoint_state_t`
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone, Copy)]
+pub enum CheckpointState {
+    None,
+    Exists,
+    Discarding,
+}
+
+impl CheckpointState {
+    pub fn from_raw(n: u64) -> Option<CheckpointState> {
+        match n {
+            0 => Some(CheckpointState::None),
+            1 => Some(CheckpointState::Exists),
+            2 => Some(CheckpointState::Discarding),
+            _ => None,
+        }
+    }
+}
+
+/// A pool checkpoint, see `Zpool::checkpoint_status`
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone)]
+pub struct CheckpointStatus {
+    pub state: CheckpointState,
+    /// Seconds since the unix epoch
+    pub start_time: u64,
+    /// Space held by the checkpoint, in bytes
+    pub space: u64,
+}
+
+/// The kind of scan, mirroring `pool_scan_func_t`
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone, Copy)]
+pub enum ScanFunction {
+    None,
+    Scrub,
+    Resilver,
+}
+
+impl ScanFunction {
+    pub fn from_raw(n: u64) -> Option<ScanFunction> {
+        match n {
+            0 => Some(ScanFunction::None),
+            1 => Some(ScanFunction::Scrub),
+            2 => Some(ScanFunction::Resilver),
+            _ => None,
+        }
+    }
+}
+
+/// Progress of the current or last scrub or resilver, see `Zpool::scan_status`
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone)]
+pub struct ScanStatus {
+    pub function: ScanFunction,
+    pub state: ScanState,
+    /// Seconds since the unix epoch
+    pub start_time: u64,
+    pub end_time: u64,
+    pub to_examine: u64,
+    pub examined: u64,
+    /// Bytes whose I/O has been issued, on ZFS 0.8 and later
+    pub issued: Option<u64>,
+    pub errors: u64,
+    pub paused: bool,
+    /// Bytes issued per second in the current pass,
+    /// or examined before ZFS 0.8
+    pub rate: u64,
+    /// Estimated seconds remaining, if the scan is running
+    pub eta: Option<u64>,
+}
+
+/// State of a per-vdev TRIM or initialize,
+/// mirroring `vdev_trim_state_t` and `vdev_initializing_state_t`
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone, Copy)]
+pub enum VDevOpState {
+    None,
+    Active,
+    Canceled,
+    Suspended,
+    Complete,
+}
+
+impl VDevOpState {
+    pub fn from_raw(n: u64) -> Option<VDevOpState> {
+        match n {
+            0 => Some(VDevOpState::None),
+            1 => Some(VDevOpState::Active),
+            2 => Some(VDevOpState::Canceled),
+            3 => Some(VDevOpState::Suspended),
+            4 => Some(VDevOpState::Complete),
+            _ => None,
+        }
+    }
+}
+
+/// Progress of a TRIM or initialize on a leaf vdev
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone)]
+pub struct VDevProgress {
+    pub state: VDevOpState,
+    pub bytes_done: u64,
+    pub bytes_est: u64,
+    /// When the state last changed, in seconds since the unix epoch
+    pub action_time: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk(path: &str, state: &str) -> VDev {
+        VDev::Disk {
+            guid: None,
+            state: state.to_string(),
+            path: path.into(),
+            dev_id: None,
+            phys_path: None,
+            whole_disk: None,
+            is_log: None,
+            read_errors: Some(0),
+            write_errors: Some(0),
+            checksum_errors: Some(2),
+        }
+    }
+
+    #[test]
+    fn renders_vdev_tree() {
+        let log = VDev::Mirror {
+            children: vec![disk("/dev/sdf", "ONLINE"), disk("/dev/sdg", "ONLINE")],
+            is_log: Some(true),
+            id: Some(2),
+        };
+
+        let root = VDev::Root {
+            children: vec![
+                VDev::Mirror {
+                    children: vec![disk("/dev/sdb", "ONLINE"), disk("/dev/sdc", "FAULTED")],
+                    is_log: None,
+                    id: Some(0),
+                },
+                VDev::RaidZ {
+                    children: vec![disk("/dev/sdd", "ONLINE"), disk("/dev/sde", "ONLINE")],
+                    parity: Some(1),
+                    id: Some(1),
+                },
+                log.clone(),
+            ],
+            spares: vec![],
+            cache: vec![],
+            logs: vec![log],
+            special: vec![],
+            dedup: vec![],
+        };
+
+        assert_eq!(
+            root.render_tree("test"),
+            "NAME          STATE     READ WRITE CKSUM
+test
+  mirror-0
+    /dev/sdb  ONLINE       0     0     2
+    /dev/sdc  FAULTED      0     0     2
+  raidz1-1
+    /dev/sdd  ONLINE       0     0     2
+    /dev/sde  ONLINE       0     0     2
+logs
+  mirror-2
+    /dev/sdf  ONLINE       0     0     2
+    /dev/sdg  ONLINE       0     0     2
+"
+        );
+    }
+
+    #[test]
+    fn labels_vdevs_without_id_by_place() {
+        let x = VDev::RaidZ {
+            children: vec![],
+            parity: Some(2),
+            id: None,
+        };
+
+        assert_eq!(x.label(3), "raidz2-3");
+
+        let x = VDev::RaidZ {
+            children: vec![],
+            parity: None,
+            id: Some(0),
+        };
+
+        assert_eq!(x.label(3), "raidz1-0");
+    }
+}
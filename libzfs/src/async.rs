@@ -0,0 +1,280 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Tokio wrappers, behind the `async` feature
+//!
+//! libzfs calls block, often for seconds while devices are scanned or
+//! datasets unmounted. `AsyncLibzfs` runs each call on tokio's blocking
+//! thread pool against one shared `Libzfs`, a call at a time. It hands
+//! back plain data rather than `Zpool` or `Zfs` handles, so nothing
+//! touches libzfs outside that lock.
+//!
+
+use dataset_iter::DatasetIter;
+use libzfs::Libzfs;
+use libzfs_types::{Dataset, LibZfsError, Pool, PoolProp, PropValue, Result};
+use state::{convert_to_dataset, convert_to_js_pool};
+use std::future::Future;
+use std::io::{Error, ErrorKind};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::task::{self, JoinHandle};
+use zfs::Zfs;
+use zfs_prop::ZfsProp;
+use zpool::Zpool;
+
+/// A libzfs call running on the blocking thread pool
+pub struct Blocking<T>(JoinHandle<Result<T>>);
+
+impl<T> Future for Blocking<T> {
+    type Output = Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<T>> {
+        match Pin::new(&mut self.0).poll(cx) {
+            Poll::Ready(Ok(x)) => Poll::Ready(x),
+            Poll::Ready(Err(e)) => {
+                Poll::Ready(Err(LibZfsError::Io(Error::new(ErrorKind::Other, e))))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// A `Libzfs` handle that can be shared between tasks
+#[derive(Clone)]
+pub struct AsyncLibzfs {
+    inner: Arc<Mutex<Libzfs>>,
+}
+
+impl Default for AsyncLibzfs {
+    fn default() -> Self {
+        AsyncLibzfs::new()
+    }
+}
+
+fn pool(z: &mut Libzfs, name: &str) -> Result<Zpool> {
+    z.pool_by_name(name)
+        .ok_or_else(|| LibZfsError::PoolNotFound(Some(name.to_string()), None))
+}
+
+fn dataset(z: &mut Libzfs, name: &str) -> Result<Zfs> {
+    z.dataset_by_name(name)
+        .ok_or_else(|| LibZfsError::ZfsNotFound(name.to_string()))
+}
+
+impl AsyncLibzfs {
+    pub fn new() -> AsyncLibzfs {
+        AsyncLibzfs::from_libzfs(Libzfs::new())
+    }
+    pub fn from_libzfs(libzfs: Libzfs) -> AsyncLibzfs {
+        AsyncLibzfs {
+            inner: Arc::new(Mutex::new(libzfs)),
+        }
+    }
+    /// Runs `f` on the blocking thread pool with the shared handle.
+    ///
+    /// Any `Zpool` or `Zfs` opened in `f` must be dropped before it returns,
+    /// so this is kept to the crate; callers use the methods below.
+    pub(crate) fn run<F, T>(&self, f: F) -> Blocking<T>
+    where
+        F: FnOnce(&mut Libzfs) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let inner = Arc::clone(&self.inner);
+
+        Blocking(task::spawn_blocking(move || {
+            let mut z = inner.lock().unwrap_or_else(|e| e.into_inner());
+
+            f(&mut z)
+        }))
+    }
+    pub fn imported_pools(&self) -> Blocking<Vec<Pool>> {
+        self.run(|z| {
+            z.get_imported_pools()?
+                .iter()
+                .map(convert_to_js_pool)
+                .collect()
+        })
+    }
+    pub fn pool(&self, name: &str) -> Blocking<Pool> {
+        let name = name.to_string();
+
+        self.run(move |z| convert_to_js_pool(&pool(z, &name)?))
+    }
+    /// Lists every filesystem and volume in a pool, at any depth.
+    pub fn datasets(&self, pool_name: &str) -> Blocking<Vec<Dataset>> {
+        let name = pool_name.to_string();
+
+        self.run(move |z| {
+            pool(z, &name)?
+                .all_datasets()?
+                .iter()
+                .map(convert_to_dataset)
+                .collect()
+        })
+    }
+    /// Streams every filesystem and volume in a pool, at any depth,
+    /// without listing them all up front.
+    pub fn dataset_stream(&self, pool_name: &str) -> Blocking<DatasetStream> {
+        let name = pool_name.to_string();
+        let libzfs = self.clone();
+
+        self.run(move |z| {
+            Ok(DatasetStream {
+                libzfs,
+                iter: Arc::new(Mutex::new(pool(z, &name)?.dataset_iter()?)),
+            })
+        })
+    }
+    pub fn dataset(&self, name: &str) -> Blocking<Dataset> {
+        let name = name.to_string();
+
+        self.run(move |z| convert_to_dataset(&dataset(z, &name)?))
+    }
+    /// The names of the pools that could be imported.
+    pub fn importable_pools(&self) -> Blocking<Vec<String>> {
+        self.run(|z| {
+            Ok(z.find_importable_pools()
+                .iter()
+                .map(|x| x.name().to_string_lossy().into_owned())
+                .collect())
+        })
+    }
+    /// Imports a pool found by `importable_pools`.
+    pub fn import(&self, name: &str) -> Blocking<()> {
+        let name = name.to_string();
+
+        self.run(move |z| {
+            let nvl = z.find_importable_pools();
+
+            let config = nvl
+                .iter()
+                .find(|x| x.name().to_string_lossy() == name)
+                .ok_or_else(|| LibZfsError::PoolNotFound(Some(name.clone()), None))?
+                .value_nv_list_ref()?;
+
+            z.import(config).map_err(|e| LibZfsError::PoolImport {
+                pool: name,
+                source: Box::new(e),
+            })
+        })
+    }
+    /// Unmounts a pool's datasets and exports it.
+    pub fn export(&self, name: &str) -> Blocking<()> {
+        let name = name.to_string();
+
+        self.run(move |z| {
+            let p = pool(z, &name)?;
+
+            z.export_all(&[p]).map(|_| ())
+        })
+    }
+    pub fn pool_props(&self, name: &str) -> Blocking<Vec<PoolProp>> {
+        let name = name.to_string();
+
+        self.run(move |z| pool(z, &name)?.props())
+    }
+    pub fn set_pool_prop(&self, pool_name: &str, name: &str, value: &str) -> Blocking<()> {
+        let (pool_name, name, value) = (pool_name.to_string(), name.to_string(), value.to_string());
+
+        self.run(move |z| pool(z, &pool_name)?.set_prop(&name, &value))
+    }
+    pub fn dataset_prop(&self, name: &str, prop: ZfsProp) -> Blocking<PropValue> {
+        let name = name.to_string();
+
+        self.run(move |z| dataset(z, &name)?.get_prop(prop))
+    }
+    pub fn set_dataset_prop(&self, ds_name: &str, name: &str, value: &str) -> Blocking<()> {
+        let (ds_name, name, value) = (ds_name.to_string(), name.to_string(), value.to_string());
+
+        self.run(move |z| dataset(z, &ds_name)?.set_prop(&name, &value))
+    }
+}
+
+/// The datasets of a pool, read one at a time on the blocking thread pool
+pub struct DatasetStream {
+    libzfs: AsyncLibzfs,
+    iter: Arc<Mutex<DatasetIter>>,
+}
+
+impl DatasetStream {
+    /// The next dataset, or `None` once the pool has been walked.
+    pub fn next_dataset(&mut self) -> Blocking<Option<Dataset>> {
+        let iter = Arc::clone(&self.iter);
+
+        self.libzfs.run(move |_| {
+            let mut iter = iter.lock().unwrap_or_else(|e| e.into_inner());
+
+            iter.next()
+                .map(|x| x.and_then(|z| convert_to_dataset(&z)))
+                .transpose()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+    use tokio::runtime::{Builder, Runtime};
+
+    fn runtime() -> Runtime {
+        Builder::new_current_thread().build().unwrap()
+    }
+
+    #[test]
+    fn runs_calls_one_at_a_time() {
+        let rt = runtime();
+        let _g = rt.enter();
+
+        let z = AsyncLibzfs::new();
+        let running = Arc::new(AtomicUsize::new(0));
+
+        let calls: Vec<_> = (0..4)
+            .map(|_| {
+                let running = Arc::clone(&running);
+
+                z.clone().run(move |_| {
+                    let others = running.fetch_add(1, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+
+                    Ok(others)
+                })
+            })
+            .collect();
+
+        for x in calls {
+            assert_eq!(rt.block_on(x).unwrap(), 0);
+        }
+    }
+
+    #[test]
+    fn passes_errors_through() {
+        let rt = runtime();
+        let _g = rt.enter();
+
+        let x = AsyncLibzfs::new()
+            .run(|_| -> Result<()> { Err(LibZfsError::ZfsNotFound("test/missing".to_string())) });
+
+        match rt.block_on(x) {
+            Err(LibZfsError::ZfsNotFound(ref x)) if x == "test/missing" => {}
+            x => panic!("expected ZfsNotFound, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn reports_missing_pool() {
+        let rt = runtime();
+        let _g = rt.enter();
+
+        match rt.block_on(AsyncLibzfs::new().pool("missing")) {
+            Err(LibZfsError::PoolNotFound(Some(ref x), None)) if x == "missing" => {}
+            x => panic!("expected PoolNotFound, got {:?}", x.map(|x| x.name)),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Clone dependencies within a pool
+//!
+//! A snapshot cannot be destroyed while a clone of it exists, nor can
+//! anything the clone's own snapshots were cloned into. `CloneGraph`
+//! records the origin of every clone in a pool so that pruning can tell
+//! which snapshots are pinned and what would have to go first.
+//!
+
+extern crate libzfs_sys as sys;
+
+use libzfs_types::Result;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use zpool::Zpool;
+
+/// Every clone in a pool and the snapshot it was made from
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct CloneGraph {
+    /// Clone name to origin snapshot name
+    origins: BTreeMap<String, String>,
+}
+
+/// Whether `name` is `dataset`, a snapshot of it or anything below it.
+fn within(name: &str, dataset: &str) -> bool {
+    name.starts_with(dataset)
+        && matches!(
+            name.as_bytes().get(dataset.len()),
+            None | Some(b'@') | Some(b'/')
+        )
+}
+
+impl CloneGraph {
+    /// Builds a graph from clone and origin snapshot pairs.
+    pub fn new<I, S, T>(edges: I) -> CloneGraph
+    where
+        I: IntoIterator<Item = (S, T)>,
+        S: Into<String>,
+        T: Into<String>,
+    {
+        CloneGraph {
+            origins: edges
+                .into_iter()
+                .map(|(clone, origin)| (clone.into(), origin.into()))
+                .collect(),
+        }
+    }
+    /// Reads the origin of every filesystem and volume in `pool`.
+    pub fn from_pool(pool: &Zpool) -> Result<CloneGraph> {
+        let mut origins = BTreeMap::new();
+
+        for x in pool.traverse(
+            None,
+            sys::zfs_type_t::ZFS_TYPE_FILESYSTEM | sys::zfs_type_t::ZFS_TYPE_VOLUME,
+        )? {
+            let x = x?;
+
+            if let Some(origin) = x.origin() {
+                origins.insert(x.name_str()?, origin);
+            }
+        }
+
+        Ok(CloneGraph { origins })
+    }
+    /// Each clone and its origin, by clone name.
+    pub fn edges(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.origins
+            .iter()
+            .map(|(clone, origin)| (clone.as_str(), origin.as_str()))
+    }
+    /// The snapshot `clone` was made from, if it is a clone.
+    pub fn origin(&self, clone: &str) -> Option<&str> {
+        self.origins.get(clone).map(String::as_str)
+    }
+    /// The clones made directly from `snapshot`.
+    pub fn clones(&self, snapshot: &str) -> Vec<&str> {
+        self.edges()
+            .filter(|(_, origin)| *origin == snapshot)
+            .map(|(clone, _)| clone)
+            .collect()
+    }
+    /// Whether `snapshot` has clones and so cannot be destroyed.
+    pub fn is_pinned(&self, snapshot: &str) -> bool {
+        self.origins.values().any(|x| x == snapshot)
+    }
+    /// Every clone that must be destroyed or promoted before `snapshot`
+    /// can be: its clones, clones of their snapshots and so on.
+    pub fn dependents(&self, snapshot: &str) -> Vec<&str> {
+        let mut xs: Vec<&str> = vec![];
+        let mut pending = self.clones(snapshot);
+
+        while let Some(clone) = pending.pop() {
+            if xs.contains(&clone) {
+                continue;
+            }
+
+            xs.push(clone);
+
+            pending.extend(
+                self.edges()
+                    .filter(|(_, origin)| within(origin, clone))
+                    .map(|(x, _)| x),
+            );
+        }
+
+        xs.sort();
+
+        xs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_clone_chains() {
+        let g = CloneGraph::new(vec![
+            ("test/a", "test/ds@1"),
+            ("test/b", "test/ds@1"),
+            ("test/c", "test/a@2"),
+            ("test/d", "test/a/child@3"),
+            ("test/e", "test/ab@4"),
+        ]);
+
+        assert_eq!(g.origin("test/c"), Some("test/a@2"));
+        assert_eq!(g.origin("test/ds"), None);
+        assert_eq!(g.clones("test/ds@1"), vec!["test/a", "test/b"]);
+        assert!(g.is_pinned("test/a@2"));
+        assert!(!g.is_pinned("test/ds@2"));
+        assert_eq!(
+            g.dependents("test/ds@1"),
+            vec!["test/a", "test/b", "test/c", "test/d"]
+        );
+        assert!(g.dependents("test/ds@2").is_empty());
+    }
+}
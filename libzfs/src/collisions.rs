@@ -0,0 +1,145 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Detect ambiguous pools among importable candidates
+//!
+//! Cloned LUNs can make `find_importable_pools` return several configs
+//! with the same name or guid. `find_collisions` groups them and carries
+//! enough detail (devices, txg, host) for a caller to pick one.
+//!
+
+extern crate libzfs_sys as sys;
+
+use libzfs_types::Result;
+use nvpair;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use vdev::vdev_map;
+
+/// An importable pool as found by discovery
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct DiscoveredPool {
+    pub name: String,
+    pub guid: u64,
+    pub txg: u64,
+    pub hostid: Option<u64>,
+    pub hostname: Option<String>,
+    pub devices: Vec<PathBuf>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+pub enum CollisionKind {
+    Name,
+    Guid,
+}
+
+/// A set of candidates sharing a name or guid
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct Collision {
+    pub kind: CollisionKind,
+    /// The shared name, or guid formatted in decimal
+    pub key: String,
+    pub candidates: Vec<DiscoveredPool>,
+}
+
+/// Reads the candidates out of `find_importable_pools` output.
+pub fn discovered_pools(pools: &nvpair::NvListRef) -> Result<Vec<DiscoveredPool>> {
+    pools
+        .iter()
+        .map(|x| {
+            let config = x.value_nv_list_ref()?;
+            let tree = config.lookup_nv_list_ref(sys::zpool_config_vdev_tree())?;
+
+            let mut devices: Vec<PathBuf> = vdev_map(tree)?
+                .into_values()
+                .filter_map(|v| v.path)
+                .collect();
+            devices.sort();
+
+            Ok(DiscoveredPool {
+                name: config
+                    .lookup_string(sys::zpool_config_pool_name())?
+                    .into_string()?,
+                guid: config.lookup_uint64(sys::zpool_config_guid())?,
+                txg: config.lookup_uint64(sys::zpool_config_pool_txg())?,
+                hostid: config.lookup_uint64(sys::zpool_config_hostid()).ok(),
+                hostname: config
+                    .lookup_string(sys::zpool_config_hostname())
+                    .ok()
+                    .and_then(|x| x.into_string().ok()),
+                devices,
+            })
+        })
+        .collect()
+}
+
+fn group_by<F>(pools: &[DiscoveredPool], kind: CollisionKind, key: F) -> Vec<Collision>
+where
+    F: Fn(&DiscoveredPool) -> String,
+{
+    let mut groups: BTreeMap<String, Vec<DiscoveredPool>> = BTreeMap::new();
+
+    for x in pools {
+        groups.entry(key(x)).or_default().push(x.clone());
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, xs)| xs.len() > 1)
+        .map(|(key, candidates)| Collision {
+            kind,
+            key,
+            candidates,
+        })
+        .collect()
+}
+
+/// Finds every name and guid shared by more than one candidate.
+pub fn find_collisions(pools: &[DiscoveredPool]) -> Vec<Collision> {
+    let mut xs = group_by(pools, CollisionKind::Guid, |x| x.guid.to_string());
+
+    xs.extend(group_by(pools, CollisionKind::Name, |x| x.name.clone()));
+
+    xs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(name: &str, guid: u64, dev: &str) -> DiscoveredPool {
+        DiscoveredPool {
+            name: name.to_string(),
+            guid,
+            txg: 1,
+            hostid: None,
+            hostname: None,
+            devices: vec![dev.into()],
+        }
+    }
+
+    #[test]
+    fn groups_by_name_and_guid() {
+        let pools = vec![
+            pool("test", 1, "/dev/sdb"),
+            pool("test", 1, "/dev/sdc"),
+            pool("other", 2, "/dev/sdd"),
+            pool("other", 3, "/dev/sde"),
+        ];
+
+        let xs = find_collisions(&pools);
+
+        assert_eq!(
+            xs.iter()
+                .map(|x| (x.kind, x.key.as_str(), x.candidates.len()))
+                .collect::<Vec<_>>(),
+            vec![
+                (CollisionKind::Guid, "1", 2),
+                (CollisionKind::Name, "other", 2),
+                (CollisionKind::Name, "test", 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,380 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! libzfs_core bindings
+//!
+//! libzfs_core issues the ZFS ioctls directly. Each call is a single
+//! committed operation that either happens or does not, failing with an
+//! errno and, for batches, the errno of each item that could not be done.
+//! It keeps no caches and needs no `Libzfs` handle, which suits
+//! long-running daemons. Arguments and results are nvlists laid out as
+//! documented in `libzfs_core.c`.
+//!
+//! Signatures follow ZFS 0.8, whose `lzc_receive` takes a `raw` flag
+//! and whose sends can be raw, for encrypted datasets.
+//!
+
+extern crate libzfs_sys as sys;
+
+use libzfs_types::{LibZfsError, Result};
+use nvpair::{self, ForeignType};
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString};
+use std::io::Error;
+use std::os::raw::{c_char, c_int};
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+
+/// Optional parts of a send stream, see `LibzfsCore::send`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SendFlags {
+    /// Send blocks that are embedded in block pointers as such
+    pub embed_data: bool,
+    /// Send blocks larger than 128K as they are
+    pub large_block: bool,
+    /// Send compressed blocks without decompressing them
+    pub compress: bool,
+    /// Send encrypted datasets without decrypting them
+    pub raw: bool,
+}
+
+impl SendFlags {
+    fn to_raw(self) -> sys::lzc_send_flags {
+        let mut x = 0;
+
+        if self.embed_data {
+            x |= sys::lzc_send_flags_LZC_SEND_FLAG_EMBED_DATA;
+        }
+        if self.large_block {
+            x |= sys::lzc_send_flags_LZC_SEND_FLAG_LARGE_BLOCK;
+        }
+        if self.compress {
+            x |= sys::lzc_send_flags_LZC_SEND_FLAG_COMPRESS;
+        }
+        if self.raw {
+            x |= sys::lzc_send_flags_LZC_SEND_FLAG_RAW;
+        }
+
+        x
+    }
+}
+
+fn boolean(x: bool) -> sys::boolean_t {
+    if x {
+        sys::boolean::B_TRUE
+    } else {
+        sys::boolean::B_FALSE
+    }
+}
+
+fn c_str(x: &str) -> Result<CString> {
+    Ok(CString::new(x).map_err(Error::from)?)
+}
+
+fn check(code: c_int) -> Result<()> {
+    match code {
+        0 => Ok(()),
+        x => Err(LibZfsError::Io(Error::from_raw_os_error(x))),
+    }
+}
+
+/// Reads the outcome of a batch call.
+///
+/// Batches are atomic, so when any item fails none are done, and the
+/// failure is `LibZfsError::Batch` saying why each failing item could
+/// not be. A failure with no items to blame is an I/O error.
+pub(crate) fn batch_result<T>(code: c_int, errlist: *mut T) -> Result<()> {
+    let errors = if errlist.is_null() {
+        BTreeMap::new()
+    } else {
+        let errlist = unsafe { nvpair::NvList::from_ptr(errlist as *mut _) };
+
+        errlist
+            .iter()
+            .filter_map(|x| {
+                errlist.lookup_int32(x.name()).ok().map(|e| {
+                    (
+                        x.name().to_string_lossy().into_owned(),
+                        Error::from_raw_os_error(e),
+                    )
+                })
+            })
+            .collect()
+    };
+
+    match code {
+        0 => Ok(()),
+        x if errors.is_empty() => Err(LibZfsError::Io(Error::from_raw_os_error(x))),
+        _ => Err(LibZfsError::Batch(errors)),
+    }
+}
+
+/// Keeps libzfs_core open
+///
+/// libzfs_core counts its users, so any number of these
+/// may be open at once, from any thread.
+#[derive(Debug)]
+pub struct LibzfsCore(());
+
+impl LibzfsCore {
+    pub fn new() -> Result<LibzfsCore> {
+        check(unsafe { sys::libzfs_core_init() })?;
+
+        Ok(LibzfsCore(()))
+    }
+    /// Takes the snapshots named by the keys of `snaps`, all in one pool,
+    /// setting user properties from `props` on each.
+    pub fn snapshot(
+        &self,
+        snaps: &nvpair::NvListRef,
+        props: Option<&nvpair::NvListRef>,
+    ) -> Result<()> {
+        let mut errlist = ptr::null_mut();
+
+        let code = unsafe {
+            sys::lzc_snapshot(
+                snaps.as_ptr() as *mut _,
+                props.map_or(ptr::null_mut(), |x| x.as_ptr() as *mut _),
+                &mut errlist,
+            )
+        };
+
+        batch_result(code, errlist)
+    }
+    /// Destroys the snapshots named by the keys of `snaps`, all in one pool.
+    ///
+    /// With `defer`, snapshots that are held or cloned are marked
+    /// for destruction once they are released instead.
+    pub fn destroy_snaps(&self, snaps: &nvpair::NvListRef, defer: bool) -> Result<()> {
+        let mut errlist = ptr::null_mut();
+
+        let code = unsafe {
+            sys::lzc_destroy_snaps(snaps.as_ptr() as *mut _, boolean(defer), &mut errlist)
+        };
+
+        batch_result(code, errlist)
+    }
+    /// Creates bookmarks, given as bookmark name to snapshot name.
+    pub fn bookmark(&self, bookmarks: &nvpair::NvListRef) -> Result<()> {
+        let mut errlist = ptr::null_mut();
+
+        let code = unsafe { sys::lzc_bookmark(bookmarks.as_ptr() as *mut _, &mut errlist) };
+
+        batch_result(code, errlist)
+    }
+    /// Places holds, given as snapshot name to hold tag.
+    ///
+    /// With `cleanup_fd`, an open `/dev/zfs`, the holds are
+    /// released when it is closed.
+    pub fn hold<F: AsRawFd>(
+        &self,
+        holds: &nvpair::NvListRef,
+        cleanup_fd: Option<&F>,
+    ) -> Result<()> {
+        let mut errlist = ptr::null_mut();
+
+        let code = unsafe {
+            sys::lzc_hold(
+                holds.as_ptr() as *mut _,
+                cleanup_fd.map_or(-1, |x| x.as_raw_fd()),
+                &mut errlist,
+            )
+        };
+
+        batch_result(code, errlist)
+    }
+    /// Releases holds, given as snapshot name to an nvlist
+    /// with a boolean pair for each tag.
+    pub fn release(&self, holds: &nvpair::NvListRef) -> Result<()> {
+        let mut errlist = ptr::null_mut();
+
+        let code = unsafe { sys::lzc_release(holds.as_ptr() as *mut _, &mut errlist) };
+
+        batch_result(code, errlist)
+    }
+    /// The holds on a snapshot, as tag to creation time.
+    pub fn get_holds(&self, snapname: &str) -> Result<nvpair::NvList> {
+        let c_name = c_str(snapname)?;
+        let mut out = ptr::null_mut();
+
+        check(unsafe { sys::lzc_get_holds(c_name.as_ptr(), &mut out) })?;
+
+        Ok(unsafe { nvpair::NvList::from_ptr(out as *mut _) })
+    }
+    /// Writes a send stream of `snapname` to `fd`, incremental
+    /// from the snapshot or bookmark `from` if given.
+    pub fn send<F: AsRawFd>(
+        &self,
+        snapname: &str,
+        from: Option<&str>,
+        fd: &F,
+        flags: SendFlags,
+    ) -> Result<()> {
+        let c_name = c_str(snapname)?;
+        let c_from = from.map(c_str).transpose()?;
+
+        check(unsafe {
+            sys::lzc_send(
+                c_name.as_ptr(),
+                c_from.as_ref().map_or(ptr::null(), |x| x.as_ptr()),
+                fd.as_raw_fd(),
+                flags.to_raw(),
+            )
+        })
+    }
+    /// Reads a send stream from `fd` into the snapshot `snapname`.
+    ///
+    /// `origin` names the snapshot a clone stream is based on. With
+    /// `force`, the target is rolled back to its latest snapshot first.
+    /// `raw` must be set for streams of encrypted datasets sent raw.
+    pub fn receive<F: AsRawFd>(
+        &self,
+        snapname: &str,
+        props: Option<&nvpair::NvListRef>,
+        origin: Option<&str>,
+        force: bool,
+        raw: bool,
+        fd: &F,
+    ) -> Result<()> {
+        let c_name = c_str(snapname)?;
+        let c_origin = origin.map(c_str).transpose()?;
+
+        check(unsafe {
+            sys::lzc_receive(
+                c_name.as_ptr(),
+                props.map_or(ptr::null_mut(), |x| x.as_ptr() as *mut _),
+                c_origin.as_ref().map_or(ptr::null(), |x| x.as_ptr()),
+                boolean(force),
+                boolean(raw),
+                fd.as_raw_fd(),
+            )
+        })
+    }
+    /// Runs a channel program, returning everything it output,
+    /// including the `return` or `error` pair.
+    ///
+    /// See `zcp::run_program` for a simpler interface.
+    pub fn channel_program(
+        &self,
+        pool: &str,
+        program: &str,
+        args: &nvpair::NvListRef,
+        instrlimit: u64,
+        memlimit: u64,
+    ) -> Result<nvpair::NvList> {
+        let c_pool = c_str(pool)?;
+        let c_program = c_str(program)?;
+        let mut out = ptr::null_mut();
+
+        let code = unsafe {
+            sys::lzc_channel_program(
+                c_pool.as_ptr(),
+                c_program.as_ptr(),
+                instrlimit,
+                memlimit,
+                args.as_ptr() as *mut _,
+                &mut out,
+            )
+        };
+
+        if out.is_null() {
+            check(code)?;
+
+            return Ok(nvpair::NvList::new()?);
+        }
+
+        Ok(unsafe { nvpair::NvList::from_ptr(out as *mut _) })
+    }
+    /// Whether a filesystem, volume, snapshot or bookmark exists.
+    pub fn exists(&self, name: &str) -> Result<bool> {
+        let c_name = c_str(name)?;
+
+        Ok(unsafe { sys::lzc_exists(c_name.as_ptr()) } == sys::boolean::B_TRUE)
+    }
+    /// Rolls a filesystem back to its latest snapshot,
+    /// returning the name of that snapshot.
+    pub fn rollback(&self, fsname: &str) -> Result<String> {
+        let c_name = c_str(fsname)?;
+        let mut buf = vec![0u8; sys::ZFS_MAX_DATASET_NAME_LEN as usize];
+
+        check(unsafe {
+            sys::lzc_rollback(
+                c_name.as_ptr(),
+                buf.as_mut_ptr() as *mut c_char,
+                buf.len() as c_int,
+            )
+        })?;
+
+        let snap = unsafe { CStr::from_ptr(buf.as_ptr() as *const c_char) };
+
+        Ok(snap.to_string_lossy().into_owned())
+    }
+}
+
+impl Drop for LibzfsCore {
+    fn drop(&mut self) {
+        unsafe { sys::libzfs_core_fini() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+
+    #[test]
+    fn send_flags() {
+        let x = SendFlags {
+            large_block: true,
+            compress: true,
+            ..SendFlags::default()
+        };
+
+        assert_eq!(x.to_raw(), 6);
+        assert_eq!(SendFlags::default().to_raw(), 0);
+
+        let x = SendFlags {
+            embed_data: true,
+            raw: true,
+            ..SendFlags::default()
+        };
+
+        assert_eq!(x.to_raw(), 9);
+    }
+
+    #[test]
+    fn fails_batch_with_item_errors() {
+        let mut errlist = nvpair::NvList::new().unwrap();
+        errlist.add_int32("test/ds@a", libc::EBUSY).unwrap();
+
+        let p = errlist.as_ptr();
+        mem::forget(errlist);
+
+        match batch_result(libc::EBUSY, p) {
+            Err(LibZfsError::Batch(xs)) => {
+                assert_eq!(xs.len(), 1);
+                assert_eq!(xs["test/ds@a"].raw_os_error(), Some(libc::EBUSY));
+            }
+            x => panic!("expected a batch error, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn reads_batch_outcome() {
+        assert!(batch_result(0, ptr::null_mut::<nvpair::NvListRef>()).is_ok());
+
+        match batch_result(libc::ENOENT, ptr::null_mut::<nvpair::NvListRef>()) {
+            Err(LibZfsError::Io(e)) => assert_eq!(e.raw_os_error(), Some(libc::ENOENT)),
+            x => panic!("expected an I/O error, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn dataset_exists() {
+        let z = LibzfsCore::new().unwrap();
+
+        assert!(z.exists("test/ds").unwrap());
+        assert!(!z.exists("test/missing").unwrap());
+    }
+}
@@ -0,0 +1,154 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Lazy dataset iteration
+//!
+//! `zfs_iter_filesystems` hands every child to a callback at once, so
+//! collecting a large pool holds a handle for each dataset in it.
+//! `DatasetIter` keeps only the names of datasets still to visit and
+//! opens each one as it is reached.
+//!
+
+extern crate libzfs_sys as sys;
+
+use libzfs::LibzfsHandle;
+use libzfs_types::{LibZfsError, Result};
+use std::ffi::{CStr, CString};
+use std::io::Error;
+use std::os::raw::{c_int, c_void};
+use std::sync::Arc;
+use zfs::Zfs;
+
+/// The datasets below a dataset, depth first, each before its children
+///
+/// Like `zfs list -r -d N -t TYPES`, only datasets of the wanted types
+/// are yielded, though every filesystem within the depth is walked.
+pub struct DatasetIter {
+    libzfs: Arc<LibzfsHandle>,
+    /// Datasets still to visit and their depth below the root, the next on top
+    pending: Vec<(CString, usize)>,
+    /// How far below the root to look, if limited
+    max_depth: Option<usize>,
+    /// The types of dataset to yield
+    types: sys::zfs_type_t,
+    /// A failure to list the children of the last dataset
+    error: Option<LibZfsError>,
+}
+
+unsafe extern "C" fn push_name(handle: *mut sys::zfs_handle_t, state: *mut c_void) -> c_int {
+    let state = &mut *(state as *mut Vec<CString>);
+
+    state.push(CStr::from_ptr(sys::zfs_get_name(handle)).to_owned());
+    sys::zfs_close(handle);
+
+    0
+}
+
+impl DatasetIter {
+    /// Every filesystem and volume below `root`.
+    pub(crate) fn new(root: &Zfs) -> Result<DatasetIter> {
+        DatasetIter::traverse(
+            root,
+            None,
+            sys::zfs_type_t::ZFS_TYPE_FILESYSTEM | sys::zfs_type_t::ZFS_TYPE_VOLUME,
+            false,
+        )
+    }
+    /// The datasets of `types` no more than `max_depth` below `root`,
+    /// starting with `root` itself when `include_root` is set.
+    pub(crate) fn traverse(
+        root: &Zfs,
+        max_depth: Option<usize>,
+        types: sys::zfs_type_t,
+        include_root: bool,
+    ) -> Result<DatasetIter> {
+        let mut x = DatasetIter {
+            libzfs: Arc::clone(&root.libzfs),
+            pending: vec![],
+            max_depth,
+            types,
+            error: None,
+        };
+
+        if include_root {
+            x.pending.push((root.name(), 0));
+        } else {
+            x.push_children(root.raw, 0)?;
+        }
+
+        Ok(x)
+    }
+    fn push_children(&mut self, h: *mut sys::zfs_handle_t, depth: usize) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        if self.max_depth.map_or(false, |x| depth >= x) {
+            return Ok(());
+        }
+
+        let mut names: Vec<CString> = Vec::new();
+        let state_ptr: *mut c_void = &mut names as *mut _ as *mut c_void;
+        let mut code = unsafe { sys::zfs_iter_filesystems(h, Some(push_name), state_ptr) };
+
+        if code == 0 && (self.types & sys::zfs_type_t::ZFS_TYPE_SNAPSHOT).0 != 0 {
+            code = unsafe {
+                sys::zfs_iter_snapshots(h, sys::boolean::B_FALSE, Some(push_name), state_ptr, 0, 0)
+            };
+        }
+
+        // Visit children in the order libzfs listed them
+        names.reverse();
+        self.pending
+            .extend(names.into_iter().map(|x| (x, depth + 1)));
+
+        match code {
+            0 => Ok(()),
+            x => Err(LibZfsError::Io(Error::from_raw_os_error(x))),
+        }
+    }
+}
+
+impl Iterator for DatasetIter {
+    type Item = Result<Zfs>;
+
+    fn next(&mut self) -> Option<Result<Zfs>> {
+        let _l = self.libzfs.lock();
+
+        loop {
+            if let Some(e) = self.error.take() {
+                return Some(Err(e));
+            }
+
+            let (name, depth) = self.pending.pop()?;
+
+            let h = unsafe {
+                sys::zfs_open(
+                    self.libzfs.raw(),
+                    name.as_ptr(),
+                    (sys::zfs_type_t::ZFS_TYPE_FILESYSTEM
+                        | sys::zfs_type_t::ZFS_TYPE_VOLUME
+                        | sys::zfs_type_t::ZFS_TYPE_SNAPSHOT)
+                        .0 as c_int,
+                )
+            };
+
+            // Destroyed since its parent was listed
+            if h.is_null() {
+                return Some(Err(LibZfsError::ZfsNotFound(
+                    name.to_string_lossy().into_owned(),
+                )));
+            }
+
+            let x = Zfs::new(Arc::clone(&self.libzfs), h);
+            let zfs_type = x.zfs_type();
+
+            if zfs_type != sys::zfs_type_t::ZFS_TYPE_SNAPSHOT {
+                self.error = self.push_children(h, depth).err();
+            }
+
+            if (zfs_type & self.types).0 != 0 {
+                return Some(Ok(x));
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Delegated administration, as with `zfs allow`
+//!
+//! ZFS stores delegations as an nvlist keyed by who they apply to, encoded
+//! as `<type><inheritance>$<id>`, with each value listing the granted
+//! permissions. Permission sets (names starting with `@`) are stored
+//! under a separate key type from plain permissions; `Delegation`
+//! hides that split.
+//!
+
+use libzfs_types::Result;
+use nvpair::{self, NvEncode};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Error, ErrorKind};
+
+/// Who a delegation applies to
+#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone, PartialOrd, Ord)]
+pub enum Who {
+    User(u32),
+    Group(u32),
+    Everyone,
+    /// The creator of a new descendent dataset
+    Create,
+    /// The definition of a permission set, such as `@snapshotters`
+    Set(String),
+}
+
+/// Where a delegation takes effect
+#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone, Copy, PartialOrd, Ord)]
+pub enum Scope {
+    Local,
+    Descendent,
+    /// Used for `Who::Create` and `Who::Set`, which have no scope
+    None,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct Delegation {
+    pub who: Who,
+    pub scope: Scope,
+    /// Permission names, such as `snapshot`, and permission sets, such as `@backup`
+    pub perms: BTreeSet<String>,
+}
+
+fn who_chars(who: &Who) -> (char, String) {
+    match *who {
+        Who::User(x) => ('u', x.to_string()),
+        Who::Group(x) => ('g', x.to_string()),
+        Who::Everyone => ('e', "".to_string()),
+        Who::Create => ('c', "".to_string()),
+        Who::Set(ref x) => ('s', x.clone()),
+    }
+}
+
+fn scope_char(scope: Scope) -> char {
+    match scope {
+        Scope::Local => 'l',
+        Scope::Descendent => 'd',
+        Scope::None => '-',
+    }
+}
+
+/// Formats the key a delegation is stored under.
+///
+/// `sets` selects the key for permission sets rather than plain permissions.
+pub fn who_key(who: &Who, scope: Scope, sets: bool) -> String {
+    let (t, id) = who_chars(who);
+    let t = if sets { t.to_ascii_uppercase() } else { t };
+
+    format!("{}{}${}", t, scope_char(scope), id)
+}
+
+/// Parses a stored key, returning who, the scope,
+/// and whether it holds permission sets.
+pub fn parse_who_key(key: &str) -> Option<(Who, Scope, bool)> {
+    let mut cs = key.chars();
+    let t = cs.next()?;
+    let s = cs.next()?;
+
+    if cs.next()? != '$' {
+        return None;
+    }
+
+    let id = cs.as_str();
+
+    let who = match t.to_ascii_lowercase() {
+        'u' => Who::User(id.parse().ok()?),
+        'g' => Who::Group(id.parse().ok()?),
+        'e' => Who::Everyone,
+        'c' => Who::Create,
+        's' => Who::Set(id.to_string()),
+        _ => return None,
+    };
+
+    let scope = match s {
+        'l' => Scope::Local,
+        'd' => Scope::Descendent,
+        '-' => Scope::None,
+        _ => return None,
+    };
+
+    Some((who, scope, t.is_ascii_uppercase()))
+}
+
+/// Reads the delegations stored on one dataset, merging
+/// permissions and permission sets for the same who and scope.
+pub fn decode(nvl: &nvpair::NvListRef) -> Result<Vec<Delegation>> {
+    let mut map: BTreeMap<(Who, Scope), BTreeSet<String>> = BTreeMap::new();
+
+    for x in nvl.iter() {
+        let key = x.name().to_string_lossy();
+
+        let (who, scope, _) = parse_who_key(&key).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("unknown delegation key {}", key),
+            )
+        })?;
+
+        let perms = x.value_nv_list_ref()?;
+
+        map.entry((who, scope)).or_default().extend(
+            perms
+                .iter()
+                .map(|p| p.name().to_string_lossy().into_owned()),
+        );
+    }
+
+    Ok(map
+        .into_iter()
+        .map(|((who, scope), perms)| Delegation { who, scope, perms })
+        .collect())
+}
+
+/// Builds the nvlist `zfs_set_fsacl` expects for `xs`.
+pub fn encode(xs: &[Delegation]) -> Result<nvpair::NvList> {
+    let mut nvl = nvpair::NvList::new()?;
+
+    for x in xs {
+        let (sets, perms): (Vec<&String>, Vec<&String>) =
+            x.perms.iter().partition(|p| p.starts_with('@'));
+
+        for (is_sets, names) in [(true, sets), (false, perms)] {
+            if names.is_empty() {
+                continue;
+            }
+
+            let mut inner = nvpair::NvList::new()?;
+
+            for n in names {
+                inner.add_boolean(n.as_str())?;
+            }
+
+            inner.insert(who_key(&x.who, x.scope, is_sets).as_str(), &mut nvl)?;
+        }
+    }
+
+    Ok(nvl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_keys() {
+        let xs = vec![
+            (Who::User(1000), Scope::Local, false, "ul$1000"),
+            (Who::Group(10), Scope::Descendent, true, "Gd$10"),
+            (Who::Everyone, Scope::Local, false, "el$"),
+            (Who::Create, Scope::None, false, "c-$"),
+            (
+                Who::Set("@snap".to_string()),
+                Scope::None,
+                false,
+                "s-$@snap",
+            ),
+        ];
+
+        for (who, scope, sets, key) in xs {
+            assert_eq!(who_key(&who, scope, sets), key);
+            assert_eq!(parse_who_key(key), Some((who, scope, sets)));
+        }
+
+        assert_eq!(parse_who_key("ul1000"), None);
+        assert_eq!(parse_who_key("ul$bob"), None);
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Parses the output of `zfs_show_diffs` into typed records.
+//!
+//! `zfs_show_diffs` writes to a file descriptor, so output is spooled
+//! to an unlinked temporary file and read back lazily.
+//!
+
+pub use libzfs_types::{InodeType, ZfsDiff};
+use libzfs_types::{LibZfsError, Result};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Lines};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static SPOOL_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Creates an unlinked temporary file, returning a
+/// write handle and a read handle to it.
+pub(crate) fn spool_file() -> Result<(File, File)> {
+    let n = SPOOL_COUNT.fetch_add(1, Ordering::SeqCst);
+    let path = env::temp_dir().join(format!("libzfs-diff-{}-{}", process::id(), n));
+
+    let writer = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    let reader = File::open(&path)?;
+    fs::remove_file(&path)?;
+
+    Ok((writer, reader))
+}
+
+fn parse_kind(s: &str) -> InodeType {
+    match s {
+        "B" => InodeType::BlockDevice,
+        "C" => InodeType::CharDevice,
+        "/" => InodeType::Directory,
+        ">" => InodeType::Door,
+        "|" => InodeType::Fifo,
+        "@" => InodeType::Symlink,
+        "P" => InodeType::EventPort,
+        "=" => InodeType::Socket,
+        "F" => InodeType::File,
+        _ => InodeType::Unknown,
+    }
+}
+
+/// Reverses the `\ooo` octal escaping libzfs applies to paths.
+fn unescape(s: &str) -> PathBuf {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4).and_then(|xs| {
+            if bytes[i] == b'\\' && xs.iter().all(|x| (b'0'..=b'7').contains(x)) {
+                Some(xs.iter().fold(0u32, |acc, x| acc * 8 + u32::from(x - b'0')))
+            } else {
+                None
+            }
+        });
+
+        match octal {
+            Some(x) if x <= 0xff => {
+                out.push(x as u8);
+                i += 4;
+            }
+            _ => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&out).into_owned().into()
+}
+
+/// Parses a single line of parseable, classified `zfs diff` output.
+pub fn parse_diff_line(line: &str) -> Result<ZfsDiff> {
+    let xs: Vec<&str> = line.split('\t').collect();
+
+    let invalid = || {
+        LibZfsError::Io(Error::new(
+            ErrorKind::InvalidData,
+            format!("could not parse diff line: {}", line),
+        ))
+    };
+
+    if xs.len() < 3 {
+        return Err(invalid());
+    }
+
+    let kind = parse_kind(xs[1]);
+    let path = unescape(xs[2]);
+
+    match xs[0] {
+        "+" => Ok(ZfsDiff::Added { path, kind }),
+        "-" => Ok(ZfsDiff::Removed { path, kind }),
+        "M" => Ok(ZfsDiff::Modified { path, kind }),
+        "R" => {
+            let to = xs.get(3).map(|x| unescape(x)).ok_or_else(invalid)?;
+
+            Ok(ZfsDiff::Renamed {
+                from: path,
+                to,
+                kind,
+            })
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// A lazy iterator over `ZfsDiff` records
+pub struct DiffIter {
+    lines: Lines<BufReader<File>>,
+}
+
+impl DiffIter {
+    pub(crate) fn new(f: File) -> DiffIter {
+        DiffIter {
+            lines: BufReader::new(f).lines(),
+        }
+    }
+}
+
+impl Iterator for DiffIter {
+    type Item = Result<ZfsDiff>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.lines.next()? {
+                Ok(ref x) if x.is_empty() => continue,
+                Ok(x) => return Some(parse_diff_line(&x)),
+                Err(e) => return Some(Err(LibZfsError::Io(e))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_records() {
+        assert_eq!(
+            parse_diff_line("+\tF\t/test/ds/a\\040file").unwrap(),
+            ZfsDiff::Added {
+                path: "/test/ds/a file".into(),
+                kind: InodeType::File,
+            }
+        );
+
+        assert_eq!(
+            parse_diff_line("M\t/\t/test/ds/dir\t(+1)").unwrap(),
+            ZfsDiff::Modified {
+                path: "/test/ds/dir".into(),
+                kind: InodeType::Directory,
+            }
+        );
+
+        assert_eq!(
+            parse_diff_line("R\t@\t/test/ds/old\t/test/ds/new").unwrap(),
+            ZfsDiff::Renamed {
+                from: "/test/ds/old".into(),
+                to: "/test/ds/new".into(),
+                kind: InodeType::Symlink,
+            }
+        );
+
+        assert!(parse_diff_line("garbage").is_err());
+    }
+}
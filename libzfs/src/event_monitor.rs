@@ -0,0 +1,221 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! ZEDLETs in Rust
+//!
+//! ZED runs each of its scripts for the events whose subclass the script
+//! is named after, or for every event if it is named `all`.
+//! `EventMonitor` reads events and does the same with the closures in
+//! its `Handlers`, handing each the event with its class and payload
+//! decoded into a `ZEventKind`.
+//!
+
+use events::{self, Events};
+use libzfs_types::{Result, ZEvent, ZEventKind};
+
+type Handler = Box<dyn FnMut(&ZEvent, &ZEventKind) + Send>;
+
+/// Closures registered for ZFS events, by subclass
+#[derive(Default)]
+pub struct Handlers {
+    /// Each handler and the subclass it is for, or `None` for every event
+    handlers: Vec<(Option<String>, Handler)>,
+}
+
+impl Handlers {
+    pub fn new() -> Handlers {
+        Handlers::default()
+    }
+    /// Calls `f` for each event of `subclass`, the part of its class
+    /// after `fs.zfs.`, such as `checksum`, `statechange` or `scrub_finish`.
+    pub fn on<F>(&mut self, subclass: &str, f: F) -> &mut Handlers
+    where
+        F: FnMut(&ZEvent, &ZEventKind) + Send + 'static,
+    {
+        self.handlers
+            .push((Some(subclass.to_string()), Box::new(f)));
+        self
+    }
+    /// Calls `f` for every event.
+    pub fn on_all<F>(&mut self, f: F) -> &mut Handlers
+    where
+        F: FnMut(&ZEvent, &ZEventKind) + Send + 'static,
+    {
+        self.handlers.push((None, Box::new(f)));
+        self
+    }
+    /// Calls the handlers for an event in the order they were registered,
+    /// returning how many there were.
+    pub fn dispatch(&mut self, event: &ZEvent, kind: &ZEventKind) -> usize {
+        let subclass = events::subclass(&event.class);
+        let mut n = 0;
+
+        for (x, f) in &mut self.handlers {
+            if x.as_ref().map_or(false, |x| x != subclass) {
+                continue;
+            }
+
+            f(event, kind);
+            n += 1;
+        }
+
+        n
+    }
+}
+
+/// Calls registered handlers for ZFS events as they arrive
+pub struct EventMonitor {
+    events: Events,
+    handlers: Handlers,
+}
+
+impl EventMonitor {
+    pub fn new() -> Result<EventMonitor> {
+        Ok(EventMonitor::from_events(Events::open()?))
+    }
+    pub fn from_events(events: Events) -> EventMonitor {
+        EventMonitor {
+            events,
+            handlers: Handlers::new(),
+        }
+    }
+    /// Calls `f` for each event of `subclass`, as with `Handlers::on`.
+    pub fn on<F>(&mut self, subclass: &str, f: F) -> &mut EventMonitor
+    where
+        F: FnMut(&ZEvent, &ZEventKind) + Send + 'static,
+    {
+        self.handlers.on(subclass, f);
+        self
+    }
+    /// Calls `f` for every event.
+    pub fn on_all<F>(&mut self, f: F) -> &mut EventMonitor
+    where
+        F: FnMut(&ZEvent, &ZEventKind) + Send + 'static,
+    {
+        self.handlers.on_all(f);
+        self
+    }
+    /// Handles the next event, returning `false` if
+    /// `block` is false and no event is queued.
+    pub fn handle_next(&mut self, block: bool) -> Result<bool> {
+        let nvl = match self.events.next_nvlist(block)? {
+            Some(x) => x,
+            None => return Ok(false),
+        };
+
+        let event = events::decode(&nvl)?;
+        let kind = events::decode_kind(&nvl)?;
+
+        self.handlers.dispatch(&event, &kind);
+
+        Ok(true)
+    }
+    /// Handles events as they arrive, returning only if one cannot be read.
+    pub fn run(&mut self) -> Result<()> {
+        loop {
+            self.handle_next(true)?;
+        }
+    }
+    /// The number of events the kernel discarded before they were read.
+    pub fn dropped(&self) -> u64 {
+        self.events.dropped()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nvpair::NvList;
+    use std::sync::{Arc, Mutex};
+
+    fn event(class: &str, eid: u64) -> NvList {
+        let mut nvl = NvList::new_unqiue_names().unwrap();
+        nvl.add_string("class", class).unwrap();
+        nvl.add_uint64("eid", eid).unwrap();
+        nvl.add_int64_array("time", &[1_500_000_000, 0]).unwrap();
+        nvl.add_string("pool", "test").unwrap();
+        nvl
+    }
+
+    fn dispatch(m: &mut Handlers, nvl: &NvList) -> usize {
+        m.dispatch(
+            &events::decode(nvl).unwrap(),
+            &events::decode_kind(nvl).unwrap(),
+        )
+    }
+
+    #[test]
+    fn dispatches_by_subclass() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let mut m = Handlers::new();
+
+        let s = Arc::clone(&seen);
+        m.on("checksum", move |e, _| {
+            s.lock().unwrap().push(format!("checksum {}", e.eid))
+        });
+
+        let s = Arc::clone(&seen);
+        m.on_all(move |e, _| s.lock().unwrap().push(format!("all {}", e.eid)));
+
+        let s = Arc::clone(&seen);
+        m.on("scrub_finish", move |e, _| {
+            s.lock().unwrap().push(format!("scrub_finish {}", e.eid))
+        });
+
+        assert_eq!(dispatch(&mut m, &event("ereport.fs.zfs.checksum", 1)), 2);
+        assert_eq!(
+            dispatch(&mut m, &event("sysevent.fs.zfs.scrub_finish", 2)),
+            2
+        );
+        assert_eq!(dispatch(&mut m, &event("ereport.fs.zfs.io", 3)), 1);
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec!["checksum 1", "all 1", "all 2", "scrub_finish 2", "all 3",]
+        );
+    }
+
+    #[test]
+    fn passes_decoded_kind() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let mut m = Handlers::new();
+
+        let s = Arc::clone(&seen);
+        m.on("io", move |e, k| {
+            s.lock().unwrap().push((e.pool.clone(), k.clone()))
+        });
+
+        let mut nvl = event("ereport.fs.zfs.io", 1);
+        nvl.add_int32("zio_err", 5).unwrap();
+        nvl.add_uint64("zio_offset", 4096).unwrap();
+
+        assert_eq!(dispatch(&mut m, &nvl), 1);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![(
+                Some("test".to_string()),
+                ZEventKind::Io {
+                    errno: Some(5),
+                    offset: Some(4096),
+                    size: None,
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn skips_unhandled_events() {
+        let mut m = Handlers::new();
+
+        m.on("vdev.open_failed", |_, _| {
+            panic!("called for another subclass")
+        });
+
+        assert_eq!(dispatch(&mut m, &event("ereport.fs.zfs.vdev", 1)), 0);
+        assert_eq!(
+            dispatch(&mut m, &event("sysevent.fs.zfs.pool_create", 2)),
+            0
+        );
+    }
+}
@@ -0,0 +1,306 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! ZFS events
+//!
+//! The kernel queues zevents for I/O errors, vdev state changes, finished
+//! scrubs and the like: the stream ZED and `zpool events` read. `Events`
+//! follows that stream from its own descriptor on `/dev/zfs`, starting
+//! with the oldest event the kernel still holds.
+//!
+
+extern crate libzfs_sys as sys;
+
+use libzfs::{self, Libzfs};
+use libzfs_types::{LibZfsError, Result};
+pub use libzfs_types::{ZEvent, ZEventKind};
+use nvpair::{self, ForeignType};
+use std::fs::{File, OpenOptions};
+use std::io::{Error, ErrorKind};
+use std::os::raw::c_int;
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+
+/// The ZFS control device
+pub const ZFS_DEV: &str = "/dev/zfs";
+
+const CLASS: &str = "class";
+const EID: &str = "eid";
+const TIME: &str = "time";
+const POOL: &str = "pool";
+/// Sysevents name the pool under this key instead of `pool`
+const POOL_NAME: &str = "pool_name";
+const POOL_GUID: &str = "pool_guid";
+const VDEV_GUID: &str = "vdev_guid";
+const VDEV_PATH: &str = "vdev_path";
+const ZIO_ERR: &str = "zio_err";
+const ZIO_OFFSET: &str = "zio_offset";
+const ZIO_SIZE: &str = "zio_size";
+const VDEV_STATE: &str = "vdev_state";
+const VDEV_LASTSTATE: &str = "vdev_laststate";
+
+/// Separates an event class from its subclass
+const ZFS_CLASS: &str = ".fs.zfs.";
+
+fn lookup_string(nvl: &nvpair::NvListRef, name: &str) -> Option<String> {
+    nvl.lookup_str(name)
+        .ok()
+        .map(|x| x.to_string_lossy().into_owned())
+}
+
+/// Decodes the common fields of an event.
+pub fn decode(nvl: &nvpair::NvListRef) -> Result<ZEvent> {
+    let time = nvl.lookup_int64_array(TIME)?;
+
+    if time.len() < 2 {
+        return Err(LibZfsError::Io(Error::new(
+            ErrorKind::InvalidData,
+            "event time is truncated",
+        )));
+    }
+
+    Ok(ZEvent {
+        class: lookup_string(nvl, CLASS)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "event has no class"))?,
+        eid: nvl.lookup_uint64(EID)?,
+        time_secs: time[0],
+        time_nsecs: time[1],
+        pool: lookup_string(nvl, POOL).or_else(|| lookup_string(nvl, POOL_NAME)),
+        pool_guid: nvl.lookup_uint64(POOL_GUID).ok(),
+        vdev_guid: nvl.lookup_uint64(VDEV_GUID).ok(),
+        vdev_path: lookup_string(nvl, VDEV_PATH),
+    })
+}
+
+fn split_class(class: &str) -> Option<(&str, &str)> {
+    class
+        .find(ZFS_CLASS)
+        .map(|i| (&class[..i], &class[i + ZFS_CLASS.len()..]))
+}
+
+/// The part of a class after `fs.zfs.`, such as `checksum`
+/// or `vdev.open_failed`, as ZED names its scripts by.
+pub fn subclass(class: &str) -> &str {
+    split_class(class).map_or(class, |(_, x)| x)
+}
+
+/// Decodes what an event reports.
+pub fn decode_kind(nvl: &nvpair::NvListRef) -> Result<ZEventKind> {
+    let class = lookup_string(nvl, CLASS)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "event has no class"))?;
+
+    let x = match split_class(&class) {
+        Some(("ereport", "checksum")) => ZEventKind::Checksum {
+            offset: nvl.lookup_uint64(ZIO_OFFSET).ok(),
+            size: nvl.lookup_uint64(ZIO_SIZE).ok(),
+        },
+        Some(("ereport", "io")) => ZEventKind::Io {
+            errno: nvl.lookup_int32(ZIO_ERR).ok(),
+            offset: nvl.lookup_uint64(ZIO_OFFSET).ok(),
+            size: nvl.lookup_uint64(ZIO_SIZE).ok(),
+        },
+        Some(("ereport", "probe_failure")) => ZEventKind::ProbeFailure,
+        Some(("resource", "statechange")) => ZEventKind::StateChange {
+            state: nvl.lookup_uint64(VDEV_STATE).ok(),
+            previous: nvl.lookup_uint64(VDEV_LASTSTATE).ok(),
+        },
+        Some(("ereport", x)) => ZEventKind::Ereport(x.to_string()),
+        Some(("resource", x)) => ZEventKind::Resource(x.to_string()),
+        Some(("sysevent", x)) => ZEventKind::Sysevent(x.to_string()),
+        _ => ZEventKind::Other(class.clone()),
+    };
+
+    Ok(x)
+}
+
+/// A subscription to ZFS events
+///
+/// Iterating blocks until the next event arrives.
+pub struct Events {
+    libzfs: Libzfs,
+    dev: File,
+    dropped: u64,
+}
+
+impl Events {
+    pub fn open() -> Result<Events> {
+        Ok(Events {
+            libzfs: Libzfs::new(),
+            dev: OpenOptions::new().read(true).write(true).open(ZFS_DEV)?,
+            dropped: 0,
+        })
+    }
+    /// The number of events the kernel discarded before they were read,
+    /// because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+    /// Reads the next event undecoded, or `None` if
+    /// `block` is false and no event is queued.
+    pub fn next_nvlist(&mut self, block: bool) -> Result<Option<nvpair::NvList>> {
+        let _l = self.libzfs.handle().lock();
+
+        let mut nvl = ptr::null_mut();
+        let mut dropped: c_int = 0;
+
+        let flags = if block {
+            sys::ZEVENT_NONE
+        } else {
+            sys::ZEVENT_NONBLOCK
+        };
+
+        let code = unsafe {
+            sys::zpool_events_next(
+                self.libzfs.raw,
+                &mut nvl,
+                &mut dropped,
+                flags,
+                self.dev.as_raw_fd(),
+            )
+        };
+
+        if code != 0 {
+            return Err(LibZfsError::Zfs(libzfs::zfs_error(self.libzfs.raw)));
+        }
+
+        self.dropped += dropped as u64;
+
+        if nvl.is_null() {
+            Ok(None)
+        } else {
+            Ok(Some(unsafe { nvpair::NvList::from_ptr(nvl) }))
+        }
+    }
+    /// Decodes the next event, or returns `None` if no event is queued.
+    pub fn try_next(&mut self) -> Result<Option<ZEvent>> {
+        match self.next_nvlist(false)? {
+            Some(x) => decode(&x).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Iterator for Events {
+    type Item = Result<ZEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_nvlist(true) {
+            Ok(Some(x)) => Some(decode(&x)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nvpair::NvEncode;
+    use std::ffi::CString;
+
+    fn event(class: &str) -> nvpair::NvList {
+        let mut nvl = nvpair::NvList::new().unwrap();
+
+        CString::new(class)
+            .unwrap()
+            .as_c_str()
+            .insert(CLASS, &mut nvl)
+            .unwrap();
+
+        nvl
+    }
+
+    #[test]
+    fn decodes_event_kind() {
+        let mut nvl = event("ereport.fs.zfs.io");
+        5i32.insert(ZIO_ERR, &mut nvl).unwrap();
+        4096u64.insert(ZIO_OFFSET, &mut nvl).unwrap();
+
+        assert_eq!(
+            decode_kind(&nvl).unwrap(),
+            ZEventKind::Io {
+                errno: Some(5),
+                offset: Some(4096),
+                size: None,
+            }
+        );
+
+        let mut nvl = event("resource.fs.zfs.statechange");
+        5u64.insert(VDEV_STATE, &mut nvl).unwrap();
+        7u64.insert(VDEV_LASTSTATE, &mut nvl).unwrap();
+
+        assert_eq!(
+            decode_kind(&nvl).unwrap(),
+            ZEventKind::StateChange {
+                state: Some(5),
+                previous: Some(7),
+            }
+        );
+
+        assert_eq!(
+            decode_kind(&event("ereport.fs.zfs.vdev.open_failed")).unwrap(),
+            ZEventKind::Ereport("vdev.open_failed".to_string())
+        );
+        assert_eq!(
+            decode_kind(&event("sysevent.fs.zfs.scrub_finish")).unwrap(),
+            ZEventKind::Sysevent("scrub_finish".to_string())
+        );
+        assert_eq!(subclass("ereport.fs.zfs.checksum"), "checksum");
+    }
+
+    #[test]
+    fn decodes_event() {
+        let mut nvl = event("ereport.fs.zfs.checksum");
+
+        CString::new("test")
+            .unwrap()
+            .as_c_str()
+            .insert(POOL, &mut nvl)
+            .unwrap();
+        7u64.insert(EID, &mut nvl).unwrap();
+        12u64.insert(POOL_GUID, &mut nvl).unwrap();
+        [1_500_000_000i64, 5][..].insert(TIME, &mut nvl).unwrap();
+
+        assert_eq!(
+            decode(&nvl).unwrap(),
+            ZEvent {
+                class: "ereport.fs.zfs.checksum".to_string(),
+                eid: 7,
+                time_secs: 1_500_000_000,
+                time_nsecs: 5,
+                pool: Some("test".to_string()),
+                pool_guid: Some(12),
+                vdev_guid: None,
+                vdev_path: None,
+            }
+        );
+    }
+
+    #[test]
+    fn decodes_sysevent_pool_name() {
+        // Shaped like the scrub_finish sysevent `zpool events -v` shows
+        let mut nvl = event("sysevent.fs.zfs.scrub_finish");
+
+        CString::new("test")
+            .unwrap()
+            .as_c_str()
+            .insert(POOL_NAME, &mut nvl)
+            .unwrap();
+        12u64.insert(POOL_GUID, &mut nvl).unwrap();
+        0u64.insert("pool_state", &mut nvl).unwrap();
+        CString::new("ESC_ZFS_scrub_finish")
+            .unwrap()
+            .as_c_str()
+            .insert("history_internal_name", &mut nvl)
+            .unwrap();
+        8u64.insert(EID, &mut nvl).unwrap();
+        [1_500_000_000i64, 5][..].insert(TIME, &mut nvl).unwrap();
+
+        let x = decode(&nvl).unwrap();
+
+        assert_eq!(x.pool, Some("test".to_string()));
+        assert_eq!(x.pool_guid, Some(12));
+        assert_eq!(x.eid, 8);
+    }
+}
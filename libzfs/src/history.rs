@@ -0,0 +1,154 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Reads pool history into typed records.
+//!
+//! `zpool_get_history` returns every record at once as an nvlist array;
+//! records are decoded lazily as the iterator is advanced.
+//!
+
+extern crate libzfs_sys as sys;
+
+use libzfs_types::Result;
+pub use libzfs_types::{HistoryEvent, HistoryRecord};
+use nvpair;
+use std::io::{Error, ErrorKind};
+use std::vec;
+
+fn lookup_string(nvl: &nvpair::NvListRef, name: String) -> Option<String> {
+    nvl.lookup_str(name)
+        .ok()
+        .map(|x| x.to_string_lossy().into_owned())
+}
+
+/// Decodes a single history record.
+pub fn decode(nvl: &nvpair::NvListRef) -> Result<HistoryRecord> {
+    let event = if let Some(x) = lookup_string(nvl, sys::zpool_hist_cmd()) {
+        HistoryEvent::Command(x)
+    } else if let Some(x) = lookup_string(nvl, sys::zpool_hist_ioctl()) {
+        HistoryEvent::Ioctl(x)
+    } else if let Some(info) = lookup_string(nvl, sys::zpool_hist_int_str()) {
+        let name = lookup_string(nvl, sys::zpool_hist_int_name())
+            .or_else(|| {
+                nvl.lookup_uint64(sys::zpool_hist_int_event())
+                    .ok()
+                    .map(|x| format!("event {}", x))
+            })
+            .unwrap_or_default();
+
+        HistoryEvent::Internal { name, info }
+    } else {
+        return Err(Error::new(ErrorKind::InvalidData, "unknown history record").into());
+    };
+
+    Ok(HistoryRecord {
+        time: nvl.lookup_uint64(sys::zpool_hist_time())?,
+        event,
+        txg: nvl.lookup_uint64(sys::zpool_hist_txg()).ok(),
+        who: nvl
+            .lookup_uint64(sys::zpool_hist_who())
+            .ok()
+            .map(|x| x as u32),
+        host: lookup_string(nvl, sys::zpool_hist_host()),
+        dataset: lookup_string(nvl, sys::zpool_hist_dsname()),
+    })
+}
+
+/// An iterator over `HistoryRecord`s, oldest first
+pub struct HistoryIter {
+    records: vec::IntoIter<nvpair::NvList>,
+}
+
+impl HistoryIter {
+    pub(crate) fn new(nvl: &nvpair::NvListRef) -> HistoryIter {
+        let records = nvl
+            .lookup_nv_list_array(sys::zpool_hist_record())
+            .unwrap_or_default();
+
+        HistoryIter {
+            records: records.into_iter(),
+        }
+    }
+}
+
+impl Iterator for HistoryIter {
+    type Item = Result<HistoryRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next().map(|x| decode(&x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nvpair::NvEncode;
+    use std::ffi::CString;
+
+    fn record(pairs: &[(String, &str)], ints: &[(String, u64)]) -> nvpair::NvList {
+        let mut nvl = nvpair::NvList::new().unwrap();
+
+        for (k, v) in pairs {
+            CString::new(*v)
+                .unwrap()
+                .as_c_str()
+                .insert(k.as_str(), &mut nvl)
+                .unwrap();
+        }
+
+        for (k, v) in ints {
+            v.insert(k.as_str(), &mut nvl).unwrap();
+        }
+
+        nvl
+    }
+
+    #[test]
+    fn decodes_records() {
+        let cmd = record(
+            &[
+                (sys::zpool_hist_cmd(), "zpool create test sdb"),
+                (sys::zpool_hist_host(), "oss1"),
+            ],
+            &[(sys::zpool_hist_time(), 100), (sys::zpool_hist_who(), 0)],
+        );
+
+        assert_eq!(
+            decode(&cmd).unwrap(),
+            HistoryRecord {
+                time: 100,
+                event: HistoryEvent::Command("zpool create test sdb".to_string()),
+                txg: None,
+                who: Some(0),
+                host: Some("oss1".to_string()),
+                dataset: None,
+            }
+        );
+
+        let internal = record(
+            &[
+                (sys::zpool_hist_int_name(), "snapshot"),
+                (sys::zpool_hist_int_str(), ""),
+                (sys::zpool_hist_dsname(), "test/fs@a"),
+            ],
+            &[(sys::zpool_hist_time(), 101), (sys::zpool_hist_txg(), 9)],
+        );
+
+        let x = decode(&internal).unwrap();
+
+        assert_eq!(
+            x.event,
+            HistoryEvent::Internal {
+                name: "snapshot".to_string(),
+                info: "".to_string(),
+            }
+        );
+        assert_eq!(x.txg, Some(9));
+        assert_eq!(x.dataset.as_deref(), Some("test/fs@a"));
+
+        let bad = record(&[], &[(sys::zpool_hist_time(), 102)]);
+
+        assert!(decode(&bad).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Pool auto-import policy for daemon startup
+//!
+//! An `ImportPolicy` decides which of the importable pools get imported
+//! when an embedding daemon starts. Pools that look like they are in use
+//! by another host are never forced. Every pool considered is reported
+//! back along with what happened to it.
+//!
+
+extern crate libzfs_sys as sys;
+
+use libzfs::{Libzfs, SearchOptions};
+use libzfs_types::Result;
+use nvpair;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// Which pools to import
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Leave every pool alone
+    Never,
+    /// Import every pool found
+    All,
+    /// Import only the pools with these guids
+    Guids { guids: BTreeSet<u64> },
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct ImportPolicy {
+    pub mode: ImportMode,
+    /// Discover pools from this cachefile instead of scanning devices
+    #[serde(default)]
+    pub cachefile: Option<PathBuf>,
+}
+
+/// An importable pool as described by its config
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct Candidate {
+    pub name: String,
+    pub guid: u64,
+    pub state: u64,
+    pub hostid: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub enum Outcome {
+    Imported,
+    Skipped(String),
+    Failed(String),
+}
+
+/// What happened to a single pool
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct ImportRecord {
+    pub name: String,
+    pub guid: u64,
+    pub outcome: Outcome,
+}
+
+impl Candidate {
+    pub fn from_config(config: &nvpair::NvListRef) -> Result<Candidate> {
+        Ok(Candidate {
+            name: config
+                .lookup_string(sys::zpool_config_pool_name())?
+                .into_string()?,
+            guid: config.lookup_uint64(sys::zpool_config_guid())?,
+            state: config.lookup_uint64(sys::zpool_config_pool_state())?,
+            hostid: config.lookup_uint64(sys::zpool_config_hostid()).ok(),
+        })
+    }
+    /// Whether the pool was destroyed but can still be recovered
+    pub fn destroyed(&self) -> bool {
+        self.state == u64::from(sys::pool_state::POOL_STATE_DESTROYED)
+    }
+}
+
+/// Decides whether `candidate` should be imported on a host with `hostid`.
+///
+/// Returns the reason for skipping it, if any.
+pub fn decide(mode: &ImportMode, candidate: &Candidate, hostid: u64) -> Option<String> {
+    match mode {
+        ImportMode::Never => return Some("policy imports no pools".to_string()),
+        ImportMode::Guids { guids } if !guids.contains(&candidate.guid) => {
+            return Some("guid is not in the import list".to_string())
+        }
+        _ => {}
+    };
+
+    if candidate.destroyed() {
+        return Some("pool was destroyed".to_string());
+    }
+
+    match candidate.hostid {
+        Some(x)
+            if x != 0
+                && x != hostid
+                && candidate.state != u64::from(sys::pool_state::POOL_STATE_EXPORTED) =>
+        {
+            Some(format!("pool may be in use from host {:x}", x))
+        }
+        _ => None,
+    }
+}
+
+/// Applies `policy`, returning a record for every pool found.
+pub fn run(libzfs: &mut Libzfs, policy: &ImportPolicy) -> Result<Vec<ImportRecord>> {
+    let hostid = unsafe { sys::get_system_hostid() };
+
+    let pools = libzfs.search_pools(&SearchOptions {
+        cachefile: policy.cachefile.clone(),
+        ..SearchOptions::default()
+    })?;
+
+    pools
+        .iter()
+        .map(|x| {
+            let config = x.value_nv_list_ref()?;
+            let candidate = Candidate::from_config(config)?;
+
+            let outcome = match decide(&policy.mode, &candidate, hostid) {
+                Some(reason) => Outcome::Skipped(reason),
+                None => match libzfs.import(config) {
+                    Ok(()) => Outcome::Imported,
+                    Err(e) => Outcome::Failed(format!("{}", e)),
+                },
+            };
+
+            Ok(ImportRecord {
+                name: candidate.name,
+                guid: candidate.guid,
+                outcome,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(guid: u64, state: sys::pool_state_t, hostid: Option<u64>) -> Candidate {
+        Candidate {
+            name: "test".to_string(),
+            guid,
+            state: u64::from(state),
+            hostid,
+        }
+    }
+
+    #[test]
+    fn filters_by_mode() {
+        let c = candidate(1, sys::pool_state::POOL_STATE_EXPORTED, None);
+
+        assert!(decide(&ImportMode::Never, &c, 7).is_some());
+        assert_eq!(decide(&ImportMode::All, &c, 7), None);
+
+        let guids = ImportMode::Guids {
+            guids: vec![2].into_iter().collect(),
+        };
+
+        assert!(decide(&guids, &c, 7).is_some());
+        assert_eq!(
+            decide(
+                &guids,
+                &candidate(2, sys::pool_state::POOL_STATE_EXPORTED, None),
+                7
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn never_forces() {
+        let active = candidate(1, sys::pool_state::POOL_STATE_ACTIVE, Some(8));
+
+        assert!(decide(&ImportMode::All, &active, 7).is_some());
+        assert_eq!(decide(&ImportMode::All, &active, 8), None);
+
+        let exported = candidate(1, sys::pool_state::POOL_STATE_EXPORTED, Some(8));
+
+        assert_eq!(decide(&ImportMode::All, &exported, 7), None);
+    }
+
+    #[test]
+    fn skips_destroyed() {
+        let c = candidate(1, sys::pool_state::POOL_STATE_DESTROYED, None);
+
+        assert!(c.destroyed());
+        assert!(decide(&ImportMode::All, &c, 7).is_some());
+        assert!(!candidate(1, sys::pool_state::POOL_STATE_EXPORTED, None).destroyed());
+    }
+}
@@ -0,0 +1,354 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Differential dataset inventories
+//!
+//! Rather than shipping a full dump of every `Pool` on each change, an
+//! agent can send an `InventoryDelta` against the inventory it sent last.
+//! Datasets are matched by guid and `createtxg`, so renames show up as
+//! changes rather than a remove and an add. Inventories from `collect`
+//! include snapshots, so taking or destroying one is a change too.
+//!
+
+extern crate libzfs_sys as sys;
+
+use libzfs::Libzfs;
+use libzfs_types::{Dataset, LibZfsError, Pool, Result, ZProp};
+use serde_derive::{Deserialize, Serialize};
+use state::{convert_to_dataset, convert_to_full_pool};
+use std::collections::BTreeMap;
+
+/// Captures every imported pool as an inventory, with its properties
+/// and every filesystem, volume and snapshot in it.
+pub fn collect(libzfs: &mut Libzfs) -> Result<Vec<Pool>> {
+    libzfs
+        .get_imported_pools()?
+        .iter_mut()
+        .map(|p| {
+            let mut pool = convert_to_full_pool(p)?;
+
+            for x in p.traverse(None, sys::zfs_type_t::ZFS_TYPE_SNAPSHOT)? {
+                pool.datasets.push(convert_to_dataset(&x?)?);
+            }
+
+            Ok(pool)
+        })
+        .collect()
+}
+
+/// The pool a dataset or snapshot name is in.
+fn pool_name(name: &str) -> &str {
+    name.split(['/', '@']).next().unwrap_or(name)
+}
+
+/// Identifies a dataset across inventories
+#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone, PartialOrd, Ord)]
+pub struct DatasetKey {
+    pub guid: String,
+    pub createtxg: Option<String>,
+}
+
+impl DatasetKey {
+    pub fn of(ds: &Dataset) -> DatasetKey {
+        DatasetKey {
+            guid: ds.guid.clone(),
+            createtxg: ds
+                .props
+                .iter()
+                .find(|x| x.name == "createtxg")
+                .map(|x| x.value.clone()),
+        }
+    }
+}
+
+/// A property whose value differs between inventories
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct PropDelta {
+    pub name: String,
+    /// `None` when the property is no longer present
+    pub value: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct DatasetChange {
+    pub key: DatasetKey,
+    /// The current name, which may differ after a rename
+    pub name: String,
+    pub props: Vec<PropDelta>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct InventoryDelta {
+    pub added: Vec<Dataset>,
+    pub removed: Vec<DatasetKey>,
+    pub changed: Vec<DatasetChange>,
+}
+
+fn index(pools: &[Pool]) -> BTreeMap<DatasetKey, &Dataset> {
+    pools
+        .iter()
+        .flat_map(|p| p.datasets.iter())
+        .map(|x| (DatasetKey::of(x), x))
+        .collect()
+}
+
+fn prop_deltas(prev: &[ZProp], next: &[ZProp]) -> Vec<PropDelta> {
+    let prev: BTreeMap<&str, &str> = prev
+        .iter()
+        .map(|x| (x.name.as_str(), x.value.as_str()))
+        .collect();
+    let next: BTreeMap<&str, &str> = next
+        .iter()
+        .map(|x| (x.name.as_str(), x.value.as_str()))
+        .collect();
+
+    let mut xs: Vec<PropDelta> = next
+        .iter()
+        .filter(|(k, v)| prev.get(*k) != Some(v))
+        .map(|(k, v)| PropDelta {
+            name: k.to_string(),
+            value: Some(v.to_string()),
+        })
+        .collect();
+
+    xs.extend(
+        prev.keys()
+            .filter(|k| !next.contains_key(*k))
+            .map(|k| PropDelta {
+                name: k.to_string(),
+                value: None,
+            }),
+    );
+
+    xs
+}
+
+impl InventoryDelta {
+    /// Computes the changes needed to turn `prev` into `next`.
+    pub fn between(prev: &[Pool], next: &[Pool]) -> InventoryDelta {
+        let prev = index(prev);
+        let next = index(next);
+
+        let added = next
+            .iter()
+            .filter(|(k, _)| !prev.contains_key(k))
+            .map(|(_, v)| (*v).clone())
+            .collect();
+
+        let removed = prev
+            .keys()
+            .filter(|k| !next.contains_key(k))
+            .cloned()
+            .collect();
+
+        let changed = next
+            .iter()
+            .filter_map(|(k, n)| {
+                let p = prev.get(k)?;
+                let props = prop_deltas(&p.props, &n.props);
+
+                if props.is_empty() && p.name == n.name {
+                    None
+                } else {
+                    Some(DatasetChange {
+                        key: k.clone(),
+                        name: n.name.clone(),
+                        props,
+                    })
+                }
+            })
+            .collect();
+
+        InventoryDelta {
+            added,
+            removed,
+            changed,
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+    /// Applies this delta to `pools`, as held by the receiving side.
+    ///
+    /// Added datasets go to the pool named by their first path component.
+    /// If that pool is not in `pools`, nothing is applied and the error
+    /// is `LibZfsError::PoolNotFound` naming it.
+    pub fn apply(&self, pools: &mut [Pool]) -> Result<()> {
+        if let Some(x) = self
+            .added
+            .iter()
+            .map(|x| pool_name(&x.name))
+            .find(|x| !pools.iter().any(|p| p.name == *x))
+        {
+            return Err(LibZfsError::PoolNotFound(Some(x.to_string()), None));
+        }
+
+        for p in pools.iter_mut() {
+            p.datasets
+                .retain(|x| !self.removed.contains(&DatasetKey::of(x)));
+
+            for ds in p.datasets.iter_mut() {
+                let key = DatasetKey::of(ds);
+
+                if let Some(c) = self.changed.iter().find(|c| c.key == key) {
+                    ds.name = c.name.clone();
+
+                    for d in &c.props {
+                        ds.props.retain(|x| x.name != d.name);
+
+                        if let Some(ref value) = d.value {
+                            ds.props.push(ZProp {
+                                name: d.name.clone(),
+                                value: value.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+
+            for ds in &self.added {
+                if pool_name(&ds.name) == p.name {
+                    p.datasets.push(ds.clone());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libzfs_types::VDev;
+
+    fn dataset(name: &str, guid: &str, compression: &str) -> Dataset {
+        Dataset {
+            name: name.to_string(),
+            guid: guid.to_string(),
+            kind: "filesystem".to_string(),
+            props: vec![
+                ZProp {
+                    name: "createtxg".to_string(),
+                    value: "1".to_string(),
+                },
+                ZProp {
+                    name: "compression".to_string(),
+                    value: compression.to_string(),
+                },
+            ],
+        }
+    }
+
+    fn pool(datasets: Vec<Dataset>) -> Pool {
+        Pool {
+            name: "test".to_string(),
+            guid: 1,
+            health: "ONLINE".to_string(),
+            hostname: "localhost".to_string(),
+            hostid: None,
+            state: "ACTIVE".to_string(),
+            readonly: false,
+            size: "0".to_string(),
+            vdev: VDev::Root {
+                children: vec![],
+                spares: vec![],
+                cache: vec![],
+                logs: vec![],
+                special: vec![],
+                dedup: vec![],
+            },
+            props: vec![],
+            datasets,
+        }
+    }
+
+    fn sorted(mut xs: Vec<Dataset>) -> Vec<Dataset> {
+        for x in xs.iter_mut() {
+            x.props.sort();
+        }
+        xs.sort_by(|a, b| a.guid.cmp(&b.guid));
+
+        xs
+    }
+
+    #[test]
+    fn round_trips() {
+        let prev = vec![pool(vec![
+            dataset("test/a", "1", "off"),
+            dataset("test/b", "2", "off"),
+            dataset("test/c", "3", "off"),
+        ])];
+
+        let next = vec![pool(vec![
+            dataset("test/a", "1", "off"),
+            dataset("test/b2", "2", "lz4"),
+            dataset("test/d", "4", "off"),
+        ])];
+
+        let delta = InventoryDelta::between(&prev, &next);
+
+        assert_eq!(delta.added.len(), 1);
+        assert_eq!(delta.removed.len(), 1);
+        assert_eq!(delta.changed.len(), 1);
+
+        let mut applied = prev.clone();
+        delta.apply(&mut applied).unwrap();
+
+        assert_eq!(
+            sorted(applied[0].datasets.clone()),
+            sorted(next[0].datasets.clone())
+        );
+        assert!(InventoryDelta::between(&next, &next).is_empty());
+    }
+
+    #[test]
+    fn tracks_snapshots() {
+        let prev = vec![pool(vec![
+            dataset("test/a", "1", "off"),
+            dataset("test/a@daily", "2", "off"),
+        ])];
+
+        let next = vec![pool(vec![
+            dataset("test/a", "1", "off"),
+            dataset("test@initial", "3", "off"),
+            dataset("test/a@hourly", "4", "off"),
+        ])];
+
+        let delta = InventoryDelta::between(&prev, &next);
+
+        assert_eq!(delta.added.len(), 2);
+        assert_eq!(delta.removed, vec![DatasetKey::of(&prev[0].datasets[1])]);
+
+        let mut applied = prev.clone();
+        delta.apply(&mut applied).unwrap();
+
+        assert_eq!(
+            sorted(applied[0].datasets.clone()),
+            sorted(next[0].datasets.clone())
+        );
+    }
+
+    #[test]
+    fn rejects_datasets_of_unknown_pools() {
+        let prev = vec![pool(vec![dataset("test/a", "1", "off")])];
+
+        let delta = InventoryDelta {
+            added: vec![
+                dataset("test/b", "2", "off"),
+                dataset("other/c", "3", "off"),
+            ],
+            ..InventoryDelta::default()
+        };
+
+        let mut applied = prev.clone();
+
+        match delta.apply(&mut applied) {
+            Err(LibZfsError::PoolNotFound(Some(ref x), None)) if x == "other" => {}
+            x => panic!("expected PoolNotFound, got {:?}", x),
+        }
+
+        assert_eq!(applied, prev);
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Persistent event journal
+//!
+//! Appends events as JSON lines to a size-capped file on disk.
+//! When the active file would grow past `max_bytes` it is rotated
+//! to `<path>.1`, `<path>.1` to `<path>.2` and so on, keeping at most
+//! `max_files` rotated files around.
+//!
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_derive::{Deserialize, Serialize};
+use serde_json;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A single journaled event along with the time it was recorded.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct JournalEntry<T> {
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+    pub event: T,
+}
+
+pub struct Journal {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    len: u64,
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut s = path.as_os_str().to_owned();
+    s.push(format!(".{}", n));
+
+    s.into()
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl Journal {
+    /// Opens (or creates) the journal at `path`.
+    ///
+    /// The active file is kept under `max_bytes` and at most `max_files`
+    /// rotated files are retained.
+    pub fn open<P: AsRef<Path>>(path: P, max_bytes: u64, max_files: usize) -> io::Result<Journal> {
+        let path = path.as_ref().to_path_buf();
+        let file = open_append(&path)?;
+        let len = file.metadata()?.len();
+
+        Ok(Journal {
+            path,
+            max_bytes,
+            max_files,
+            file,
+            len,
+        })
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Appends an event to the journal, rotating first if needed.
+    pub fn append<T: Serialize>(&mut self, event: &T) -> io::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let mut line = serde_json::to_vec(&JournalEntry { timestamp, event })?;
+        line.push(b'\n');
+
+        if self.len > 0 && self.len + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        self.file.write_all(&line)?;
+        self.file.flush()?;
+        self.len += line.len() as u64;
+
+        Ok(())
+    }
+    /// Forces a rotation of the active file.
+    pub fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = rotated_path(&self.path, self.max_files);
+
+            if oldest.exists() {
+                fs::remove_file(&oldest)?;
+            }
+
+            for n in (1..self.max_files).rev() {
+                let from = rotated_path(&self.path, n);
+
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, n + 1))?;
+                }
+            }
+
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+
+        self.file = open_append(&self.path)?;
+        self.len = 0;
+
+        Ok(())
+    }
+    /// Reads back every entry in the journal at `path`,
+    /// oldest rotated file first.
+    pub fn entries<T: DeserializeOwned, P: AsRef<Path>>(
+        path: P,
+    ) -> io::Result<Vec<JournalEntry<T>>> {
+        let path = path.as_ref();
+
+        let mut files: Vec<PathBuf> = (1..)
+            .map(|n| rotated_path(path, n))
+            .take_while(|p| p.exists())
+            .collect();
+        files.reverse();
+        files.push(path.to_path_buf());
+
+        let mut xs = vec![];
+
+        for p in files.iter().filter(|p| p.exists()) {
+            for line in BufReader::new(File::open(p)?).lines() {
+                let line = line?;
+
+                if line.is_empty() {
+                    continue;
+                }
+
+                xs.push(serde_json::from_str(&line)?);
+            }
+        }
+
+        Ok(xs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    fn journal_path(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("libzfs-journal-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        dir.join("events.json")
+    }
+
+    #[test]
+    fn append_and_read_back() {
+        let p = journal_path("append");
+        let mut j = Journal::open(&p, 1024, 2).unwrap();
+
+        j.append(&"first").unwrap();
+        j.append(&"second").unwrap();
+
+        let xs: Vec<JournalEntry<String>> = Journal::entries(&p).unwrap();
+
+        assert_eq!(
+            xs.into_iter().map(|x| x.event).collect::<Vec<_>>(),
+            vec!["first", "second"]
+        );
+    }
+
+    #[test]
+    fn rotates_and_caps_files() {
+        let p = journal_path("rotate");
+        let mut j = Journal::open(&p, 40, 2).unwrap();
+
+        for x in 0..10u32 {
+            j.append(&x).unwrap();
+        }
+
+        assert!(rotated_path(&p, 1).exists());
+        assert!(rotated_path(&p, 2).exists());
+        assert!(!rotated_path(&p, 3).exists());
+
+        let xs: Vec<JournalEntry<u32>> = Journal::entries(&p).unwrap();
+        let events: Vec<u32> = xs.into_iter().map(|x| x.event).collect();
+
+        assert_eq!(events.last(), Some(&9));
+        assert!(events.windows(2).all(|w| w[0] < w[1]));
+    }
+}
@@ -0,0 +1,394 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! ZFS kstats, behind the `kstat` feature
+//!
+//! The SPL publishes ZFS counters, globally and for each imported pool,
+//! as text files under `/proc/spl/kstat/zfs`. Reading them is far
+//! cheaper than going through libzfs and needs no handle, which suits
+//! agents that sample every few seconds.
+//!
+
+use libzfs_types::{LibZfsError, Result};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use support_bundle::KSTAT_DIR;
+
+fn invalid(msg: &str) -> LibZfsError {
+    LibZfsError::Io(Error::new(ErrorKind::InvalidData, msg))
+}
+
+/// The `name` and `data` of each line of a named kstat,
+/// after its header and column titles.
+fn named_rows(s: &str) -> Result<Vec<(&str, &str)>> {
+    let mut lines = s.lines();
+
+    match (lines.next(), lines.next()) {
+        (Some(_), Some(x)) if x.split_whitespace().eq(vec!["name", "type", "data"]) => {}
+        _ => return Err(invalid("not a named kstat")),
+    }
+
+    Ok(lines
+        .filter_map(|x| {
+            let (name, rest) = split_field(x.trim_start())?;
+            let (_, data) = split_field(rest)?;
+
+            Some((name, data.trim_end()))
+        })
+        .collect())
+}
+
+/// Splits off the first field of a line. What is left may itself hold
+/// spaces, as dataset names can.
+fn split_field(x: &str) -> Option<(&str, &str)> {
+    let i = x.find(char::is_whitespace)?;
+
+    Some((&x[..i], x[i..].trim_start()))
+}
+
+/// Parses a named kstat: a header line, a line of column titles, then
+/// a `name type data` line per counter.
+///
+/// Counters that are not integers are left out.
+pub fn parse_named(s: &str) -> Result<BTreeMap<String, u64>> {
+    Ok(named_rows(s)?
+        .into_iter()
+        .filter_map(|(name, data)| data.parse().ok().map(|x| (name.to_string(), x)))
+        .collect())
+}
+
+/// ARC counters from `arcstats`, sizes in bytes
+///
+/// Counters this version of ZFS does not report are 0.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct ArcStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub demand_data_hits: u64,
+    pub demand_data_misses: u64,
+    pub demand_metadata_hits: u64,
+    pub demand_metadata_misses: u64,
+    pub prefetch_data_hits: u64,
+    pub prefetch_data_misses: u64,
+    pub prefetch_metadata_hits: u64,
+    pub prefetch_metadata_misses: u64,
+    /// Hits on blocks used once recently
+    pub mru_hits: u64,
+    /// Hits on blocks just evicted from the MRU list
+    pub mru_ghost_hits: u64,
+    /// Hits on blocks used more than once
+    pub mfu_hits: u64,
+    /// Hits on blocks just evicted from the MFU list
+    pub mfu_ghost_hits: u64,
+    pub size: u64,
+    /// The size the ARC is aiming for
+    pub c: u64,
+    pub c_min: u64,
+    pub c_max: u64,
+    /// The part of `c` aimed at the MRU list
+    pub p: u64,
+    pub mru_size: u64,
+    pub mfu_size: u64,
+    pub data_size: u64,
+    pub metadata_size: u64,
+    pub l2_hits: u64,
+    pub l2_misses: u64,
+    pub l2_size: u64,
+}
+
+impl ArcStats {
+    /// Reads the current counters.
+    pub fn read() -> Result<ArcStats> {
+        ArcStats::parse(&fs::read_to_string(Path::new(KSTAT_DIR).join("arcstats"))?)
+    }
+    /// Parses the contents of `arcstats`.
+    pub fn parse(s: &str) -> Result<ArcStats> {
+        let xs = parse_named(s)?;
+        let get = |name: &str| xs.get(name).cloned().unwrap_or(0);
+
+        Ok(ArcStats {
+            hits: get("hits"),
+            misses: get("misses"),
+            demand_data_hits: get("demand_data_hits"),
+            demand_data_misses: get("demand_data_misses"),
+            demand_metadata_hits: get("demand_metadata_hits"),
+            demand_metadata_misses: get("demand_metadata_misses"),
+            prefetch_data_hits: get("prefetch_data_hits"),
+            prefetch_data_misses: get("prefetch_data_misses"),
+            prefetch_metadata_hits: get("prefetch_metadata_hits"),
+            prefetch_metadata_misses: get("prefetch_metadata_misses"),
+            mru_hits: get("mru_hits"),
+            mru_ghost_hits: get("mru_ghost_hits"),
+            mfu_hits: get("mfu_hits"),
+            mfu_ghost_hits: get("mfu_ghost_hits"),
+            size: get("size"),
+            c: get("c"),
+            c_min: get("c_min"),
+            c_max: get("c_max"),
+            p: get("p"),
+            mru_size: get("mru_size"),
+            mfu_size: get("mfu_size"),
+            data_size: get("data_size"),
+            metadata_size: get("metadata_size"),
+            l2_hits: get("l2_hits"),
+            l2_misses: get("l2_misses"),
+            l2_size: get("l2_size"),
+        })
+    }
+    /// The share of lookups served from the ARC, if there were any.
+    pub fn hit_ratio(&self) -> Option<f64> {
+        match self.hits + self.misses {
+            0 => None,
+            n => Some(self.hits as f64 / n as f64),
+        }
+    }
+}
+
+/// Reads a kstat with a row of column titles and a row of values.
+fn parse_table(s: &str) -> Result<BTreeMap<&str, u64>> {
+    let mut lines = s.lines().skip(1);
+
+    match (lines.next(), lines.next()) {
+        (Some(names), Some(values)) => names
+            .split_whitespace()
+            .zip(values.split_whitespace())
+            .map(|(k, v)| {
+                v.parse()
+                    .map(|v| (k, v))
+                    .map_err(|_| invalid("kstat value is not an integer"))
+            })
+            .collect(),
+        _ => Err(invalid("kstat is truncated")),
+    }
+}
+
+/// I/O through a pool since import, from its `io` kstat
+///
+/// Times are in nanoseconds. The `w` fields cover I/O waiting to be
+/// issued and the `r` fields I/O in flight, so `rlentime / reads`
+/// approximates the mean read latency.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct PoolIoStats {
+    /// Bytes read
+    pub nread: u64,
+    /// Bytes written
+    pub nwritten: u64,
+    pub reads: u64,
+    pub writes: u64,
+    /// Time with I/O waiting
+    pub wtime: u64,
+    /// Time waited, summed over each waiting I/O
+    pub wlentime: u64,
+    pub wupdate: u64,
+    /// Time with I/O in flight
+    pub rtime: u64,
+    /// Time in flight, summed over each I/O
+    pub rlentime: u64,
+    pub rupdate: u64,
+    /// I/Os waiting now
+    pub wcnt: u64,
+    /// I/Os in flight now
+    pub rcnt: u64,
+}
+
+impl PoolIoStats {
+    /// Parses the contents of a pool's `io` kstat.
+    pub fn parse(s: &str) -> Result<PoolIoStats> {
+        let xs = parse_table(s)?;
+        let get = |name: &str| {
+            xs.get(name)
+                .cloned()
+                .ok_or_else(|| invalid(&format!("io kstat has no {}", name)))
+        };
+
+        Ok(PoolIoStats {
+            nread: get("nread")?,
+            nwritten: get("nwritten")?,
+            reads: get("reads")?,
+            writes: get("writes")?,
+            wtime: get("wtime")?,
+            wlentime: get("wlentime")?,
+            wupdate: get("wupdate")?,
+            rtime: get("rtime")?,
+            rlentime: get("rlentime")?,
+            rupdate: get("rupdate")?,
+            wcnt: get("wcnt")?,
+            rcnt: get("rcnt")?,
+        })
+    }
+}
+
+/// Operations on a dataset since it was mounted, from an `objset-0x..` kstat
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct ObjsetStats {
+    pub objset_id: u64,
+    pub dataset_name: String,
+    pub writes: u64,
+    /// Bytes written
+    pub nwritten: u64,
+    pub reads: u64,
+    /// Bytes read
+    pub nread: u64,
+    /// Files queued to be unlinked
+    pub nunlinks: u64,
+    /// Files unlinked
+    pub nunlinked: u64,
+}
+
+impl ObjsetStats {
+    /// Parses the contents of the kstat for objset `objset_id`.
+    pub fn parse(objset_id: u64, s: &str) -> Result<ObjsetStats> {
+        let rows: BTreeMap<&str, &str> = named_rows(s)?.into_iter().collect();
+        let get = |name: &str| rows.get(name).and_then(|x| x.parse().ok()).unwrap_or(0);
+
+        Ok(ObjsetStats {
+            objset_id,
+            dataset_name: rows
+                .get("dataset_name")
+                .ok_or_else(|| invalid("objset kstat has no dataset_name"))?
+                .to_string(),
+            writes: get("writes"),
+            nwritten: get("nwritten"),
+            reads: get("reads"),
+            nread: get("nread"),
+            nunlinks: get("nunlinks"),
+            nunlinked: get("nunlinked"),
+        })
+    }
+}
+
+/// The I/O kstats of a pool and its mounted datasets
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct PoolKstats {
+    pub pool: String,
+    /// `None` where ZFS no longer publishes the `io` kstat
+    pub io: Option<PoolIoStats>,
+    /// By objset id
+    pub objsets: Vec<ObjsetStats>,
+}
+
+impl PoolKstats {
+    /// Reads the kstats of an imported pool.
+    pub fn read(pool: &str) -> Result<PoolKstats> {
+        PoolKstats::read_dir(pool, &Path::new(KSTAT_DIR).join(pool))
+    }
+    /// Reads pool kstats from `dir`, laid out as under `KSTAT_DIR`.
+    pub fn read_dir(pool: &str, dir: &Path) -> Result<PoolKstats> {
+        let io = match fs::read_to_string(dir.join("io")) {
+            Ok(x) => Some(PoolIoStats::parse(&x)?),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut objsets = vec![];
+
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+
+            let id = match name
+                .to_str()
+                .and_then(|x| x.strip_prefix("objset-0x"))
+                .and_then(|x| u64::from_str_radix(x, 16).ok())
+            {
+                Some(x) => x,
+                None => continue,
+            };
+
+            objsets.push(ObjsetStats::parse(id, &fs::read_to_string(entry.path())?)?);
+        }
+
+        objsets.sort_by_key(|x| x.objset_id);
+
+        Ok(PoolKstats {
+            pool: pool.to_string(),
+            io,
+            objsets,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARCSTATS: &str = "13 1 0x01 96 26112 3829428549 2243474470889
+name                            type data
+hits                            4    900
+misses                          4    100
+mru_hits                        4    300
+mfu_hits                        4    600
+size                            4    1048576
+c_max                           4    4194304
+arc_no_grow                     1    0
+";
+
+    #[test]
+    fn parses_arcstats() {
+        let x = ArcStats::parse(ARCSTATS).unwrap();
+
+        assert_eq!(x.hits, 900);
+        assert_eq!(x.mru_hits, 300);
+        assert_eq!(x.mfu_hits, 600);
+        assert_eq!(x.size, 1_048_576);
+        assert_eq!(x.c_max, 4_194_304);
+        assert_eq!(x.l2_hits, 0);
+        assert_eq!(x.hit_ratio(), Some(0.9));
+
+        assert!(ArcStats::parse("hits 4 900\n").is_err());
+    }
+
+    #[test]
+    fn parses_pool_io() {
+        let x = PoolIoStats::parse(
+            "12 3 0x00 1 80 2229512985 2360289838830
+nread    nwritten reads    writes   wtime    wlentime wupdate  rtime    rlentime rupdate  wcnt     rcnt
+4096     8192     1        2        10       20       30       40       50       60       0        1
+",
+        )
+        .unwrap();
+
+        assert_eq!(x.nread, 4096);
+        assert_eq!(x.writes, 2);
+        assert_eq!(x.rlentime, 50);
+        assert_eq!(x.rcnt, 1);
+
+        assert!(PoolIoStats::parse("12 3 0x00 1 80 2229512985 2360289838830\n").is_err());
+    }
+
+    #[test]
+    fn parses_objset() {
+        let x = ObjsetStats::parse(
+            0x36,
+            "49 1 0x01 7 2160 5248873455 163598577421
+name                            type data
+dataset_name                    7    test/my ds
+writes                          4    3
+nwritten                        4    12288
+reads                           4    5
+nread                           4    20480
+nunlinks                        4    0
+nunlinked                       4    0
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            x,
+            ObjsetStats {
+                objset_id: 0x36,
+                dataset_name: "test/my ds".to_string(),
+                writes: 3,
+                nwritten: 12288,
+                reads: 5,
+                nread: 20480,
+                nunlinks: 0,
+                nunlinked: 0,
+            }
+        );
+    }
+}
@@ -5,7 +5,10 @@
 //! libzfs — Rusty wrapper around libzfs-sys.
 //!
 
+#[macro_use]
+extern crate serde;
 extern crate serde_derive;
+extern crate serde_json;
 
 #[macro_use]
 extern crate foreign_types;
@@ -13,28 +16,102 @@ extern crate foreign_types;
 #[macro_use]
 extern crate lazy_static;
 
+extern crate flate2;
+extern crate libc;
+
+#[cfg(feature = "async")]
+extern crate tokio;
+
 extern crate libzfs_sys as sys;
 
 pub extern crate libzfs_types;
 
-mod nvpair;
+pub mod nv_serde;
+pub mod nvpair;
+pub use nv_serde::{from_nvlist, to_nvlist};
 
 pub use libzfs_types::*;
 
 pub mod vdev;
 pub use vdev::VDev;
+pub mod vdev_path;
+pub use vdev_path::PathResolver;
+pub mod vdev_spec;
+pub use vdev_spec::VDevSpec;
 
 pub mod zprop_list;
 pub use zprop_list::ZProp;
 
+pub mod delegation;
+pub use delegation::Delegation;
+
+pub mod diff;
+pub use diff::DiffIter;
+
+pub mod dataset_iter;
+pub use dataset_iter::DatasetIter;
+
 pub mod zfs;
 pub use zfs::Zfs;
 
+pub mod clone_graph;
+pub use clone_graph::CloneGraph;
+
+pub mod zfs_prop;
+pub use zfs_prop::ZfsProp;
+
+pub mod zvol;
+
+pub mod history;
+pub use history::HistoryIter;
+
+pub mod events;
+pub use events::Events;
+pub mod event_monitor;
+pub use event_monitor::EventMonitor;
+
 pub mod zpool;
 pub use zpool::Zpool;
 
 pub mod libzfs;
-pub use libzfs::Libzfs;
+pub use libzfs::{HandleGuard, ImportOptions, Libzfs, LibzfsHandle, RewindPolicy, SearchOptions};
 
 pub mod state;
 pub use state::*;
+
+#[cfg(feature = "async")]
+pub mod r#async;
+
+pub mod reconcile;
+
+pub mod inventory;
+pub use inventory::InventoryDelta;
+
+pub mod profile;
+pub use profile::Profile;
+
+pub mod zcp;
+
+pub mod core;
+pub use core::LibzfsCore;
+
+pub mod import_policy;
+
+pub mod collisions;
+
+pub mod pool_lock;
+pub use pool_lock::PoolLock;
+
+pub mod lustre;
+
+pub mod journal;
+pub use journal::Journal;
+
+pub mod support_bundle;
+pub use support_bundle::SupportBundle;
+
+#[cfg(feature = "kstat")]
+pub mod kstat;
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
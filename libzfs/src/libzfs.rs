@@ -4,25 +4,320 @@
 
 extern crate libzfs_sys as sys;
 
-use libzfs_types::{LibZfsError, Result};
+use core::LibzfsCore;
+use libc;
+use libzfs_types::{LibZfsError, Result, ZfsError, ZfsErrorKind, ZfsState};
 use nvpair;
-use nvpair::ForeignType;
-use std::ffi::CString;
-use std::io::Error;
-use std::os::raw::{c_int, c_void};
+use nvpair::{ForeignType, NvEncode};
+use serde_derive::{Deserialize, Serialize};
+use state;
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{Error, ErrorKind};
+use std::marker::PhantomData;
+use std::os::raw::{c_char, c_int, c_void};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
 use std::ptr;
-use std::sync::Mutex;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+use support_bundle::SupportBundle;
+use vdev_spec::VDevSpec;
 use zfs::Zfs;
 use zpool::Zpool;
 
+/// Classifies a `zfs_error_t` code.
+///
+/// `EZFS_NOENT` is used for both pools and datasets;
+/// it is taken to be a dataset here.
+pub(crate) fn error_kind(errno: u32) -> ZfsErrorKind {
+    match errno {
+        sys::zfs_error_EZFS_NOENT => ZfsErrorKind::DatasetNotFound,
+        sys::zfs_error_EZFS_BUSY => ZfsErrorKind::DatasetBusy,
+        sys::zfs_error_EZFS_ACTIVE_POOL => ZfsErrorKind::PoolActive,
+        sys::zfs_error_EZFS_EXISTS => ZfsErrorKind::AlreadyExists,
+        sys::zfs_error_EZFS_BADPROP | sys::zfs_error_EZFS_PROPTYPE => ZfsErrorKind::NoSuchProperty,
+        sys::zfs_error_EZFS_PERM | sys::zfs_error_EZFS_NODELEGATION => {
+            ZfsErrorKind::PermissionDenied
+        }
+        sys::zfs_error_EZFS_POOLUNAVAIL => ZfsErrorKind::PoolIoSuspended,
+        sys::zfs_error_EZFS_NOSPC | sys::zfs_error_EZFS_PROPSPACE => ZfsErrorKind::NoSpace,
+        sys::zfs_error_EZFS_DSREADONLY
+        | sys::zfs_error_EZFS_POOLREADONLY
+        | sys::zfs_error_EZFS_PROPREADONLY => ZfsErrorKind::ReadOnly,
+        sys::zfs_error_EZFS_INVALIDNAME
+        | sys::zfs_error_EZFS_NAMETOOLONG
+        | sys::zfs_error_EZFS_BADWHO
+        | sys::zfs_error_EZFS_BADPERM
+        | sys::zfs_error_EZFS_BADPERMSET => ZfsErrorKind::InvalidName,
+        sys::zfs_error_EZFS_NOTSUP
+        | sys::zfs_error_EZFS_POOL_NOTSUP
+        | sys::zfs_error_EZFS_VDEVNOTSUP
+        | sys::zfs_error_EZFS_BADVERSION
+        | sys::zfs_error_EZFS_IOC_NOTSUPPORTED
+        | sys::zfs_error_EZFS_TRIM_NOTSUP => ZfsErrorKind::NotSupported,
+        sys::zfs_error_EZFS_NODEVICE => ZfsErrorKind::DeviceNotFound,
+        sys::zfs_error_EZFS_BADDEV
+        | sys::zfs_error_EZFS_BADTARGET
+        | sys::zfs_error_EZFS_INVALCONFIG
+        | sys::zfs_error_EZFS_NOREPLICAS => ZfsErrorKind::InvalidDevice,
+        sys::zfs_error_EZFS_IO => ZfsErrorKind::Io,
+        _ => ZfsErrorKind::Other,
+    }
+}
+
+/// Reads the error libzfs recorded on `h` for the last failed call.
+pub(crate) fn zfs_error(h: *mut sys::libzfs_handle_t) -> ZfsError {
+    let errno = unsafe { sys::libzfs_errno(h) } as u32;
+
+    unsafe {
+        ZfsError {
+            errno,
+            kind: error_kind(errno),
+            action: CStr::from_ptr(sys::libzfs_error_action(h))
+                .to_string_lossy()
+                .into_owned(),
+            description: CStr::from_ptr(sys::libzfs_error_description(h))
+                .to_string_lossy()
+                .into_owned(),
+        }
+    }
+}
+
+/// As `zfs_error`, for calls that act on a pool, so that
+/// `EZFS_NOENT` means the pool could not be found.
+pub(crate) fn pool_error(h: *mut sys::libzfs_handle_t) -> ZfsError {
+    let mut e = zfs_error(h);
+
+    if e.kind == ZfsErrorKind::DatasetNotFound {
+        e.kind = ZfsErrorKind::PoolNotFound;
+    }
+
+    e
+}
+
 lazy_static! {
+    /// Serializes the non-reentrant parts of libzfs, such as pool discovery.
     pub static ref LOCK: Mutex<()> = Mutex::new(());
+    /// Serializes `libzfs_init` and `libzfs_fini`, which set up and
+    /// tear down state shared by every handle in the process.
+    static ref SETUP: Mutex<()> = Mutex::new(());
+    /// The handle behind `Libzfs::shared`, opened on first use.
+    static ref SHARED: Mutex<Option<Arc<LibzfsHandle>>> = Mutex::new(None);
 }
 
-pub struct Libzfs {
+/// Takes `m` even if a previous holder panicked,
+/// so one failed caller does not wedge every other handle.
+fn lock<'a, T>(m: &'a Mutex<T>) -> MutexGuard<'a, T> {
+    m.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Where `Libzfs::search_pools` looks for importable pools
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchOptions {
+    /// Directories of devices to scan, such as `/dev/disk/by-id` or
+    /// `/dev/mapper`, instead of the default locations
+    pub dirs: Vec<PathBuf>,
+    /// Reads pool configs from this cachefile instead of scanning
+    /// devices, as with `zpool import -c`
+    pub cachefile: Option<PathBuf>,
+    /// Finds only destroyed pools that can still be recovered,
+    /// as with `zpool import -D`, rather than only live ones
+    pub destroyed: bool,
+}
+
+/// How far back to look for a usable txg when a pool cannot be opened,
+/// as with the `-F`, `-n` and `-X` options of `zpool import`
+///
+/// It is passed in the config's `load-policy`, the key ZFS 0.8 reads;
+/// ZFS 0.7 named it `rewind-policy`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RewindPolicy {
+    /// Open the pool as it is
+    #[default]
+    None,
+    /// Check whether rewinding would make the pool importable, without rewinding
+    Try,
+    /// Discard the last few transactions if that makes the pool importable
+    Do,
+    /// Like `Do`, but search much further back
+    Extreme,
+}
+
+impl RewindPolicy {
+    fn raw(self) -> u32 {
+        match self {
+            RewindPolicy::None => sys::ZPOOL_NO_REWIND,
+            RewindPolicy::Try => sys::ZPOOL_TRY_REWIND,
+            RewindPolicy::Do => sys::ZPOOL_DO_REWIND,
+            RewindPolicy::Extreme => sys::ZPOOL_DO_REWIND | sys::ZPOOL_EXTREME_REWIND,
+        }
+    }
+}
+
+/// How `Libzfs::import_with` imports a pool
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportOptions {
+    /// Imports under this root, without updating the cachefile
+    pub altroot: Option<PathBuf>,
+    /// Mounts the pool's datasets once it is imported
+    pub mount: bool,
+    pub readonly: bool,
+    /// Imports even if the pool may be in use from another host
+    pub force: bool,
+    /// Imports the pool under this name instead of its own
+    pub new_name: Option<String>,
+    pub rewind: RewindPolicy,
+    /// Rewinds the pool to its checkpoint, discarding the checkpoint
+    pub checkpoint: bool,
+}
+
+/// A lock that the thread holding it may take again,
+/// since methods that use a handle call one another
+#[derive(Default)]
+struct HandleLock {
+    /// The thread holding the lock and how many times it has taken it
+    owner: Mutex<(Option<ThreadId>, usize)>,
+    released: Condvar,
+}
+
+impl HandleLock {
+    fn acquire(&self) {
+        let me = thread::current().id();
+        let mut owner = lock(&self.owner);
+
+        loop {
+            match *owner {
+                (Some(x), ref mut n) if x == me => {
+                    *n += 1;
+                    return;
+                }
+                (None, _) => {
+                    *owner = (Some(me), 1);
+                    return;
+                }
+                _ => {
+                    owner = self.released.wait(owner).unwrap_or_else(|e| e.into_inner());
+                }
+            }
+        }
+    }
+    fn release(&self) {
+        let mut owner = lock(&self.owner);
+
+        owner.1 -= 1;
+
+        if owner.1 == 0 {
+            owner.0 = None;
+            self.released.notify_one();
+        }
+    }
+}
+
+/// Keeps other threads off a `LibzfsHandle` until dropped
+pub struct HandleGuard {
+    lock: Arc<HandleLock>,
+    /// Must be released on the thread that took it
+    _thread: PhantomData<*const ()>,
+}
+
+impl Drop for HandleGuard {
+    fn drop(&mut self) {
+        self.lock.release();
+    }
+}
+
+/// An open `libzfs_handle_t`
+///
+/// The handle is closed once the last `Libzfs`, `Zpool` or `Zfs`
+/// holding it is dropped, so pools and datasets can outlive
+/// the `Libzfs` they were opened from.
+pub struct LibzfsHandle {
     raw: *mut sys::libzfs_handle_t,
+    lock: Arc<HandleLock>,
 }
 
+// Nothing in a libzfs handle is tied to the thread that opened it.
+// Global setup and pool discovery are serialized by `SETUP` and `LOCK`,
+// and every other use of `raw` in this crate is made under `lock`.
+unsafe impl Send for LibzfsHandle {}
+unsafe impl Sync for LibzfsHandle {}
+
+impl LibzfsHandle {
+    fn open() -> LibzfsHandle {
+        let raw = {
+            let _l = lock(&SETUP);
+
+            unsafe { sys::libzfs_init() }
+        };
+
+        LibzfsHandle {
+            raw,
+            lock: Arc::new(HandleLock::default()),
+        }
+    }
+    pub fn raw(&self) -> *mut sys::libzfs_handle_t {
+        self.raw
+    }
+    /// Waits until no other thread is using the handle, and keeps
+    /// them off it until the guard is dropped.
+    ///
+    /// libzfs does no locking of its own, so every call on the handle,
+    /// or on a pool or dataset opened from it, must be made under this.
+    /// The thread holding the guard may take it again.
+    pub fn lock(&self) -> HandleGuard {
+        self.lock.acquire();
+
+        HandleGuard {
+            lock: Arc::clone(&self.lock),
+            _thread: PhantomData,
+        }
+    }
+}
+
+impl fmt::Debug for LibzfsHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LibzfsHandle")
+            .field("raw", &self.raw)
+            .finish()
+    }
+}
+
+impl PartialEq for LibzfsHandle {
+    fn eq(&self, other: &LibzfsHandle) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl Drop for LibzfsHandle {
+    fn drop(&mut self) {
+        let _l = lock(&SETUP);
+
+        unsafe { sys::libzfs_fini(self.raw) }
+    }
+}
+
+/// A handle to libzfs
+///
+/// libzfs keeps per-handle state, such as the last error and its caches
+/// of pools and mounts, without any locking of its own.
+///
+/// The `Zpool` and `Zfs` handles opened from a `Libzfs` share its state,
+/// so every call that uses it, on any of them, holds `LibzfsHandle::lock`
+/// throughout. They may all be moved to other threads and used at once;
+/// calls on the same handle simply wait their turn.
+pub struct Libzfs {
+    handle: Arc<LibzfsHandle>,
+    pub(crate) raw: *mut sys::libzfs_handle_t,
+}
+
+// Every use of `raw` is made under the handle's lock.
+unsafe impl Send for Libzfs {}
+
 impl Default for Libzfs {
     fn default() -> Self {
         Libzfs::new()
@@ -30,12 +325,42 @@ impl Default for Libzfs {
 }
 
 impl Libzfs {
+    /// Opens a handle to libzfs.
+    ///
+    /// Handles may be opened and dropped from several threads at once;
+    /// setup and teardown of the library's global state is serialized.
     pub fn new() -> Libzfs {
+        Libzfs::from_handle(Arc::new(LibzfsHandle::open()))
+    }
+    /// A handle to the process-wide libzfs handle, opened on first use
+    /// and kept open for the life of the process.
+    ///
+    /// This avoids setting up and tearing down libzfs for every caller.
+    /// Every `Libzfs` returned shares the same state, so calls made through
+    /// them from different threads are serialized by `LibzfsHandle::lock`.
+    pub fn shared() -> Libzfs {
+        let mut shared = lock(&SHARED);
+
+        let handle = shared
+            .get_or_insert_with(|| Arc::new(LibzfsHandle::open()))
+            .clone();
+
+        Libzfs::from_handle(handle)
+    }
+    pub fn from_handle(handle: Arc<LibzfsHandle>) -> Libzfs {
         Libzfs {
-            raw: unsafe { sys::libzfs_init() },
+            raw: handle.raw(),
+            handle,
         }
     }
+    /// The underlying handle, which the pools and datasets
+    /// opened from this `Libzfs` keep alive.
+    pub fn handle(&self) -> &Arc<LibzfsHandle> {
+        &self.handle
+    }
     pub fn pool_by_name(&mut self, name: &str) -> Option<Zpool> {
+        let _l = self.handle.lock();
+
         unsafe {
             let pool_name = CString::new(name).unwrap();
 
@@ -44,11 +369,13 @@ impl Libzfs {
             if pool.is_null() {
                 None
             } else {
-                Some(Zpool::new(pool))
+                Some(Zpool::new(self.handle.clone(), pool))
             }
         }
     }
     pub fn dataset_by_name(&mut self, name: &str) -> Option<Zfs> {
+        let _l = self.handle.lock();
+
         unsafe {
             let x = CString::new(name).unwrap();
             let name = x.into_raw();
@@ -59,15 +386,62 @@ impl Libzfs {
             if ds.is_null() {
                 None
             } else {
-                Some(Zfs::new(ds))
+                Some(Zfs::new(self.handle.clone(), ds))
             }
         }
     }
     pub fn find_importable_pools(&mut self) -> nvpair::NvList {
-        let _l = LOCK.lock().unwrap();
+        self.search_import(&[], None)
+    }
+    /// Finds importable pools as `find_importable_pools` does,
+    /// searching where `opts` says.
+    ///
+    /// Each pool's state is in its config under `ZPOOL_CONFIG_POOL_STATE`.
+    pub fn search_pools(&mut self, opts: &SearchOptions) -> Result<nvpair::NvList> {
+        let _l = self.handle.lock();
+
+        let dirs = opts
+            .dirs
+            .iter()
+            .map(|x| CString::new(x.as_os_str().as_bytes()))
+            .collect::<::std::result::Result<Vec<_>, _>>()
+            .map_err(Error::from)?;
+
+        let cachefile = match opts.cachefile {
+            Some(ref x) => Some(CString::new(x.as_os_str().as_bytes()).map_err(Error::from)?),
+            None => None,
+        };
+
+        let found = self.search_import(&dirs, cachefile.as_deref());
+        let mut pools = nvpair::NvList::new()?;
+
+        for x in found.iter() {
+            let config = x.value_nv_list_ref()?;
+            let destroyed = config.lookup_uint64(sys::zpool_config_pool_state())?
+                == u64::from(sys::pool_state::POOL_STATE_DESTROYED);
+
+            if destroyed == opts.destroyed {
+                config.insert(x.name(), &mut pools)?;
+            }
+        }
+
+        Ok(pools)
+    }
+    fn search_import(&mut self, dirs: &[CString], cachefile: Option<&CStr>) -> nvpair::NvList {
+        let _l = self.handle.lock();
+
+        let mut paths: Vec<*mut c_char> = dirs.iter().map(|x| x.as_ptr() as *mut _).collect();
+
+        let _l = lock(&LOCK);
         unsafe {
             sys::thread_init();
             let mut args = sys::import_args();
+            args.cachefile = cachefile.map_or(ptr::null_mut(), |x| x.as_ptr() as *mut _);
+
+            if !paths.is_empty() {
+                args.path = paths.as_mut_ptr();
+                args.paths = paths.len() as c_int;
+            }
 
             let x = sys::zpool_search_import(self.raw, &mut args as *mut sys::importargs);
             sys::thread_fini();
@@ -75,67 +449,562 @@ impl Libzfs {
             nvpair::NvList::from_ptr(x)
         }
     }
+    /// Imports every pool in the result of `find_importable_pools`.
+    ///
+    /// A failure is reported as `LibZfsError::PoolImport`
+    /// naming the pool that could not be imported.
     pub fn import_all(&mut self, nvl: &nvpair::NvList) -> Result<Vec<()>> {
         nvl.iter()
             .map(|x| {
-                let nvl2 = x.value_nv_list()?;
-
-                let code = unsafe {
-                    sys::zpool_import(
-                        self.raw,
-                        nvl2.as_ptr() as *mut _,
-                        ptr::null(),
-                        ptr::null_mut(),
-                    )
-                };
-
-                match code {
-                    0 => Ok(()),
-                    x => Err(LibZfsError::Io(Error::from_raw_os_error(x))),
-                }
+                self.import(x.value_nv_list_ref()?)
+                    .map_err(|e| LibZfsError::PoolImport {
+                        pool: x.name().to_string_lossy().into_owned(),
+                        source: Box::new(e),
+                    })
             })
             .collect()
     }
+    /// Imports a single pool given its config from `find_importable_pools`.
+    pub fn import(&mut self, config: &nvpair::NvListRef) -> Result<()> {
+        self.import_with(config, &ImportOptions::default())
+    }
+    /// Imports a pool given its config from `find_importable_pools`,
+    /// rewinding it to its checkpoint. The checkpoint is discarded.
+    pub fn import_checkpoint(&mut self, config: &nvpair::NvListRef) -> Result<()> {
+        self.import_with(
+            config,
+            &ImportOptions {
+                checkpoint: true,
+                ..ImportOptions::default()
+            },
+        )
+    }
+    /// Imports a pool given its config from `find_importable_pools`.
+    pub fn import_with(&mut self, config: &nvpair::NvListRef, opts: &ImportOptions) -> Result<()> {
+        let _l = self.handle.lock();
+
+        let mut config = config.try_to_owned()?;
+
+        let mut policy = nvpair::NvList::new()?;
+        opts.rewind
+            .raw()
+            .insert(sys::zpool_load_rewind_policy().as_str(), &mut policy)?;
+        policy.insert(sys::zpool_load_policy().as_str(), &mut config)?;
+
+        let mut props = nvpair::NvList::new()?;
+
+        if let Some(ref x) = opts.altroot {
+            CString::new(x.as_os_str().as_bytes())
+                .map_err(Error::from)?
+                .as_c_str()
+                .insert("altroot", &mut props)?;
+            CString::new("none")
+                .map_err(Error::from)?
+                .as_c_str()
+                .insert("cachefile", &mut props)?;
+        }
+
+        if opts.readonly {
+            CString::new("on")
+                .map_err(Error::from)?
+                .as_c_str()
+                .insert("readonly", &mut props)?;
+        }
+
+        let new_name = match opts.new_name {
+            Some(ref x) => Some(CString::new(x.as_str()).map_err(Error::from)?),
+            None => None,
+        };
+
+        let mut flags = sys::ZFS_IMPORT_NORMAL;
+
+        if opts.force {
+            flags |= sys::ZFS_IMPORT_ANY_HOST;
+        }
+
+        if opts.checkpoint {
+            flags |= sys::ZFS_IMPORT_CHECKPOINT;
+        }
+
+        let code = unsafe {
+            sys::zpool_import_props(
+                self.raw,
+                config.as_mut_ptr() as *mut _,
+                new_name.as_ref().map_or(ptr::null(), |x| x.as_ptr()),
+                if props.is_empty() {
+                    ptr::null_mut()
+                } else {
+                    props.as_mut_ptr() as *mut _
+                },
+                flags as c_int,
+            )
+        };
+
+        if code != 0 {
+            return Err(LibZfsError::Zfs(self.zfs_error()));
+        }
+
+        if opts.mount {
+            let name = match opts.new_name {
+                Some(ref x) => x.clone(),
+                None => config
+                    .lookup_string(sys::zpool_config_pool_name())?
+                    .into_string()?,
+            };
+
+            self.pool_by_name(&name)
+                .ok_or_else(|| LibZfsError::PoolNotFound(Some(name.clone()), None))?
+                .enable_datasets()?;
+        }
+
+        Ok(())
+    }
+    /// Clears the ZFS labels on `device` so it can be reused.
+    ///
+    /// `zpool_clear_label` wipes whatever it is given, so the checks are
+    /// made here. Devices in an imported pool, and spares and cache devices,
+    /// which may be in use by a pool that is imported, are always refused.
+    /// Devices that belong to an exported or possibly active pool are only
+    /// cleared with `force`, which also clears labels that cannot be
+    /// checked at all.
+    pub fn labelclear(&mut self, device: &Path, force: bool) -> Result<()> {
+        let _l = self.handle.lock();
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_EXCL)
+            .open(device)?;
+
+        let fd = file.as_raw_fd();
+
+        let mut state = sys::pool_state::POOL_STATE_UNINITIALIZED;
+        let mut name: *mut c_char = ptr::null_mut();
+        let mut in_use = sys::boolean::B_FALSE;
+
+        let code = unsafe { sys::zpool_in_use(self.raw, fd, &mut state, &mut name, &mut in_use) };
+
+        if code != 0 && !force {
+            return Err(LibZfsError::Zfs(self.zfs_error()));
+        }
+
+        let pool = if name.is_null() {
+            String::new()
+        } else {
+            unsafe {
+                let s = CStr::from_ptr(name).to_string_lossy().into_owned();
+                libc::free(name as *mut c_void);
+                s
+            }
+        };
+
+        if code == 0 && in_use == sys::boolean::B_TRUE {
+            match state {
+                sys::pool_state::POOL_STATE_ACTIVE
+                | sys::pool_state::POOL_STATE_SPARE
+                | sys::pool_state::POOL_STATE_L2CACHE => {
+                    return Err(LibZfsError::ZfsBusy(device.display().to_string()));
+                }
+                sys::pool_state::POOL_STATE_DESTROYED => {}
+                _ if !force => {
+                    return Err(LibZfsError::Io(Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("{} belongs to pool {}", device.display(), pool),
+                    )));
+                }
+                _ => {}
+            }
+        }
+
+        match unsafe { sys::zpool_clear_label(fd) } {
+            0 => Ok(()),
+            _ => Err(LibZfsError::Io(Error::last_os_error())),
+        }
+    }
+    fn zfs_error(&self) -> ZfsError {
+        let _l = self.handle.lock();
+
+        pool_error(self.raw)
+    }
+    /// Creates the pool `name` with the vdevs in `spec`.
+    ///
+    /// `pool_props` are set on the pool, as with `zpool create -o`,
+    /// and `fs_props` on its root dataset, as with `zpool create -O`.
+    pub fn create_pool(
+        &mut self,
+        name: &str,
+        spec: &VDevSpec,
+        pool_props: &BTreeMap<String, String>,
+        fs_props: &BTreeMap<String, String>,
+    ) -> Result<Zpool> {
+        let _l = self.handle.lock();
+
+        fn to_nvlist(xs: &BTreeMap<String, String>) -> Result<Option<nvpair::NvList>> {
+            if xs.is_empty() {
+                return Ok(None);
+            }
+
+            let mut nvl = nvpair::NvList::new()?;
+
+            for (k, v) in xs {
+                CString::new(v.as_str())
+                    .map_err(Error::from)?
+                    .as_c_str()
+                    .insert(k.as_str(), &mut nvl)?;
+            }
+
+            Ok(Some(nvl))
+        }
+
+        let c_name = CString::new(name).map_err(Error::from)?;
+        let mut root = spec.to_nvlist()?;
+        let mut pool_props = to_nvlist(pool_props)?;
+        let mut fs_props = to_nvlist(fs_props)?;
+
+        let code = unsafe {
+            sys::zpool_create(
+                self.raw,
+                c_name.as_ptr(),
+                root.as_mut_ptr() as *mut _,
+                pool_props
+                    .as_mut()
+                    .map_or(ptr::null_mut(), |x| x.as_mut_ptr() as *mut _),
+                fs_props
+                    .as_mut()
+                    .map_or(ptr::null_mut(), |x| x.as_mut_ptr() as *mut _),
+            )
+        };
+
+        if code != 0 {
+            return Err(LibZfsError::Zfs(self.zfs_error()));
+        }
+
+        self.pool_by_name(name)
+            .ok_or_else(|| LibZfsError::PoolNotFound(Some(name.to_string()), None))
+    }
+    /// Destroys many snapshots, given as `(dataset, snapname)`, in one call.
+    ///
+    /// The snapshots must all be in the same pool, and are destroyed
+    /// atomically: if any can't be destroyed, none are, and the error is
+    /// `LibZfsError::Batch` saying why each failing snapshot could not be.
+    /// With `defer`, snapshots that are held or cloned are marked for
+    /// deferred destruction instead.
+    pub fn destroy_snapshots(&mut self, snaps: &[(&str, &str)], defer: bool) -> Result<()> {
+        let mut nvl = nvpair::NvList::new()?;
+
+        for (ds, snap) in snaps {
+            nvl.add_boolean(format!("{}@{}", ds, snap).as_str())?;
+        }
+
+        LibzfsCore::new()?.destroy_snaps(&nvl, defer)
+    }
+    /// Writes a gzipped JSON diagnostic bundle to `path`.
+    ///
+    /// See `SupportBundle` for what is collected.
+    pub fn support_bundle<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        redact_hostnames: bool,
+    ) -> Result<()> {
+        let mut bundle = SupportBundle::collect(self)?;
+
+        if redact_hostnames {
+            bundle.redact();
+        }
+
+        bundle.write(path)
+    }
+    /// Exports each pool, stopping at the first failure.
+    ///
+    /// A failure is reported as `LibZfsError::PoolExport`
+    /// naming the pool that could not be exported.
     pub fn export_all(&mut self, pools: &[Zpool]) -> Result<Vec<()>> {
         pools
             .iter()
-            .map(|x| x.disable_datasets().and_then(|_| x.export()))
+            .map(|x| {
+                x.disable_datasets()
+                    .and_then(|_| x.export())
+                    .map_err(|e| LibZfsError::PoolExport {
+                        pool: x.name().to_string_lossy().into_owned(),
+                        source: Box::new(e),
+                    })
+            })
             .collect()
     }
+    /// Captures every imported pool with its vdev tree and properties,
+    /// and every filesystem and volume in it with theirs, as one
+    /// document that can be serialized.
+    pub fn export_state(&mut self) -> Result<ZfsState> {
+        Ok(ZfsState {
+            pools: self
+                .get_imported_pools()?
+                .iter_mut()
+                .map(state::convert_to_full_pool)
+                .collect::<Result<_>>()?,
+        })
+    }
     pub fn get_imported_pools(&mut self) -> Result<Vec<Zpool>> {
+        let _l = self.handle.lock();
+
         unsafe extern "C" fn callback(
             handle: *mut sys::zpool_handle_t,
             state: *mut c_void,
         ) -> c_int {
-            let state = &mut *(state as *mut Vec<Zpool>);
+            let state = &mut *(state as *mut Vec<*mut sys::zpool_handle_t>);
 
-            state.push(Zpool::new(handle));
+            state.push(handle);
 
             0
         }
-        let mut state: Vec<Zpool> = Vec::new();
+        let mut state: Vec<*mut sys::zpool_handle_t> = Vec::new();
         let state_ptr: *mut c_void = &mut state as *mut _ as *mut c_void;
         let code = unsafe { sys::zpool_iter(self.raw, Some(callback), state_ptr) };
 
+        let pools: Vec<Zpool> = state
+            .into_iter()
+            .map(|x| Zpool::new(self.handle.clone(), x))
+            .collect();
+
         match code {
-            0 => Ok(state),
+            0 => Ok(pools),
             x => Err(LibZfsError::Io(Error::from_raw_os_error(x))),
         }
     }
 }
 
-impl Drop for Libzfs {
-    fn drop(&mut self) {
-        unsafe { sys::libzfs_fini(self.raw) }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use import_policy::Candidate;
+    use serde_json;
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn classifies_errors() {
+        assert_eq!(
+            error_kind(sys::zfs_error_EZFS_POOLUNAVAIL),
+            ZfsErrorKind::PoolIoSuspended
+        );
+        assert_eq!(
+            error_kind(sys::zfs_error_EZFS_BADPROP),
+            ZfsErrorKind::NoSuchProperty
+        );
+        assert_eq!(
+            error_kind(sys::zfs_error_EZFS_NODEVICE),
+            ZfsErrorKind::DeviceNotFound
+        );
+        assert_eq!(
+            error_kind(sys::zfs_error_EZFS_BADDEV),
+            ZfsErrorKind::InvalidDevice
+        );
+        assert_eq!(
+            error_kind(sys::zfs_error_EZFS_NODELEGATION),
+            ZfsErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            error_kind(sys::zfs_error_EZFS_BADPERM),
+            ZfsErrorKind::InvalidName
+        );
+        assert_eq!(error_kind(sys::zfs_error_EZFS_UNKNOWN), ZfsErrorKind::Other);
+
+        let e = LibZfsError::PoolExport {
+            pool: "test".to_string(),
+            source: Box::new(LibZfsError::ZfsBusy("test/ds".to_string())),
+        };
+
+        assert_eq!(e.kind(), Some(ZfsErrorKind::DatasetBusy));
+    }
+
+    #[test]
+    fn uses_load_policy_keys() {
+        assert_eq!(sys::zpool_load_policy(), "load-policy\0");
+        assert_eq!(sys::zpool_load_rewind_policy(), "load-rewind-policy\0");
+    }
+
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn labelclear_unused_file() {
+        let path = env::temp_dir().join(format!("labelclear-{}", process::id()));
+        fs::File::create(&path)
+            .and_then(|x| x.set_len(64 << 20))
+            .unwrap();
+
+        let x = Libzfs::new().labelclear(&path, false);
+        fs::remove_file(&path).unwrap();
+
+        x.unwrap();
+    }
+
+    #[test]
+    fn labelclear_missing_device() {
+        match Libzfs::new().labelclear(Path::new("/dev/labelclear-missing"), false) {
+            Err(LibZfsError::Io(ref e)) if e.kind() == ErrorKind::NotFound => {}
+            x => panic!("expected NotFound, got {:?}", x),
+        }
+    }
+
+    /// The pools `search_pools` finds in `dir`.
+    fn search(dir: &Path, destroyed: bool) -> Vec<Candidate> {
+        let opts = SearchOptions {
+            dirs: vec![dir.to_path_buf()],
+            destroyed,
+            ..SearchOptions::default()
+        };
+
+        Libzfs::new()
+            .search_pools(&opts)
+            .unwrap()
+            .iter()
+            .map(|x| Candidate::from_config(x.value_nv_list_ref().unwrap()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn searches_destroyed_pools() {
+        let dir = env::temp_dir().join(format!("destroyed-{}", process::id()));
+        let name = format!("destroyed{}", process::id());
+        let dev = dir.join("dev");
+
+        fs::create_dir(&dir).unwrap();
+        fs::File::create(&dev)
+            .and_then(|x| x.set_len(64 << 20))
+            .unwrap();
+
+        let none = BTreeMap::new();
+        let spec = VDevSpec::new().device(&dev);
+
+        let x = Libzfs::new()
+            .create_pool(&name, &spec, &none, &none)
+            .and_then(|p| p.destroy(false))
+            .map(|_| (search(&dir, true), search(&dir, false)));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let (destroyed, live) = x.unwrap();
+
+        assert_eq!(destroyed.len(), 1);
+        assert_eq!(destroyed[0].name, name);
+        assert!(destroyed[0].destroyed());
+        assert!(live.is_empty());
+    }
+
+    #[test]
+    fn handles_are_send() {
+        assert_send::<Libzfs>();
+        assert_send::<Zpool>();
+        assert_send::<Zfs>();
+    }
+
+    #[test]
+    fn handle_lock_is_reentrant() {
+        let x = Arc::new(HandleLock::default());
+
+        x.acquire();
+        x.acquire();
+
+        let (tx, rx) = mpsc::channel();
+        let y = Arc::clone(&x);
+
+        let t = thread::spawn(move || {
+            y.acquire();
+            tx.send(()).unwrap();
+            y.release();
+        });
+
+        x.release();
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+
+        x.release();
+        rx.recv().unwrap();
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn move_handle_to_thread() {
+        let mut z = Libzfs::new();
+
+        thread::spawn(move || {
+            z.find_importable_pools();
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn exports_state() {
+        let x = Libzfs::new().export_state().unwrap();
+        let pool = x.pools.iter().find(|x| x.name == "test").unwrap();
+
+        assert!(pool.props.iter().any(|x| x.name == "health"));
+        assert!(pool.datasets.iter().any(|x| x.name == "test/ds"));
+
+        let s = serde_json::to_string(&x).unwrap();
+
+        assert_eq!(serde_json::from_str::<ZfsState>(&s).unwrap(), x);
+    }
+
+    #[test]
+    fn shares_one_handle() {
+        let a = Libzfs::shared();
+        let b = Libzfs::shared();
+
+        assert!(Arc::ptr_eq(a.handle(), b.handle()));
+        assert!(!Arc::ptr_eq(a.handle(), Libzfs::new().handle()));
+    }
+
+    #[test]
+    fn use_shared_concurrently() {
+        let xs: Vec<_> = (0..8)
+            .map(|_| {
+                thread::spawn(|| {
+                    let mut z = Libzfs::shared();
+                    let pool = z.pool_by_name("test").unwrap();
+
+                    // Used while `z` is in use on this thread
+                    let t = thread::spawn(move || {
+                        for _ in 0..10 {
+                            assert_eq!(pool.health_str().unwrap(), "ONLINE");
+                        }
+                    });
+
+                    for _ in 0..10 {
+                        assert!(!z.get_imported_pools().unwrap().is_empty());
+                    }
+
+                    t.join().unwrap();
+                })
+            })
+            .collect();
+
+        for x in xs {
+            x.join().unwrap();
+        }
+    }
 
     #[test]
     fn open_close_handle() {
         Libzfs::new();
     }
+
+    #[test]
+    fn open_close_concurrently() {
+        let xs: Vec<_> = (0..8)
+            .map(|_| {
+                thread::spawn(|| {
+                    for _ in 0..10 {
+                        let mut z = Libzfs::new();
+                        z.find_importable_pools();
+                    }
+                })
+            })
+            .collect();
+
+        for x in xs {
+            x.join().unwrap();
+        }
+    }
 }
@@ -0,0 +1,174 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Helpers for datasets backing Lustre targets
+//!
+//! Lustre's `mkfs.lustre` tags the datasets it formats with
+//! `lustre:*` user properties. These helpers recognise such datasets
+//! and group them by Lustre filesystem.
+//!
+
+use libzfs_types::{Dataset, Pool};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub const SVNAME_PROP: &str = "lustre:svname";
+pub const FSNAME_PROP: &str = "lustre:fsname";
+pub const INDEX_PROP: &str = "lustre:index";
+
+#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone, Copy, PartialOrd, Ord)]
+pub enum TargetRole {
+    Mgs,
+    Mdt,
+    Ost,
+}
+
+/// A dataset that backs a Lustre target
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct LustreTarget {
+    pub svname: String,
+    pub fs_name: Option<String>,
+    pub role: TargetRole,
+    pub index: Option<u32>,
+    pub dataset: String,
+    pub pool: String,
+}
+
+/// Splits a target service name such as `testfs-OST0001` or `MGS`
+/// into its filesystem name, role and index.
+pub fn parse_svname(svname: &str) -> Option<(Option<String>, TargetRole, Option<u32>)> {
+    if svname == "MGS" {
+        return Some((None, TargetRole::Mgs, None));
+    }
+
+    let pos = svname.rfind('-')?;
+    let (fs_name, target) = (&svname[..pos], &svname[pos + 1..]);
+
+    if fs_name.is_empty() || target.len() < 4 {
+        return None;
+    }
+
+    let role = match target.get(..3) {
+        Some("MDT") => TargetRole::Mdt,
+        Some("OST") => TargetRole::Ost,
+        _ => return None,
+    };
+
+    let index = u32::from_str_radix(target.get(3..)?, 16).ok()?;
+
+    Some((Some(fs_name.to_string()), role, Some(index)))
+}
+
+fn prop<'a>(ds: &'a Dataset, name: &str) -> Option<&'a str> {
+    ds.props
+        .iter()
+        .find(|x| x.name == name)
+        .map(|x| x.value.as_str())
+}
+
+/// Returns the Lustre target backed by `ds`, if any.
+///
+/// `lustre:fsname` and `lustre:index` take precedence over
+/// what can be derived from `lustre:svname`.
+pub fn lustre_target(ds: &Dataset) -> Option<LustreTarget> {
+    let svname = prop(ds, SVNAME_PROP)?;
+    let (fs_name, role, index) = parse_svname(svname)?;
+
+    let fs_name = prop(ds, FSNAME_PROP).map(|x| x.to_string()).or(fs_name);
+
+    let index = prop(ds, INDEX_PROP).and_then(|x| x.parse().ok()).or(index);
+
+    let pool = ds.name.split('/').next().unwrap_or("").to_string();
+
+    Some(LustreTarget {
+        svname: svname.to_string(),
+        fs_name,
+        role,
+        index,
+        dataset: ds.name.clone(),
+        pool,
+    })
+}
+
+/// Lists every Lustre target found in `pools`.
+pub fn lustre_targets(pools: &[Pool]) -> Vec<LustreTarget> {
+    pools
+        .iter()
+        .flat_map(|p| p.datasets.iter())
+        .filter_map(lustre_target)
+        .collect()
+}
+
+/// Maps each Lustre filesystem name to the pools backing its targets.
+///
+/// The MGS is not part of any one filesystem and is left out.
+pub fn pools_by_filesystem(pools: &[Pool]) -> BTreeMap<String, BTreeSet<String>> {
+    lustre_targets(pools)
+        .into_iter()
+        .filter_map(|LustreTarget { fs_name, pool, .. }| fs_name.map(|fs| (fs, pool)))
+        .fold(BTreeMap::new(), |mut acc, (fs, pool)| {
+            acc.entry(fs).or_insert_with(BTreeSet::new).insert(pool);
+            acc
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libzfs_types::ZProp;
+
+    fn dataset(name: &str, svname: &str) -> Dataset {
+        Dataset {
+            name: name.to_string(),
+            guid: "1".to_string(),
+            kind: "filesystem".to_string(),
+            props: vec![ZProp {
+                name: SVNAME_PROP.to_string(),
+                value: svname.to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn parses_svnames() {
+        assert_eq!(
+            parse_svname("testfs-OST000a"),
+            Some((Some("testfs".to_string()), TargetRole::Ost, Some(10)))
+        );
+        assert_eq!(
+            parse_svname("my-fs-MDT0000"),
+            Some((Some("my-fs".to_string()), TargetRole::Mdt, Some(0)))
+        );
+        assert_eq!(parse_svname("MGS"), Some((None, TargetRole::Mgs, None)));
+        assert_eq!(parse_svname("testfs-XYZ0000"), None);
+        assert_eq!(parse_svname("nodash"), None);
+    }
+
+    #[test]
+    fn prefers_explicit_props() {
+        let mut ds = dataset("ost0/ost", "testfs-OST0001");
+        ds.props.push(ZProp {
+            name: INDEX_PROP.to_string(),
+            value: "7".to_string(),
+        });
+
+        let t = lustre_target(&ds).unwrap();
+
+        assert_eq!(t.index, Some(7));
+        assert_eq!(t.pool, "ost0");
+        assert_eq!(t.fs_name, Some("testfs".to_string()));
+    }
+
+    #[test]
+    fn ignores_plain_datasets() {
+        let ds = Dataset {
+            name: "test/ds".to_string(),
+            guid: "1".to_string(),
+            kind: "filesystem".to_string(),
+            props: vec![],
+        };
+
+        assert_eq!(lustre_target(&ds), None);
+    }
+}
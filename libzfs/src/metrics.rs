@@ -0,0 +1,378 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Prometheus metrics, behind the `metrics` feature
+//!
+//! `Metrics` collects pool health, capacity, vdev error counters, scan
+//! progress and ARC statistics and renders them in the Prometheus text
+//! exposition format, ready to serve from an exporter's `/metrics`.
+//!
+
+extern crate libzfs_sys as sys;
+
+use kstat::ArcStats;
+use libzfs::Libzfs;
+use libzfs_types::{Result, ScanFunction, ScanState, VDev};
+use std::fmt;
+use zpool::Zpool;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MetricType {
+    Counter,
+    Gauge,
+}
+
+impl fmt::Display for MetricType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MetricType::Counter => write!(f, "counter"),
+            MetricType::Gauge => write!(f, "gauge"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Family {
+    name: String,
+    help: String,
+    kind: MetricType,
+    /// Each sample's labels and value
+    samples: Vec<(Vec<(String, String)>, f64)>,
+}
+
+/// Samples grouped into metric families
+#[derive(Debug, Default)]
+pub struct Metrics {
+    families: Vec<Family>,
+}
+
+fn escape(x: &str) -> String {
+    x.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Each leaf vdev below `x`, in any section.
+fn leaves<'a>(x: &'a VDev, out: &mut Vec<&'a VDev>) {
+    let groups: Vec<&Vec<VDev>> = match *x {
+        VDev::Mirror { ref children, .. }
+        | VDev::RaidZ { ref children, .. }
+        | VDev::Draid { ref children, .. }
+        | VDev::Replacing { ref children }
+        | VDev::Spare { ref children } => vec![children],
+        VDev::Root {
+            ref children,
+            ref spares,
+            ref cache,
+            ..
+        } => vec![children, cache, spares],
+        _ => {
+            out.push(x);
+            return;
+        }
+    };
+
+    for x in groups.into_iter().flatten() {
+        leaves(x, out);
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+    /// Collects the metrics of every imported pool and the ARC.
+    pub fn gather(libzfs: &mut Libzfs) -> Result<Metrics> {
+        let mut x = Metrics::new();
+
+        for p in libzfs.get_imported_pools()? {
+            x.add_pool(&p)?;
+        }
+
+        x.add_arc(&ArcStats::read()?);
+
+        Ok(x)
+    }
+    /// Adds a sample, declaring its family on first use.
+    pub fn add(
+        &mut self,
+        name: &str,
+        help: &str,
+        kind: MetricType,
+        labels: &[(&str, &str)],
+        value: f64,
+    ) {
+        let labels = labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        if let Some(x) = self.families.iter_mut().find(|x| x.name == name) {
+            x.samples.push((labels, value));
+            return;
+        }
+
+        self.families.push(Family {
+            name: name.to_string(),
+            help: help.to_string(),
+            kind,
+            samples: vec![(labels, value)],
+        });
+    }
+    /// Adds a pool's health, capacity, vdev errors and scan progress.
+    pub fn add_pool(&mut self, pool: &Zpool) -> Result<()> {
+        let name = pool.name_str()?;
+        let health = pool.health_str()?;
+        let pool_label = [("pool", name.as_str())];
+
+        self.add(
+            "zfs_pool_health",
+            "Pool health, labelled by state",
+            MetricType::Gauge,
+            &[("pool", &name), ("health", &health)],
+            1.0,
+        );
+
+        for (metric, help, prop) in &[
+            (
+                "zfs_pool_size_bytes",
+                "Pool capacity in bytes",
+                sys::zpool_prop_t::ZPOOL_PROP_SIZE,
+            ),
+            (
+                "zfs_pool_allocated_bytes",
+                "Pool space allocated in bytes",
+                sys::zpool_prop_t::ZPOOL_PROP_ALLOCATED,
+            ),
+            (
+                "zfs_pool_free_bytes",
+                "Pool space free in bytes",
+                sys::zpool_prop_t::ZPOOL_PROP_FREE,
+            ),
+        ] {
+            self.add(
+                metric,
+                help,
+                MetricType::Gauge,
+                &pool_label,
+                pool.prop_int(*prop) as f64,
+            );
+        }
+
+        let tree = pool.vdev_tree()?;
+        let mut xs = vec![];
+        leaves(&tree, &mut xs);
+
+        for x in xs {
+            let (r, w, c) = match x.error_counts() {
+                Some(x) => x,
+                None => continue,
+            };
+            let vdev = match *x {
+                VDev::Disk { ref path, .. } | VDev::File { ref path, .. } => {
+                    path.display().to_string()
+                }
+                _ => continue,
+            };
+            let labels = [("pool", name.as_str()), ("vdev", vdev.as_str())];
+
+            self.add(
+                "zfs_vdev_read_errors_total",
+                "Read errors on a leaf vdev",
+                MetricType::Counter,
+                &labels,
+                r as f64,
+            );
+            self.add(
+                "zfs_vdev_write_errors_total",
+                "Write errors on a leaf vdev",
+                MetricType::Counter,
+                &labels,
+                w as f64,
+            );
+            self.add(
+                "zfs_vdev_checksum_errors_total",
+                "Checksum errors on a leaf vdev",
+                MetricType::Counter,
+                &labels,
+                c as f64,
+            );
+        }
+
+        if let Some(x) = pool.scan_status()? {
+            let function = match x.function {
+                ScanFunction::None => "none",
+                ScanFunction::Scrub => "scrub",
+                ScanFunction::Resilver => "resilver",
+            };
+            let labels = [("pool", name.as_str()), ("function", function)];
+
+            self.add(
+                "zfs_scan_active",
+                "Whether a scrub or resilver is running",
+                MetricType::Gauge,
+                &labels,
+                if x.state == ScanState::Scanning && !x.paused {
+                    1.0
+                } else {
+                    0.0
+                },
+            );
+            self.add(
+                "zfs_scan_examined_bytes",
+                "Bytes examined by the current or last scan",
+                MetricType::Gauge,
+                &labels,
+                x.examined as f64,
+            );
+            self.add(
+                "zfs_scan_to_examine_bytes",
+                "Bytes the current or last scan has to examine",
+                MetricType::Gauge,
+                &labels,
+                x.to_examine as f64,
+            );
+            self.add(
+                "zfs_scan_errors",
+                "Errors found by the current or last scan",
+                MetricType::Gauge,
+                &labels,
+                x.errors as f64,
+            );
+        }
+
+        Ok(())
+    }
+    /// Adds ARC hit rates and sizes.
+    pub fn add_arc(&mut self, arc: &ArcStats) {
+        for (metric, help, kind, value) in &[
+            (
+                "zfs_arc_hits_total",
+                "ARC hits",
+                MetricType::Counter,
+                arc.hits,
+            ),
+            (
+                "zfs_arc_misses_total",
+                "ARC misses",
+                MetricType::Counter,
+                arc.misses,
+            ),
+            (
+                "zfs_arc_mru_hits_total",
+                "ARC hits on the most recently used list",
+                MetricType::Counter,
+                arc.mru_hits,
+            ),
+            (
+                "zfs_arc_mfu_hits_total",
+                "ARC hits on the most frequently used list",
+                MetricType::Counter,
+                arc.mfu_hits,
+            ),
+            (
+                "zfs_arc_size_bytes",
+                "ARC size in bytes",
+                MetricType::Gauge,
+                arc.size,
+            ),
+            (
+                "zfs_arc_target_size_bytes",
+                "ARC target size in bytes",
+                MetricType::Gauge,
+                arc.c,
+            ),
+            (
+                "zfs_arc_max_size_bytes",
+                "ARC size limit in bytes",
+                MetricType::Gauge,
+                arc.c_max,
+            ),
+            (
+                "zfs_arc_mru_size_bytes",
+                "ARC most recently used list size in bytes",
+                MetricType::Gauge,
+                arc.mru_size,
+            ),
+            (
+                "zfs_arc_mfu_size_bytes",
+                "ARC most frequently used list size in bytes",
+                MetricType::Gauge,
+                arc.mfu_size,
+            ),
+        ] {
+            self.add(metric, help, *kind, &[], *value as f64);
+        }
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for x in &self.families {
+            writeln!(f, "# HELP {} {}", x.name, x.help)?;
+            writeln!(f, "# TYPE {} {}", x.name, x.kind)?;
+
+            for (labels, value) in &x.samples {
+                write!(f, "{}", x.name)?;
+
+                if !labels.is_empty() {
+                    let labels: Vec<String> = labels
+                        .iter()
+                        .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+                        .collect();
+
+                    write!(f, "{{{}}}", labels.join(","))?;
+                }
+
+                writeln!(f, " {}", value)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_exposition_format() {
+        let mut x = Metrics::new();
+
+        x.add(
+            "zfs_pool_health",
+            "Pool health, labelled by state",
+            MetricType::Gauge,
+            &[("pool", "test"), ("health", "ONLINE")],
+            1.0,
+        );
+        x.add_arc(&ArcStats {
+            hits: 9,
+            size: 1024,
+            ..ArcStats::default()
+        });
+        x.add(
+            "zfs_pool_health",
+            "Pool health, labelled by state",
+            MetricType::Gauge,
+            &[("pool", "a\"b"), ("health", "DEGRADED")],
+            1.0,
+        );
+
+        let s = x.to_string();
+
+        assert!(s.starts_with(
+            "# HELP zfs_pool_health Pool health, labelled by state
+# TYPE zfs_pool_health gauge
+zfs_pool_health{pool=\"test\",health=\"ONLINE\"} 1
+zfs_pool_health{pool=\"a\\\"b\",health=\"DEGRADED\"} 1
+# HELP zfs_arc_hits_total ARC hits
+# TYPE zfs_arc_hits_total counter
+zfs_arc_hits_total 9
+"
+        ));
+        assert!(s.contains("\nzfs_arc_size_bytes 1024\n"));
+    }
+}
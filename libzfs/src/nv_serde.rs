@@ -0,0 +1,1065 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Serde support for nvlists
+//!
+//! `from_nvlist` reads any nvlist, such as a pool config or a zevent,
+//! into a type implementing `Deserialize`, matching pairs to fields by
+//! name. `to_nvlist` goes the other way, keeping integer widths so the
+//! result can be handed to libzfs.
+//!
+//! Sequences become nvlist arrays when their elements are structs or maps,
+//! and typed arrays otherwise. There is no array of doubles, so a sequence
+//! of floats becomes an nvlist with its elements named by index. Unit
+//! values become boolean flags, which deserialize as `true`. `None` fields
+//! are left out.
+//!
+
+use libzfs_types::{self, LibZfsError};
+use nvpair::{self, NvData, NvDataType, NvEncode};
+use serde::de::{self, Deserialize, IntoDeserializer, Visitor};
+use serde::ser::{self, Impossible, Serialize};
+use std::error;
+use std::ffi::CString;
+use std::fmt;
+use std::io::{self, ErrorKind};
+
+#[derive(Debug)]
+struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error(msg.to_string())
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error(msg.to_string())
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error(e.to_string())
+    }
+}
+
+impl From<Error> for LibZfsError {
+    fn from(e: Error) -> LibZfsError {
+        LibZfsError::Io(io::Error::new(ErrorKind::InvalidData, e.0))
+    }
+}
+
+fn cstring(s: &str) -> Result<CString, Error> {
+    CString::new(s).map_err(|e| Error(e.to_string()))
+}
+
+/// Reads `nvl` into `T`, matching pairs to fields by name.
+pub fn from_nvlist<'a, T: Deserialize<'a>>(nvl: &'a nvpair::NvListRef) -> libzfs_types::Result<T> {
+    Ok(T::deserialize(ListDeserializer(nvl))?)
+}
+
+/// Builds an nvlist from `x`, which must serialize as a struct or map.
+pub fn to_nvlist<T: Serialize + ?Sized>(x: &T) -> libzfs_types::Result<nvpair::NvList> {
+    let mut root = nvpair::NvList::new()?;
+
+    x.serialize(PairSerializer {
+        nvl: &mut root,
+        name: "",
+    })?;
+
+    root.lookup_nv_list("").map_err(|_| {
+        LibZfsError::from(Error(
+            "only structs and maps can be written as an nvlist".to_string(),
+        ))
+    })
+}
+
+#[cfg(feature = "json")]
+impl nvpair::NvListRef {
+    /// Converts to a JSON object, with nested nvlists as objects
+    /// and arrays as JSON arrays.
+    pub fn to_json(&self) -> libzfs_types::Result<serde_json::Value> {
+        from_nvlist(self)
+    }
+}
+
+#[cfg(feature = "json")]
+impl nvpair::NvList {
+    /// Builds an nvlist from a JSON object.
+    ///
+    /// Numbers become `uint64`, `int64` or `double` pairs, and `null`
+    /// becomes a boolean flag.
+    pub fn from_json(x: &serde_json::Value) -> libzfs_types::Result<nvpair::NvList> {
+        to_nvlist(x)
+    }
+}
+
+struct ListDeserializer<'de>(&'de nvpair::NvListRef);
+
+impl<'de> de::Deserializer<'de> for ListDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(PairsAccess {
+            pairs: self.0.iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    /// Reads the values in order, ignoring their names,
+    /// as a sequence of floats is written.
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        seq(visitor, self.0.iter().map(PairDeserializer))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct PairsAccess<'de> {
+    pairs: nvpair::NvListIter<'de>,
+    value: Option<&'de nvpair::NvPair>,
+}
+
+impl<'de> de::MapAccess<'de> for PairsAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.pairs.next() {
+            Some(x) => {
+                self.value = Some(x);
+
+                let name = x.name().to_str().map_err(|e| Error(e.to_string()))?;
+
+                seed.deserialize(name.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let x = self
+            .value
+            .take()
+            .ok_or_else(|| Error("value requested before key".to_string()))?;
+
+        seed.deserialize(PairDeserializer(x))
+    }
+}
+
+struct PairDeserializer<'de>(&'de nvpair::NvPair);
+
+fn seq<'de, V, I>(visitor: V, xs: I) -> Result<V::Value, Error>
+where
+    V: Visitor<'de>,
+    I: IntoIterator,
+    I::Item: IntoDeserializer<'de, Error>,
+{
+    let mut access = de::value::SeqDeserializer::new(xs.into_iter());
+    let value = visitor.visit_seq(&mut access)?;
+
+    access.end()?;
+
+    Ok(value)
+}
+
+impl<'de> de::Deserializer<'de> for PairDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let x = self.0;
+
+        match x.data()? {
+            NvData::Bool => visitor.visit_bool(true),
+            NvData::BoolV(v) => visitor.visit_bool(v),
+            NvData::Byte(v) | NvData::Uint8(v) => visitor.visit_u8(v),
+            NvData::Int8(v) => visitor.visit_i8(v),
+            NvData::Int16(v) => visitor.visit_i16(v),
+            NvData::Uint16(v) => visitor.visit_u16(v),
+            NvData::Int32(v) => visitor.visit_i32(v),
+            NvData::Uint32(v) => visitor.visit_u32(v),
+            NvData::Int64(v) | NvData::HrTime(v) => visitor.visit_i64(v),
+            NvData::Uint64(v) => visitor.visit_u64(v),
+            NvData::Double(v) => visitor.visit_f64(v),
+            NvData::Str(v) => {
+                visitor.visit_borrowed_str(v.to_str().map_err(|e| Error(e.to_string()))?)
+            }
+            NvData::NvListRef(v) => ListDeserializer(v).deserialize_any(visitor),
+            NvData::BoolArray(v) => seq(visitor, v),
+            NvData::ByteArray(v) | NvData::Uint8Array(v) => seq(visitor, v),
+            NvData::Int8Array(v) => seq(visitor, v),
+            NvData::Int16Array(v) => seq(visitor, v),
+            NvData::Uint16Array(v) => seq(visitor, v),
+            NvData::Int32Array(v) => seq(visitor, v),
+            NvData::Uint32Array(v) => seq(visitor, v),
+            NvData::Int64Array(v) => seq(visitor, v),
+            NvData::Uint64Array(v) => seq(visitor, v),
+            NvData::StrArray(v) => {
+                let xs = v
+                    .into_iter()
+                    .map(|x| x.to_str())
+                    .collect::<Result<Vec<&str>, _>>()
+                    .map_err(|e| Error(e.to_string()))?;
+
+                seq(visitor, xs)
+            }
+            NvData::NvListRefArray(v) => seq(visitor, v.into_iter().map(ListDeserializer)),
+            NvData::Unknown => Err(Error(format!(
+                "{} has unsupported type {:?}",
+                x.name().to_string_lossy(),
+                x.data_type()
+            ))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0.data_type() {
+            NvDataType::DATA_TYPE_NVLIST => de::Deserializer::deserialize_seq(
+                ListDeserializer(self.0.value_nv_list_ref()?),
+                visitor,
+            ),
+            _ => de::Deserializer::deserialize_any(self, visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let x = self.0;
+
+        match x.data_type() {
+            NvDataType::DATA_TYPE_STRING => {
+                let s = x.value_str()?.to_str().map_err(|e| Error(e.to_string()))?;
+
+                visitor.visit_enum(s.into_deserializer())
+            }
+            NvDataType::DATA_TYPE_NVLIST => {
+                let nvl = x.value_nv_list_ref()?;
+
+                match (nvl.first(), nvl.iter().nth(1)) {
+                    (Some(variant), None) => visitor.visit_enum(VariantAccess(variant)),
+                    _ => Err(Error(format!(
+                        "{} should hold exactly one variant",
+                        x.name().to_string_lossy()
+                    ))),
+                }
+            }
+            _ => Err(Error(format!(
+                "{} is not an enum",
+                x.name().to_string_lossy()
+            ))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for ListDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for PairDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// An enum variant stored as a single pair named after it
+struct VariantAccess<'de>(&'de nvpair::NvPair);
+
+impl<'de> de::EnumAccess<'de> for VariantAccess<'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let name = self.0.name().to_str().map_err(|e| Error(e.to_string()))?;
+        let d: de::value::StrDeserializer<Error> = name.into_deserializer();
+        let v = seed.deserialize(d)?;
+
+        Ok((v, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(PairDeserializer(self.0))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(PairDeserializer(self.0), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_any(PairDeserializer(self.0), visitor)
+    }
+}
+
+/// Writes one value into `nvl` as the pair `name`
+struct PairSerializer<'a> {
+    nvl: &'a mut nvpair::NvListRef,
+    name: &'a str,
+}
+
+impl<'a> PairSerializer<'a> {
+    fn insert<T: NvEncode + ?Sized>(self, x: &T) -> Result<(), Error> {
+        Ok(x.insert(self.name, self.nvl)?)
+    }
+    /// Writes a variant's value as a single-pair nvlist named after
+    /// the variant, the way serde_json writes it as an object.
+    fn variant<F>(self, variant: &str, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(PairSerializer) -> Result<(), Error>,
+    {
+        let mut outer = nvpair::NvList::new()?;
+
+        f(PairSerializer {
+            nvl: &mut outer,
+            name: variant,
+        })?;
+
+        self.insert(&*outer)
+    }
+}
+
+impl<'a> ser::Serializer for PairSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = SeqSerializer<'a>;
+    type SerializeTuple = SeqSerializer<'a>;
+    type SerializeTupleStruct = SeqSerializer<'a>;
+    type SerializeTupleVariant = SeqSerializer<'a>;
+    type SerializeMap = MapSerializer<'a>;
+    type SerializeStruct = MapSerializer<'a>;
+    type SerializeStructVariant = MapSerializer<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.insert(&v)
+    }
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.insert(&v)
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.insert(&v)
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.insert(&v)
+    }
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.insert(&v)
+    }
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.insert(&v)
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.insert(&v)
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.insert(&v)
+    }
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.insert(&v)
+    }
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.insert(&f64::from(v))
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.insert(&v)
+    }
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.serialize_str(&v.to_string())
+    }
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.insert(cstring(v)?.as_c_str())
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.insert(v)
+    }
+    fn serialize_none(self) -> Result<(), Error> {
+        Ok(())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, v: &T) -> Result<(), Error> {
+        v.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(self.nvl.add_boolean(self.name)?)
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_str(variant)
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        v: &T,
+    ) -> Result<(), Error> {
+        v.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        v: &T,
+    ) -> Result<(), Error> {
+        self.variant(variant, |p| v.serialize(p))
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<SeqSerializer<'a>, Error> {
+        Ok(SeqSerializer {
+            parent: self,
+            variant: None,
+            items: nvpair::NvList::new()?,
+            len: 0,
+        })
+    }
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer<'a>, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer<'a>, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SeqSerializer<'a>, Error> {
+        let mut x = self.serialize_seq(None)?;
+        x.variant = Some(variant);
+        Ok(x)
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer<'a>, Error> {
+        Ok(MapSerializer {
+            parent: self,
+            variant: None,
+            inner: nvpair::NvList::new()?,
+            key: None,
+        })
+    }
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<MapSerializer<'a>, Error> {
+        self.serialize_map(Some(len))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<MapSerializer<'a>, Error> {
+        let mut x = self.serialize_map(None)?;
+        x.variant = Some(variant);
+        Ok(x)
+    }
+}
+
+/// Collects elements as pairs of a scratch nvlist,
+/// then writes them as one array of their common type.
+struct SeqSerializer<'a> {
+    parent: PairSerializer<'a>,
+    /// Set when serializing a tuple variant
+    variant: Option<&'static str>,
+    items: nvpair::NvList,
+    len: usize,
+}
+
+impl<'a> SeqSerializer<'a> {
+    fn push<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), Error> {
+        let name = self.len.to_string();
+
+        v.serialize(PairSerializer {
+            nvl: &mut self.items,
+            name: &name,
+        })?;
+
+        self.len += 1;
+
+        Ok(())
+    }
+    fn finish(self) -> Result<(), Error> {
+        let (items, len) = (self.items, self.len);
+
+        match self.variant {
+            Some(v) => self.parent.variant(v, |p| write_array(p, &items, len)),
+            None => write_array(self.parent, &items, len),
+        }
+    }
+}
+
+/// Writes the `len` pairs of `items` as one array.
+fn write_array(parent: PairSerializer, items: &nvpair::NvListRef, len: usize) -> Result<(), Error> {
+    let pairs: Vec<&nvpair::NvPair> = items.iter().collect();
+
+    if pairs.len() != len {
+        return Err(Error(format!(
+            "{} has elements that can't be stored in an array",
+            parent.name
+        )));
+    }
+
+    // An empty sequence has no element type to go by; an empty nvlist
+    // array reads back as an empty sequence of any type.
+    let t = match pairs.first() {
+        Some(x) => x.data_type(),
+        None => {
+            let empty: Vec<nvpair::NvList> = vec![];
+            return parent.insert(&empty[..]);
+        }
+    };
+
+    if pairs.iter().any(|x| x.data_type() != t) {
+        return Err(Error(format!(
+            "{} has elements of mixed types",
+            parent.name
+        )));
+    }
+
+    macro_rules! collect {
+        ($f:ident) => {
+            pairs
+                .iter()
+                .map(|x| x.$f())
+                .collect::<io::Result<Vec<_>>>()?
+        };
+    }
+
+    match t {
+        NvDataType::DATA_TYPE_BOOLEAN_VALUE => parent.insert(&collect!(value_boolean)[..]),
+        NvDataType::DATA_TYPE_INT8 => parent.insert(&collect!(value_int8)[..]),
+        NvDataType::DATA_TYPE_UINT8 => parent.insert(&collect!(value_uint8)[..]),
+        NvDataType::DATA_TYPE_INT16 => parent.insert(&collect!(value_int16)[..]),
+        NvDataType::DATA_TYPE_UINT16 => parent.insert(&collect!(value_uint16)[..]),
+        NvDataType::DATA_TYPE_INT32 => parent.insert(&collect!(value_int32)[..]),
+        NvDataType::DATA_TYPE_UINT32 => parent.insert(&collect!(value_uint32)[..]),
+        NvDataType::DATA_TYPE_INT64 => parent.insert(&collect!(value_int64)[..]),
+        NvDataType::DATA_TYPE_UINT64 => parent.insert(&collect!(value_uint64)[..]),
+        NvDataType::DATA_TYPE_STRING => {
+            let xs: Vec<CString> = collect!(value_str)
+                .into_iter()
+                .map(|x| x.to_owned())
+                .collect();
+
+            parent.insert(&xs[..])
+        }
+        NvDataType::DATA_TYPE_NVLIST => {
+            let xs = collect!(value_nv_list);
+
+            parent.insert(&xs[..])
+        }
+        NvDataType::DATA_TYPE_DOUBLE => parent.insert(items),
+        t => Err(Error(format!(
+            "{} has elements of unsupported type {:?}",
+            parent.name, t
+        ))),
+    }
+}
+
+impl<'a> ser::SerializeSeq for SeqSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), Error> {
+        self.push(v)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTuple for SeqSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), Error> {
+        self.push(v)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for SeqSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), Error> {
+        self.push(v)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+/// Writes fields into a nested nvlist.
+struct MapSerializer<'a> {
+    parent: PairSerializer<'a>,
+    /// Set when serializing a struct variant
+    variant: Option<&'static str>,
+    inner: nvpair::NvList,
+    key: Option<String>,
+}
+
+impl<'a> MapSerializer<'a> {
+    fn field<T: Serialize + ?Sized>(&mut self, name: &str, v: &T) -> Result<(), Error> {
+        v.serialize(PairSerializer {
+            nvl: &mut self.inner,
+            name,
+        })
+    }
+    fn finish(self) -> Result<(), Error> {
+        let inner = self.inner;
+
+        match self.variant {
+            Some(v) => self.parent.variant(v, |p| p.insert(&*inner)),
+            None => self.parent.insert(&*inner),
+        }
+    }
+}
+
+impl<'a> ser::SerializeMap for MapSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, k: &T) -> Result<(), Error> {
+        self.key = Some(k.serialize(KeySerializer)?);
+
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), Error> {
+        let k = self
+            .key
+            .take()
+            .ok_or_else(|| Error("value written before key".to_string()))?;
+
+        self.field(&k, v)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeStruct for MapSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        name: &'static str,
+        v: &T,
+    ) -> Result<(), Error> {
+        self.field(name, v)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeTupleVariant for SeqSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, v: &T) -> Result<(), Error> {
+        self.push(v)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<'a> ser::SerializeStructVariant for MapSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        name: &'static str,
+        v: &T,
+    ) -> Result<(), Error> {
+        self.field(name, v)
+    }
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+/// Accepts only string map keys
+struct KeySerializer;
+
+macro_rules! reject_key {
+    ($($f:ident: $t:ty),*) => {
+        $(
+            fn $f(self, _v: $t) -> Result<String, Error> {
+                Err(Error("nvlist keys must be strings".to_string()))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_str(self, v: &str) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+    fn serialize_char(self, v: char) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String, Error> {
+        Ok(variant.to_string())
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        v: &T,
+    ) -> Result<String, Error> {
+        v.serialize(self)
+    }
+
+    reject_key!(
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_bytes: &[u8],
+        serialize_unit_struct: &'static str
+    );
+
+    fn serialize_none(self) -> Result<String, Error> {
+        Err(Error("nvlist keys must be strings".to_string()))
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, _v: &T) -> Result<String, Error> {
+        Err(Error("nvlist keys must be strings".to_string()))
+    }
+    fn serialize_unit(self) -> Result<String, Error> {
+        Err(Error("nvlist keys must be strings".to_string()))
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _v: &T,
+    ) -> Result<String, Error> {
+        Err(Error("nvlist keys must be strings".to_string()))
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(Error("nvlist keys must be strings".to_string()))
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(Error("nvlist keys must be strings".to_string()))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(Error("nvlist keys must be strings".to_string()))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(Error("nvlist keys must be strings".to_string()))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error("nvlist keys must be strings".to_string()))
+    }
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(Error("nvlist keys must be strings".to_string()))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error("nvlist keys must be strings".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_derive::{Deserialize, Serialize};
+    #[cfg(feature = "json")]
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Kind {
+        Disk,
+        Mirror { ashift: u64 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Child {
+        path: String,
+        guid: u64,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Tree {
+        name: String,
+        id: u32,
+        offset: i64,
+        readonly: bool,
+        whole_disk: Option<u64>,
+        kind: Kind,
+        other: Kind,
+        stats: Vec<u64>,
+        children: Vec<Child>,
+    }
+
+    #[test]
+    fn round_trips_struct() {
+        let x = Tree {
+            name: "test".to_string(),
+            id: 4,
+            offset: -1,
+            readonly: false,
+            whole_disk: None,
+            kind: Kind::Disk,
+            other: Kind::Mirror { ashift: 12 },
+            stats: vec![1, 2, 3],
+            children: vec![
+                Child {
+                    path: "/dev/sdb1".to_string(),
+                    guid: 1,
+                },
+                Child {
+                    path: "/dev/sdc1".to_string(),
+                    guid: 2,
+                },
+            ],
+        };
+
+        let nvl = to_nvlist(&x).unwrap();
+
+        assert!(!nvl.exists("whole_disk"));
+        assert_eq!(nvl.lookup_uint64_array("stats").unwrap(), vec![1, 2, 3]);
+        assert_eq!(nvl.lookup_nv_list_array_ref("children").unwrap().len(), 2);
+
+        assert_eq!(from_nvlist::<Tree>(&nvl).unwrap(), x);
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Array<T> {
+        xs: Vec<T>,
+    }
+
+    fn round_trip<T>(xs: Vec<T>) -> nvpair::NvList
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + fmt::Debug,
+    {
+        let x = Array { xs };
+        let nvl = to_nvlist(&x).unwrap();
+
+        assert_eq!(from_nvlist::<Array<T>>(&nvl).unwrap(), x);
+
+        nvl
+    }
+
+    fn array_type(nvl: &nvpair::NvListRef) -> NvDataType {
+        nvl.lookup("xs").unwrap().data_type()
+    }
+
+    #[test]
+    fn round_trips_bool_array() {
+        let nvl = round_trip(vec![true, false]);
+
+        assert_eq!(array_type(&nvl), NvDataType::DATA_TYPE_BOOLEAN_ARRAY);
+    }
+
+    #[test]
+    fn round_trips_u8_array() {
+        let nvl = round_trip(vec![0u8, 1, 255]);
+
+        assert_eq!(array_type(&nvl), NvDataType::DATA_TYPE_UINT8_ARRAY);
+    }
+
+    #[test]
+    fn round_trips_i8_array() {
+        let nvl = round_trip(vec![-128i8, 0, 127]);
+
+        assert_eq!(array_type(&nvl), NvDataType::DATA_TYPE_INT8_ARRAY);
+    }
+
+    #[test]
+    fn round_trips_i16_array() {
+        let nvl = round_trip(vec![-32768i16, 0, 32767]);
+
+        assert_eq!(array_type(&nvl), NvDataType::DATA_TYPE_INT16_ARRAY);
+    }
+
+    #[test]
+    fn round_trips_u16_array() {
+        let nvl = round_trip(vec![0u16, 512, 65535]);
+
+        assert_eq!(array_type(&nvl), NvDataType::DATA_TYPE_UINT16_ARRAY);
+    }
+
+    #[test]
+    fn round_trips_i32_array() {
+        let nvl = round_trip(vec![-1i32, 0, 1]);
+
+        assert_eq!(array_type(&nvl), NvDataType::DATA_TYPE_INT32_ARRAY);
+    }
+
+    #[test]
+    fn round_trips_u32_array() {
+        let nvl = round_trip(vec![0u32, 4096]);
+
+        assert_eq!(array_type(&nvl), NvDataType::DATA_TYPE_UINT32_ARRAY);
+    }
+
+    #[test]
+    fn round_trips_i64_array() {
+        let nvl = round_trip(vec![i64::MIN, 0, i64::MAX]);
+
+        assert_eq!(array_type(&nvl), NvDataType::DATA_TYPE_INT64_ARRAY);
+    }
+
+    #[test]
+    fn round_trips_u64_array() {
+        let nvl = round_trip(vec![0u64, u64::MAX]);
+
+        assert_eq!(array_type(&nvl), NvDataType::DATA_TYPE_UINT64_ARRAY);
+    }
+
+    #[test]
+    fn round_trips_f64_array() {
+        let nvl = round_trip(vec![0.5f64, -1.25, 1e10]);
+
+        assert_eq!(array_type(&nvl), NvDataType::DATA_TYPE_NVLIST);
+    }
+
+    #[test]
+    fn round_trips_string_array() {
+        let nvl = round_trip(vec!["a".to_string(), "b".to_string()]);
+
+        assert_eq!(array_type(&nvl), NvDataType::DATA_TYPE_STRING_ARRAY);
+    }
+
+    #[test]
+    fn round_trips_empty_array() {
+        round_trip::<u64>(vec![]);
+        round_trip::<f64>(vec![]);
+        round_trip::<Child>(vec![]);
+    }
+
+    #[test]
+    fn rejects_mixed_array() {
+        assert!(to_nvlist(&Array {
+            xs: vec![Kind::Disk, Kind::Mirror { ashift: 12 }],
+        })
+        .is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn round_trips_json() {
+        let x = json!({
+            "name": "test",
+            "guid": 42,
+            "children": [{ "path": "/dev/sdb1" }],
+        });
+
+        let nvl = nvpair::NvList::from_json(&x).unwrap();
+
+        assert_eq!(nvl.lookup_uint64("guid").unwrap(), 42);
+        assert_eq!(nvl.to_json().unwrap(), x);
+    }
+}
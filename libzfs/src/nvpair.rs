@@ -2,18 +2,31 @@ extern crate cstr_argument;
 extern crate nvpair_sys as nv_sys;
 
 use self::cstr_argument::CStrArgument;
+pub use self::nv_sys::data_type_t as NvDataType;
 pub use foreign_types::{ForeignType, ForeignTypeRef, Opaque};
 use std::ffi;
+use std::fmt;
 use std::io;
 use std::mem;
-use std::os::raw::{c_int, c_uint};
+use std::os::raw::{c_char, c_int, c_uint};
 use std::ptr;
+use std::slice;
 
 pub trait NvEncode {
     fn insert<S: CStrArgument>(&self, S, &mut NvListRef) -> io::Result<()>;
     //fn read(NvPair &nv) -> io::Result<Self>;
 }
 
+/// Borrows an array libnvpair handed back, which is a null
+/// pointer rather than a dangling one when the array is empty.
+unsafe fn array<'a, T>(p: *const T, len: c_uint) -> &'a [T] {
+    if p.is_null() || len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(p, len as usize)
+    }
+}
+
 impl NvEncode for bool {
     fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
         let name = name.into_cstr();
@@ -49,6 +62,19 @@ impl NvEncode for u32 {
     }
 }
 
+impl NvEncode for u64 {
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        let name = name.into_cstr();
+        let v =
+            unsafe { nv_sys::nvlist_add_uint64(nv.as_mut_ptr(), name.as_ref().as_ptr(), *self) };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(())
+        }
+    }
+}
+
 impl NvEncode for ffi::CStr {
     fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
         let name = name.into_cstr();
@@ -81,6 +107,150 @@ impl NvEncode for NvListRef {
     }
 }
 
+impl NvEncode for [NvList] {
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        let name = name.into_cstr();
+        let mut xs: Vec<*mut nv_sys::nvlist> = self.iter().map(|x| x.as_ptr() as *mut _).collect();
+        let v = unsafe {
+            nv_sys::nvlist_add_nvlist_array(
+                nv.as_mut_ptr(),
+                name.as_ref().as_ptr(),
+                xs.as_mut_ptr(),
+                xs.len() as c_uint,
+            )
+        };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl NvEncode for [i64] {
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        let name = name.into_cstr();
+        let v = unsafe {
+            nv_sys::nvlist_add_int64_array(
+                nv.as_mut_ptr(),
+                name.as_ref().as_ptr(),
+                self.as_ptr() as *mut _,
+                self.len() as c_uint,
+            )
+        };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+macro_rules! nv_encode_scalar {
+    ($t:ty, $f:ident) => {
+        impl NvEncode for $t {
+            fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+                let name = name.into_cstr();
+                let v = unsafe { nv_sys::$f(nv.as_mut_ptr(), name.as_ref().as_ptr(), *self) };
+                if v != 0 {
+                    Err(io::Error::from_raw_os_error(v))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    };
+}
+
+nv_encode_scalar!(i8, nvlist_add_int8);
+nv_encode_scalar!(u8, nvlist_add_uint8);
+nv_encode_scalar!(i16, nvlist_add_int16);
+nv_encode_scalar!(u16, nvlist_add_uint16);
+nv_encode_scalar!(i32, nvlist_add_int32);
+nv_encode_scalar!(i64, nvlist_add_int64);
+nv_encode_scalar!(f64, nvlist_add_double);
+
+macro_rules! nv_encode_array {
+    ($t:ty, $f:ident) => {
+        impl NvEncode for [$t] {
+            fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+                let name = name.into_cstr();
+                let v = unsafe {
+                    nv_sys::$f(
+                        nv.as_mut_ptr(),
+                        name.as_ref().as_ptr(),
+                        self.as_ptr() as *mut _,
+                        self.len() as c_uint,
+                    )
+                };
+                if v != 0 {
+                    Err(io::Error::from_raw_os_error(v))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    };
+}
+
+nv_encode_array!(u8, nvlist_add_uint8_array);
+nv_encode_array!(i8, nvlist_add_int8_array);
+nv_encode_array!(i16, nvlist_add_int16_array);
+nv_encode_array!(u16, nvlist_add_uint16_array);
+nv_encode_array!(i32, nvlist_add_int32_array);
+nv_encode_array!(u32, nvlist_add_uint32_array);
+nv_encode_array!(u64, nvlist_add_uint64_array);
+
+impl NvEncode for [bool] {
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        let name = name.into_cstr();
+        let mut xs: Vec<nv_sys::boolean_t> = self
+            .iter()
+            .map(|x| {
+                if *x {
+                    nv_sys::boolean::B_TRUE
+                } else {
+                    nv_sys::boolean::B_FALSE
+                }
+            })
+            .collect();
+        let v = unsafe {
+            nv_sys::nvlist_add_boolean_array(
+                nv.as_mut_ptr(),
+                name.as_ref().as_ptr(),
+                xs.as_mut_ptr(),
+                xs.len() as c_uint,
+            )
+        };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl NvEncode for [ffi::CString] {
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        let name = name.into_cstr();
+        let mut xs: Vec<*const c_char> = self.iter().map(|x| x.as_ptr()).collect();
+        let v = unsafe {
+            nv_sys::nvlist_add_string_array(
+                nv.as_mut_ptr(),
+                name.as_ref().as_ptr(),
+                xs.as_mut_ptr() as *mut _,
+                xs.len() as c_uint,
+            )
+        };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NvEncoding {
     Native,
     Xdr,
@@ -130,6 +300,17 @@ impl NvList {
         }
     }
 
+    /// Reads an `NvList` written by `pack`, in either encoding.
+    pub fn unpack(buf: &[u8]) -> io::Result<Self> {
+        let mut n = ptr::null_mut();
+        let v = unsafe { nv_sys::nvlist_unpack(buf.as_ptr() as *mut _, buf.len(), &mut n, 0) };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(unsafe { Self::from_ptr(n) })
+        }
+    }
+
     pub fn try_clone(&self) -> io::Result<Self> {
         let mut n = ptr::null_mut();
         let v = unsafe { nv_sys::nvlist_dup(self.0, &mut n, 0) };
@@ -148,10 +329,16 @@ impl Clone for NvList {
 }
 
 impl NvListRef {
+    /// # Safety
+    ///
+    /// `v` must point to a valid `nvlist` that outlives the returned reference.
     pub unsafe fn from_mut_ptr<'a>(v: *mut nv_sys::nvlist) -> &'a mut Self {
         mem::transmute::<*mut nv_sys::nvlist, &mut Self>(v)
     }
 
+    /// # Safety
+    ///
+    /// `v` must point to a valid `nvlist` that outlives the returned reference.
     pub unsafe fn from_ptr<'a>(v: *const nv_sys::nvlist) -> &'a Self {
         mem::transmute::<*const nv_sys::nvlist, &Self>(v)
     }
@@ -174,6 +361,30 @@ impl NvListRef {
         }
     }
 
+    /// Serializes to a buffer, such as the XDR wire format ZFS
+    /// uses for labels and cachefiles.
+    pub fn pack(&self, encoding: NvEncoding) -> io::Result<Vec<u8>> {
+        let mut len = self.encoded_size(encoding)?;
+        let mut buf = vec![0u8; len];
+        let mut p = buf.as_mut_ptr() as *mut c_char;
+
+        let v = unsafe {
+            nv_sys::nvlist_pack(
+                self.as_ptr() as *mut _,
+                &mut p,
+                &mut len,
+                encoding.as_raw(),
+                0,
+            )
+        };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            buf.truncate(len);
+            Ok(buf)
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         let v = unsafe { nv_sys::nvlist_empty(self.as_ptr() as *mut _) };
         v != nv_sys::boolean::B_FALSE
@@ -189,6 +400,104 @@ impl NvListRef {
         }
     }
 
+    pub fn add_boolean_value<S: CStrArgument>(&mut self, name: S, v: bool) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_int8<S: CStrArgument>(&mut self, name: S, v: i8) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_uint8<S: CStrArgument>(&mut self, name: S, v: u8) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_int16<S: CStrArgument>(&mut self, name: S, v: i16) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_uint16<S: CStrArgument>(&mut self, name: S, v: u16) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_int32<S: CStrArgument>(&mut self, name: S, v: i32) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_uint32<S: CStrArgument>(&mut self, name: S, v: u32) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_int64<S: CStrArgument>(&mut self, name: S, v: i64) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_uint64<S: CStrArgument>(&mut self, name: S, v: u64) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_double<S: CStrArgument>(&mut self, name: S, v: f64) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_string<S: CStrArgument, V: CStrArgument>(
+        &mut self,
+        name: S,
+        v: V,
+    ) -> io::Result<()> {
+        v.into_cstr().as_ref().insert(name, self)
+    }
+
+    /// Adds a copy of `v`.
+    pub fn add_nvlist<S: CStrArgument>(&mut self, name: S, v: &NvListRef) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    /// Adds copies of `v`.
+    pub fn add_nvlist_array<S: CStrArgument>(&mut self, name: S, v: &[NvList]) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_int32_array<S: CStrArgument>(&mut self, name: S, v: &[i32]) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_uint32_array<S: CStrArgument>(&mut self, name: S, v: &[u32]) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_int64_array<S: CStrArgument>(&mut self, name: S, v: &[i64]) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_uint64_array<S: CStrArgument>(&mut self, name: S, v: &[u64]) -> io::Result<()> {
+        v.insert(name, self)
+    }
+
+    pub fn add_string_array<S: CStrArgument, V: AsRef<str>>(
+        &mut self,
+        name: S,
+        v: &[V],
+    ) -> io::Result<()> {
+        let xs = v
+            .iter()
+            .map(|x| ffi::CString::new(x.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        xs[..].insert(name, self)
+    }
+
+    /// Removes every pair called `name`, whatever its type.
+    pub fn remove_all<S: CStrArgument>(&mut self, name: S) -> io::Result<()> {
+        let name = name.into_cstr();
+        let v = unsafe { nv_sys::nvlist_remove_all(self.as_mut_ptr(), name.as_ref().as_ptr()) };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn first(&self) -> Option<&NvPair> {
         let np = unsafe { nv_sys::nvlist_next_nvpair(self.as_ptr() as *mut _, ptr::null_mut()) };
         if np.is_null() {
@@ -205,6 +514,11 @@ impl NvListRef {
         }
     }
 
+    /// Iterates over each pair's name and typed value.
+    pub fn data_iter(&self) -> NvDataIter<'_> {
+        NvDataIter(self.iter())
+    }
+
     pub fn exists<S: CStrArgument>(&self, name: S) -> bool {
         let name = name.into_cstr();
         let v = unsafe { nv_sys::nvlist_exists(self.as_ptr() as *mut _, name.as_ref().as_ptr()) };
@@ -240,7 +554,13 @@ impl NvListRef {
         }
     }
 
+    /// Looks up a nested `NvList`, returning an owned copy.
     pub fn lookup_nv_list<S: CStrArgument>(&self, name: S) -> io::Result<NvList> {
+        self.lookup_nv_list_ref(name)?.try_to_owned()
+    }
+
+    /// Looks up a nested `NvList` without copying it.
+    pub fn lookup_nv_list_ref<S: CStrArgument>(&self, name: S) -> io::Result<&NvListRef> {
         let name = name.into_cstr();
 
         let mut n = ptr::null_mut();
@@ -251,27 +571,27 @@ impl NvListRef {
         if v != 0 {
             Err(io::Error::from_raw_os_error(v))
         } else {
-            let r = unsafe { NvList::from_ptr(n) };
-
-            Ok(r)
+            Ok(unsafe { NvListRef::from_ptr(n) })
         }
     }
 
     pub fn lookup_string<S: CStrArgument>(&self, name: S) -> io::Result<ffi::CString> {
+        self.lookup_str(name).map(|x| x.to_owned())
+    }
+
+    /// Looks up a string without copying it out of the `NvList`.
+    pub fn lookup_str<S: CStrArgument>(&self, name: S) -> io::Result<&ffi::CStr> {
         let name = name.into_cstr();
-        let mut n;
+        let mut n = ptr::null_mut();
 
         let v = unsafe {
-            n = mem::uninitialized();
-
             nv_sys::nvlist_lookup_string(self.as_ptr() as *mut _, name.as_ref().as_ptr(), &mut n)
         };
 
         if v != 0 {
             Err(io::Error::from_raw_os_error(v))
         } else {
-            let s = unsafe { ffi::CStr::from_ptr(n).to_owned() };
-            Ok(s)
+            Ok(unsafe { ffi::CStr::from_ptr(n) })
         }
     }
 
@@ -291,7 +611,33 @@ impl NvListRef {
         }
     }
 
+    pub fn lookup_int32<S: CStrArgument>(&self, name: S) -> io::Result<i32> {
+        let name = name.into_cstr();
+        let mut n = 0;
+
+        let v = unsafe {
+            nv_sys::nvlist_lookup_int32(self.as_ptr() as *mut _, name.as_ref().as_ptr(), &mut n)
+        };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(n)
+        }
+    }
+
+    /// Looks up an array of nested `NvList`s, returning owned copies.
     pub fn lookup_nv_list_array<S: CStrArgument>(&self, name: S) -> io::Result<Vec<NvList>> {
+        self.lookup_nv_list_array_ref(name)?
+            .into_iter()
+            .map(|x| x.try_to_owned())
+            .collect()
+    }
+
+    /// Looks up an array of nested `NvList`s without copying them.
+    pub fn lookup_nv_list_array_ref<S: CStrArgument>(
+        &self,
+        name: S,
+    ) -> io::Result<Vec<&NvListRef>> {
         let name = name.into_cstr();
 
         let mut n = ptr::null_mut();
@@ -311,9 +657,9 @@ impl NvListRef {
             Err(io::Error::from_raw_os_error(v))
         } else {
             let r = unsafe {
-                ::std::slice::from_raw_parts(n, len as usize)
+                array(n, len)
                     .iter()
-                    .map(|x| NvList::from_ptr(*x))
+                    .map(|x| NvListRef::from_ptr(*x))
                     .collect()
             };
 
@@ -341,17 +687,35 @@ impl NvListRef {
         if v != 0 {
             Err(io::Error::from_raw_os_error(v))
         } else {
-            let r = unsafe {
-                ::std::slice::from_raw_parts(n, len as usize)
-                    .iter()
-                    .map(|x| *x)
-                    .collect()
-            };
+            let r = unsafe { array(n, len).to_vec() };
 
             Ok(r)
         }
     }
 
+    pub fn lookup_int64_array<S: CStrArgument>(&self, name: S) -> io::Result<Vec<i64>> {
+        let name = name.into_cstr();
+
+        let mut n = ptr::null_mut();
+
+        let mut len: c_uint = 0;
+
+        let v = unsafe {
+            nv_sys::nvlist_lookup_int64_array(
+                self.as_ptr() as *mut _,
+                name.as_ref().as_ptr(),
+                &mut n,
+                &mut len,
+            )
+        };
+
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(unsafe { array(n, len).to_vec() })
+        }
+    }
+
     pub fn try_to_owned(&self) -> io::Result<NvList> {
         let mut n = NvList(ptr::null_mut());
         let v = unsafe { nv_sys::nvlist_dup(self.as_ptr() as *mut _, &mut n.0, 0) };
@@ -363,6 +727,38 @@ impl NvListRef {
     }
 }
 
+/// An iterator over the pairs of an `NvList` and their values
+pub struct NvDataIter<'a>(NvListIter<'a>);
+
+impl<'a> Iterator for NvDataIter<'a> {
+    type Item = io::Result<(&'a ffi::CStr, NvData<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|x| Ok((x.name(), x.data()?)))
+    }
+}
+
+impl fmt::Debug for NvListRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut m = f.debug_map();
+
+        for x in self.iter() {
+            match x.data() {
+                Ok(d) => m.entry(&x.name(), &d),
+                Err(e) => m.entry(&x.name(), &e),
+            };
+        }
+
+        m.finish()
+    }
+}
+
+impl fmt::Debug for NvList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
 pub struct NvListIter<'a> {
     parent: &'a NvListRef,
     pos: *mut nv_sys::nvpair,
@@ -382,6 +778,69 @@ impl<'a> Iterator for NvListIter<'a> {
     }
 }
 
+/// A pair's value, borrowed from its `NvList` where possible
+#[derive(Debug)]
+pub enum NvData<'a> {
+    Unknown,
+    /// A boolean flag, which is true when present
+    Bool,
+    BoolV(bool),
+    Byte(u8),
+    Int8(i8),
+    Uint8(u8),
+    Int16(i16),
+    Uint16(u16),
+    Int32(i32),
+    Uint32(u32),
+    Int64(i64),
+    Uint64(u64),
+    HrTime(i64),
+    Double(f64),
+    Str(&'a ffi::CStr),
+    NvListRef(&'a NvListRef),
+    BoolArray(Vec<bool>),
+    ByteArray(Vec<u8>),
+    Int8Array(Vec<i8>),
+    Uint8Array(Vec<u8>),
+    Int16Array(Vec<i16>),
+    Uint16Array(Vec<u16>),
+    Int32Array(Vec<i32>),
+    Uint32Array(Vec<u32>),
+    Int64Array(Vec<i64>),
+    Uint64Array(Vec<u64>),
+    StrArray(Vec<&'a ffi::CStr>),
+    NvListRefArray(Vec<&'a NvListRef>),
+}
+
+macro_rules! nv_value {
+    ($name:ident, $f:ident, $t:ty) => {
+        pub fn $name(&self) -> io::Result<$t> {
+            let mut n: $t = Default::default();
+            let v = unsafe { nv_sys::$f(self.as_ptr(), &mut n) };
+            if v != 0 {
+                Err(io::Error::from_raw_os_error(v))
+            } else {
+                Ok(n)
+            }
+        }
+    };
+}
+
+macro_rules! nv_value_array {
+    ($name:ident, $f:ident, $t:ty) => {
+        pub fn $name(&self) -> io::Result<Vec<$t>> {
+            let mut n = ptr::null_mut();
+            let mut len: c_uint = 0;
+            let v = unsafe { nv_sys::$f(self.as_ptr(), &mut n, &mut len) };
+            if v != 0 {
+                Err(io::Error::from_raw_os_error(v))
+            } else {
+                Ok(unsafe { array(n, len).to_vec() })
+            }
+        }
+    };
+}
+
 pub struct NvPair(Opaque);
 impl ForeignTypeRef for NvPair {
     type CType = nv_sys::nvpair;
@@ -392,17 +851,414 @@ impl NvPair {
         unsafe { ffi::CStr::from_ptr(nv_sys::nvpair_name(self.as_ptr())) }
     }
 
+    /// The type of this pair's value
+    pub fn data_type(&self) -> NvDataType {
+        unsafe { nv_sys::nvpair_type(self.as_ptr()) }
+    }
+
+    /// Reads this pair's value, whatever its type.
+    pub fn data(&self) -> io::Result<NvData<'_>> {
+        Ok(match self.data_type() {
+            NvDataType::DATA_TYPE_BOOLEAN => NvData::Bool,
+            NvDataType::DATA_TYPE_BOOLEAN_VALUE => NvData::BoolV(self.value_boolean()?),
+            NvDataType::DATA_TYPE_BYTE => NvData::Byte(self.value_byte()?),
+            NvDataType::DATA_TYPE_INT8 => NvData::Int8(self.value_int8()?),
+            NvDataType::DATA_TYPE_UINT8 => NvData::Uint8(self.value_uint8()?),
+            NvDataType::DATA_TYPE_INT16 => NvData::Int16(self.value_int16()?),
+            NvDataType::DATA_TYPE_UINT16 => NvData::Uint16(self.value_uint16()?),
+            NvDataType::DATA_TYPE_INT32 => NvData::Int32(self.value_int32()?),
+            NvDataType::DATA_TYPE_UINT32 => NvData::Uint32(self.value_uint32()?),
+            NvDataType::DATA_TYPE_INT64 => NvData::Int64(self.value_int64()?),
+            NvDataType::DATA_TYPE_UINT64 => NvData::Uint64(self.value_uint64()?),
+            NvDataType::DATA_TYPE_HRTIME => NvData::HrTime(self.value_hrtime()?),
+            NvDataType::DATA_TYPE_DOUBLE => NvData::Double(self.value_double()?),
+            NvDataType::DATA_TYPE_STRING => NvData::Str(self.value_str()?),
+            NvDataType::DATA_TYPE_NVLIST => NvData::NvListRef(self.value_nv_list_ref()?),
+            NvDataType::DATA_TYPE_BOOLEAN_ARRAY => NvData::BoolArray(self.value_boolean_array()?),
+            NvDataType::DATA_TYPE_BYTE_ARRAY => NvData::ByteArray(self.value_byte_array()?),
+            NvDataType::DATA_TYPE_INT8_ARRAY => NvData::Int8Array(self.value_int8_array()?),
+            NvDataType::DATA_TYPE_UINT8_ARRAY => NvData::Uint8Array(self.value_uint8_array()?),
+            NvDataType::DATA_TYPE_INT16_ARRAY => NvData::Int16Array(self.value_int16_array()?),
+            NvDataType::DATA_TYPE_UINT16_ARRAY => NvData::Uint16Array(self.value_uint16_array()?),
+            NvDataType::DATA_TYPE_INT32_ARRAY => NvData::Int32Array(self.value_int32_array()?),
+            NvDataType::DATA_TYPE_UINT32_ARRAY => NvData::Uint32Array(self.value_uint32_array()?),
+            NvDataType::DATA_TYPE_INT64_ARRAY => NvData::Int64Array(self.value_int64_array()?),
+            NvDataType::DATA_TYPE_UINT64_ARRAY => NvData::Uint64Array(self.value_uint64_array()?),
+            NvDataType::DATA_TYPE_STRING_ARRAY => NvData::StrArray(self.value_str_array()?),
+            NvDataType::DATA_TYPE_NVLIST_ARRAY => {
+                NvData::NvListRefArray(self.value_nv_list_array_ref()?)
+            }
+            _ => NvData::Unknown,
+        })
+    }
+
+    pub fn value_boolean(&self) -> io::Result<bool> {
+        let mut n = nv_sys::boolean::B_FALSE;
+        let v = unsafe { nv_sys::nvpair_value_boolean_value(self.as_ptr(), &mut n) };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(n != nv_sys::boolean::B_FALSE)
+        }
+    }
+
+    nv_value!(value_byte, nvpair_value_byte, u8);
+    nv_value!(value_int8, nvpair_value_int8, i8);
+    nv_value!(value_uint8, nvpair_value_uint8, u8);
+    nv_value!(value_int16, nvpair_value_int16, i16);
+    nv_value!(value_uint16, nvpair_value_uint16, u16);
+    nv_value!(value_int32, nvpair_value_int32, i32);
+    nv_value!(value_uint32, nvpair_value_uint32, u32);
+    nv_value!(value_int64, nvpair_value_int64, i64);
+    nv_value!(value_uint64, nvpair_value_uint64, u64);
+    nv_value!(value_hrtime, nvpair_value_hrtime, i64);
+    nv_value!(value_double, nvpair_value_double, f64);
+
+    /// Borrows this pair's string value.
+    pub fn value_str(&self) -> io::Result<&ffi::CStr> {
+        let mut n = ptr::null_mut();
+        let v = unsafe { nv_sys::nvpair_value_string(self.as_ptr(), &mut n) };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(unsafe { ffi::CStr::from_ptr(n) })
+        }
+    }
+
+    pub fn value_boolean_array(&self) -> io::Result<Vec<bool>> {
+        let mut n = ptr::null_mut();
+        let mut len: c_uint = 0;
+        let v = unsafe { nv_sys::nvpair_value_boolean_array(self.as_ptr(), &mut n, &mut len) };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(unsafe { array(n, len) }
+                .iter()
+                .map(|x| *x != nv_sys::boolean::B_FALSE)
+                .collect())
+        }
+    }
+
+    nv_value_array!(value_byte_array, nvpair_value_byte_array, u8);
+    nv_value_array!(value_int8_array, nvpair_value_int8_array, i8);
+    nv_value_array!(value_uint8_array, nvpair_value_uint8_array, u8);
+    nv_value_array!(value_int16_array, nvpair_value_int16_array, i16);
+    nv_value_array!(value_uint16_array, nvpair_value_uint16_array, u16);
+    nv_value_array!(value_int32_array, nvpair_value_int32_array, i32);
+    nv_value_array!(value_uint32_array, nvpair_value_uint32_array, u32);
+    nv_value_array!(value_int64_array, nvpair_value_int64_array, i64);
+    nv_value_array!(value_uint64_array, nvpair_value_uint64_array, u64);
+
+    /// Borrows this pair's string array value.
+    pub fn value_str_array(&self) -> io::Result<Vec<&ffi::CStr>> {
+        let mut n = ptr::null_mut();
+        let mut len: c_uint = 0;
+        let v = unsafe { nv_sys::nvpair_value_string_array(self.as_ptr(), &mut n, &mut len) };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(unsafe { array(n, len) }
+                .iter()
+                .map(|x| unsafe { ffi::CStr::from_ptr(*x) })
+                .collect())
+        }
+    }
+
+    /// Borrows this pair's `NvList` array value.
+    pub fn value_nv_list_array_ref(&self) -> io::Result<Vec<&NvListRef>> {
+        let mut n = ptr::null_mut();
+        let mut len: c_uint = 0;
+        let v = unsafe { nv_sys::nvpair_value_nvlist_array(self.as_ptr(), &mut n, &mut len) };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(unsafe { array(n, len) }
+                .iter()
+                .map(|x| unsafe { NvListRef::from_ptr(*x) })
+                .collect())
+        }
+    }
+
+    /// Returns an owned copy of this pair's `NvList` value.
     pub fn value_nv_list(&self) -> io::Result<NvList> {
+        self.value_nv_list_ref()?.try_to_owned()
+    }
+
+    /// Borrows this pair's `NvList` value.
+    pub fn value_nv_list_ref(&self) -> io::Result<&NvListRef> {
         let mut nvl_target = ptr::null_mut();
 
         unsafe {
             let code = nv_sys::nvpair_value_nvlist(self.as_ptr(), &mut nvl_target);
 
             if code == 0 {
-                Ok(NvList::from_ptr(nvl_target))
+                Ok(NvListRef::from_ptr(nvl_target))
             } else {
                 Err(io::Error::from_raw_os_error(code))
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_typed_pairs() {
+        let mut child = NvList::new_unqiue_names().unwrap();
+        child.add_string("path", "/dev/sdb").unwrap();
+
+        let mut nvl = NvList::new_unqiue_names().unwrap();
+        nvl.add_boolean("flag").unwrap();
+        nvl.add_boolean_value("readonly", true).unwrap();
+        nvl.add_int8("i8", -8).unwrap();
+        nvl.add_uint8("u8", 8).unwrap();
+        nvl.add_int16("i16", -16).unwrap();
+        nvl.add_uint16("u16", 16).unwrap();
+        nvl.add_int32("i32", -32).unwrap();
+        nvl.add_uint32("u32", 32).unwrap();
+        nvl.add_int64("i64", -64).unwrap();
+        nvl.add_uint64("guid", 64).unwrap();
+        nvl.add_double("ratio", 1.5).unwrap();
+        nvl.add_string("name", "test").unwrap();
+        nvl.add_nvlist("child", &child).unwrap();
+        nvl.add_nvlist_array("children", &[child.try_clone().unwrap(), child])
+            .unwrap();
+        nvl.add_int32_array("i32s", &[-1, 2]).unwrap();
+        nvl.add_uint32_array("u32s", &[1, 2]).unwrap();
+        nvl.add_int64_array("i64s", &[-1, 2]).unwrap();
+        nvl.add_uint64_array("u64s", &[1, 2]).unwrap();
+        nvl.add_string_array("paths", &["/dev/sdb", "/dev/sdc"])
+            .unwrap();
+
+        assert!(nvl.exists("flag"));
+        assert!(nvl.lookup("readonly").unwrap().value_boolean().unwrap());
+        assert_eq!(nvl.lookup("i8").unwrap().value_int8().unwrap(), -8);
+        assert_eq!(nvl.lookup("u8").unwrap().value_uint8().unwrap(), 8);
+        assert_eq!(nvl.lookup("i16").unwrap().value_int16().unwrap(), -16);
+        assert_eq!(nvl.lookup("u16").unwrap().value_uint16().unwrap(), 16);
+        assert_eq!(nvl.lookup_int32("i32").unwrap(), -32);
+        assert_eq!(nvl.lookup("u32").unwrap().value_uint32().unwrap(), 32);
+        assert_eq!(nvl.lookup("i64").unwrap().value_int64().unwrap(), -64);
+        assert_eq!(nvl.lookup_uint64("guid").unwrap(), 64);
+        assert_eq!(nvl.lookup("ratio").unwrap().value_double().unwrap(), 1.5);
+        assert_eq!(nvl.lookup_str("name").unwrap().to_str().unwrap(), "test");
+        assert_eq!(
+            nvl.lookup_nv_list_ref("child")
+                .unwrap()
+                .lookup_str("path")
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "/dev/sdb"
+        );
+        assert_eq!(nvl.lookup_nv_list_array("children").unwrap().len(), 2);
+        assert_eq!(
+            nvl.lookup("i32s").unwrap().value_int32_array().unwrap(),
+            vec![-1, 2]
+        );
+        assert_eq!(
+            nvl.lookup("u32s").unwrap().value_uint32_array().unwrap(),
+            vec![1, 2]
+        );
+        assert_eq!(nvl.lookup_int64_array("i64s").unwrap(), vec![-1, 2]);
+        assert_eq!(nvl.lookup_uint64_array("u64s").unwrap(), vec![1, 2]);
+        assert_eq!(
+            nvl.lookup("paths").unwrap().value_str_array().unwrap(),
+            vec![
+                ffi::CString::new("/dev/sdb").unwrap().as_c_str(),
+                ffi::CString::new("/dev/sdc").unwrap().as_c_str(),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_strings_with_nul() {
+        let mut nvl = NvList::new_unqiue_names().unwrap();
+
+        let e = nvl
+            .add_string_array("paths", &["/dev/sdb", "bad\0"])
+            .unwrap_err();
+
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        assert!(!nvl.exists("paths"));
+    }
+
+    #[test]
+    fn removes_pairs_of_any_type() {
+        let mut nvl = NvList::new_unqiue_names().unwrap();
+        nvl.add_uint64("guid", 1).unwrap();
+        nvl.add_string("name", "test").unwrap();
+
+        nvl.remove_all("guid").unwrap();
+
+        assert!(!nvl.exists("guid"));
+        assert!(nvl.exists("name"));
+        assert!(nvl.remove_all("guid").is_err());
+
+        nvl.remove_all("name").unwrap();
+
+        assert!(nvl.is_empty());
+    }
+
+    #[test]
+    fn iterates_typed_pairs() {
+        let mut child = NvList::new_unqiue_names().unwrap();
+        child.add_uint64("guid", 2).unwrap();
+
+        let mut nvl = NvList::new_unqiue_names().unwrap();
+        nvl.add_boolean("flag").unwrap();
+        nvl.add_boolean_value("readonly", false).unwrap();
+        nvl.add_int32("i32", -32).unwrap();
+        nvl.add_uint64("guid", 1).unwrap();
+        nvl.add_double("ratio", 0.5).unwrap();
+        nvl.add_string("name", "test").unwrap();
+        nvl.add_nvlist("child", &child).unwrap();
+        nvl.add_nvlist_array("children", &[child]).unwrap();
+        nvl.add_uint64_array("stats", &[1, 2]).unwrap();
+        nvl.add_string_array("paths", &["/dev/sdb"]).unwrap();
+
+        let xs = nvl.data_iter().collect::<io::Result<Vec<_>>>().unwrap();
+
+        let names: Vec<_> = xs.iter().map(|x| x.0.to_str().unwrap()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "flag", "readonly", "i32", "guid", "ratio", "name", "child", "children", "stats",
+                "paths",
+            ]
+        );
+
+        assert!(matches!(xs[0].1, NvData::Bool));
+        assert!(matches!(xs[1].1, NvData::BoolV(false)));
+        assert!(matches!(xs[2].1, NvData::Int32(-32)));
+        assert!(matches!(xs[3].1, NvData::Uint64(1)));
+        assert!(matches!(xs[4].1, NvData::Double(x) if x == 0.5));
+        assert!(matches!(xs[5].1, NvData::Str(x) if x.to_str().unwrap() == "test"));
+
+        match xs[6].1 {
+            NvData::NvListRef(x) => assert_eq!(x.lookup_uint64("guid").unwrap(), 2),
+            ref x => panic!("expected an nvlist, got {:?}", x),
+        }
+
+        match xs[7].1 {
+            NvData::NvListRefArray(ref xs) => {
+                assert_eq!(xs.len(), 1);
+                assert_eq!(xs[0].lookup_uint64("guid").unwrap(), 2);
+            }
+            ref x => panic!("expected an nvlist array, got {:?}", x),
+        }
+
+        assert!(matches!(xs[8].1, NvData::Uint64Array(ref x) if x == &[1, 2]));
+        assert!(matches!(xs[9].1, NvData::StrArray(ref x) if x[0].to_str().unwrap() == "/dev/sdb"));
+    }
+
+    /// Counts the scalar values in `nvl` without knowing its layout.
+    fn count_values(nvl: &NvListRef) -> usize {
+        nvl.data_iter()
+            .map(|x| match x.unwrap().1 {
+                NvData::NvListRef(x) => count_values(x),
+                NvData::NvListRefArray(xs) => xs.into_iter().map(count_values).sum(),
+                _ => 1,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn walks_nested_lists() {
+        let mut leaf = NvList::new_unqiue_names().unwrap();
+        leaf.add_string("path", "/dev/sdb").unwrap();
+        leaf.add_uint64("guid", 1).unwrap();
+
+        let mut mirror = NvList::new_unqiue_names().unwrap();
+        mirror.add_string("type", "mirror").unwrap();
+        mirror
+            .add_nvlist_array("children", &[leaf.try_clone().unwrap(), leaf])
+            .unwrap();
+
+        let mut root = NvList::new_unqiue_names().unwrap();
+        root.add_nvlist("vdev_tree", &mirror).unwrap();
+        root.add_string("name", "test").unwrap();
+
+        assert_eq!(count_values(&root), 6);
+        assert_eq!(count_values(&NvList::new().unwrap()), 0);
+    }
+
+    fn vdev_config() -> NvList {
+        let mut leaf = NvList::new_unqiue_names().unwrap();
+        leaf.add_string("type", "disk").unwrap();
+        leaf.add_string("path", "/dev/sdb").unwrap();
+        leaf.add_uint64("guid", u64::MAX).unwrap();
+
+        let mut tree = NvList::new_unqiue_names().unwrap();
+        tree.add_string("type", "root").unwrap();
+        tree.add_nvlist_array("children", &[leaf]).unwrap();
+
+        let mut config = NvList::new_unqiue_names().unwrap();
+        config.add_string("name", "test").unwrap();
+        config.add_int64("state", -1).unwrap();
+        config.add_boolean_value("readonly", true).unwrap();
+        config.add_uint64_array("stats", &[1, 2, 3]).unwrap();
+        config.add_string_array("features", &["a", "b"]).unwrap();
+        config.add_nvlist("vdev_tree", &tree).unwrap();
+        config
+    }
+
+    fn assert_config(nvl: &NvListRef) {
+        assert_eq!(nvl.lookup_str("name").unwrap().to_str().unwrap(), "test");
+        assert_eq!(nvl.lookup("state").unwrap().value_int64().unwrap(), -1);
+        assert!(nvl.lookup("readonly").unwrap().value_boolean().unwrap());
+        assert_eq!(nvl.lookup_uint64_array("stats").unwrap(), vec![1, 2, 3]);
+        assert_eq!(
+            nvl.lookup("features")
+                .unwrap()
+                .value_str_array()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let children = nvl
+            .lookup_nv_list_ref("vdev_tree")
+            .unwrap()
+            .lookup_nv_list_array_ref("children")
+            .unwrap();
+
+        assert_eq!(children.len(), 1);
+        assert_eq!(
+            children[0].lookup_str("path").unwrap().to_str().unwrap(),
+            "/dev/sdb"
+        );
+        assert_eq!(children[0].lookup_uint64("guid").unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn round_trips_packed_lists() {
+        let config = vdev_config();
+
+        for &encoding in &[NvEncoding::Native, NvEncoding::Xdr] {
+            let buf = config.pack(encoding).unwrap();
+
+            assert_eq!(buf.len(), config.encoded_size(encoding).unwrap());
+            assert_eq!(buf[0] as c_int, encoding.as_raw());
+
+            assert_config(&NvList::unpack(&buf).unwrap());
+        }
+    }
+
+    #[test]
+    fn packs_empty_lists() {
+        let buf = NvList::new().unwrap().pack(NvEncoding::Xdr).unwrap();
+
+        assert!(NvList::unpack(&buf).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_damaged_buffers() {
+        let buf = vdev_config().pack(NvEncoding::Xdr).unwrap();
+
+        assert!(NvList::unpack(&buf[..buf.len() / 2]).is_err());
+        assert!(NvList::unpack(&[]).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Advisory pool ownership lock
+//!
+//! Cooperating management daemons on the same host take a `PoolLock`
+//! before importing, exporting or changing a pool. The lock is an `flock`
+//! on `<dir>/<guid>.lock`, so it is dropped by the kernel if the holder dies.
+//! The file also carries a `Lease` naming the current holder, which the
+//! holder renews while it keeps working on the pool.
+//!
+
+use libc;
+use serde_derive::{Deserialize, Serialize};
+use serde_json;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where pool locks are kept by default
+pub const LOCK_DIR: &str = "/run/libzfs";
+
+/// Who holds a pool lock, and until when
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct Lease {
+    pub owner: String,
+    pub pid: u32,
+    /// Seconds since the unix epoch
+    pub expires: u64,
+}
+
+impl Lease {
+    pub fn is_expired(&self) -> bool {
+        now() >= self.expires
+    }
+}
+
+pub struct PoolLock {
+    path: PathBuf,
+    file: File,
+    lease: Lease,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn lock_path(dir: &Path, guid: u64) -> PathBuf {
+    dir.join(format!("{}.lock", guid))
+}
+
+fn flock(f: &File, op: libc::c_int) -> io::Result<()> {
+    match unsafe { libc::flock(f.as_raw_fd(), op) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+fn open(dir: &Path, guid: u64) -> io::Result<(PathBuf, File)> {
+    fs::create_dir_all(dir)?;
+
+    let path = lock_path(dir, guid);
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)?;
+
+    Ok((path, file))
+}
+
+impl PoolLock {
+    /// Takes the lock for the pool with `guid`, waiting for any other holder.
+    pub fn acquire<P: AsRef<Path>>(
+        dir: P,
+        guid: u64,
+        owner: &str,
+        ttl: Duration,
+    ) -> io::Result<PoolLock> {
+        let (path, file) = open(dir.as_ref(), guid)?;
+
+        flock(&file, libc::LOCK_EX)?;
+
+        PoolLock::with_lease(path, file, owner, ttl)
+    }
+    /// Takes the lock for the pool with `guid` if nobody else holds it.
+    pub fn try_acquire<P: AsRef<Path>>(
+        dir: P,
+        guid: u64,
+        owner: &str,
+        ttl: Duration,
+    ) -> io::Result<Option<PoolLock>> {
+        let (path, file) = open(dir.as_ref(), guid)?;
+
+        match flock(&file, libc::LOCK_EX | libc::LOCK_NB) {
+            Ok(()) => PoolLock::with_lease(path, file, owner, ttl).map(Some),
+            Err(ref e) if e.raw_os_error() == Some(libc::EWOULDBLOCK) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    fn with_lease(path: PathBuf, file: File, owner: &str, ttl: Duration) -> io::Result<PoolLock> {
+        let mut lock = PoolLock {
+            path,
+            file,
+            lease: Lease {
+                owner: owner.to_string(),
+                pid: process::id(),
+                expires: 0,
+            },
+        };
+
+        lock.renew(ttl)?;
+
+        Ok(lock)
+    }
+    pub fn lease(&self) -> &Lease {
+        &self.lease
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Extends the lease to `ttl` from now.
+    pub fn renew(&mut self, ttl: Duration) -> io::Result<()> {
+        self.lease.expires = now() + ttl.as_secs();
+
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        serde_json::to_writer(&self.file, &self.lease)?;
+        self.file.flush()
+    }
+    /// Clears the lease and gives up the lock.
+    pub fn release(self) -> io::Result<()> {
+        self.file.set_len(0)?;
+
+        flock(&self.file, libc::LOCK_UN)
+    }
+}
+
+/// Reads the lease on the pool with `guid`, if it is currently held.
+pub fn current_lease<P: AsRef<Path>>(dir: P, guid: u64) -> io::Result<Option<Lease>> {
+    let mut file = match File::open(lock_path(dir.as_ref(), guid)) {
+        Ok(x) => x,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    match flock(&file, libc::LOCK_SH | libc::LOCK_NB) {
+        Ok(()) => return Ok(None),
+        Err(ref e) if e.raw_os_error() == Some(libc::EWOULDBLOCK) => {}
+        Err(e) => return Err(e),
+    };
+
+    let mut s = String::new();
+    file.read_to_string(&mut s)?;
+
+    if s.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_str(&s)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn lock_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("libzfs-lock-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        dir
+    }
+
+    #[test]
+    fn excludes_other_holders() {
+        let dir = lock_dir("exclude");
+        let ttl = Duration::from_secs(30);
+
+        let lock = PoolLock::try_acquire(&dir, 1, "a", ttl).unwrap().unwrap();
+
+        assert!(PoolLock::try_acquire(&dir, 1, "b", ttl).unwrap().is_none());
+        assert!(PoolLock::try_acquire(&dir, 2, "b", ttl).unwrap().is_some());
+
+        lock.release().unwrap();
+
+        assert!(PoolLock::try_acquire(&dir, 1, "b", ttl).unwrap().is_some());
+    }
+
+    #[test]
+    fn reports_current_lease() {
+        let dir = lock_dir("lease");
+
+        assert_eq!(current_lease(&dir, 1).unwrap(), None);
+
+        let mut lock = PoolLock::acquire(&dir, 1, "a", Duration::from_secs(0)).unwrap();
+
+        assert!(current_lease(&dir, 1).unwrap().unwrap().is_expired());
+
+        lock.renew(Duration::from_secs(30)).unwrap();
+
+        let lease = current_lease(&dir, 1).unwrap().unwrap();
+
+        assert_eq!(lease.owner, "a");
+        assert!(!lease.is_expired());
+
+        drop(lock);
+
+        assert_eq!(current_lease(&dir, 1).unwrap(), None);
+    }
+}
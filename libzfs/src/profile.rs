@@ -0,0 +1,142 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Named property profiles
+//!
+//! A `Profile` is a named bundle of property values, such as
+//! "database" or "lustre-ost", supplied by the caller. Applying one sets
+//! every differing property in a single operation and reports what changed.
+//!
+
+extern crate libzfs_sys as sys;
+
+use libzfs_types::Result;
+use nvpair::{self, NvEncode};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::io::Error;
+use zfs::Zfs;
+use zfs_prop::ALL_PROPS;
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub props: BTreeMap<String, String>,
+}
+
+/// A property that differs from the profile
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct PropChange {
+    pub name: String,
+    /// The value before the profile was applied, if the property was readable
+    pub from: Option<String>,
+    pub to: String,
+}
+
+/// Compares `profile` against the current values returned by `current`.
+pub fn profile_diff<F>(profile: &Profile, current: F) -> Vec<PropChange>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    profile
+        .props
+        .iter()
+        .filter_map(|(name, to)| {
+            let from = current(name);
+
+            if from.as_ref() == Some(to) {
+                None
+            } else {
+                Some(PropChange {
+                    name: name.clone(),
+                    from,
+                    to: to.clone(),
+                })
+            }
+        })
+        .collect()
+}
+
+fn current_value(ds: &Zfs, name: &str) -> Option<String> {
+    match ALL_PROPS.iter().find(|x| x.name() == name) {
+        Some(x) => ds.get_prop(*x).ok().map(|x| x.value),
+        None => ds
+            .user_props()
+            .lookup_nv_list_ref(name)
+            .and_then(|x| x.lookup_string(sys::zprop_value()))
+            .ok()
+            .and_then(|x| x.into_string().ok()),
+    }
+}
+
+/// Lists the changes applying `profile` to `ds` would make.
+pub fn plan_profile(ds: &Zfs, profile: &Profile) -> Vec<PropChange> {
+    profile_diff(profile, |name| current_value(ds, name))
+}
+
+/// Applies `profile` to `ds` in a single property update,
+/// returning the properties that were changed.
+pub fn apply_profile(ds: &Zfs, profile: &Profile) -> Result<Vec<PropChange>> {
+    let changes = plan_profile(ds, profile);
+
+    if changes.is_empty() {
+        return Ok(changes);
+    }
+
+    let mut nvl = nvpair::NvList::new()?;
+
+    for x in &changes {
+        CString::new(x.to.as_str())
+            .map_err(Error::from)?
+            .as_c_str()
+            .insert(x.name.as_str(), &mut nvl)?;
+    }
+
+    ds.set_props(&nvl)?;
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_only_differences() {
+        let profile = Profile {
+            name: "database".to_string(),
+            props: vec![
+                ("recordsize", "16384"),
+                ("compression", "lz4"),
+                ("iml:role", "db"),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        };
+
+        let current = |name: &str| match name {
+            "recordsize" => Some("131072".to_string()),
+            "compression" => Some("lz4".to_string()),
+            _ => None,
+        };
+
+        assert_eq!(
+            profile_diff(&profile, current),
+            vec![
+                PropChange {
+                    name: "iml:role".to_string(),
+                    from: None,
+                    to: "db".to_string(),
+                },
+                PropChange {
+                    name: "recordsize".to_string(),
+                    from: Some("131072".to_string()),
+                    to: "16384".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,481 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Compare a desired pool layout against live state
+//!
+//! `reconcile` takes a `PoolSpec` and the current `Pool` (see `state`)
+//! and produces an ordered list of `Action`s that would bring the pool
+//! in line with the spec. Nothing is applied; callers decide what to do
+//! with the plan.
+//!
+//! Only what is missing is planned: vdevs and datasets that are not
+//! in the spec are left alone.
+//!
+
+use libzfs_types::{Dataset, LibZfsError, Pool, Result, VDev};
+use serde_derive::{Deserialize, Serialize};
+use state;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+use vdev_spec::TopLevel;
+use zpool::Zpool;
+
+/// Desired state of a dataset
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct DatasetSpec {
+    pub name: String,
+    #[serde(default)]
+    pub props: BTreeMap<String, String>,
+}
+
+/// Desired state of a pool
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct PoolSpec {
+    pub name: String,
+    /// Top-level data vdevs
+    #[serde(default)]
+    pub data: Vec<TopLevel>,
+    /// Separate intent log vdevs
+    #[serde(default)]
+    pub logs: Vec<TopLevel>,
+    #[serde(default)]
+    pub spares: Vec<PathBuf>,
+    #[serde(default)]
+    pub cache: Vec<PathBuf>,
+    #[serde(default)]
+    pub datasets: Vec<DatasetSpec>,
+}
+
+/// A single step needed to converge on a `PoolSpec`
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub enum Action {
+    AddVdev {
+        pool: String,
+        vdev: TopLevel,
+    },
+    AddLog {
+        pool: String,
+        vdev: TopLevel,
+    },
+    AddSpare {
+        pool: String,
+        path: PathBuf,
+    },
+    AddCache {
+        pool: String,
+        path: PathBuf,
+    },
+    CreateDataset {
+        name: String,
+        props: BTreeMap<String, String>,
+    },
+    SetProp {
+        dataset: String,
+        name: String,
+        value: String,
+    },
+}
+
+fn leaf_paths(xs: &[VDev]) -> Vec<PathBuf> {
+    xs.iter()
+        .flat_map(|x| match x {
+            VDev::Disk { path, .. } | VDev::File { path, .. } => vec![path.clone()],
+            VDev::Mirror { children, .. }
+            | VDev::RaidZ { children, .. }
+            | VDev::Draid { children, .. }
+            | VDev::Replacing { children }
+            | VDev::Spare { children } => leaf_paths(children),
+            VDev::Root { children, .. } => leaf_paths(children),
+            VDev::L2Cache { path, .. } => path.iter().cloned().collect(),
+            VDev::DraidSpare { .. }
+            | VDev::Hole { .. }
+            | VDev::Indirect { .. }
+            | VDev::Missing { .. } => vec![],
+        })
+        .collect()
+}
+
+fn missing_devices(desired: &[PathBuf], actual: &[VDev]) -> Vec<PathBuf> {
+    let actual = leaf_paths(actual);
+
+    desired
+        .iter()
+        .filter(|x| !actual.contains(x))
+        .cloned()
+        .collect()
+}
+
+/// Whether `x` is laid out as `want`, with the same devices.
+fn same_shape(want: &TopLevel, x: &VDev) -> bool {
+    fn same_paths(want: &[PathBuf], xs: &[VDev]) -> bool {
+        let mut want = want.to_vec();
+        let mut xs = leaf_paths(xs);
+
+        want.sort();
+        xs.sort();
+
+        want == xs
+    }
+
+    match (want, x) {
+        (TopLevel::Device(p), VDev::Disk { path, .. })
+        | (TopLevel::Device(p), VDev::File { path, .. }) => p == path,
+        (TopLevel::Mirror(ps), VDev::Mirror { children, .. })
+        | (TopLevel::RaidZ { children: ps, .. }, VDev::RaidZ { children, .. }) => {
+            same_paths(ps, children)
+        }
+        _ => false,
+    }
+}
+
+fn missing_top_levels(desired: &[TopLevel], actual: &[&VDev]) -> Vec<TopLevel> {
+    desired
+        .iter()
+        .filter(|x| !actual.iter().any(|y| same_shape(x, y)))
+        .cloned()
+        .collect()
+}
+
+fn dataset_actions(spec: &DatasetSpec, actual: Option<&Dataset>) -> Vec<Action> {
+    match actual {
+        None => vec![Action::CreateDataset {
+            name: spec.name.clone(),
+            props: spec.props.clone(),
+        }],
+        Some(ds) => spec
+            .props
+            .iter()
+            .filter(|(k, v)| !ds.props.iter().any(|p| &&p.name == k && &&p.value == v))
+            .map(|(k, v)| Action::SetProp {
+                dataset: spec.name.clone(),
+                name: k.clone(),
+                value: v.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// Diffs `desired` against `actual` and returns the actions needed to converge.
+///
+/// `actual` must list every dataset in the pool, as `plan` and
+/// `Libzfs::export_state` do, or nested datasets that already exist
+/// will be planned for creation.
+///
+/// Pool level actions come first, then datasets ordered so parents are
+/// handled before their children. Errors if `desired` is for another pool.
+pub fn reconcile(desired: &PoolSpec, actual: &Pool) -> Result<Vec<Action>> {
+    if desired.name != actual.name {
+        return Err(LibZfsError::Io(Error::new(
+            ErrorKind::InvalidInput,
+            format!("spec is for pool {}, not {}", desired.name, actual.name),
+        )));
+    }
+
+    let empty = vec![];
+
+    let (children, logs, spares, cache) = match actual.vdev {
+        VDev::Root {
+            ref children,
+            ref logs,
+            ref spares,
+            ref cache,
+            ..
+        } => (children, logs, spares, cache),
+        _ => (&empty, &empty, &empty, &empty),
+    };
+
+    let data: Vec<&VDev> = children.iter().filter(|x| !logs.contains(x)).collect();
+    let logs: Vec<&VDev> = logs.iter().collect();
+
+    let mut actions: Vec<Action> = missing_top_levels(&desired.data, &data)
+        .into_iter()
+        .map(|vdev| Action::AddVdev {
+            pool: actual.name.clone(),
+            vdev,
+        })
+        .collect();
+
+    actions.extend(
+        missing_top_levels(&desired.logs, &logs)
+            .into_iter()
+            .map(|vdev| Action::AddLog {
+                pool: actual.name.clone(),
+                vdev,
+            }),
+    );
+
+    actions.extend(
+        missing_devices(&desired.spares, spares)
+            .into_iter()
+            .map(|path| Action::AddSpare {
+                pool: actual.name.clone(),
+                path,
+            }),
+    );
+
+    actions.extend(
+        missing_devices(&desired.cache, cache)
+            .into_iter()
+            .map(|path| Action::AddCache {
+                pool: actual.name.clone(),
+                path,
+            }),
+    );
+
+    let existing: HashMap<&str, &Dataset> = actual
+        .datasets
+        .iter()
+        .map(|x| (x.name.as_str(), x))
+        .collect();
+
+    let mut datasets: Vec<&DatasetSpec> = desired.datasets.iter().collect();
+    datasets.sort_by_key(|x| (x.name.matches('/').count(), x.name.clone()));
+
+    for spec in datasets {
+        let ds = existing.get(spec.name.as_str()).cloned();
+
+        actions.extend(dataset_actions(spec, ds));
+    }
+
+    Ok(actions)
+}
+
+/// Reads the live state of `pool`, with every dataset in it,
+/// and plans the actions that would bring it in line with `desired`.
+pub fn plan(desired: &PoolSpec, pool: &mut Zpool) -> Result<Vec<Action>> {
+    reconcile(desired, &state::convert_to_full_pool(pool)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libzfs_types::ZProp;
+
+    fn disk(path: &str) -> VDev {
+        VDev::Disk {
+            guid: None,
+            state: "ONLINE".to_string(),
+            path: path.into(),
+            dev_id: None,
+            phys_path: None,
+            whole_disk: None,
+            is_log: None,
+            read_errors: None,
+            write_errors: None,
+            checksum_errors: None,
+        }
+    }
+
+    fn pool() -> Pool {
+        Pool {
+            name: "test".to_string(),
+            guid: 1,
+            health: "ONLINE".to_string(),
+            hostname: "localhost".to_string(),
+            hostid: None,
+            state: "ACTIVE".to_string(),
+            readonly: false,
+            size: "0".to_string(),
+            vdev: VDev::Root {
+                children: vec![disk("/dev/sdb1")],
+                spares: vec![disk("/dev/sde1")],
+                cache: vec![],
+                logs: vec![],
+                special: vec![],
+                dedup: vec![],
+            },
+            props: vec![],
+            datasets: vec![Dataset {
+                name: "test/ds".to_string(),
+                guid: "2".to_string(),
+                kind: "filesystem".to_string(),
+                props: vec![ZProp {
+                    name: "compression".to_string(),
+                    value: "off".to_string(),
+                }],
+            }],
+        }
+    }
+
+    fn dataset(name: &str) -> DatasetSpec {
+        DatasetSpec {
+            name: name.to_string(),
+            props: BTreeMap::new(),
+        }
+    }
+
+    fn spec() -> PoolSpec {
+        PoolSpec {
+            name: "test".to_string(),
+            data: vec![],
+            logs: vec![],
+            spares: vec![],
+            cache: vec![],
+            datasets: vec![],
+        }
+    }
+
+    #[test]
+    fn no_drift_no_actions() {
+        let spec = PoolSpec {
+            name: "test".to_string(),
+            data: vec![],
+            logs: vec![],
+            spares: vec!["/dev/sde1".into()],
+            cache: vec![],
+            datasets: vec![DatasetSpec {
+                name: "test/ds".to_string(),
+                props: vec![("compression".to_string(), "off".to_string())]
+                    .into_iter()
+                    .collect(),
+            }],
+        };
+
+        assert_eq!(reconcile(&spec, &pool()).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn plans_ordered_actions() {
+        let mut props = BTreeMap::new();
+        props.insert("compression".to_string(), "lz4".to_string());
+
+        let spec = PoolSpec {
+            name: "test".to_string(),
+            data: vec![],
+            logs: vec![],
+            spares: vec!["/dev/sde1".into(), "/dev/sdf1".into()],
+            cache: vec!["/dev/sdd1".into()],
+            datasets: vec![
+                DatasetSpec {
+                    name: "test/ds/child".to_string(),
+                    props: BTreeMap::new(),
+                },
+                DatasetSpec {
+                    name: "test/ds".to_string(),
+                    props: props.clone(),
+                },
+            ],
+        };
+
+        assert_eq!(
+            reconcile(&spec, &pool()).unwrap(),
+            vec![
+                Action::AddSpare {
+                    pool: "test".to_string(),
+                    path: "/dev/sdf1".into(),
+                },
+                Action::AddCache {
+                    pool: "test".to_string(),
+                    path: "/dev/sdd1".into(),
+                },
+                Action::SetProp {
+                    dataset: "test/ds".to_string(),
+                    name: "compression".to_string(),
+                    value: "lz4".to_string(),
+                },
+                Action::CreateDataset {
+                    name: "test/ds/child".to_string(),
+                    props: BTreeMap::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_nested_datasets() {
+        let mut actual = pool();
+        actual.datasets.push(Dataset {
+            name: "test/ds/child".to_string(),
+            guid: "3".to_string(),
+            kind: "filesystem".to_string(),
+            props: vec![],
+        });
+
+        let spec = PoolSpec {
+            datasets: vec![dataset("test/ds/child"), dataset("test/ds/child/leaf")],
+            ..spec()
+        };
+
+        assert_eq!(
+            reconcile(&spec, &actual).unwrap(),
+            vec![Action::CreateDataset {
+                name: "test/ds/child/leaf".to_string(),
+                props: BTreeMap::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_other_pool() {
+        let spec = PoolSpec {
+            name: "other".to_string(),
+            ..spec()
+        };
+
+        assert!(reconcile(&spec, &pool()).is_err());
+    }
+
+    #[test]
+    fn plans_missing_vdevs() {
+        let mut actual = pool();
+        let log = VDev::Mirror {
+            children: vec![disk("/dev/sdg1"), disk("/dev/sdh1")],
+            is_log: Some(true),
+            id: Some(2),
+        };
+
+        if let VDev::Root {
+            ref mut children,
+            ref mut logs,
+            ..
+        } = actual.vdev
+        {
+            children.push(log.clone());
+            logs.push(log);
+        }
+
+        let mirror = TopLevel::Mirror(vec!["/dev/sdc1".into(), "/dev/sdd1".into()]);
+
+        let spec = PoolSpec {
+            data: vec![TopLevel::Device("/dev/sdb1".into()), mirror.clone()],
+            logs: vec![
+                TopLevel::Mirror(vec!["/dev/sdh1".into(), "/dev/sdg1".into()]),
+                TopLevel::Device("/dev/sdi1".into()),
+            ],
+            ..spec()
+        };
+
+        assert_eq!(
+            reconcile(&spec, &actual).unwrap(),
+            vec![
+                Action::AddVdev {
+                    pool: "test".to_string(),
+                    vdev: mirror,
+                },
+                Action::AddLog {
+                    pool: "test".to_string(),
+                    vdev: TopLevel::Device("/dev/sdi1".into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn leaf_paths_skips_placeholders() {
+        let xs = vec![
+            VDev::Spare {
+                children: vec![disk("/dev/sdb1"), disk("/dev/sde1")],
+            },
+            VDev::Hole { guid: Some(3) },
+            VDev::Indirect { guid: Some(4) },
+            VDev::Missing { guid: None },
+        ];
+
+        assert_eq!(
+            leaf_paths(&xs),
+            vec![PathBuf::from("/dev/sdb1"), PathBuf::from("/dev/sde1")]
+        );
+    }
+}
@@ -8,17 +8,21 @@
 //! Collects into a struct that can be serialized using `serde`.
 //!
 
+use std::cmp;
 use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
 
 use libzfs::Libzfs;
-use libzfs_types::{Dataset, Pool};
+use libzfs_types::{Dataset, Pool, ZProp};
 use libzfs_types::{LibZfsError, Result};
 use zfs::Zfs;
 use zpool::Zpool;
 
 /// Takes a Zfs reference and converts it into a
 /// `Dataset`
-fn convert_to_dataset(x: &Zfs) -> Result<Dataset> {
+pub(crate) fn convert_to_dataset(x: &Zfs) -> Result<Dataset> {
     let props = x.props()?;
 
     let guid = props
@@ -28,8 +32,8 @@ fn convert_to_dataset(x: &Zfs) -> Result<Dataset> {
         .map(|x| x.value.clone())?;
 
     Ok(Dataset {
-        name: x.name().into_string()?,
-        kind: x.zfs_type_name().into_string()?,
+        name: x.name_str()?,
+        kind: x.zfs_type_name_str()?,
         guid,
         props,
     })
@@ -37,26 +41,52 @@ fn convert_to_dataset(x: &Zfs) -> Result<Dataset> {
 
 /// Takes a Zpool reference and converts it into a
 /// `Pool`
-fn convert_to_js_pool(p: &Zpool) -> Result<Pool> {
+pub(crate) fn convert_to_js_pool(p: &Zpool) -> Result<Pool> {
     let xs: Vec<Dataset> = p
         .datasets()?
         .iter()
         .map(convert_to_dataset)
         .collect::<Result<_>>()?;
 
-    let hostname = p.hostname()?;
+    pool_with_datasets(p, xs)
+}
 
-    let hostid = p.hostid().ok();
+/// As `convert_to_js_pool`, with the pool's properties and every
+/// filesystem and volume in it at any depth, not just the first.
+pub(crate) fn convert_to_full_pool(p: &mut Zpool) -> Result<Pool> {
+    let xs: Vec<Dataset> = p
+        .dataset_iter()?
+        .map(|x| x.and_then(|x| convert_to_dataset(&x)))
+        .collect::<Result<_>>()?;
 
-    let health = p.health()?;
+    Ok(Pool {
+        props: convert_to_zprops(p)?,
+        ..pool_with_datasets(p, xs)?
+    })
+}
+
+/// Takes a Zpool reference and converts its properties
+/// into `ZProp`s, leaving out their sources.
+pub(crate) fn convert_to_zprops(p: &mut Zpool) -> Result<Vec<ZProp>> {
+    Ok(p.props()?
+        .into_iter()
+        .map(|x| ZProp {
+            name: x.name,
+            value: x.value,
+        })
+        .collect())
+}
+
+fn pool_with_datasets(p: &Zpool, xs: Vec<Dataset>) -> Result<Pool> {
+    let hostid = p.hostid().ok();
 
     Ok(Pool {
-        name: p.name().into_string()?,
-        health: health.into_string()?,
+        name: p.name_str()?,
+        health: p.health_str()?,
         guid: p.guid(),
-        hostname: hostname.into_string()?,
+        hostname: p.hostname_str()?,
         hostid,
-        state: p.state_name().into_string()?,
+        state: p.state_name_str()?,
         readonly: p.read_only(),
         size: p.size().to_string(),
         props: vec![],
@@ -109,3 +139,60 @@ pub fn get_imported_pools() -> Result<Vec<Pool>> {
         .map(convert_to_js_pool)
         .collect()
 }
+
+/// As `get_imported_pools`, but reads pools on up to `workers` threads,
+/// each with its own `Libzfs` handle, so a host with many pools
+/// is not walked one pool at a time.
+///
+/// Pools are returned in the order libzfs lists them. A pool exported
+/// while the snapshot is taken is left out.
+pub fn get_imported_pools_parallel(workers: usize) -> Result<Vec<Pool>> {
+    let names: Vec<String> = Libzfs::new()
+        .get_imported_pools()?
+        .iter()
+        .map(|x| x.name().into_string())
+        .collect::<::std::result::Result<_, _>>()?;
+
+    let workers = cmp::max(1, cmp::min(workers, names.len()));
+    let names = Arc::new(names);
+    let next = Arc::new(AtomicUsize::new(0));
+
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            let names = Arc::clone(&names);
+            let next = Arc::clone(&next);
+
+            thread::spawn(move || {
+                let mut libzfs = Libzfs::new();
+                let mut out = vec![];
+
+                loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+
+                    if i >= names.len() {
+                        break;
+                    }
+
+                    if let Some(p) = libzfs.pool_by_name(&names[i]) {
+                        out.push((i, convert_to_js_pool(&p)));
+                    }
+                }
+
+                out
+            })
+        })
+        .collect();
+
+    let mut xs = vec![];
+
+    for h in handles {
+        xs.extend(
+            h.join()
+                .map_err(|_| io::Error::new(io::ErrorKind::Other, "pool worker panicked"))?,
+        );
+    }
+
+    xs.sort_by_key(|x| x.0);
+
+    xs.into_iter().map(|(_, x)| x).collect()
+}
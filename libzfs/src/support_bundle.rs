@@ -0,0 +1,297 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Diagnostic support bundles
+//!
+//! Collects what support usually asks for when a pool misbehaves:
+//! each imported pool with its vdev tree, datasets and properties,
+//! its scan status, the most recent zevents, and the ZFS kstats.
+//! The bundle is written as gzipped JSON, optionally with hostnames
+//! redacted.
+//!
+
+use events::{self, Events};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use libzfs::Libzfs;
+use libzfs_types::{Pool, PoolProp, Result, ScanStatus, ZEvent};
+use serde_derive::{Deserialize, Serialize};
+use serde_json;
+use state::convert_to_js_pool;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zpool::Zpool;
+
+/// Where the SPL publishes ZFS kstats
+pub const KSTAT_DIR: &str = "/proc/spl/kstat/zfs";
+
+/// Replaces hostnames when redacting
+pub const REDACTED: &str = "<redacted>";
+
+/// The most zevents a bundle holds, keeping the newest
+pub const MAX_EVENTS: usize = 1000;
+
+/// Where a failure to read zevents is recorded in `SupportBundle::errors`
+pub const EVENTS_KEY: &str = "zevents";
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct PoolReport {
+    pub pool: Pool,
+    pub props: Vec<PoolProp>,
+    pub scan: Option<ScanStatus>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct SupportBundle {
+    /// The version of this crate that wrote the bundle
+    pub version: String,
+    /// Seconds since the unix epoch
+    pub created: u64,
+    pub hostname: Option<String>,
+    pub pools: Vec<PoolReport>,
+    /// Pools that could not be collected, with the reason,
+    /// and `EVENTS_KEY` if the zevents could not be read
+    pub errors: BTreeMap<String, String>,
+    /// The newest zevents the kernel still held, oldest first
+    #[serde(default)]
+    pub events: Vec<ZEvent>,
+    /// Kstat contents, keyed by path relative to `KSTAT_DIR`
+    pub kstats: BTreeMap<String, String>,
+}
+
+impl SupportBundle {
+    /// Collects a bundle from every imported pool.
+    pub fn collect(libzfs: &mut Libzfs) -> Result<SupportBundle> {
+        let mut pools = vec![];
+        let mut errors = BTreeMap::new();
+
+        for mut p in libzfs.get_imported_pools()? {
+            match pool_report(&mut p) {
+                Ok(x) => pools.push(x),
+                Err(e) => {
+                    errors.insert(p.name().to_string_lossy().into_owned(), e.to_string());
+                }
+            }
+        }
+
+        let events = match read_events(MAX_EVENTS) {
+            Ok(x) => x,
+            Err(e) => {
+                errors.insert(EVENTS_KEY.to_string(), e.to_string());
+                vec![]
+            }
+        };
+
+        Ok(SupportBundle {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            hostname: fs::read_to_string("/proc/sys/kernel/hostname")
+                .ok()
+                .map(|x| x.trim().to_string()),
+            pools,
+            errors,
+            events,
+            kstats: read_kstats(Path::new(KSTAT_DIR)),
+        })
+    }
+    /// Replaces every hostname in the bundle, including any
+    /// mention of this host in kstat contents.
+    pub fn redact(&mut self) {
+        let mut names: Vec<String> = self.hostname.iter().cloned().collect();
+
+        for x in &mut self.pools {
+            names.push(x.pool.hostname.clone());
+            x.pool.hostname = REDACTED.to_string();
+        }
+
+        names.retain(|x| !x.is_empty() && x != REDACTED);
+
+        for v in self.kstats.values_mut() {
+            for n in &names {
+                *v = v.replace(n.as_str(), REDACTED);
+            }
+        }
+
+        if self.hostname.is_some() {
+            self.hostname = Some(REDACTED.to_string());
+        }
+    }
+    /// Writes the bundle to `path` as gzipped JSON.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut gz = GzEncoder::new(File::create(path)?, Compression::default());
+
+        serde_json::to_writer(&mut gz, self).map_err(::std::io::Error::from)?;
+
+        gz.finish()?.flush()?;
+
+        Ok(())
+    }
+}
+
+fn pool_report(p: &mut Zpool) -> Result<PoolReport> {
+    Ok(PoolReport {
+        pool: convert_to_js_pool(p)?,
+        props: p.props()?,
+        scan: p.scan_status()?,
+    })
+}
+
+/// Pushes `x`, dropping the oldest entry to stay within `max`.
+fn push_bounded<T>(xs: &mut VecDeque<T>, x: T, max: usize) {
+    if max == 0 {
+        return;
+    }
+
+    if xs.len() == max {
+        xs.pop_front();
+    }
+
+    xs.push_back(x);
+}
+
+/// Reads the zevents the kernel still holds without waiting for more,
+/// keeping the newest `max`. Events that cannot be decoded are skipped.
+fn read_events(max: usize) -> Result<Vec<ZEvent>> {
+    let mut events = Events::open()?;
+    let mut out = VecDeque::new();
+
+    while let Some(nvl) = events.next_nvlist(false)? {
+        if let Ok(x) = events::decode(&nvl) {
+            push_bounded(&mut out, x, max);
+        }
+    }
+
+    Ok(out.into_iter().collect())
+}
+
+/// Reads every kstat under `dir`, including per-pool directories.
+fn read_kstats(dir: &Path) -> BTreeMap<String, String> {
+    fn walk(root: &Path, dir: &Path, out: &mut BTreeMap<String, String>) {
+        let entries = match fs::read_dir(dir) {
+            Ok(x) => x,
+            Err(_) => return,
+        };
+
+        for entry in entries.filter_map(|x| x.ok()) {
+            let path = entry.path();
+
+            if path.is_dir() {
+                walk(root, &path, out);
+            } else if let Ok(s) = fs::read_to_string(&path) {
+                let key = path.strip_prefix(root).unwrap_or(&path);
+
+                out.insert(key.to_string_lossy().into_owned(), s);
+            }
+        }
+    }
+
+    let mut out = BTreeMap::new();
+
+    walk(dir, dir, &mut out);
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libzfs_types::VDev;
+
+    #[test]
+    fn redacts_hostnames() {
+        let mut bundle = SupportBundle {
+            version: "0".to_string(),
+            created: 0,
+            hostname: Some("oss1".to_string()),
+            pools: vec![PoolReport {
+                pool: Pool {
+                    name: "test".to_string(),
+                    guid: 1,
+                    health: "ONLINE".to_string(),
+                    hostname: "oss1".to_string(),
+                    hostid: None,
+                    state: "ACTIVE".to_string(),
+                    readonly: false,
+                    size: "0".to_string(),
+                    vdev: VDev::Root {
+                        children: vec![],
+                        spares: vec![],
+                        cache: vec![],
+                        logs: vec![],
+                        special: vec![],
+                        dedup: vec![],
+                    },
+                    props: vec![],
+                    datasets: vec![],
+                },
+                props: vec![],
+                scan: None,
+            }],
+            errors: BTreeMap::new(),
+            events: vec![],
+            kstats: vec![("test/state".to_string(), "imported on oss1\n".to_string())]
+                .into_iter()
+                .collect(),
+        };
+
+        bundle.redact();
+
+        assert_eq!(bundle.hostname.as_deref(), Some(REDACTED));
+        assert_eq!(bundle.pools[0].pool.hostname, REDACTED);
+        assert_eq!(bundle.kstats["test/state"], "imported on <redacted>\n");
+    }
+
+    fn event(eid: u64) -> ZEvent {
+        ZEvent {
+            class: "sysevent.fs.zfs.scrub_finish".to_string(),
+            eid,
+            time_secs: 0,
+            time_nsecs: 0,
+            pool: Some("test".to_string()),
+            pool_guid: None,
+            vdev_guid: None,
+            vdev_path: None,
+        }
+    }
+
+    #[test]
+    fn keeps_newest_events() {
+        let mut xs = VecDeque::new();
+
+        for i in 0..5 {
+            push_bounded(&mut xs, event(i), 3);
+        }
+
+        let eids: Vec<u64> = xs.iter().map(|x| x.eid).collect();
+
+        assert_eq!(eids, vec![2, 3, 4]);
+
+        let mut xs = VecDeque::new();
+        push_bounded(&mut xs, event(0), 0);
+
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn reads_bundle_without_events() {
+        let x = r#"{
+            "version": "0.6.16",
+            "created": 0,
+            "hostname": null,
+            "pools": [],
+            "errors": {},
+            "kstats": {}
+        }"#;
+
+        let bundle: SupportBundle = serde_json::from_str(x).unwrap();
+
+        assert!(bundle.events.is_empty());
+    }
+}
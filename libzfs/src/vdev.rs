@@ -4,85 +4,142 @@
 
 extern crate libzfs_sys as sys;
 
-pub use libzfs_types::VDev;
 use libzfs_types::{LibZfsError, Result};
+pub use libzfs_types::{VDev, VDevInfo, VDevOpState, VDevProgress, VDevStats};
 use nvpair;
-use std::ffi::CStr;
+use std::collections::HashMap;
+use std::ffi::{CStr, OsStr};
 use std::io::{Error, ErrorKind};
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
 
-pub fn enumerate_vdev_tree(tree: &nvpair::NvList) -> Result<VDev> {
-    let tmp = tree.lookup_string(sys::zpool_config_type())?;
-    let x = tmp.as_bytes_with_nul();
+fn lookup_state(tree: &nvpair::NvListRef) -> Result<String> {
+    let vdev_stats = tree
+        .lookup_uint64_array(sys::zpool_config_vdev_stats())
+        .map(sys::to_vdev_stat)?;
 
-    fn get_children(tree: &nvpair::NvList) -> Result<Vec<VDev>> {
-        tree.lookup_nv_list_array(sys::zpool_config_children())?
-            .iter()
+    let state = unsafe {
+        let s = sys::zpool_state_to_name(
+            sys::to_vdev_state(vdev_stats.vs_state as u32)
+                .ok_or_else(|| Error::new(ErrorKind::NotFound, "vs_state not in enum range"))?,
+            sys::to_vdev_aux(vdev_stats.vs_aux as u32)
+                .ok_or_else(|| Error::new(ErrorKind::NotFound, "vs_aux not in enum range"))?,
+        );
+
+        CStr::from_ptr(s)
+    };
+
+    state.to_owned().into_string().map_err(LibZfsError::from)
+}
+
+/// dRAID vdev types and config keys, which came with OpenZFS 2.1
+/// and so are missing from the generated bindings.
+const VDEV_TYPE_DRAID: &[u8] = b"draid\0";
+const VDEV_TYPE_DRAID_SPARE: &[u8] = b"dspare\0";
+const ZPOOL_CONFIG_DRAID_NDATA: &str = "draid_ndata";
+const ZPOOL_CONFIG_DRAID_NSPARES: &str = "draid_nspares";
+const ZPOOL_CONFIG_DRAID_NGROUPS: &str = "draid_ngroups";
+
+/// Index of `ZIO_TYPE_READ` in `vs_ops` and `vs_bytes`
+const ZIO_TYPE_READ: usize = 1;
+/// Index of `ZIO_TYPE_WRITE` in `vs_ops` and `vs_bytes`
+const ZIO_TYPE_WRITE: usize = 2;
+
+/// Reads the counters of a vdev's `vdev_stat_t`.
+///
+/// Only the leading fields, which every supported ZFS version
+/// lays out the same way, are read.
+pub fn lookup_stats(tree: &nvpair::NvListRef) -> Result<VDevStats> {
+    let xs = tree.lookup_uint64_array(sys::zpool_config_vdev_stats())?;
+
+    if xs.len() < 23 {
+        return Err(LibZfsError::Io(Error::new(
+            ErrorKind::InvalidData,
+            "vdev stats are truncated",
+        )));
+    }
+
+    let ops = &xs[8..14];
+    let bytes = &xs[14..20];
+
+    Ok(VDevStats {
+        alloc: xs[3],
+        space: xs[4],
+        dspace: xs[5],
+        rsize: xs[6],
+        esize: xs[7],
+        read_ops: ops[ZIO_TYPE_READ],
+        write_ops: ops[ZIO_TYPE_WRITE],
+        read_bytes: bytes[ZIO_TYPE_READ],
+        write_bytes: bytes[ZIO_TYPE_WRITE],
+        read_errors: xs[20],
+        write_errors: xs[21],
+        checksum_errors: xs[22],
+    })
+}
+
+pub fn enumerate_vdev_tree(tree: &nvpair::NvListRef) -> Result<VDev> {
+    let x = tree
+        .lookup_str(sys::zpool_config_type())?
+        .to_bytes_with_nul();
+
+    fn get_children(tree: &nvpair::NvListRef) -> Result<Vec<VDev>> {
+        tree.lookup_nv_list_array_ref(sys::zpool_config_children())?
+            .into_iter()
             .map(enumerate_vdev_tree)
             .collect()
     }
 
-    fn get_spares(tree: &nvpair::NvList) -> Result<Vec<VDev>> {
-        let spares = tree.lookup_nv_list_array(sys::zpool_config_spares());
+    fn get_spares(tree: &nvpair::NvListRef) -> Result<Vec<VDev>> {
+        let spares = tree.lookup_nv_list_array_ref(sys::zpool_config_spares());
 
         match spares {
-            Ok(x) => x.iter().map(enumerate_vdev_tree).collect(),
+            Ok(x) => x.into_iter().map(enumerate_vdev_tree).collect(),
             Err(_) => Ok(vec![]),
         }
     }
 
-    fn get_cache(tree: &nvpair::NvList) -> Result<Vec<VDev>> {
-        let cache = tree.lookup_nv_list_array(sys::zpool_config_l2cache());
+    fn get_cache(tree: &nvpair::NvListRef) -> Result<Vec<VDev>> {
+        let cache = tree.lookup_nv_list_array_ref(sys::zpool_config_l2cache());
 
         match cache {
-            Ok(x) => x.iter().map(enumerate_vdev_tree).collect(),
+            Ok(x) => x.into_iter().map(enumerate_vdev_tree).collect(),
             Err(_) => Ok(vec![]),
         }
     }
 
-    fn lookup_tree_str(tree: &nvpair::NvList, name: String) -> Result<Option<String>> {
-        let x = tree.lookup_string(name);
+    fn lookup_tree_str(tree: &nvpair::NvListRef, name: String) -> Result<Option<String>> {
+        let x = tree.lookup_str(name);
 
         match x {
-            Ok(x) => Ok(Some(x.into_string()?)),
+            Ok(x) => Ok(Some(x.to_owned().into_string()?)),
             Err(_) => Ok(None),
         }
     }
 
-    fn lookup_is_log(tree: &nvpair::NvList) -> Option<bool> {
+    fn lookup_path(tree: &nvpair::NvListRef) -> Result<PathBuf> {
+        let x = tree.lookup_str(sys::zpool_config_path())?;
+
+        Ok(OsStr::from_bytes(x.to_bytes()).into())
+    }
+
+    fn lookup_is_log(tree: &nvpair::NvListRef) -> Option<bool> {
         tree.lookup_uint64(sys::zpool_config_is_log())
             .map(|x| x == 1)
             .ok()
     }
 
-    fn lookup_guid(tree: &nvpair::NvList) -> Option<u64> {
+    fn lookup_guid(tree: &nvpair::NvListRef) -> Option<u64> {
         tree.lookup_uint64(sys::zpool_config_guid()).ok()
     }
 
-    fn lookup_state(tree: &nvpair::NvList) -> Result<String> {
-        let vdev_stats = tree
-            .lookup_uint64_array(sys::zpool_config_vdev_stats())
-            .map(sys::to_vdev_stat)?;
-
-        let state = unsafe {
-            let s = sys::zpool_state_to_name(
-                sys::to_vdev_state(vdev_stats.vs_state as u32)
-                    .ok_or_else(|| Error::new(ErrorKind::NotFound, "vs_state not in enum range"))?,
-                sys::to_vdev_aux(vdev_stats.vs_aux as u32)
-                    .ok_or_else(|| Error::new(ErrorKind::NotFound, "vs_aux not in enum range"))?,
-            );
-
-            CStr::from_ptr(s)
-        };
-
-        state.to_owned().into_string().map_err(LibZfsError::from)
+    fn lookup_id(tree: &nvpair::NvListRef) -> Option<u64> {
+        tree.lookup_uint64(sys::zpool_config_id()).ok()
     }
 
     match x {
         x if x == sys::VDEV_TYPE_DISK => {
-            let path = tree
-                .lookup_string(sys::zpool_config_path())?
-                .into_string()?
-                .into();
+            let path = lookup_path(tree)?;
             let dev_id = lookup_tree_str(tree, sys::zpool_config_dev_id())?;
             let phys_path = lookup_tree_str(tree, sys::zpool_config_phys_path())?;
             let whole_disk = tree
@@ -90,6 +147,8 @@ pub fn enumerate_vdev_tree(tree: &nvpair::NvList) -> Result<VDev> {
                 .map(|x| x == 1)
                 .ok();
 
+            let stats = lookup_stats(tree).ok();
+
             Ok(VDev::Disk {
                 guid: lookup_guid(tree),
                 state: lookup_state(tree)?,
@@ -98,19 +157,24 @@ pub fn enumerate_vdev_tree(tree: &nvpair::NvList) -> Result<VDev> {
                 phys_path,
                 whole_disk,
                 is_log: lookup_is_log(tree),
+                read_errors: stats.as_ref().map(|x| x.read_errors),
+                write_errors: stats.as_ref().map(|x| x.write_errors),
+                checksum_errors: stats.as_ref().map(|x| x.checksum_errors),
             })
         }
         x if x == sys::VDEV_TYPE_FILE => {
-            let path = tree
-                .lookup_string(sys::zpool_config_path())?
-                .into_string()?
-                .into();
+            let path = lookup_path(tree)?;
+
+            let stats = lookup_stats(tree).ok();
 
             Ok(VDev::File {
                 guid: lookup_guid(tree),
                 state: lookup_state(tree)?,
                 path,
                 is_log: lookup_is_log(tree),
+                read_errors: stats.as_ref().map(|x| x.read_errors),
+                write_errors: stats.as_ref().map(|x| x.write_errors),
+                checksum_errors: stats.as_ref().map(|x| x.checksum_errors),
             })
         }
         x if x == sys::VDEV_TYPE_MIRROR => {
@@ -120,21 +184,87 @@ pub fn enumerate_vdev_tree(tree: &nvpair::NvList) -> Result<VDev> {
                 .map(|x| x == 1)
                 .ok();
 
-            Ok(VDev::Mirror { children, is_log })
+            Ok(VDev::Mirror {
+                children,
+                is_log,
+                id: lookup_id(tree),
+            })
         }
         x if x == sys::VDEV_TYPE_RAIDZ => {
             let children = get_children(tree)?;
 
-            Ok(VDev::RaidZ { children })
+            Ok(VDev::RaidZ {
+                children,
+                parity: tree.lookup_uint64(sys::zpool_config_nparity()).ok(),
+                id: lookup_id(tree),
+            })
+        }
+        x if x == VDEV_TYPE_DRAID => {
+            let children = get_children(tree)?;
+            let lookup = |name| tree.lookup_uint64(name).ok();
+
+            Ok(VDev::Draid {
+                children,
+                parity: lookup(sys::zpool_config_nparity().as_str()),
+                data_disks: lookup(ZPOOL_CONFIG_DRAID_NDATA),
+                spares: lookup(ZPOOL_CONFIG_DRAID_NSPARES),
+                groups: lookup(ZPOOL_CONFIG_DRAID_NGROUPS),
+                id: lookup_id(tree),
+            })
         }
+        x if x == VDEV_TYPE_DRAID_SPARE => Ok(VDev::DraidSpare {
+            guid: lookup_guid(tree),
+            state: lookup_state(tree)?,
+            name: lookup_path(tree)?.to_string_lossy().into_owned(),
+        }),
         x if x == sys::VDEV_TYPE_REPLACING => {
             let children = get_children(tree)?;
 
             Ok(VDev::Replacing { children })
         }
-        x if x == sys::VDEV_TYPE_ROOT => {
+        x if x == sys::VDEV_TYPE_SPARE => {
             let children = get_children(tree)?;
 
+            Ok(VDev::Spare { children })
+        }
+        x if x == sys::VDEV_TYPE_L2CACHE => Ok(VDev::L2Cache {
+            guid: lookup_guid(tree),
+            path: lookup_path(tree).ok(),
+        }),
+        x if x == sys::VDEV_TYPE_HOLE => Ok(VDev::Hole {
+            guid: lookup_guid(tree),
+        }),
+        x if x == sys::VDEV_TYPE_INDIRECT => Ok(VDev::Indirect {
+            guid: lookup_guid(tree),
+        }),
+        x if x == sys::VDEV_TYPE_MISSING => Ok(VDev::Missing {
+            guid: lookup_guid(tree),
+        }),
+        x if x == sys::VDEV_TYPE_ROOT => {
+            let mut children = vec![];
+            let mut logs = vec![];
+            let mut special = vec![];
+            let mut dedup = vec![];
+
+            for x in tree.lookup_nv_list_array_ref(sys::zpool_config_children())? {
+                let bias = x
+                    .lookup_str(sys::zpool_config_allocation_bias())
+                    .map(|x| x.to_bytes_with_nul().to_vec())
+                    .unwrap_or_default();
+
+                let vdev = enumerate_vdev_tree(x)?;
+
+                if lookup_is_log(x) == Some(true) {
+                    logs.push(vdev.clone());
+                } else if bias == sys::VDEV_ALLOC_BIAS_SPECIAL {
+                    special.push(vdev.clone());
+                } else if bias == sys::VDEV_ALLOC_BIAS_DEDUP {
+                    dedup.push(vdev.clone());
+                }
+
+                children.push(vdev);
+            }
+
             let spares = get_spares(tree)?;
 
             let cache = get_cache(tree)?;
@@ -143,6 +273,9 @@ pub fn enumerate_vdev_tree(tree: &nvpair::NvList) -> Result<VDev> {
                 children,
                 spares,
                 cache,
+                logs,
+                special,
+                dedup,
             })
         }
         _ => Err(LibZfsError::Io(Error::new(
@@ -151,3 +284,424 @@ pub fn enumerate_vdev_tree(tree: &nvpair::NvList) -> Result<VDev> {
         ))),
     }
 }
+
+/// Indexes every vdev in `tree` by guid in a single pass.
+///
+/// Spares and cache devices are included.
+pub fn vdev_map(tree: &nvpair::NvListRef) -> Result<HashMap<u64, VDevInfo>> {
+    fn walk(
+        tree: &nvpair::NvListRef,
+        parent: Option<u64>,
+        map: &mut HashMap<u64, VDevInfo>,
+    ) -> Result<()> {
+        let guid = tree.lookup_uint64(sys::zpool_config_guid())?;
+
+        let path = tree
+            .lookup_str(sys::zpool_config_path())
+            .ok()
+            .map(|x| OsStr::from_bytes(x.to_bytes()).into());
+
+        map.insert(
+            guid,
+            VDevInfo {
+                guid,
+                kind: tree
+                    .lookup_str(sys::zpool_config_type())?
+                    .to_string_lossy()
+                    .into_owned(),
+                path,
+                state: lookup_state(tree)?,
+                parent,
+                stats: lookup_stats(tree)?,
+                ashift: tree.lookup_uint64(sys::zpool_config_ashift()).ok(),
+                asize: tree.lookup_uint64(sys::zpool_config_asize()).ok(),
+                metaslab_shift: tree.lookup_uint64(sys::zpool_config_metaslab_shift()).ok(),
+            },
+        );
+
+        for key in &[
+            sys::zpool_config_children(),
+            sys::zpool_config_spares(),
+            sys::zpool_config_l2cache(),
+        ] {
+            if let Ok(xs) = tree.lookup_nv_list_array_ref(key.as_str()) {
+                for x in xs {
+                    walk(x, Some(guid), map)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut map = HashMap::new();
+
+    walk(tree, None, &mut map)?;
+
+    Ok(map)
+}
+
+/// Paths of the leaf vdevs that hold pool data, leaving out spares and cache devices.
+pub fn data_leaf_paths(tree: &nvpair::NvListRef) -> Result<Vec<PathBuf>> {
+    fn walk(tree: &nvpair::NvListRef, out: &mut Vec<PathBuf>) -> Result<()> {
+        if let Ok(xs) = tree.lookup_nv_list_array_ref(sys::zpool_config_children()) {
+            for x in xs {
+                walk(x, out)?;
+            }
+        } else if let Ok(x) = tree.lookup_str(sys::zpool_config_path()) {
+            out.push(OsStr::from_bytes(x.to_bytes()).into());
+        }
+
+        Ok(())
+    }
+
+    let mut out = vec![];
+
+    walk(tree, &mut out)?;
+
+    Ok(out)
+}
+
+/// Where initialize progress starts in a ZFS 0.8 `vdev_stat_t`
+pub const VS_INITIALIZE_OFFSET: usize = 28;
+/// Where TRIM progress starts in a ZFS 0.8 `vdev_stat_t`
+pub const VS_TRIM_OFFSET: usize = 37;
+
+/// Where the deferred resilver flag is in a ZFS 0.8 `vdev_stat_t`
+const VS_RESILVER_DEFERRED: usize = 33;
+
+/// Guids of the leaf vdevs waiting on a deferred resilver.
+pub fn deferred_resilvers(tree: &nvpair::NvListRef) -> Result<Vec<u64>> {
+    fn walk(tree: &nvpair::NvListRef, out: &mut Vec<u64>) -> Result<()> {
+        if let Ok(xs) = tree.lookup_nv_list_array_ref(sys::zpool_config_children()) {
+            for x in xs {
+                walk(x, out)?;
+            }
+
+            return Ok(());
+        }
+
+        let xs = tree.lookup_uint64_array(sys::zpool_config_vdev_stats())?;
+
+        if xs.get(VS_RESILVER_DEFERRED).cloned().unwrap_or(0) != 0 {
+            out.push(tree.lookup_uint64(sys::zpool_config_guid())?);
+        }
+
+        Ok(())
+    }
+
+    let mut out = vec![];
+
+    walk(tree, &mut out)?;
+
+    Ok(out)
+}
+
+/// Reads per-leaf progress stored at `offset` in each leaf's `vdev_stat_t`,
+/// keyed by guid.
+///
+/// Leaves are left out if their stats predate the operation,
+/// as with ZFS older than 0.8.
+pub fn leaf_progress(
+    tree: &nvpair::NvListRef,
+    offset: usize,
+) -> Result<HashMap<u64, VDevProgress>> {
+    fn walk(
+        tree: &nvpair::NvListRef,
+        offset: usize,
+        map: &mut HashMap<u64, VDevProgress>,
+    ) -> Result<()> {
+        if let Ok(xs) = tree.lookup_nv_list_array_ref(sys::zpool_config_children()) {
+            for x in xs {
+                walk(x, offset, map)?;
+            }
+
+            return Ok(());
+        }
+
+        let xs = tree.lookup_uint64_array(sys::zpool_config_vdev_stats())?;
+
+        if xs.len() < offset + 4 {
+            return Ok(());
+        }
+
+        map.insert(
+            tree.lookup_uint64(sys::zpool_config_guid())?,
+            VDevProgress {
+                bytes_done: xs[offset],
+                bytes_est: xs[offset + 1],
+                state: VDevOpState::from_raw(xs[offset + 2]).ok_or_else(|| {
+                    Error::new(ErrorKind::InvalidData, "vdev op state not in enum range")
+                })?,
+                action_time: xs[offset + 3],
+            },
+        );
+
+        Ok(())
+    }
+
+    let mut map = HashMap::new();
+
+    walk(tree, offset, &mut map)?;
+
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk(path: &str, guid: u64) -> nvpair::NvList {
+        let mut x = nvpair::NvList::new().unwrap();
+        x.add_string(sys::zpool_config_type(), "disk").unwrap();
+        x.add_string(sys::zpool_config_path(), path).unwrap();
+        x.add_uint64(sys::zpool_config_guid(), guid).unwrap();
+        x.add_uint64_array(sys::zpool_config_vdev_stats(), &[0; 23])
+            .unwrap();
+
+        x
+    }
+
+    fn mirror(children: &[nvpair::NvList]) -> nvpair::NvList {
+        let mut x = nvpair::NvList::new().unwrap();
+        x.add_string(sys::zpool_config_type(), "mirror").unwrap();
+        x.add_nvlist_array(sys::zpool_config_children(), children)
+            .unwrap();
+
+        x
+    }
+
+    fn root(children: &[nvpair::NvList]) -> nvpair::NvList {
+        let mut x = nvpair::NvList::new().unwrap();
+        x.add_string(sys::zpool_config_type(), "root").unwrap();
+        x.add_nvlist_array(sys::zpool_config_children(), children)
+            .unwrap();
+
+        x
+    }
+
+    fn raidz(parity: u64, children: &[nvpair::NvList]) -> nvpair::NvList {
+        let mut x = nvpair::NvList::new().unwrap();
+        x.add_string(sys::zpool_config_type(), "raidz").unwrap();
+        x.add_uint64(sys::zpool_config_nparity(), parity).unwrap();
+        x.add_nvlist_array(sys::zpool_config_children(), children)
+            .unwrap();
+
+        x
+    }
+
+    fn with_id(mut x: nvpair::NvList, id: u64) -> nvpair::NvList {
+        x.add_uint64(sys::zpool_config_id(), id).unwrap();
+
+        x
+    }
+
+    fn as_log(mut x: nvpair::NvList) -> nvpair::NvList {
+        x.add_uint64(sys::zpool_config_is_log(), 1).unwrap();
+
+        x
+    }
+
+    fn with_bias(mut x: nvpair::NvList, bias: &str) -> nvpair::NvList {
+        x.add_string(sys::zpool_config_allocation_bias(), bias)
+            .unwrap();
+
+        x
+    }
+
+    fn guid(x: &VDev) -> Option<u64> {
+        match *x {
+            VDev::Disk { guid, .. } => guid,
+            VDev::Mirror { ref children, .. } => guid(&children[0]),
+            _ => None,
+        }
+    }
+
+    fn guids(xs: &[VDev]) -> Vec<Option<u64>> {
+        xs.iter().map(guid).collect()
+    }
+
+    #[test]
+    fn lists_classes_alongside_children() {
+        let tree = root(&[
+            mirror(&[disk("/dev/sdb", 1), disk("/dev/sdc", 2)]),
+            as_log(mirror(&[disk("/dev/sdd", 3), disk("/dev/sde", 4)])),
+            with_bias(disk("/dev/sdf", 5), "special"),
+            with_bias(disk("/dev/sdg", 6), "dedup"),
+            as_log(disk("/dev/sdh", 7)),
+        ]);
+
+        match enumerate_vdev_tree(&tree).unwrap() {
+            VDev::Root {
+                children,
+                logs,
+                special,
+                dedup,
+                ..
+            } => {
+                assert_eq!(
+                    guids(&children),
+                    vec![Some(1), Some(3), Some(5), Some(6), Some(7)]
+                );
+                assert_eq!(guids(&logs), vec![Some(3), Some(7)]);
+                assert_eq!(guids(&special), vec![Some(5)]);
+                assert_eq!(guids(&dedup), vec![Some(6)]);
+            }
+            x => panic!("expected a root vdev, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn leaves_classes_empty_without_them() {
+        let tree = root(&[disk("/dev/sdb", 1), disk("/dev/sdc", 2)]);
+
+        match enumerate_vdev_tree(&tree).unwrap() {
+            VDev::Root {
+                children,
+                logs,
+                special,
+                dedup,
+                ..
+            } => {
+                assert_eq!(guids(&children), vec![Some(1), Some(2)]);
+                assert!(logs.is_empty());
+                assert!(special.is_empty());
+                assert!(dedup.is_empty());
+            }
+            x => panic!("expected a root vdev, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn renders_each_vdev_once() {
+        let tree = root(&[
+            mirror(&[disk("/dev/sdb", 1), disk("/dev/sdc", 2)]),
+            as_log(disk("/dev/sdd", 3)),
+            with_bias(disk("/dev/sde", 4), "special"),
+        ]);
+
+        let out = enumerate_vdev_tree(&tree).unwrap().render_tree("test");
+
+        for x in &["/dev/sdb", "/dev/sdd", "/dev/sde"] {
+            assert_eq!(out.matches(x).count(), 1, "{}", out);
+        }
+    }
+
+    #[test]
+    fn labels_top_levels_by_id() {
+        let tree = root(&[
+            with_id(mirror(&[disk("/dev/sdb", 1), disk("/dev/sdc", 2)]), 0),
+            with_id(
+                as_log(mirror(&[disk("/dev/sdd", 3), disk("/dev/sde", 4)])),
+                1,
+            ),
+            with_id(
+                with_bias(
+                    raidz(2, &[disk("/dev/sdf", 5), disk("/dev/sdg", 6)]),
+                    "special",
+                ),
+                2,
+            ),
+        ]);
+
+        let out = enumerate_vdev_tree(&tree).unwrap().render_tree("test");
+        let labels: Vec<&str> = out
+            .lines()
+            .map(|x| x.split_whitespace().next().unwrap())
+            .collect();
+
+        assert_eq!(
+            labels,
+            vec![
+                "NAME", "test", "mirror-0", "/dev/sdb", "/dev/sdc", "logs", "mirror-1", "/dev/sdd",
+                "/dev/sde", "special", "raidz2-2", "/dev/sdf", "/dev/sdg",
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_draid_layout() {
+        let mut spare = nvpair::NvList::new().unwrap();
+        spare
+            .add_string(sys::zpool_config_type(), "dspare")
+            .unwrap();
+        spare
+            .add_string(sys::zpool_config_path(), "draid2-0-0")
+            .unwrap();
+        spare.add_uint64(sys::zpool_config_guid(), 3).unwrap();
+        spare
+            .add_uint64_array(sys::zpool_config_vdev_stats(), &[0; 23])
+            .unwrap();
+
+        let mut draid = nvpair::NvList::new().unwrap();
+        draid.add_string(sys::zpool_config_type(), "draid").unwrap();
+        draid.add_uint64(sys::zpool_config_nparity(), 2).unwrap();
+        draid.add_uint64(ZPOOL_CONFIG_DRAID_NDATA, 4).unwrap();
+        draid.add_uint64(ZPOOL_CONFIG_DRAID_NSPARES, 1).unwrap();
+        draid.add_uint64(ZPOOL_CONFIG_DRAID_NGROUPS, 2).unwrap();
+        draid
+            .add_nvlist_array(
+                sys::zpool_config_children(),
+                &[disk("/dev/sdb", 1), disk("/dev/sdc", 2)],
+            )
+            .unwrap();
+        let draid = with_id(draid, 0);
+
+        let mut tree = root(&[draid]);
+        tree.add_nvlist_array(sys::zpool_config_spares(), &[spare])
+            .unwrap();
+
+        let vdev = enumerate_vdev_tree(&tree).unwrap();
+
+        match vdev {
+            VDev::Root {
+                ref children,
+                ref spares,
+                ..
+            } => {
+                match children[0] {
+                    VDev::Draid {
+                        ref children,
+                        parity,
+                        data_disks,
+                        spares,
+                        groups,
+                        id,
+                    } => {
+                        assert_eq!(guids(children), vec![Some(1), Some(2)]);
+                        assert_eq!(
+                            (parity, data_disks, spares, groups, id),
+                            (Some(2), Some(4), Some(1), Some(2), Some(0))
+                        );
+                    }
+                    ref x => panic!("expected a draid vdev, got {:?}", x),
+                }
+
+                match spares[0] {
+                    VDev::DraidSpare { guid, ref name, .. } => {
+                        assert_eq!((guid, name.as_str()), (Some(3), "draid2-0-0"));
+                    }
+                    ref x => panic!("expected a draid spare, got {:?}", x),
+                }
+            }
+            ref x => panic!("expected a root vdev, got {:?}", x),
+        }
+
+        let out = vdev.render_tree("test");
+
+        assert!(out.contains("draid2-0"), "{}", out);
+        assert!(out.contains("draid2-0-0"), "{}", out);
+    }
+
+    #[test]
+    fn vdev_stat_matches_raw_offsets() {
+        let x = sys::to_vdev_stat((0..41).collect());
+
+        assert_eq!(x.vs_checksum_errors, 22);
+        assert_eq!(x.vs_initialize_errors, 23);
+        assert_eq!(x.vs_fragmentation, 27);
+        assert_eq!(x.vs_initialize_bytes_done, VS_INITIALIZE_OFFSET as u64);
+        assert_eq!(x.vs_resilver_deferred, VS_RESILVER_DEFERRED as u64);
+        assert_eq!(x.vs_trim_bytes_done, VS_TRIM_OFFSET as u64);
+    }
+}
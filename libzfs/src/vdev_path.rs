@@ -0,0 +1,250 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Stable vdev identities
+//!
+//! `/dev/sdX` names can change across reboots, so comparing the topology
+//! seen before and after a reboot by raw path produces false differences.
+//! `PathResolver` maps each disk vdev to the most stable name available:
+//! a `/dev/disk/by-id` link, then a WWN link, then the devid and
+//! physical path ZFS recorded in the label, then the raw path.
+//!
+
+use libzfs_types::VDev;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const BY_ID_DIR: &str = "/dev/disk/by-id";
+pub const BY_PATH_DIR: &str = "/dev/disk/by-path";
+
+/// Where a stable identity came from, from most to least preferred
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+pub enum IdentityKind {
+    ById,
+    Wwn,
+    DevId,
+    PhysPath,
+    Raw,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Identity {
+    pub kind: IdentityKind,
+    pub path: PathBuf,
+}
+
+pub struct PathResolver {
+    by_id_dir: PathBuf,
+    by_path_dir: PathBuf,
+    /// by-id link names, keyed by the device they point to
+    links: HashMap<PathBuf, Vec<String>>,
+}
+
+impl PathResolver {
+    /// Reads the links under `/dev/disk`.
+    pub fn new() -> io::Result<PathResolver> {
+        PathResolver::from_dirs(BY_ID_DIR, BY_PATH_DIR)
+    }
+    pub fn from_dirs<P: AsRef<Path>, Q: AsRef<Path>>(
+        by_id_dir: P,
+        by_path_dir: Q,
+    ) -> io::Result<PathResolver> {
+        let mut links: HashMap<PathBuf, Vec<String>> = HashMap::new();
+
+        for entry in fs::read_dir(by_id_dir.as_ref())? {
+            let entry = entry?;
+
+            if let Ok(target) = fs::canonicalize(entry.path()) {
+                links
+                    .entry(target)
+                    .or_default()
+                    .push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+
+        for xs in links.values_mut() {
+            xs.sort();
+        }
+
+        Ok(PathResolver {
+            by_id_dir: by_id_dir.as_ref().into(),
+            by_path_dir: by_path_dir.as_ref().into(),
+            links,
+        })
+    }
+    fn existing(&self, dir: &Path, name: &str) -> Option<PathBuf> {
+        let p = dir.join(name);
+
+        if p.exists() {
+            Some(p)
+        } else {
+            None
+        }
+    }
+    /// Lists the identities of a disk, most stable first.
+    pub fn identities(
+        &self,
+        path: &Path,
+        dev_id: Option<&str>,
+        phys_path: Option<&str>,
+    ) -> Vec<Identity> {
+        let mut xs = vec![];
+
+        if path.starts_with(&self.by_id_dir) {
+            let kind = match path.file_name() {
+                Some(x) if x.to_string_lossy().starts_with("wwn-") => IdentityKind::Wwn,
+                _ => IdentityKind::ById,
+            };
+
+            xs.push(Identity {
+                kind,
+                path: path.into(),
+            });
+        }
+
+        let names = fs::canonicalize(path)
+            .ok()
+            .and_then(|x| self.links.get(&x))
+            .map(|x| x.as_slice())
+            .unwrap_or(&[]);
+
+        let (wwns, ids): (Vec<&String>, Vec<&String>) =
+            names.iter().partition(|x| x.starts_with("wwn-"));
+
+        xs.extend(ids.into_iter().map(|x| Identity {
+            kind: IdentityKind::ById,
+            path: self.by_id_dir.join(x),
+        }));
+        xs.extend(wwns.into_iter().map(|x| Identity {
+            kind: IdentityKind::Wwn,
+            path: self.by_id_dir.join(x),
+        }));
+
+        if let Some(p) = dev_id.and_then(|x| self.existing(&self.by_id_dir, x)) {
+            xs.push(Identity {
+                kind: IdentityKind::DevId,
+                path: p,
+            });
+        }
+
+        if let Some(p) = phys_path.and_then(|x| self.existing(&self.by_path_dir, x)) {
+            xs.push(Identity {
+                kind: IdentityKind::PhysPath,
+                path: p,
+            });
+        }
+
+        xs.push(Identity {
+            kind: IdentityKind::Raw,
+            path: path.into(),
+        });
+
+        xs.sort_by_key(|x| x.kind);
+
+        let mut seen = HashSet::new();
+        xs.retain(|x| seen.insert(x.path.clone()));
+
+        xs
+    }
+    /// The most stable path for a disk or file vdev.
+    ///
+    /// Returns `None` for vdevs that are not backed by a single device.
+    pub fn stable_path(&self, vdev: &VDev) -> Option<PathBuf> {
+        match *vdev {
+            VDev::Disk {
+                ref path,
+                ref dev_id,
+                ref phys_path,
+                ..
+            } => self
+                .identities(path, dev_id.as_deref(), phys_path.as_deref())
+                .into_iter()
+                .next()
+                .map(|x| x.path),
+            VDev::File { ref path, .. } => Some(path.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::File;
+    use std::os::unix::fs::symlink;
+    use std::process;
+
+    fn dev_dir() -> PathBuf {
+        let dir = env::temp_dir().join(format!("libzfs-vdev-path-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        for x in &["by-id", "by-path"] {
+            fs::create_dir_all(dir.join(x)).unwrap();
+        }
+
+        File::create(dir.join("sdb1")).unwrap();
+        File::create(dir.join("sdc1")).unwrap();
+
+        symlink(dir.join("sdb1"), dir.join("by-id/wwn-0x5000-part1")).unwrap();
+        symlink(dir.join("sdb1"), dir.join("by-id/ata-DISK_1-part1")).unwrap();
+        symlink(
+            dir.join("sdc1"),
+            dir.join("by-path/pci-0000:00:1f.2-ata-2-part1"),
+        )
+        .unwrap();
+
+        dir
+    }
+
+    fn disk(path: PathBuf, phys_path: Option<&str>) -> VDev {
+        VDev::Disk {
+            guid: None,
+            state: "ONLINE".to_string(),
+            path,
+            dev_id: None,
+            phys_path: phys_path.map(|x| x.to_string()),
+            whole_disk: Some(true),
+            is_log: None,
+            read_errors: None,
+            write_errors: None,
+            checksum_errors: None,
+        }
+    }
+
+    #[test]
+    fn prefers_stable_names() {
+        let dir = dev_dir();
+        let r = PathResolver::from_dirs(dir.join("by-id"), dir.join("by-path")).unwrap();
+
+        let kinds: Vec<IdentityKind> = r
+            .identities(&dir.join("sdb1"), Some("ata-DISK_1-part1"), None)
+            .into_iter()
+            .map(|x| x.kind)
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![IdentityKind::ById, IdentityKind::Wwn, IdentityKind::Raw]
+        );
+
+        assert_eq!(
+            r.stable_path(&disk(dir.join("sdb1"), None)),
+            Some(dir.join("by-id/ata-DISK_1-part1"))
+        );
+        assert_eq!(
+            r.stable_path(&disk(
+                dir.join("sdc1"),
+                Some("pci-0000:00:1f.2-ata-2-part1")
+            )),
+            Some(dir.join("by-path/pci-0000:00:1f.2-ata-2-part1"))
+        );
+        assert_eq!(
+            r.stable_path(&disk(dir.join("sdd1"), None)),
+            Some(dir.join("sdd1"))
+        );
+    }
+}
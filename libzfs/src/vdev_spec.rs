@@ -0,0 +1,262 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Vdev layouts for creating pools
+//!
+//! `VDevSpec` describes a new pool's vdevs the way `zpool create` takes
+//! them on the command line, and converts them to the nvlist tree libzfs
+//! expects. Devices are used as given: whole disks are not partitioned,
+//! so pass a partition or a file.
+//!
+
+extern crate libzfs_sys as sys;
+
+use libzfs_types::Result;
+use nvpair::{self, NvEncode};
+use serde_derive::{Deserialize, Serialize};
+use std::ffi::CString;
+use std::fs;
+use std::io::Error;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// A top-level vdev
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub enum TopLevel {
+    Device(PathBuf),
+    Mirror(Vec<PathBuf>),
+    RaidZ { parity: u8, children: Vec<PathBuf> },
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct VDevSpec {
+    pub data: Vec<TopLevel>,
+    pub logs: Vec<TopLevel>,
+    pub cache: Vec<PathBuf>,
+    pub spares: Vec<PathBuf>,
+}
+
+fn paths<I, P>(xs: I) -> Vec<PathBuf>
+where
+    I: IntoIterator<Item = P>,
+    P: Into<PathBuf>,
+{
+    xs.into_iter().map(Into::into).collect()
+}
+
+impl VDevSpec {
+    pub fn new() -> VDevSpec {
+        VDevSpec::default()
+    }
+    /// Adds a single device or file.
+    pub fn device<P: Into<PathBuf>>(mut self, path: P) -> VDevSpec {
+        self.data.push(TopLevel::Device(path.into()));
+        self
+    }
+    pub fn mirror<I, P>(mut self, xs: I) -> VDevSpec
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.data.push(TopLevel::Mirror(paths(xs)));
+        self
+    }
+    /// Adds a raidz group with `parity` of 1, 2 or 3.
+    pub fn raidz<I, P>(mut self, parity: u8, xs: I) -> VDevSpec
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.data.push(TopLevel::RaidZ {
+            parity,
+            children: paths(xs),
+        });
+        self
+    }
+    /// Adds a separate intent log, which may be a device or a mirror.
+    pub fn log(mut self, x: TopLevel) -> VDevSpec {
+        self.logs.push(x);
+        self
+    }
+    pub fn cache<P: Into<PathBuf>>(mut self, path: P) -> VDevSpec {
+        self.cache.push(path.into());
+        self
+    }
+    pub fn spare<P: Into<PathBuf>>(mut self, path: P) -> VDevSpec {
+        self.spares.push(path.into());
+        self
+    }
+    /// Builds the root vdev nvlist passed to `zpool_create`.
+    pub fn to_nvlist(&self) -> Result<nvpair::NvList> {
+        let mut children = vec![];
+
+        for x in &self.data {
+            children.push(top_level(x, false)?);
+        }
+
+        for x in &self.logs {
+            children.push(top_level(x, true)?);
+        }
+
+        let mut root = vdev(sys::VDEV_TYPE_ROOT)?;
+        children[..].insert(sys::zpool_config_children().as_str(), &mut root)?;
+
+        if !self.cache.is_empty() {
+            leaves(&self.cache)?[..].insert(sys::zpool_config_l2cache().as_str(), &mut root)?;
+        }
+
+        if !self.spares.is_empty() {
+            leaves(&self.spares)?[..].insert(sys::zpool_config_spares().as_str(), &mut root)?;
+        }
+
+        Ok(root)
+    }
+}
+
+fn vdev(kind: &[u8]) -> Result<nvpair::NvList> {
+    let mut nvl = nvpair::NvList::new()?;
+
+    CString::new(&kind[..kind.len() - 1])
+        .map_err(Error::from)?
+        .as_c_str()
+        .insert(sys::zpool_config_type().as_str(), &mut nvl)?;
+
+    Ok(nvl)
+}
+
+/// A disk or file, told apart the way `zpool create` does.
+fn leaf(path: &Path) -> Result<nvpair::NvList> {
+    let is_file = fs::metadata(path).map(|x| x.is_file()).unwrap_or(false);
+
+    let mut nvl = vdev(if is_file {
+        sys::VDEV_TYPE_FILE
+    } else {
+        sys::VDEV_TYPE_DISK
+    })?;
+
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(Error::from)?
+        .as_c_str()
+        .insert(sys::zpool_config_path().as_str(), &mut nvl)?;
+
+    if !is_file {
+        0u64.insert(sys::zpool_config_whole_disk().as_str(), &mut nvl)?;
+    }
+
+    Ok(nvl)
+}
+
+fn leaves(xs: &[PathBuf]) -> Result<Vec<nvpair::NvList>> {
+    xs.iter().map(|x| leaf(x)).collect()
+}
+
+fn top_level(x: &TopLevel, is_log: bool) -> Result<nvpair::NvList> {
+    let mut nvl = match *x {
+        TopLevel::Device(ref p) => leaf(p)?,
+        TopLevel::Mirror(ref xs) => {
+            let mut nvl = vdev(sys::VDEV_TYPE_MIRROR)?;
+            leaves(xs)?[..].insert(sys::zpool_config_children().as_str(), &mut nvl)?;
+            nvl
+        }
+        TopLevel::RaidZ {
+            parity,
+            ref children,
+        } => {
+            let mut nvl = vdev(sys::VDEV_TYPE_RAIDZ)?;
+            u64::from(parity).insert(sys::zpool_config_nparity().as_str(), &mut nvl)?;
+            leaves(children)?[..].insert(sys::zpool_config_children().as_str(), &mut nvl)?;
+            nvl
+        }
+    };
+
+    u64::from(is_log).insert(sys::zpool_config_is_log().as_str(), &mut nvl)?;
+
+    Ok(nvl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_spec() {
+        let spec = VDevSpec::new()
+            .mirror(vec!["/dev/sdb1", "/dev/sdc1"])
+            .raidz(2, vec!["/dev/sdd1", "/dev/sde1", "/dev/sdf1"])
+            .log(TopLevel::Device("/dev/nvme0n1p1".into()))
+            .cache("/dev/nvme0n1p2")
+            .spare("/dev/sdg1");
+
+        assert_eq!(spec.data.len(), 2);
+        assert_eq!(
+            spec.data[1],
+            TopLevel::RaidZ {
+                parity: 2,
+                children: vec!["/dev/sdd1".into(), "/dev/sde1".into(), "/dev/sdf1".into()],
+            }
+        );
+        assert_eq!(spec.logs, vec![TopLevel::Device("/dev/nvme0n1p1".into())]);
+        assert_eq!(spec.cache, vec![PathBuf::from("/dev/nvme0n1p2")]);
+        assert_eq!(spec.spares, vec![PathBuf::from("/dev/sdg1")]);
+
+        let kind = |x: &nvpair::NvListRef| {
+            x.lookup_str(sys::zpool_config_type())
+                .unwrap()
+                .to_bytes_with_nul()
+                .to_vec()
+        };
+        let path = |x: &nvpair::NvListRef| x.lookup_string(sys::zpool_config_path()).unwrap();
+        let is_log = |x: &nvpair::NvListRef| x.lookup_uint64(sys::zpool_config_is_log()).unwrap();
+
+        let root = spec.to_nvlist().unwrap();
+        assert_eq!(kind(&root), sys::VDEV_TYPE_ROOT);
+
+        let children = root
+            .lookup_nv_list_array_ref(sys::zpool_config_children())
+            .unwrap();
+        assert_eq!(children.len(), 3);
+
+        let mirror = children[0];
+        assert_eq!(kind(mirror), sys::VDEV_TYPE_MIRROR);
+        assert_eq!(is_log(mirror), 0);
+
+        let xs = mirror
+            .lookup_nv_list_array_ref(sys::zpool_config_children())
+            .unwrap();
+        assert_eq!(
+            xs.iter().map(|x| path(x)).collect::<Vec<_>>(),
+            vec![
+                CString::new("/dev/sdb1").unwrap(),
+                CString::new("/dev/sdc1").unwrap(),
+            ]
+        );
+        assert_eq!(kind(xs[0]), sys::VDEV_TYPE_DISK);
+
+        let raidz = children[1];
+        assert_eq!(kind(raidz), sys::VDEV_TYPE_RAIDZ);
+        assert_eq!(raidz.lookup_uint64(sys::zpool_config_nparity()).unwrap(), 2);
+        assert_eq!(
+            raidz
+                .lookup_nv_list_array_ref(sys::zpool_config_children())
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let log = children[2];
+        assert_eq!(kind(log), sys::VDEV_TYPE_DISK);
+        assert_eq!(path(log), CString::new("/dev/nvme0n1p1").unwrap());
+        assert_eq!(is_log(log), 1);
+
+        let cache = root
+            .lookup_nv_list_array_ref(sys::zpool_config_l2cache())
+            .unwrap();
+        assert_eq!(path(cache[0]), CString::new("/dev/nvme0n1p2").unwrap());
+
+        let spares = root
+            .lookup_nv_list_array_ref(sys::zpool_config_spares())
+            .unwrap();
+        assert_eq!(path(spares[0]), CString::new("/dev/sdg1").unwrap());
+    }
+}
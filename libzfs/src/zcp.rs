@@ -0,0 +1,228 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Bundled ZFS channel programs
+//!
+//! Channel programs run as Lua inside the kernel and complete within a
+//! single transaction group, so a sequence of operations is applied
+//! atomically. A few vetted programs are embedded here behind typed
+//! wrappers; `run_program` runs arbitrary ones.
+//!
+
+extern crate libzfs_sys as sys;
+
+use libzfs_types::{LibZfsError, Result};
+use nvpair::{self, ForeignType, NvEncode};
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::io::{Error, ErrorKind};
+use std::ptr;
+
+pub const SNAPSHOT_PRUNE: &str = include_str!("zcp/snapshot_prune.lua");
+pub const LIST_PROPS: &str = include_str!("zcp/list_props.lua");
+pub const SAFE_DESTROY: &str = include_str!("zcp/safe_destroy.lua");
+
+/// Default instruction limit, as used by `zfs program`
+pub const DEFAULT_INSTRLIMIT: u64 = 10 * 1000 * 1000;
+/// Default memory limit, as used by `zfs program`
+pub const DEFAULT_MEMLIMIT: u64 = 10 * 1024 * 1024;
+
+/// Runs `program` against `pool` with `args`,
+/// returning the value the program returned.
+pub fn run_program(
+    pool: &str,
+    program: &str,
+    args: &nvpair::NvListRef,
+    instrlimit: u64,
+    memlimit: u64,
+) -> Result<nvpair::NvList> {
+    let c_pool = CString::new(pool).map_err(Error::from)?;
+    let c_program = CString::new(program).map_err(Error::from)?;
+    let mut out = ptr::null_mut();
+
+    let code = unsafe {
+        sys::libzfs_core_init();
+
+        let code = sys::lzc_channel_program(
+            c_pool.as_ptr(),
+            c_program.as_ptr(),
+            instrlimit,
+            memlimit,
+            args.as_ptr() as *mut _,
+            &mut out,
+        );
+
+        sys::libzfs_core_fini();
+
+        code
+    };
+
+    if out.is_null() {
+        return Err(LibZfsError::Io(Error::from_raw_os_error(code)));
+    }
+
+    let out = unsafe { nvpair::NvList::from_ptr(out) };
+
+    match code {
+        0 => Ok(out
+            .lookup_nv_list("return")
+            .or_else(|_| nvpair::NvList::new())?),
+        x => Err(LibZfsError::Io(match out.lookup_str("error") {
+            Ok(e) => Error::new(ErrorKind::Other, e.to_string_lossy().into_owned()),
+            Err(_) => Error::from_raw_os_error(x),
+        })),
+    }
+}
+
+fn run_bundled(pool_of: &str, program: &str, args: &nvpair::NvListRef) -> Result<nvpair::NvList> {
+    let pool = pool_of.split(&['/', '@'][..]).next().unwrap_or("");
+
+    run_program(pool, program, args, DEFAULT_INSTRLIMIT, DEFAULT_MEMLIMIT)
+}
+
+fn add_str(nvl: &mut nvpair::NvListRef, name: &str, value: &str) -> Result<()> {
+    CString::new(value)
+        .map_err(Error::from)?
+        .as_c_str()
+        .insert(name, nvl)?;
+
+    Ok(())
+}
+
+/// Reads back a Lua array, which is returned as an `NvList` keyed `1..n`.
+fn string_array(nvl: &nvpair::NvListRef) -> Result<Vec<String>> {
+    (1..=nvl.iter().count())
+        .map(|i| {
+            nvl.lookup_string(i.to_string().as_str())?
+                .into_string()
+                .map_err(LibZfsError::from)
+        })
+        .collect()
+}
+
+/// The outcome of `snapshot_prune`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Pruned {
+    pub snapshot: String,
+    pub destroyed: Vec<String>,
+}
+
+/// Channel programs only see signed 64-bit numbers, so `keep` is
+/// passed as one and checked here rather than in Lua.
+fn prune_args(dataset: &str, snapshot: &str, keep: i64) -> Result<nvpair::NvList> {
+    if keep < 0 {
+        return Err(LibZfsError::Io(Error::new(
+            ErrorKind::InvalidInput,
+            format!("keep must not be negative, got {}", keep),
+        )));
+    }
+
+    let mut args = nvpair::NvList::new()?;
+    add_str(&mut args, "dataset", dataset)?;
+    add_str(&mut args, "snapshot", snapshot)?;
+    keep.insert("keep", &mut args)?;
+
+    Ok(args)
+}
+
+/// Atomically snapshots `dataset@snapshot` and destroys the oldest
+/// existing snapshots so at most `keep` of them remain beside the new one.
+pub fn snapshot_prune(dataset: &str, snapshot: &str, keep: i64) -> Result<Pruned> {
+    let args = prune_args(dataset, snapshot, keep)?;
+
+    let out = run_bundled(dataset, SNAPSHOT_PRUNE, &args)?;
+
+    Ok(Pruned {
+        snapshot: out.lookup_string("snapshot")?.into_string()?,
+        destroyed: match out.lookup_nv_list_ref("destroyed") {
+            Ok(xs) => string_array(xs)?,
+            Err(_) => vec![],
+        },
+    })
+}
+
+/// Lists `dataset` and its descendants with the values of `props`,
+/// read in a single transaction group.
+pub fn list_with_props(
+    dataset: &str,
+    props: &[&str],
+) -> Result<BTreeMap<String, BTreeMap<String, String>>> {
+    let mut args = nvpair::NvList::new()?;
+    add_str(&mut args, "dataset", dataset)?;
+    add_str(&mut args, "props", &props.join(","))?;
+
+    let out = run_bundled(dataset, LIST_PROPS, &args)?;
+
+    out.iter()
+        .map(|ds| {
+            let xs = ds.value_nv_list_ref()?;
+
+            let values = xs
+                .iter()
+                .map(|p| {
+                    Ok((
+                        p.name().to_string_lossy().into_owned(),
+                        xs.lookup_string(p.name())?.into_string()?,
+                    ))
+                })
+                .collect::<Result<_>>()?;
+
+            Ok((ds.name().to_string_lossy().into_owned(), values))
+        })
+        .collect()
+}
+
+/// Destroys `dataset` and its snapshots, refusing if it has children
+/// or any snapshot has a dependent clone. Returns the destroyed snapshots.
+pub fn safe_destroy(dataset: &str) -> Result<Vec<String>> {
+    let mut args = nvpair::NvList::new()?;
+    add_str(&mut args, "dataset", dataset)?;
+
+    let out = run_bundled(dataset, SAFE_DESTROY, &args)?;
+
+    string_array(&out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nvpair::NvData;
+
+    #[test]
+    fn builds_prune_args() {
+        let args = prune_args("test/ds", "daily", 3).unwrap();
+
+        assert_eq!(
+            args.lookup_string("dataset").unwrap().to_str(),
+            Ok("test/ds")
+        );
+        assert_eq!(
+            args.lookup_string("snapshot").unwrap().to_str(),
+            Ok("daily")
+        );
+
+        match args.lookup("keep").unwrap().data().unwrap() {
+            NvData::Int64(x) => assert_eq!(x, 3),
+            x => panic!("keep should be an int64, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn keeps_nothing() {
+        assert!(prune_args("test/ds", "daily", 0).is_ok());
+    }
+
+    #[test]
+    fn rejects_negative_keep() {
+        match prune_args("test/ds", "daily", -1) {
+            Err(LibZfsError::Io(ref e)) => assert_eq!(e.kind(), ErrorKind::InvalidInput),
+            x => panic!("expected InvalidInput, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn rejects_nul_in_snapshot() {
+        assert!(prune_args("test/ds", "dai\0ly", 1).is_err());
+    }
+}
@@ -0,0 +1,30 @@
+-- Lists a dataset and its descendants along with the requested
+-- properties, formatted as strings.
+--
+-- args: dataset, props (comma separated)
+-- returns: { ["<dataset>"] = { ["<prop>"] = "<value>", ... }, ... }
+
+args = ...
+results = {}
+
+function collect(ds)
+    local props = {}
+
+    for p in string.gmatch(args["props"], "[^,]+") do
+        local value = zfs.get_prop(ds, p)
+
+        if value ~= nil then
+            props[p] = tostring(value)
+        end
+    end
+
+    results[ds] = props
+
+    for child in zfs.list.children(ds) do
+        collect(child)
+    end
+end
+
+collect(args["dataset"])
+
+return results
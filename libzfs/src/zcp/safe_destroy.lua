@@ -0,0 +1,48 @@
+-- Destroys a dataset and its snapshots, refusing if the dataset has
+-- children or any of its snapshots have dependent clones.
+-- Every destroy is checked before any is performed.
+--
+-- args: dataset
+-- returns: { "<destroyed snapshot>", ... }
+
+args = ...
+ds = args["dataset"]
+
+for child in zfs.list.children(ds) do
+    error(ds .. " has child dataset " .. child)
+end
+
+snaps = {}
+for s in zfs.list.snapshots(ds) do
+    for clone in zfs.list.clones(s) do
+        error(s .. " has dependent clone " .. clone)
+    end
+
+    table.insert(snaps, s)
+end
+
+for _, s in ipairs(snaps) do
+    err = zfs.check.destroy(s)
+    if err ~= 0 then
+        error("cannot destroy " .. s .. ": " .. err)
+    end
+end
+
+err = zfs.check.destroy(ds)
+if err ~= 0 then
+    error("cannot destroy " .. ds .. ": " .. err)
+end
+
+for _, s in ipairs(snaps) do
+    err = zfs.sync.destroy(s)
+    if err ~= 0 then
+        error("could not destroy " .. s .. ": " .. err)
+    end
+end
+
+err = zfs.sync.destroy(ds)
+if err ~= 0 then
+    error("could not destroy " .. ds .. ": " .. err)
+end
+
+return snaps
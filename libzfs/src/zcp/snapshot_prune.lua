@@ -0,0 +1,39 @@
+-- Takes a snapshot and destroys the oldest existing snapshots beyond
+-- `keep`, all within a single transaction group.
+--
+-- args: dataset, snapshot, keep
+-- returns: { snapshot = "<name>", destroyed = { "<name>", ... } }
+
+args = ...
+ds = args["dataset"]
+name = ds .. "@" .. args["snapshot"]
+keep = args["keep"]
+
+snaps = {}
+for s in zfs.list.snapshots(ds) do
+    table.insert(snaps, {name = s, txg = zfs.get_prop(s, "createtxg")})
+end
+table.sort(snaps, function(a, b) return a.txg < b.txg end)
+
+err = zfs.check.snapshot(name)
+if err ~= 0 then
+    error("cannot snapshot " .. name .. ": " .. err)
+end
+
+prune = {}
+for i = 1, #snaps - keep do
+    if zfs.check.destroy(snaps[i].name) == 0 then
+        table.insert(prune, snaps[i].name)
+    end
+end
+
+zfs.sync.snapshot(name)
+
+destroyed = {}
+for _, s in ipairs(prune) do
+    if zfs.sync.destroy(s) == 0 then
+        table.insert(destroyed, s)
+    end
+end
+
+return {snapshot = name, destroyed = destroyed}
@@ -4,43 +4,120 @@
 
 extern crate libzfs_sys as sys;
 
-use libzfs_types::{LibZfsError, Result};
-use nvpair;
-use std::ffi::{CStr, CString};
-use std::io::Error;
+use dataset_iter::DatasetIter;
+use delegation::{self, Delegation};
+use diff::{spool_file, DiffIter};
+use libc;
+use libzfs::{self, LibzfsHandle};
+use libzfs_types::{
+    LibZfsError, PropSource, PropValue, QuotaTarget, Result, UserspaceEntry, ZfsError, ZfsInfo,
+};
+use nvpair::{self, ForeignType};
+use state;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::{CStr, CString, OsStr};
+use std::fs::{self, File};
+use std::io::{self, Error, ErrorKind, Write};
+use std::os::raw::{c_char, c_int, c_void};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::{Path, PathBuf};
 use std::ptr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use zfs_prop::{ZfsProp, ALL_PROPS};
+use zpool::Zpool;
 use zprop_list::{ZProp, ZpropItem, ZpropList};
+use zvol::{self, ZvolDevice};
 
+/// The property naming where a wrapping key is loaded from
+const KEYLOCATION: &str = "keylocation";
+
+/// An open dataset
+///
+/// See `Libzfs` for how datasets may be used across threads.
 #[derive(Debug, PartialEq)]
 pub struct Zfs {
-    raw: *mut sys::zfs_handle_t,
+    pub(crate) raw: *mut sys::zfs_handle_t,
+    pub(crate) libzfs: Arc<LibzfsHandle>,
 }
 
+// Every call on `raw` is made under the lock of the handle it was opened on.
+unsafe impl Send for Zfs {}
+
 impl Zfs {
-    pub fn new(raw: *mut sys::zfs_handle_t) -> Zfs {
-        Zfs { raw }
+    /// Takes ownership of `raw`, which must have been opened on `libzfs`.
+    pub fn new(libzfs: Arc<LibzfsHandle>, raw: *mut sys::zfs_handle_t) -> Zfs {
+        Zfs { raw, libzfs }
     }
     pub fn name(&self) -> CString {
+        let _l = self.libzfs.lock();
+
         let s = unsafe { CStr::from_ptr(sys::zfs_get_name(self.raw)) };
         s.to_owned()
     }
+    /// As `name`, as a `String`.
+    pub fn name_str(&self) -> Result<String> {
+        Ok(self.name().into_string()?)
+    }
+    /// The name of the pool this dataset is in.
+    pub fn pool_name(&self) -> CString {
+        let _l = self.libzfs.lock();
+
+        let s = unsafe { CStr::from_ptr(sys::zfs_get_pool_name(self.raw)) };
+        s.to_owned()
+    }
+    /// Opens the pool this dataset is in.
+    ///
+    /// libzfs keeps its own handle to the pool for the life of the
+    /// library handle, so this opens a separate one the caller owns.
+    pub fn pool(&self) -> Result<Zpool> {
+        let _l = self.libzfs.lock();
+
+        let name = self.pool_name();
+        let h = unsafe { sys::zpool_open_canfail(self.libzfs.raw(), name.as_ptr()) };
+
+        if h.is_null() {
+            return Err(LibZfsError::PoolNotFound(Some(name.into_string()?), None));
+        }
+
+        Ok(Zpool::new(self.libzfs.clone(), h))
+    }
+    /// Captures the dataset and its properties as plain data
+    /// that does not hold the dataset open.
+    pub fn to_info(&self) -> Result<ZfsInfo> {
+        state::convert_to_dataset(self)
+    }
     pub fn user_props(&self) -> &nvpair::NvListRef {
+        let _l = self.libzfs.lock();
+
         unsafe {
             let x = sys::zfs_get_user_props(self.raw);
             nvpair::NvListRef::from_mut_ptr(x)
         }
     }
     pub fn zfs_type(&self) -> sys::zfs_type_t {
+        let _l = self.libzfs.lock();
+
         unsafe { sys::zfs_get_type(self.raw) }
     }
     pub fn zfs_type_name(&self) -> CString {
+        let _l = self.libzfs.lock();
+
         let x = self.zfs_type();
 
         let s = unsafe { CStr::from_ptr(sys::zfs_type_to_name(x)) };
 
         s.to_owned()
     }
+    /// As `zfs_type_name`, as a `String`.
+    pub fn zfs_type_name_str(&self) -> Result<String> {
+        Ok(self.zfs_type_name().into_string()?)
+    }
     pub fn prop_list(&self) -> Result<ZpropList> {
+        let _l = self.libzfs.lock();
+
         let mut prop_list_ptr: *mut sys::zprop_list_t = ptr::null_mut();
 
         let code = unsafe {
@@ -58,6 +135,8 @@ impl Zfs {
         }
     }
     pub fn props(&self) -> Result<(Vec<ZProp>)> {
+        let _l = self.libzfs.lock();
+
         let buff_size = 319;
         let pl = self.prop_list()?;
 
@@ -65,7 +144,7 @@ impl Zfs {
             .filter_map(|x: ZpropItem| match x.prop() {
                 sys::zfs_prop_t_ZFS_PROP_BAD => self
                     .user_props()
-                    .lookup_nv_list(x.user_prop())
+                    .lookup_nv_list_ref(x.user_prop())
                     .and_then(|nv| nv.lookup_string(sys::zprop_value()))
                     .map(|v| ZProp {
                         name: x.user_prop().to_owned().into_string().unwrap(),
@@ -106,10 +185,1001 @@ impl Zfs {
 
         Ok(xs)
     }
+    /// Reads several native or user properties at once, formatted
+    /// as with `get_str`.
+    ///
+    /// Native properties share one buffer, and the user properties are
+    /// walked once rather than looked up by name. Properties that are
+    /// not set or do not apply to this dataset are left out.
+    pub fn get_props<S: AsRef<str>>(&self, names: &[S]) -> Result<BTreeMap<String, String>> {
+        let _l = self.libzfs.lock();
+
+        let mut out = BTreeMap::new();
+        let mut user = HashSet::new();
+        let mut buf = vec![0u8; sys::ZFS_MAXPROPLEN as usize];
+
+        for name in names {
+            let name = name.as_ref();
+            let c_name = CString::new(name).map_err(Error::from)?;
+
+            let prop = unsafe { sys::zfs_name_to_prop(c_name.as_ptr()) };
+
+            if prop == sys::zfs_prop_t_ZFS_PROP_BAD {
+                user.insert(name);
+                continue;
+            }
+
+            let code = unsafe {
+                sys::zfs_prop_get(
+                    self.raw,
+                    prop,
+                    buf.as_mut_ptr() as *mut c_char,
+                    buf.len(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    0,
+                    sys::boolean::B_TRUE,
+                )
+            };
+
+            if code == 0 {
+                let value = unsafe { CStr::from_ptr(buf.as_ptr() as *const c_char) };
+
+                out.insert(name.to_string(), value.to_string_lossy().into_owned());
+            }
+        }
+
+        if user.is_empty() {
+            return Ok(out);
+        }
+
+        for x in self.user_props().iter() {
+            let name = x.name().to_string_lossy();
+
+            if !user.contains(name.as_ref()) {
+                continue;
+            }
+
+            let value = x.value_nv_list_ref()?.lookup_str(sys::zprop_value())?;
+
+            out.insert(name.into_owned(), value.to_string_lossy().into_owned());
+        }
+
+        Ok(out)
+    }
+    /// Gets a numeric property, such as `ZfsProp::Used`.
+    pub fn get_int(&self, prop: ZfsProp) -> Result<u64> {
+        let _l = self.libzfs.lock();
+
+        let mut value = 0;
+
+        let code = unsafe {
+            sys::zfs_prop_get_numeric(
+                self.raw,
+                prop.to_raw(),
+                &mut value,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                0,
+            )
+        };
+
+        match code {
+            0 => Ok(value),
+            _ => Err(LibZfsError::Io(Error::new(
+                ErrorKind::NotFound,
+                format!("{} is not available as a number", prop.name()),
+            ))),
+        }
+    }
+    /// Gets a property formatted as a string, such as `ZfsProp::Mountpoint`.
+    pub fn get_str(&self, prop: ZfsProp) -> Result<CString> {
+        let _l = self.libzfs.lock();
+
+        let mut buf = vec![0u8; sys::ZFS_MAXPROPLEN as usize];
+
+        let code = unsafe {
+            sys::zfs_prop_get(
+                self.raw,
+                prop.to_raw(),
+                buf.as_mut_ptr() as *mut c_char,
+                buf.len(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                0,
+                sys::boolean::B_TRUE,
+            )
+        };
+
+        match code {
+            0 => Ok(unsafe { CStr::from_ptr(buf.as_ptr() as *const c_char) }.to_owned()),
+            _ => Err(LibZfsError::Io(Error::new(
+                ErrorKind::NotFound,
+                format!("{} is not available", prop.name()),
+            ))),
+        }
+    }
+    /// As `get_str`, as a `String`.
+    pub fn get_string(&self, prop: ZfsProp) -> Result<String> {
+        Ok(self.get_str(prop)?.into_string()?)
+    }
+    /// Gets a property along with where its value comes from.
+    pub fn get_prop(&self, prop: ZfsProp) -> Result<PropValue> {
+        let _l = self.libzfs.lock();
+
+        let mut buf = vec![0u8; sys::ZFS_MAXPROPLEN as usize];
+        let mut statbuf = vec![0u8; sys::ZFS_MAX_DATASET_NAME_LEN as usize];
+        let mut src = sys::zprop_source_t_ZPROP_SRC_NONE;
+
+        let code = unsafe {
+            sys::zfs_prop_get(
+                self.raw,
+                prop.to_raw(),
+                buf.as_mut_ptr() as *mut c_char,
+                buf.len(),
+                &mut src,
+                statbuf.as_mut_ptr() as *mut c_char,
+                statbuf.len(),
+                sys::boolean::B_TRUE,
+            )
+        };
+
+        if code != 0 {
+            return Err(LibZfsError::Io(Error::new(
+                ErrorKind::NotFound,
+                format!("{} is not available", prop.name()),
+            )));
+        }
+
+        let to_string = |x: &[u8]| {
+            unsafe { CStr::from_ptr(x.as_ptr() as *const c_char) }
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        let source = match src {
+            sys::zprop_source_t_ZPROP_SRC_DEFAULT => PropSource::Default,
+            sys::zprop_source_t_ZPROP_SRC_TEMPORARY => PropSource::Temporary,
+            sys::zprop_source_t_ZPROP_SRC_LOCAL => PropSource::Local,
+            sys::zprop_source_t_ZPROP_SRC_INHERITED => PropSource::Inherited(to_string(&statbuf)),
+            sys::zprop_source_t_ZPROP_SRC_RECEIVED => PropSource::Received,
+            _ => PropSource::None,
+        };
+
+        let mut recvd = vec![0u8; sys::ZFS_MAXPROPLEN as usize];
+
+        let code = unsafe {
+            sys::zfs_prop_get_recvd(
+                self.raw,
+                CString::new(prop.name()).map_err(Error::from)?.as_ptr(),
+                recvd.as_mut_ptr() as *mut c_char,
+                recvd.len(),
+                sys::boolean::B_TRUE,
+            )
+        };
+
+        let value = to_string(&buf);
+
+        Ok(PropValue {
+            numeric: value.parse().ok(),
+            value,
+            source,
+            received: match code {
+                0 => Some(to_string(&recvd)).filter(|x| x != "-"),
+                _ => None,
+            },
+        })
+    }
+    /// Gets every native property that applies to this dataset,
+    /// keyed by name as in `zfs get all`.
+    pub fn all_props(&self) -> HashMap<String, PropValue> {
+        ALL_PROPS
+            .iter()
+            .filter_map(|x| self.get_prop(*x).ok().map(|v| (x.name().to_string(), v)))
+            .collect()
+    }
+    fn zfs_error(&self) -> ZfsError {
+        let _l = self.libzfs.lock();
+
+        libzfs::zfs_error(unsafe { sys::zfs_get_handle(self.raw) })
+    }
+    pub fn set_prop(&self, name: &str, value: &str) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let c_name = CString::new(name).map_err(Error::from)?;
+        let c_value = CString::new(value).map_err(Error::from)?;
+
+        let code = unsafe { sys::zfs_prop_set(self.raw, c_name.as_ptr(), c_value.as_ptr()) };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(LibZfsError::PropSet(
+                vec![name.to_string()],
+                self.zfs_error(),
+            )),
+        }
+    }
+    /// Sets a user property, such as `iml:managed`.
+    ///
+    /// User property names must contain a `:` to separate the namespace.
+    pub fn set_user_prop(&self, name: &str, value: &str) -> Result<()> {
+        if !name.contains(':') {
+            return Err(LibZfsError::Io(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not a user property", name),
+            )));
+        }
+
+        self.set_prop(name, value)
+    }
+    /// Sets every property in `props` in a single operation.
+    pub fn set_props(&self, props: &nvpair::NvListRef) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let code = unsafe { sys::zfs_prop_set_list(self.raw, props.as_ptr() as *mut _) };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(LibZfsError::PropSet(
+                props
+                    .iter()
+                    .map(|x| x.name().to_string_lossy().into_owned())
+                    .collect(),
+                self.zfs_error(),
+            )),
+        }
+    }
+    /// Clears a locally set property so it is inherited again.
+    ///
+    /// With `revert_to_received` the received value is restored instead, if any.
+    pub fn inherit_prop(&self, name: &str, revert_to_received: bool) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let c_name = CString::new(name).map_err(Error::from)?;
+
+        let received = if revert_to_received {
+            sys::boolean::B_TRUE
+        } else {
+            sys::boolean::B_FALSE
+        };
+
+        let code = unsafe { sys::zfs_prop_inherit(self.raw, c_name.as_ptr(), received) };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(LibZfsError::PropSet(
+                vec![name.to_string()],
+                self.zfs_error(),
+            )),
+        }
+    }
+    /// Parses a human readable size such as `10G` into bytes.
+    pub fn parse_size(&self, size: &str) -> Result<u64> {
+        let _l = self.libzfs.lock();
+
+        let c_size = CString::new(size).map_err(Error::from)?;
+        let mut value = 0;
+
+        let code = unsafe {
+            sys::zfs_nicestrtonum(sys::zfs_get_handle(self.raw), c_size.as_ptr(), &mut value)
+        };
+
+        match code {
+            0 => Ok(value),
+            _ => Err(LibZfsError::Zfs(self.zfs_error())),
+        }
+    }
+    fn set_int(&self, prop: ZfsProp, value: u64) -> Result<()> {
+        self.set_prop(prop.name(), &value.to_string())
+    }
+    /// The quota in bytes, `0` if unset.
+    pub fn quota(&self) -> Result<u64> {
+        self.get_int(ZfsProp::Quota)
+    }
+    /// Sets the quota in bytes, `0` clears it.
+    pub fn set_quota(&self, bytes: u64) -> Result<()> {
+        self.set_int(ZfsProp::Quota, bytes)
+    }
+    pub fn refquota(&self) -> Result<u64> {
+        self.get_int(ZfsProp::Refquota)
+    }
+    pub fn set_refquota(&self, bytes: u64) -> Result<()> {
+        self.set_int(ZfsProp::Refquota, bytes)
+    }
+    pub fn reservation(&self) -> Result<u64> {
+        self.get_int(ZfsProp::Reservation)
+    }
+    pub fn set_reservation(&self, bytes: u64) -> Result<()> {
+        self.set_int(ZfsProp::Reservation, bytes)
+    }
+    pub fn refreservation(&self) -> Result<u64> {
+        self.get_int(ZfsProp::Refreservation)
+    }
+    pub fn set_refreservation(&self, bytes: u64) -> Result<()> {
+        self.set_int(ZfsProp::Refreservation, bytes)
+    }
+    pub fn is_volume(&self) -> bool {
+        let _l = self.libzfs.lock();
+
+        self.zfs_type() == sys::zfs_type_t::ZFS_TYPE_VOLUME
+    }
+    /// The logical size of a volume in bytes.
+    pub fn volsize(&self) -> Result<u64> {
+        self.get_int(ZfsProp::Volsize)
+    }
+    /// Resizes a volume.
+    pub fn set_volsize(&self, bytes: u64) -> Result<()> {
+        self.set_int(ZfsProp::Volsize, bytes)
+    }
+    /// The block size of a volume in bytes, fixed at creation.
+    pub fn volblocksize(&self) -> Result<u64> {
+        self.get_int(ZfsProp::Volblocksize)
+    }
+    /// Waits up to `timeout` for this volume's device node to appear.
+    pub fn zvol_device(&self, timeout: Duration) -> Result<ZvolDevice> {
+        if !self.is_volume() {
+            return Err(LibZfsError::Io(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not a volume", self.name().to_string_lossy()),
+            )));
+        }
+
+        zvol::wait_for_device(&self.name().to_string_lossy(), timeout)
+    }
+    fn get_userquota_int(&self, name: &str) -> Result<u64> {
+        let _l = self.libzfs.lock();
+
+        let c_name = CString::new(name).map_err(Error::from)?;
+        let mut value = 0;
+
+        let code =
+            unsafe { sys::zfs_prop_get_userquota_int(self.raw, c_name.as_ptr(), &mut value) };
+
+        match code {
+            0 => Ok(value),
+            _ => Err(LibZfsError::Zfs(self.zfs_error())),
+        }
+    }
+    /// The quota in bytes for `target`, `0` if unset.
+    pub fn userquota(&self, target: QuotaTarget) -> Result<u64> {
+        self.get_userquota_int(&userquota_prop(target, "quota"))
+    }
+    /// Sets the quota in bytes for `target`, `0` clears it.
+    pub fn set_userquota(&self, target: QuotaTarget, bytes: u64) -> Result<()> {
+        self.set_prop(&userquota_prop(target, "quota"), &bytes.to_string())
+    }
+    /// The space in bytes charged to `target`.
+    pub fn userused(&self, target: QuotaTarget) -> Result<u64> {
+        self.get_userquota_int(&userquota_prop(target, "used"))
+    }
+    /// Lists the space used by every user, group or project
+    /// on this dataset, depending on `prop`.
+    pub fn userspace(&self, prop: UserspaceProp) -> Result<Vec<UserspaceEntry>> {
+        let _l = self.libzfs.lock();
+
+        unsafe extern "C" fn callback(
+            state: *mut c_void,
+            domain: *const c_char,
+            rid: sys::uid_t,
+            space: u64,
+        ) -> c_int {
+            let state = &mut *(state as *mut Vec<UserspaceEntry>);
+
+            state.push(UserspaceEntry {
+                domain: if domain.is_null() {
+                    String::new()
+                } else {
+                    CStr::from_ptr(domain).to_string_lossy().into_owned()
+                },
+                id: rid,
+                space,
+            });
+
+            0
+        }
+
+        let mut state: Vec<UserspaceEntry> = Vec::new();
+        let state_ptr: *mut c_void = &mut state as *mut _ as *mut c_void;
+
+        let code =
+            unsafe { sys::zfs_userspace(self.raw, prop.to_raw(), Some(callback), state_ptr) };
+
+        match code {
+            0 => Ok(state),
+            _ => Err(LibZfsError::Zfs(self.zfs_error())),
+        }
+    }
+    /// Whether `.zfs` is visible in directory listings.
+    pub fn snapdir_visible(&self) -> Result<bool> {
+        Ok(self.get_str(ZfsProp::Snapdir)?.to_bytes() == b"visible")
+    }
+    pub fn set_snapdir_visible(&self, visible: bool) -> Result<()> {
+        self.set_prop(
+            ZfsProp::Snapdir.name(),
+            if visible { "visible" } else { "hidden" },
+        )
+    }
+    /// Where the snapshot `snapname` of this filesystem is automounted.
+    ///
+    /// `snapname` is the part after the `@`.
+    pub fn snapshot_mount_path(&self, snapname: &str) -> Result<PathBuf> {
+        let mountpoint = self.get_str(ZfsProp::Mountpoint)?;
+        let mountpoint = Path::new(OsStr::from_bytes(mountpoint.to_bytes()));
+
+        if !mountpoint.is_absolute() {
+            return Err(LibZfsError::Io(Error::new(
+                ErrorKind::NotFound,
+                format!("{} has no mountpoint", self.name().to_string_lossy()),
+            )));
+        }
+
+        Ok(mountpoint.join(".zfs").join("snapshot").join(snapname))
+    }
+    /// Triggers the automount of the snapshot `snapname`
+    /// and checks it can be read, returning its path.
+    pub fn mount_snapshot_dir(&self, snapname: &str) -> Result<PathBuf> {
+        let path = self.snapshot_mount_path(snapname)?;
+
+        fs::read_dir(&path)?;
+
+        Ok(path)
+    }
+    pub fn is_mounted(&self) -> bool {
+        let _l = self.libzfs.lock();
+
+        unsafe { sys::zfs_is_mounted(self.raw, ptr::null_mut()) == sys::boolean::B_TRUE }
+    }
+    /// Mounts this filesystem at its `mountpoint`.
+    ///
+    /// `options` are passed to mount, as with `zfs mount -o`.
+    pub fn mount(&self, options: Option<&str>) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let options = match options {
+            Some(x) => Some(CString::new(x).map_err(Error::from)?),
+            None => None,
+        };
+
+        let code = unsafe {
+            sys::zfs_mount(
+                self.raw,
+                options.as_ref().map_or(ptr::null(), |x| x.as_ptr()),
+                0,
+            )
+        };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(LibZfsError::Zfs(self.zfs_error())),
+        }
+    }
+    /// Unmounts this filesystem.
+    ///
+    /// With `force` open files are ignored; with `lazy` the unmount
+    /// completes once the filesystem is no longer busy.
+    pub fn unmount(&self, force: bool, lazy: bool) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let mut flags = 0;
+
+        if force {
+            flags |= sys::MS_FORCE;
+        }
+
+        if lazy {
+            flags |= sys::MS_DETACH;
+        }
+
+        let code = unsafe { sys::zfs_unmount(self.raw, ptr::null(), flags as c_int) };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(LibZfsError::Zfs(self.zfs_error())),
+        }
+    }
+    /// Loads the wrapping key of an encrypted dataset.
+    ///
+    /// `key_material` is passed to ZFS through a pipe, so keys can come
+    /// from a secrets manager rather than a file or prompt. With `None` the
+    /// dataset's `keylocation` is used, unless it is `prompt`: that would
+    /// wait on the terminal, so key material must be given for it instead.
+    /// With `noop` the key is only verified.
+    pub fn load_key(&self, key_material: Option<&[u8]>, noop: bool) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let pipe = match key_material {
+            Some(x) => Some(key_pipe(x)?),
+            None if self.keylocation()? == "prompt" => {
+                return Err(LibZfsError::Io(Error::new(
+                    ErrorKind::InvalidInput,
+                    "keylocation is prompt, so the key material must be given",
+                )));
+            }
+            None => None,
+        };
+
+        let location = match pipe {
+            Some(ref x) => Some(
+                CString::new(format!("file:///dev/fd/{}", x.reader.as_raw_fd()))
+                    .map_err(Error::from)?,
+            ),
+            None => None,
+        };
+
+        let noop = if noop {
+            sys::boolean::B_TRUE
+        } else {
+            sys::boolean::B_FALSE
+        };
+
+        let code = unsafe {
+            sys::zfs_crypto_load_key(
+                self.raw,
+                noop,
+                location
+                    .as_ref()
+                    .map_or(ptr::null_mut(), |x| x.as_ptr() as *mut _),
+            )
+        };
+
+        let written = pipe.map(KeyPipe::finish);
+
+        match (code, written) {
+            (0, Some(Err(e))) => Err(LibZfsError::Io(e)),
+            (0, _) => Ok(()),
+            _ => Err(LibZfsError::Zfs(self.zfs_error())),
+        }
+    }
+    /// Unloads the wrapping key of an encrypted dataset.
+    ///
+    /// Fails with `LibZfsError::ZfsBusy` if the dataset is still mounted or in use.
+    pub fn unload_key(&self) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        if self.get_int(ZfsProp::Mounted)? != 0 {
+            return Err(LibZfsError::ZfsBusy(
+                self.name().to_string_lossy().into_owned(),
+            ));
+        }
+
+        let code = unsafe { sys::zfs_crypto_unload_key(self.raw) };
+
+        if code == 0 {
+            return Ok(());
+        }
+
+        let errno = unsafe { sys::libzfs_errno(sys::zfs_get_handle(self.raw)) };
+
+        if errno as u32 == sys::zfs_error_EZFS_BUSY {
+            Err(LibZfsError::ZfsBusy(
+                self.name().to_string_lossy().into_owned(),
+            ))
+        } else {
+            Err(LibZfsError::Zfs(self.zfs_error()))
+        }
+    }
+    /// Changes the wrapping key of an encryption root.
+    ///
+    /// `props` may set `keylocation`, `keyformat` and `pbkdf2iters`.
+    ///
+    /// With `key_material` the new key is passed to ZFS through a pipe, as
+    /// for `load_key`, and `keylocation` is then set to the one in `props`,
+    /// or back to the current one. Without it the new key is read from the
+    /// new (or current) `keylocation`, which must not be `prompt`.
+    pub fn change_key(&self, props: &nvpair::NvListRef, key_material: Option<&[u8]>) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let location = match props.lookup_string(KEYLOCATION) {
+            Ok(x) => x.into_string()?,
+            Err(_) => self.keylocation()?,
+        };
+
+        let pipe = match key_material {
+            Some(x) => Some(key_pipe(x)?),
+            None if location == "prompt" => {
+                return Err(LibZfsError::Io(Error::new(
+                    ErrorKind::InvalidInput,
+                    "keylocation is prompt, so the key material must be given",
+                )));
+            }
+            None => None,
+        };
+
+        let mut rewrap = rewrap_props(props, pipe.as_ref())?;
+
+        let code = unsafe {
+            sys::zfs_crypto_rewrap(
+                self.raw,
+                rewrap.as_mut_ptr() as *mut _,
+                sys::boolean::B_FALSE,
+            )
+        };
+
+        if code != 0 {
+            let e = self.zfs_error();
+
+            if let Some(x) = pipe {
+                let _ = x.finish();
+            }
+
+            return Err(LibZfsError::PropSet(
+                props
+                    .iter()
+                    .map(|x| x.name().to_string_lossy().into_owned())
+                    .collect(),
+                e,
+            ));
+        }
+
+        match pipe {
+            Some(x) => {
+                let written = x.finish();
+
+                self.set_prop(KEYLOCATION, &location)?;
+
+                written.map_err(LibZfsError::from)
+            }
+            None => Ok(()),
+        }
+    }
+    /// Gets a native property by name, as the running libzfs numbers it.
+    fn get_named_str(&self, name: &str) -> Result<String> {
+        let _l = self.libzfs.lock();
+
+        let c_name = CString::new(name).map_err(Error::from)?;
+        let prop = unsafe { sys::zfs_name_to_prop(c_name.as_ptr()) };
+
+        if prop == sys::zfs_prop_t_ZFS_PROP_BAD {
+            return Err(LibZfsError::Io(Error::new(
+                ErrorKind::NotFound,
+                format!("{} is not supported by this version of ZFS", name),
+            )));
+        }
+
+        let mut buf = vec![0u8; sys::ZFS_MAXPROPLEN as usize];
+
+        let code = unsafe {
+            sys::zfs_prop_get(
+                self.raw,
+                prop,
+                buf.as_mut_ptr() as *mut c_char,
+                buf.len(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                0,
+                sys::boolean::B_TRUE,
+            )
+        };
+
+        match code {
+            0 => Ok(unsafe { CStr::from_ptr(buf.as_ptr() as *const c_char) }
+                .to_string_lossy()
+                .into_owned()),
+            _ => Err(LibZfsError::Io(Error::new(
+                ErrorKind::NotFound,
+                format!("{} is not available", name),
+            ))),
+        }
+    }
+    /// The encryption suite, such as `aes-256-gcm`, or `off`.
+    pub fn encryption(&self) -> Result<String> {
+        self.get_named_str("encryption")
+    }
+    /// Whether the wrapping key is `available` or `unavailable`,
+    /// or `none` for unencrypted datasets.
+    pub fn keystatus(&self) -> Result<String> {
+        self.get_named_str("keystatus")
+    }
+    /// Where the wrapping key is loaded from, such as `prompt` or a `file://` URI.
+    pub fn keylocation(&self) -> Result<String> {
+        self.get_named_str(KEYLOCATION)
+    }
+    /// The name of the dataset this one inherits its wrapping key from,
+    /// or `None` if it is not encrypted.
+    pub fn encryptionroot(&self) -> Result<Option<String>> {
+        let x = self.get_named_str("encryptionroot")?;
+
+        Ok(Some(x).filter(|x| !x.is_empty() && x != "-"))
+    }
+    /// Opens the encryption root of this dataset.
+    ///
+    /// Keys must be loaded on encryption roots before their descendants
+    /// can be mounted, so this gives the order to load keys in for a tree.
+    pub fn encryption_root(&self) -> Result<Option<Zfs>> {
+        let _l = self.libzfs.lock();
+
+        let name = match self.encryptionroot()? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+
+        let c_name = CString::new(name.as_str()).map_err(Error::from)?;
+
+        let h = unsafe {
+            sys::zfs_open(
+                sys::zfs_get_handle(self.raw),
+                c_name.as_ptr(),
+                (sys::zfs_type_t::ZFS_TYPE_FILESYSTEM | sys::zfs_type_t::ZFS_TYPE_VOLUME).0
+                    as c_int,
+            )
+        };
+
+        if h.is_null() {
+            Err(LibZfsError::ZfsNotFound(name))
+        } else {
+            Ok(Some(Zfs::new(self.libzfs.clone(), h)))
+        }
+    }
+    /// The snapshot this dataset was cloned from, if it is a clone.
+    pub fn origin(&self) -> Option<String> {
+        self.get_prop(ZfsProp::Origin)
+            .ok()
+            .map(|x| x.value)
+            .filter(|x| !x.is_empty())
+    }
+    /// The datasets cloned from this snapshot.
+    ///
+    /// Only snapshots have clones; for anything else this is empty.
+    pub fn clones(&self) -> Result<Vec<String>> {
+        let _l = self.libzfs.lock();
+
+        if self.zfs_type() != sys::zfs_type_t::ZFS_TYPE_SNAPSHOT {
+            return Ok(vec![]);
+        }
+
+        let nvl = unsafe { sys::zfs_get_clones_nvl(self.raw) };
+
+        if nvl.is_null() {
+            return Err(LibZfsError::Zfs(self.zfs_error()));
+        }
+
+        // Owned by the handle
+        let nvl = unsafe { nvpair::NvListRef::from_ptr(nvl as *const _) };
+
+        Ok(nvl
+            .iter()
+            .map(|x| x.name().to_string_lossy().into_owned())
+            .collect())
+    }
+    /// Walks this dataset and those below it like `zfs list -r -d N -t TYPES`,
+    /// see `Zpool::traverse`.
+    pub fn traverse(
+        &self,
+        max_depth: Option<usize>,
+        types: sys::zfs_type_t,
+    ) -> Result<DatasetIter> {
+        DatasetIter::traverse(self, max_depth, types, true)
+    }
+    /// Lists every snapshot, clone and descendant that depends on this
+    /// dataset, in an order they can be destroyed in.
+    ///
+    /// This is what `zfs destroy -R` would destroy along with it.
+    pub fn dependents(&self) -> Result<Vec<Zfs>> {
+        let _l = self.libzfs.lock();
+
+        unsafe extern "C" fn callback(handle: *mut sys::zfs_handle_t, state: *mut c_void) -> c_int {
+            let state = &mut *(state as *mut Vec<*mut sys::zfs_handle_t>);
+
+            state.push(handle);
+
+            0
+        }
+
+        let mut state: Vec<*mut sys::zfs_handle_t> = Vec::new();
+        let state_ptr: *mut c_void = &mut state as *mut _ as *mut c_void;
+        let code = unsafe {
+            sys::zfs_iter_dependents(self.raw, sys::boolean::B_FALSE, Some(callback), state_ptr)
+        };
+
+        let xs: Vec<Zfs> = state
+            .into_iter()
+            .map(|x| Zfs::new(self.libzfs.clone(), x))
+            .collect();
+
+        match code {
+            0 => Ok(xs),
+            _ => Err(LibZfsError::Zfs(self.zfs_error())),
+        }
+    }
+    /// Lists the delegations that apply to this dataset,
+    /// keyed by the dataset each is set on, as with `zfs allow`.
+    pub fn delegations(&self) -> Result<BTreeMap<String, Vec<Delegation>>> {
+        let _l = self.libzfs.lock();
+
+        let mut nvl = ptr::null_mut();
+
+        let code = unsafe { sys::zfs_get_fsacl(self.raw, &mut nvl) };
+
+        if code != 0 {
+            return Err(LibZfsError::Zfs(self.zfs_error()));
+        }
+
+        if nvl.is_null() {
+            return Ok(BTreeMap::new());
+        }
+
+        let nvl = unsafe { nvpair::NvList::from_ptr(nvl as *mut _) };
+
+        nvl.iter()
+            .map(|x| {
+                Ok((
+                    x.name().to_string_lossy().into_owned(),
+                    delegation::decode(x.value_nv_list_ref()?)?,
+                ))
+            })
+            .collect()
+    }
+    fn set_fsacl(&self, un: bool, xs: &[Delegation]) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let mut nvl = delegation::encode(xs)?;
+
+        let un = if un {
+            sys::boolean::B_TRUE
+        } else {
+            sys::boolean::B_FALSE
+        };
+
+        let code = unsafe { sys::zfs_set_fsacl(self.raw, un, nvl.as_mut_ptr() as *mut _) };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(LibZfsError::Zfs(self.zfs_error())),
+        }
+    }
+    /// Grants permissions on this dataset, as with `zfs allow`.
+    ///
+    /// Failures are classified by the libzfs error: `PermissionDenied` when
+    /// delegation is off or not allowed, and `InvalidName` for an unknown
+    /// user, group, permission or permission set.
+    pub fn allow(&self, xs: &[Delegation]) -> Result<()> {
+        self.set_fsacl(false, xs)
+    }
+    /// Revokes permissions on this dataset, as with `zfs unallow`.
+    pub fn unallow(&self, xs: &[Delegation]) -> Result<()> {
+        self.set_fsacl(true, xs)
+    }
+    /// Lists the changes between this snapshot and `other`,
+    /// which may be a later snapshot or the live filesystem.
+    pub fn diff(&self, other: &Zfs) -> Result<DiffIter> {
+        let _l = self.libzfs.lock();
+
+        let from = self.name();
+        let to = other.name();
+
+        let fs_name = match from.to_bytes().iter().position(|x| *x == b'@') {
+            Some(i) => CString::new(&from.to_bytes()[..i]).unwrap(),
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "diff source must be a snapshot",
+            ))?,
+        };
+
+        let fs = unsafe {
+            let h = sys::zfs_open(
+                sys::zfs_get_handle(self.raw),
+                fs_name.as_ptr(),
+                sys::zfs_type_t::ZFS_TYPE_FILESYSTEM.0 as c_int,
+            );
+
+            if h.is_null() {
+                return Err(LibZfsError::ZfsNotFound(fs_name.into_string()?));
+            }
+
+            Zfs::new(self.libzfs.clone(), h)
+        };
+
+        let (writer, reader) = spool_file()?;
+
+        let code = unsafe {
+            sys::zfs_show_diffs(
+                fs.raw,
+                writer.as_raw_fd(),
+                from.as_ptr() as *mut _,
+                to.as_ptr() as *mut _,
+                (sys::ZFS_DIFF_PARSEABLE | sys::ZFS_DIFF_CLASSIFY) as c_int,
+            )
+        };
+
+        match code {
+            0 => Ok(DiffIter::new(reader)),
+            _ => Err(LibZfsError::Zfs(self.zfs_error())),
+        }
+    }
+}
+
+/// A pipe ZFS reads a key from
+///
+/// The key is written from a thread of its own, so keys larger than
+/// the pipe buffer do not block before ZFS starts reading.
+struct KeyPipe {
+    reader: File,
+    writer: thread::JoinHandle<io::Result<()>>,
+}
+
+impl KeyPipe {
+    /// Closes the read end, so a writer ZFS stopped reading from
+    /// fails rather than blocks, and waits for the writer.
+    fn finish(self) -> io::Result<()> {
+        drop(self.reader);
+
+        self.writer
+            .join()
+            .unwrap_or_else(|_| Err(Error::new(ErrorKind::Other, "key writer panicked")))
+    }
+}
+
+/// Copies `props` for `zfs_crypto_rewrap`, pointing `keylocation`
+/// at `pipe` when the key comes through one.
+///
+/// `props` may hold several pairs of one name, and libzfs reads the
+/// first, so any `keylocation` given is removed before the pipe's is added.
+fn rewrap_props(props: &nvpair::NvListRef, pipe: Option<&KeyPipe>) -> Result<nvpair::NvList> {
+    let mut rewrap = props.try_to_owned()?;
+
+    if let Some(x) = pipe {
+        if rewrap.exists(KEYLOCATION) {
+            rewrap.remove_all(KEYLOCATION)?;
+        }
+
+        rewrap.add_string(
+            KEYLOCATION,
+            format!("file:///dev/fd/{}", x.reader.as_raw_fd()).as_str(),
+        )?;
+    }
+
+    Ok(rewrap)
+}
+
+/// Starts writing `key` into a pipe whose ends are closed on exec.
+fn key_pipe(key: &[u8]) -> Result<KeyPipe> {
+    let mut fds = [0; 2];
+
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(LibZfsError::Io(Error::last_os_error()));
+    }
+
+    let (reader, mut writer) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+
+    let key = key.to_vec();
+
+    Ok(KeyPipe {
+        reader,
+        writer: thread::spawn(move || writer.write_all(&key)),
+    })
+}
+
+/// Which space accounting `Zfs::userspace` reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UserspaceProp {
+    UserUsed,
+    GroupUsed,
+    ProjectUsed,
+}
+
+impl UserspaceProp {
+    pub fn to_raw(self) -> sys::zfs_userquota_prop_t {
+        match self {
+            UserspaceProp::UserUsed => sys::zfs_userquota_prop_t_ZFS_PROP_USERUSED,
+            UserspaceProp::GroupUsed => sys::zfs_userquota_prop_t_ZFS_PROP_GROUPUSED,
+            UserspaceProp::ProjectUsed => sys::zfs_userquota_prop_t_ZFS_PROP_PROJECTUSED,
+        }
+    }
+}
+
+/// Builds a property name such as `userquota@1000`
+fn userquota_prop(target: QuotaTarget, suffix: &str) -> String {
+    match target {
+        QuotaTarget::User(x) => format!("user{}@{}", suffix, x),
+        QuotaTarget::Group(x) => format!("group{}@{}", suffix, x),
+        QuotaTarget::Project(x) => format!("project{}@{}", suffix, x),
+    }
 }
 
 impl Drop for Zfs {
     fn drop(&mut self) {
+        let _l = self.libzfs.lock();
+
         unsafe { sys::zfs_close(self.raw) }
     }
 }
@@ -119,6 +1189,7 @@ mod tests {
     use super::*;
     use libzfs::Libzfs;
     use std::ffi::CString;
+    use std::io::Read;
     use std::panic;
     use std::str;
     use zprop_list::ZProp;
@@ -145,6 +1216,59 @@ mod tests {
         result.unwrap();
     }
 
+    #[test]
+    fn pipes_large_keys() {
+        let key: Vec<u8> = (0..1 << 20).map(|x| x as u8).collect();
+
+        let mut pipe = key_pipe(&key).unwrap();
+
+        let flags = unsafe { libc::fcntl(pipe.reader.as_raw_fd(), libc::F_GETFD) };
+        assert_eq!(flags & libc::FD_CLOEXEC, libc::FD_CLOEXEC);
+
+        let mut read = vec![];
+        pipe.reader.read_to_end(&mut read).unwrap();
+
+        assert_eq!(read, key);
+        pipe.finish().unwrap();
+    }
+
+    #[test]
+    fn finishes_unread_key() {
+        let pipe = key_pipe(&[0; 1 << 20]).unwrap();
+
+        assert!(pipe.finish().is_err());
+    }
+
+    #[test]
+    fn rewraps_from_pipe_over_given_location() {
+        let mut props = nvpair::NvList::new().unwrap();
+        props.add_string(KEYLOCATION, "prompt").unwrap();
+        props.add_string("keyformat", "passphrase").unwrap();
+
+        let pipe = key_pipe(b"password").unwrap();
+        let rewrap = rewrap_props(&props, Some(&pipe)).unwrap();
+
+        let locations: Vec<_> = rewrap
+            .iter()
+            .filter(|x| x.name().to_bytes() == KEYLOCATION.as_bytes())
+            .map(|x| x.value_str().unwrap().to_string_lossy().into_owned())
+            .collect();
+
+        assert_eq!(
+            locations,
+            vec![format!("file:///dev/fd/{}", pipe.reader.as_raw_fd())]
+        );
+        assert!(rewrap.exists("keyformat"));
+
+        drop(pipe);
+
+        let rewrap = rewrap_props(&props, None).unwrap();
+        assert_eq!(
+            rewrap.lookup_str(KEYLOCATION).unwrap().to_bytes(),
+            b"prompt"
+        );
+    }
+
     #[test]
     fn dataset_type_name() {
         zfs_by_name("test/ds", |ds| {
@@ -156,9 +1280,151 @@ mod tests {
     fn dataset_name() {
         zfs_by_name("test/ds", |ds| {
             assert_eq!(ds.name(), CString::new("test/ds").unwrap());
+            assert_eq!(ds.name_str().unwrap(), "test/ds");
         });
     }
 
+    #[test]
+    fn dataset_typed_props() {
+        zfs_by_name("test/ds", |ds| {
+            assert_eq!(
+                ds.get_str(ZfsProp::Mountpoint).unwrap(),
+                CString::new("/test/ds").unwrap()
+            );
+            assert_eq!(ds.get_int(ZfsProp::Recordsize).unwrap(), 131_072);
+        });
+    }
+
+    #[test]
+    fn dataset_batched_props() {
+        zfs_by_name("test/ds", |z| {
+            let xs = z
+                .get_props(&["mountpoint", "recordsize", "lustre:missing"])
+                .unwrap();
+
+            assert_eq!(xs.get("mountpoint").map(|x| x.as_str()), Some("/test/ds"));
+            assert_eq!(
+                xs.get("recordsize"),
+                Some(
+                    &z.get_str(ZfsProp::Recordsize)
+                        .unwrap()
+                        .into_string()
+                        .unwrap()
+                )
+            );
+            assert!(!xs.contains_key("lustre:missing"));
+        })
+    }
+
+    #[test]
+    fn dataset_dependents() {
+        zfs_by_name("test/ds", |z| {
+            let xs: Vec<CString> = z.dependents().unwrap().iter().map(|x| x.name()).collect();
+
+            assert!(!xs.contains(&z.name()));
+            assert!(xs.iter().all(|x| x.to_bytes().starts_with(b"test/ds")));
+        })
+    }
+
+    #[test]
+    fn dataset_pool() {
+        zfs_by_name("test/ds", |z| {
+            assert_eq!(z.pool_name(), CString::new("test").unwrap());
+            assert_eq!(z.pool().unwrap().name(), CString::new("test").unwrap());
+        })
+    }
+
+    #[test]
+    fn dataset_origin() {
+        zfs_by_name("test/ds", |z| {
+            assert_eq!(z.origin(), None);
+            assert!(z.clones().unwrap().is_empty());
+        })
+    }
+
+    #[test]
+    fn dataset_prop_source() {
+        zfs_by_name("test/ds", |z| {
+            let x = z.get_prop(ZfsProp::Type).unwrap();
+
+            assert_eq!(x.value, "filesystem");
+            assert_eq!(x.source, PropSource::None);
+            assert_eq!(x.received, None);
+        })
+    }
+
+    #[test]
+    fn dataset_snapshot_mount_path() {
+        zfs_by_name("test/ds", |z| {
+            assert_eq!(
+                z.snapshot_mount_path("snap1").unwrap(),
+                PathBuf::from("/test/ds/.zfs/snapshot/snap1")
+            );
+            assert!(!z.snapdir_visible().unwrap());
+        })
+    }
+
+    #[test]
+    fn dataset_userquota() {
+        zfs_by_name("test/ds", |z| {
+            assert_eq!(
+                userquota_prop(QuotaTarget::Group(10), "quota"),
+                "groupquota@10"
+            );
+            assert_eq!(z.userquota(QuotaTarget::User(1000)).unwrap(), 0);
+        })
+    }
+
+    #[test]
+    fn dataset_userspace() {
+        zfs_by_name("test/ds", |z| {
+            let xs = z.userspace(UserspaceProp::UserUsed).unwrap();
+
+            assert!(xs.iter().any(|x| x.id == 0 && x.domain.is_empty()));
+        })
+    }
+
+    #[test]
+    fn dataset_quota() {
+        zfs_by_name("test/ds", |z| {
+            assert_eq!(z.quota().unwrap(), 0);
+            assert_eq!(z.parse_size("10G").unwrap(), 10 << 30);
+            assert!(z.parse_size("ten").is_err());
+        })
+    }
+
+    #[test]
+    fn dataset_encryption_root() {
+        zfs_by_name("test/ds", |z| {
+            if let Ok(x) = z.encryption() {
+                assert_eq!(x, "off");
+                assert!(z.encryption_root().unwrap().is_none());
+            }
+        })
+    }
+
+    #[test]
+    fn dataset_mount() {
+        zfs_by_name("test/ds", |z| {
+            z.unmount(false, false).unwrap();
+            assert!(!z.is_mounted());
+
+            z.mount(None).unwrap();
+            assert!(z.is_mounted());
+        })
+    }
+
+    #[test]
+    fn dataset_all_props() {
+        zfs_by_name("test/ds", |z| {
+            let xs = z.all_props();
+
+            assert_eq!(xs["type"].value, "filesystem");
+            assert_eq!(xs["recordsize"].numeric, Some(131_072));
+            assert!(!xs.contains_key("volsize"));
+        })
+    }
+
     #[test]
     fn dataset_props() {
         zfs_by_name("test/ds", |ds| {
@@ -0,0 +1,275 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+extern crate libzfs_sys as sys;
+
+use std::ffi::CStr;
+
+/// Native dataset properties, mirroring `zfs_prop_t`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ZfsProp {
+    Type,
+    Creation,
+    Used,
+    Available,
+    Referenced,
+    Compressratio,
+    Mounted,
+    Origin,
+    Quota,
+    Reservation,
+    Volsize,
+    Volblocksize,
+    Recordsize,
+    Mountpoint,
+    Sharenfs,
+    Checksum,
+    Compression,
+    Atime,
+    Devices,
+    Exec,
+    Setuid,
+    Readonly,
+    Zoned,
+    Snapdir,
+    Private,
+    Aclinherit,
+    Createtxg,
+    Name,
+    Canmount,
+    Iscsioptions,
+    Xattr,
+    Numclones,
+    Copies,
+    Version,
+    Utf8only,
+    Normalize,
+    Case,
+    Vscan,
+    Nbmand,
+    Sharesmb,
+    Refquota,
+    Refreservation,
+    Guid,
+    Primarycache,
+    Secondarycache,
+    Usedsnap,
+    Usedds,
+    Usedchild,
+    Usedrefreserv,
+    Useraccounting,
+    StmfShareInfo,
+    DeferDestroy,
+    Userrefs,
+    Logbias,
+    Unique,
+    Objsetid,
+    Dedup,
+    Mlslabel,
+    Sync,
+    Dnodesize,
+    Refratio,
+    Written,
+    Clones,
+    Logicalused,
+    Logicalreferenced,
+    Inconsistent,
+    Volmode,
+    FilesystemLimit,
+    SnapshotLimit,
+    FilesystemCount,
+    SnapshotCount,
+    Snapdev,
+    Acltype,
+    SelinuxContext,
+    SelinuxFsContext,
+    SelinuxDefContext,
+    SelinuxRootContext,
+    Relatime,
+    RedundantMetadata,
+    Overlay,
+    PrevSnap,
+    ReceiveResumeToken,
+}
+
+/// Every `ZfsProp`, in `zfs_prop_t` order
+pub const ALL_PROPS: &[ZfsProp] = &[
+    ZfsProp::Type,
+    ZfsProp::Creation,
+    ZfsProp::Used,
+    ZfsProp::Available,
+    ZfsProp::Referenced,
+    ZfsProp::Compressratio,
+    ZfsProp::Mounted,
+    ZfsProp::Origin,
+    ZfsProp::Quota,
+    ZfsProp::Reservation,
+    ZfsProp::Volsize,
+    ZfsProp::Volblocksize,
+    ZfsProp::Recordsize,
+    ZfsProp::Mountpoint,
+    ZfsProp::Sharenfs,
+    ZfsProp::Checksum,
+    ZfsProp::Compression,
+    ZfsProp::Atime,
+    ZfsProp::Devices,
+    ZfsProp::Exec,
+    ZfsProp::Setuid,
+    ZfsProp::Readonly,
+    ZfsProp::Zoned,
+    ZfsProp::Snapdir,
+    ZfsProp::Private,
+    ZfsProp::Aclinherit,
+    ZfsProp::Createtxg,
+    ZfsProp::Name,
+    ZfsProp::Canmount,
+    ZfsProp::Iscsioptions,
+    ZfsProp::Xattr,
+    ZfsProp::Numclones,
+    ZfsProp::Copies,
+    ZfsProp::Version,
+    ZfsProp::Utf8only,
+    ZfsProp::Normalize,
+    ZfsProp::Case,
+    ZfsProp::Vscan,
+    ZfsProp::Nbmand,
+    ZfsProp::Sharesmb,
+    ZfsProp::Refquota,
+    ZfsProp::Refreservation,
+    ZfsProp::Guid,
+    ZfsProp::Primarycache,
+    ZfsProp::Secondarycache,
+    ZfsProp::Usedsnap,
+    ZfsProp::Usedds,
+    ZfsProp::Usedchild,
+    ZfsProp::Usedrefreserv,
+    ZfsProp::Useraccounting,
+    ZfsProp::StmfShareInfo,
+    ZfsProp::DeferDestroy,
+    ZfsProp::Userrefs,
+    ZfsProp::Logbias,
+    ZfsProp::Unique,
+    ZfsProp::Objsetid,
+    ZfsProp::Dedup,
+    ZfsProp::Mlslabel,
+    ZfsProp::Sync,
+    ZfsProp::Dnodesize,
+    ZfsProp::Refratio,
+    ZfsProp::Written,
+    ZfsProp::Clones,
+    ZfsProp::Logicalused,
+    ZfsProp::Logicalreferenced,
+    ZfsProp::Inconsistent,
+    ZfsProp::Volmode,
+    ZfsProp::FilesystemLimit,
+    ZfsProp::SnapshotLimit,
+    ZfsProp::FilesystemCount,
+    ZfsProp::SnapshotCount,
+    ZfsProp::Snapdev,
+    ZfsProp::Acltype,
+    ZfsProp::SelinuxContext,
+    ZfsProp::SelinuxFsContext,
+    ZfsProp::SelinuxDefContext,
+    ZfsProp::SelinuxRootContext,
+    ZfsProp::Relatime,
+    ZfsProp::RedundantMetadata,
+    ZfsProp::Overlay,
+    ZfsProp::PrevSnap,
+    ZfsProp::ReceiveResumeToken,
+];
+
+impl ZfsProp {
+    pub fn to_raw(self) -> sys::zfs_prop_t {
+        match self {
+            ZfsProp::Type => sys::zfs_prop_t_ZFS_PROP_TYPE,
+            ZfsProp::Creation => sys::zfs_prop_t_ZFS_PROP_CREATION,
+            ZfsProp::Used => sys::zfs_prop_t_ZFS_PROP_USED,
+            ZfsProp::Available => sys::zfs_prop_t_ZFS_PROP_AVAILABLE,
+            ZfsProp::Referenced => sys::zfs_prop_t_ZFS_PROP_REFERENCED,
+            ZfsProp::Compressratio => sys::zfs_prop_t_ZFS_PROP_COMPRESSRATIO,
+            ZfsProp::Mounted => sys::zfs_prop_t_ZFS_PROP_MOUNTED,
+            ZfsProp::Origin => sys::zfs_prop_t_ZFS_PROP_ORIGIN,
+            ZfsProp::Quota => sys::zfs_prop_t_ZFS_PROP_QUOTA,
+            ZfsProp::Reservation => sys::zfs_prop_t_ZFS_PROP_RESERVATION,
+            ZfsProp::Volsize => sys::zfs_prop_t_ZFS_PROP_VOLSIZE,
+            ZfsProp::Volblocksize => sys::zfs_prop_t_ZFS_PROP_VOLBLOCKSIZE,
+            ZfsProp::Recordsize => sys::zfs_prop_t_ZFS_PROP_RECORDSIZE,
+            ZfsProp::Mountpoint => sys::zfs_prop_t_ZFS_PROP_MOUNTPOINT,
+            ZfsProp::Sharenfs => sys::zfs_prop_t_ZFS_PROP_SHARENFS,
+            ZfsProp::Checksum => sys::zfs_prop_t_ZFS_PROP_CHECKSUM,
+            ZfsProp::Compression => sys::zfs_prop_t_ZFS_PROP_COMPRESSION,
+            ZfsProp::Atime => sys::zfs_prop_t_ZFS_PROP_ATIME,
+            ZfsProp::Devices => sys::zfs_prop_t_ZFS_PROP_DEVICES,
+            ZfsProp::Exec => sys::zfs_prop_t_ZFS_PROP_EXEC,
+            ZfsProp::Setuid => sys::zfs_prop_t_ZFS_PROP_SETUID,
+            ZfsProp::Readonly => sys::zfs_prop_t_ZFS_PROP_READONLY,
+            ZfsProp::Zoned => sys::zfs_prop_t_ZFS_PROP_ZONED,
+            ZfsProp::Snapdir => sys::zfs_prop_t_ZFS_PROP_SNAPDIR,
+            ZfsProp::Private => sys::zfs_prop_t_ZFS_PROP_PRIVATE,
+            ZfsProp::Aclinherit => sys::zfs_prop_t_ZFS_PROP_ACLINHERIT,
+            ZfsProp::Createtxg => sys::zfs_prop_t_ZFS_PROP_CREATETXG,
+            ZfsProp::Name => sys::zfs_prop_t_ZFS_PROP_NAME,
+            ZfsProp::Canmount => sys::zfs_prop_t_ZFS_PROP_CANMOUNT,
+            ZfsProp::Iscsioptions => sys::zfs_prop_t_ZFS_PROP_ISCSIOPTIONS,
+            ZfsProp::Xattr => sys::zfs_prop_t_ZFS_PROP_XATTR,
+            ZfsProp::Numclones => sys::zfs_prop_t_ZFS_PROP_NUMCLONES,
+            ZfsProp::Copies => sys::zfs_prop_t_ZFS_PROP_COPIES,
+            ZfsProp::Version => sys::zfs_prop_t_ZFS_PROP_VERSION,
+            ZfsProp::Utf8only => sys::zfs_prop_t_ZFS_PROP_UTF8ONLY,
+            ZfsProp::Normalize => sys::zfs_prop_t_ZFS_PROP_NORMALIZE,
+            ZfsProp::Case => sys::zfs_prop_t_ZFS_PROP_CASE,
+            ZfsProp::Vscan => sys::zfs_prop_t_ZFS_PROP_VSCAN,
+            ZfsProp::Nbmand => sys::zfs_prop_t_ZFS_PROP_NBMAND,
+            ZfsProp::Sharesmb => sys::zfs_prop_t_ZFS_PROP_SHARESMB,
+            ZfsProp::Refquota => sys::zfs_prop_t_ZFS_PROP_REFQUOTA,
+            ZfsProp::Refreservation => sys::zfs_prop_t_ZFS_PROP_REFRESERVATION,
+            ZfsProp::Guid => sys::zfs_prop_t_ZFS_PROP_GUID,
+            ZfsProp::Primarycache => sys::zfs_prop_t_ZFS_PROP_PRIMARYCACHE,
+            ZfsProp::Secondarycache => sys::zfs_prop_t_ZFS_PROP_SECONDARYCACHE,
+            ZfsProp::Usedsnap => sys::zfs_prop_t_ZFS_PROP_USEDSNAP,
+            ZfsProp::Usedds => sys::zfs_prop_t_ZFS_PROP_USEDDS,
+            ZfsProp::Usedchild => sys::zfs_prop_t_ZFS_PROP_USEDCHILD,
+            ZfsProp::Usedrefreserv => sys::zfs_prop_t_ZFS_PROP_USEDREFRESERV,
+            ZfsProp::Useraccounting => sys::zfs_prop_t_ZFS_PROP_USERACCOUNTING,
+            ZfsProp::StmfShareInfo => sys::zfs_prop_t_ZFS_PROP_STMF_SHAREINFO,
+            ZfsProp::DeferDestroy => sys::zfs_prop_t_ZFS_PROP_DEFER_DESTROY,
+            ZfsProp::Userrefs => sys::zfs_prop_t_ZFS_PROP_USERREFS,
+            ZfsProp::Logbias => sys::zfs_prop_t_ZFS_PROP_LOGBIAS,
+            ZfsProp::Unique => sys::zfs_prop_t_ZFS_PROP_UNIQUE,
+            ZfsProp::Objsetid => sys::zfs_prop_t_ZFS_PROP_OBJSETID,
+            ZfsProp::Dedup => sys::zfs_prop_t_ZFS_PROP_DEDUP,
+            ZfsProp::Mlslabel => sys::zfs_prop_t_ZFS_PROP_MLSLABEL,
+            ZfsProp::Sync => sys::zfs_prop_t_ZFS_PROP_SYNC,
+            ZfsProp::Dnodesize => sys::zfs_prop_t_ZFS_PROP_DNODESIZE,
+            ZfsProp::Refratio => sys::zfs_prop_t_ZFS_PROP_REFRATIO,
+            ZfsProp::Written => sys::zfs_prop_t_ZFS_PROP_WRITTEN,
+            ZfsProp::Clones => sys::zfs_prop_t_ZFS_PROP_CLONES,
+            ZfsProp::Logicalused => sys::zfs_prop_t_ZFS_PROP_LOGICALUSED,
+            ZfsProp::Logicalreferenced => sys::zfs_prop_t_ZFS_PROP_LOGICALREFERENCED,
+            ZfsProp::Inconsistent => sys::zfs_prop_t_ZFS_PROP_INCONSISTENT,
+            ZfsProp::Volmode => sys::zfs_prop_t_ZFS_PROP_VOLMODE,
+            ZfsProp::FilesystemLimit => sys::zfs_prop_t_ZFS_PROP_FILESYSTEM_LIMIT,
+            ZfsProp::SnapshotLimit => sys::zfs_prop_t_ZFS_PROP_SNAPSHOT_LIMIT,
+            ZfsProp::FilesystemCount => sys::zfs_prop_t_ZFS_PROP_FILESYSTEM_COUNT,
+            ZfsProp::SnapshotCount => sys::zfs_prop_t_ZFS_PROP_SNAPSHOT_COUNT,
+            ZfsProp::Snapdev => sys::zfs_prop_t_ZFS_PROP_SNAPDEV,
+            ZfsProp::Acltype => sys::zfs_prop_t_ZFS_PROP_ACLTYPE,
+            ZfsProp::SelinuxContext => sys::zfs_prop_t_ZFS_PROP_SELINUX_CONTEXT,
+            ZfsProp::SelinuxFsContext => sys::zfs_prop_t_ZFS_PROP_SELINUX_FSCONTEXT,
+            ZfsProp::SelinuxDefContext => sys::zfs_prop_t_ZFS_PROP_SELINUX_DEFCONTEXT,
+            ZfsProp::SelinuxRootContext => sys::zfs_prop_t_ZFS_PROP_SELINUX_ROOTCONTEXT,
+            ZfsProp::Relatime => sys::zfs_prop_t_ZFS_PROP_RELATIME,
+            ZfsProp::RedundantMetadata => sys::zfs_prop_t_ZFS_PROP_REDUNDANT_METADATA,
+            ZfsProp::Overlay => sys::zfs_prop_t_ZFS_PROP_OVERLAY,
+            ZfsProp::PrevSnap => sys::zfs_prop_t_ZFS_PROP_PREV_SNAP,
+            ZfsProp::ReceiveResumeToken => sys::zfs_prop_t_ZFS_PROP_RECEIVE_RESUME_TOKEN,
+        }
+    }
+    /// The property name as used by `zfs get`
+    pub fn name(self) -> &'static str {
+        let s = unsafe { CStr::from_ptr(sys::zfs_prop_to_name(self.to_raw())) };
+
+        s.to_str().unwrap_or("")
+    }
+}
@@ -4,33 +4,110 @@
 
 extern crate libzfs_sys as sys;
 
-use libzfs_types::{LibZfsError, Result};
-use nvpair;
+use dataset_iter::DatasetIter;
+use history::HistoryIter;
+use libc;
+use libzfs::{self, LibzfsHandle};
+use libzfs_types::{
+    CheckpointState, CheckpointStatus, Feature, FeatureState, LibZfsError, PersistentError,
+    PoolProp, PoolProps, PropSource, RemovalStatus, Result, ScanFunction, ScanState, ScanStatus,
+    StatusReason, VDevOpState, ZfsError, ZpoolInfo, ZpoolStatus,
+};
+use nvpair::{self, ForeignType};
+use state;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::{CStr, CString};
-use std::io::Error;
-use std::os::raw::{c_int, c_void};
+use std::io::{Error, ErrorKind};
+use std::os::raw::{c_char, c_int, c_void};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
 use std::ptr;
-use vdev::{enumerate_vdev_tree, VDev};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use vdev::{
+    data_leaf_paths, deferred_resilvers, enumerate_vdev_tree, leaf_progress, vdev_map, VDev,
+    VDevInfo, VDevProgress, VS_INITIALIZE_OFFSET, VS_TRIM_OFFSET,
+};
+use vdev_spec::VDevSpec;
 use zfs::Zfs;
+use zprop_list::{ZpropItem, ZpropList};
 
+/// How often `Zpool::wait` rereads the pool's stats
+const WAIT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Maps a `zpool_status_t` to a `StatusReason`.
+fn status_reason(raw: sys::zpool_status_t) -> StatusReason {
+    match raw {
+        sys::zpool_status_t_ZPOOL_STATUS_CORRUPT_CACHE => StatusReason::CorruptCache,
+        sys::zpool_status_t_ZPOOL_STATUS_MISSING_DEV_R => StatusReason::MissingDevR,
+        sys::zpool_status_t_ZPOOL_STATUS_MISSING_DEV_NR => StatusReason::MissingDevNr,
+        sys::zpool_status_t_ZPOOL_STATUS_CORRUPT_LABEL_R => StatusReason::CorruptLabelR,
+        sys::zpool_status_t_ZPOOL_STATUS_CORRUPT_LABEL_NR => StatusReason::CorruptLabelNr,
+        sys::zpool_status_t_ZPOOL_STATUS_BAD_GUID_SUM => StatusReason::BadGuidSum,
+        sys::zpool_status_t_ZPOOL_STATUS_CORRUPT_POOL => StatusReason::CorruptPool,
+        sys::zpool_status_t_ZPOOL_STATUS_CORRUPT_DATA => StatusReason::CorruptData,
+        sys::zpool_status_t_ZPOOL_STATUS_FAILING_DEV => StatusReason::FailingDev,
+        sys::zpool_status_t_ZPOOL_STATUS_VERSION_NEWER => StatusReason::VersionNewer,
+        sys::zpool_status_t_ZPOOL_STATUS_HOSTID_MISMATCH => StatusReason::HostidMismatch,
+        sys::zpool_status_t_ZPOOL_STATUS_HOSTID_ACTIVE => StatusReason::HostidActive,
+        sys::zpool_status_t_ZPOOL_STATUS_HOSTID_REQUIRED => StatusReason::HostidRequired,
+        sys::zpool_status_t_ZPOOL_STATUS_IO_FAILURE_WAIT => StatusReason::IoFailureWait,
+        sys::zpool_status_t_ZPOOL_STATUS_IO_FAILURE_CONTINUE => StatusReason::IoFailureContinue,
+        sys::zpool_status_t_ZPOOL_STATUS_IO_FAILURE_MMP => StatusReason::IoFailureMmp,
+        sys::zpool_status_t_ZPOOL_STATUS_BAD_LOG => StatusReason::BadLog,
+        sys::zpool_status_t_ZPOOL_STATUS_ERRATA => StatusReason::Errata,
+        sys::zpool_status_t_ZPOOL_STATUS_UNSUP_FEAT_READ => StatusReason::UnsupFeatRead,
+        sys::zpool_status_t_ZPOOL_STATUS_UNSUP_FEAT_WRITE => StatusReason::UnsupFeatWrite,
+        sys::zpool_status_t_ZPOOL_STATUS_FAULTED_DEV_R => StatusReason::FaultedDevR,
+        sys::zpool_status_t_ZPOOL_STATUS_FAULTED_DEV_NR => StatusReason::FaultedDevNr,
+        sys::zpool_status_t_ZPOOL_STATUS_VERSION_OLDER => StatusReason::VersionOlder,
+        sys::zpool_status_t_ZPOOL_STATUS_FEAT_DISABLED => StatusReason::FeatDisabled,
+        sys::zpool_status_t_ZPOOL_STATUS_RESILVERING => StatusReason::Resilvering,
+        sys::zpool_status_t_ZPOOL_STATUS_OFFLINE_DEV => StatusReason::OfflineDev,
+        sys::zpool_status_t_ZPOOL_STATUS_REMOVED_DEV => StatusReason::RemovedDev,
+        sys::zpool_status_t_ZPOOL_STATUS_NON_NATIVE_ASHIFT => StatusReason::NonNativeAshift,
+        sys::zpool_status_t_ZPOOL_STATUS_OK => StatusReason::Ok,
+        x => StatusReason::Unknown(x),
+    }
+}
+
+/// An open pool
+///
+/// See `Libzfs` for how pools may be used across threads.
 #[derive(Debug, PartialEq)]
 pub struct Zpool {
     raw: *mut sys::zpool_handle_t,
+    libzfs: Arc<LibzfsHandle>,
 }
 
+// Every call on `raw` is made under the lock of the handle it was opened on.
+unsafe impl Send for Zpool {}
+
 impl Zpool {
-    pub fn new(raw: *mut sys::zpool_handle_t) -> Zpool {
-        Zpool { raw }
+    /// Takes ownership of `raw`, which must have been opened on `libzfs`.
+    pub fn new(libzfs: Arc<LibzfsHandle>, raw: *mut sys::zpool_handle_t) -> Zpool {
+        Zpool { raw, libzfs }
     }
     pub fn name(&self) -> CString {
+        let _l = self.libzfs.lock();
+
         let s = unsafe { CStr::from_ptr(sys::zpool_get_name(self.raw)) };
         s.to_owned()
     }
+    /// As `name`, as a `String`.
+    pub fn name_str(&self) -> Result<String> {
+        Ok(self.name().into_string()?)
+    }
     pub fn state(&self) -> sys::pool_state_t {
+        let _l = self.libzfs.lock();
+
         let state = unsafe { sys::zpool_get_state(self.raw) };
         state as sys::pool_state_t
     }
     pub fn state_name(&self) -> CString {
+        let _l = self.libzfs.lock();
+
         let state = self.state();
 
         let name = unsafe {
@@ -41,10 +118,18 @@ impl Zpool {
 
         name.to_owned()
     }
+    /// As `state_name`, as a `String`.
+    pub fn state_name_str(&self) -> Result<String> {
+        Ok(self.state_name().into_string()?)
+    }
     pub fn prop_int(&self, prop: sys::zpool_prop_t::Type) -> u64 {
+        let _l = self.libzfs.lock();
+
         unsafe { sys::zpool_get_prop_int(self.raw, prop, ptr::null_mut()) }
     }
     pub fn prop_str(&self, prop: sys::zpool_prop_t::Type) -> Result<CString> {
+        let _l = self.libzfs.lock();
+
         let s = String::with_capacity(sys::ZPOOL_MAXPROPLEN as usize);
         let c_string = CString::new(s).unwrap();
         let raw = c_string.into_raw();
@@ -62,23 +147,228 @@ impl Zpool {
             let out = CString::from_raw(raw);
 
             if r != 0 {
-                Err(::std::io::Error::from_raw_os_error(r))?
+                Err(LibZfsError::PropLookup {
+                    name: CStr::from_ptr(sys::zpool_prop_to_name(prop))
+                        .to_string_lossy()
+                        .into_owned(),
+                    source: Box::new(Error::from_raw_os_error(r).into()),
+                })
             } else {
                 Ok(out)
             }
         }
     }
+    pub fn prop_list(&mut self) -> Result<ZpropList> {
+        let _l = self.libzfs.lock();
+
+        let mut prop_list_ptr: *mut sys::zprop_list_t = ptr::null_mut();
+
+        let code = unsafe { sys::zpool_expand_proplist(self.raw, &mut prop_list_ptr) };
+
+        match code {
+            0 => Ok(ZpropList::new(prop_list_ptr)),
+            x => Err(LibZfsError::Io(Error::from_raw_os_error(x))),
+        }
+    }
+    fn prop_item(&mut self, x: &ZpropItem) -> Option<PoolProp> {
+        let _l = self.libzfs.lock();
+
+        let mut buf = vec![0u8; sys::ZPOOL_MAXPROPLEN as usize];
+        let mut src = sys::zprop_source_t_ZPROP_SRC_NONE;
+
+        let (name, code) = unsafe {
+            if x.raw_prop() == sys::ZPROP_INVAL {
+                let name = x.user_prop();
+
+                let code = sys::zpool_prop_get_feature(
+                    self.raw,
+                    name.as_ptr(),
+                    buf.as_mut_ptr() as *mut c_char,
+                    buf.len(),
+                );
+
+                // libzfs gives no source for features; one that is still
+                // disabled has never been set, the others were enabled here
+                if code == 0 {
+                    src = match CStr::from_ptr(buf.as_ptr() as *const c_char).to_bytes() {
+                        b"disabled" => sys::zprop_source_t_ZPROP_SRC_DEFAULT,
+                        _ => sys::zprop_source_t_ZPROP_SRC_LOCAL,
+                    };
+                }
+
+                (name, code)
+            } else {
+                let prop = x.raw_prop() as sys::zpool_prop_t::Type;
+
+                let code = sys::zpool_get_prop(
+                    self.raw,
+                    prop,
+                    buf.as_mut_ptr() as *mut c_char,
+                    buf.len(),
+                    &mut src,
+                    sys::boolean::B_TRUE,
+                );
+
+                (CStr::from_ptr(sys::zpool_prop_to_name(prop)), code)
+            }
+        };
+
+        if code != 0 {
+            return None;
+        }
+
+        let source = match src {
+            sys::zprop_source_t_ZPROP_SRC_DEFAULT => PropSource::Default,
+            sys::zprop_source_t_ZPROP_SRC_TEMPORARY => PropSource::Temporary,
+            sys::zprop_source_t_ZPROP_SRC_LOCAL => PropSource::Local,
+            _ => PropSource::None,
+        };
+
+        Some(PoolProp {
+            name: name.to_string_lossy().into_owned(),
+            value: unsafe { CStr::from_ptr(buf.as_ptr() as *const c_char) }
+                .to_string_lossy()
+                .into_owned(),
+            source,
+        })
+    }
+    /// Captures the pool, its vdev tree, properties and datasets
+    /// as plain data that does not hold the pool open.
+    pub fn to_info(&mut self) -> Result<ZpoolInfo> {
+        Ok(ZpoolInfo {
+            props: state::convert_to_zprops(self)?,
+            ..state::convert_to_js_pool(self)?
+        })
+    }
+    /// Lists every property the running libzfs knows about, including
+    /// `feature@` properties and any added after these bindings were generated.
+    ///
+    /// Reading features refreshes the config of a pool that predates
+    /// feature flags, so this takes `&mut self` as `refresh_stats` does.
+    pub fn props(&mut self) -> Result<Vec<PoolProp>> {
+        Ok(self
+            .prop_list()?
+            .filter_map(|x| self.prop_item(&x))
+            .collect())
+    }
+    /// Every feature this libzfs knows of, with its state on this pool.
+    ///
+    /// Pools that predate feature flags report every feature as disabled.
+    pub fn features(&mut self) -> Result<Vec<Feature>> {
+        let _l = self.libzfs.lock();
+
+        let stats = unsafe { sys::zpool_get_features(self.raw) };
+
+        let refcounts: BTreeMap<String, u64> = if stats.is_null() {
+            BTreeMap::new()
+        } else {
+            let stats = unsafe { nvpair::NvListRef::from_ptr(stats) };
+
+            stats
+                .iter()
+                .filter_map(|x| {
+                    stats
+                        .lookup_uint64(x.name())
+                        .ok()
+                        .map(|n| (x.name().to_string_lossy().into_owned(), n))
+                })
+                .collect()
+        };
+
+        let mut xs = vec![];
+
+        for x in self.props()? {
+            let name = match x.name.splitn(2, '@').collect::<Vec<_>>()[..] {
+                ["feature", name] => name.to_string(),
+                _ => continue,
+            };
+
+            let state = FeatureState::from_name(&x.value).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("unknown state {} for feature {}", x.value, name),
+                )
+            })?;
+
+            let guid = refcounts
+                .keys()
+                .find(|k| k.rsplit(':').next() == Some(name.as_str()))
+                .cloned();
+
+            xs.push(Feature {
+                refcount: guid.as_ref().map(|k| refcounts[k]),
+                name,
+                guid,
+                state,
+            });
+        }
+
+        Ok(xs)
+    }
+    /// Reads every native property into `PoolProps`.
+    pub fn all_props(&mut self) -> Result<PoolProps> {
+        let _l = self.libzfs.lock();
+
+        let mut map: HashMap<String, String> = self
+            .props()?
+            .into_iter()
+            .map(|x| (x.name, x.value))
+            .collect();
+
+        // Hidden, so zpool_expand_proplist leaves them out
+        for (name, prop) in &[
+            ("maxblocksize", sys::zpool_prop_t::ZPOOL_PROP_MAXBLOCKSIZE),
+            ("maxdnodesize", sys::zpool_prop_t::ZPOOL_PROP_MAXDNODESIZE),
+        ] {
+            map.entry(name.to_string())
+                .or_insert_with(|| self.prop_int(*prop).to_string());
+        }
+
+        pool_props(&map)
+    }
+    /// Sets a pool property, such as `autoreplace` or `failmode`,
+    /// or enables a feature with `feature@<name>` set to `enabled`.
+    pub fn set_prop(&self, name: &str, value: &str) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let c_name = CString::new(name).map_err(Error::from)?;
+        let c_value = CString::new(value).map_err(Error::from)?;
+
+        let code = unsafe { sys::zpool_set_prop(self.raw, c_name.as_ptr(), c_value.as_ptr()) };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(LibZfsError::PropSet(
+                vec![name.to_string()],
+                self.zfs_error(),
+            )),
+        }
+    }
     pub fn health(&self) -> Result<CString> {
+        let _l = self.libzfs.lock();
+
         self.prop_str(sys::zpool_prop_t::ZPOOL_PROP_HEALTH)
     }
+    /// As `health`, as a `String`.
+    pub fn health_str(&self) -> Result<String> {
+        Ok(self.health()?.into_string()?)
+    }
     pub fn hostname(&self) -> Result<CString> {
+        let _l = self.libzfs.lock();
+
         let config = self.get_config();
 
         let s = config.lookup_string(sys::zpool_config_hostname())?;
 
         Ok(s)
     }
+    /// As `hostname`, as a `String`.
+    pub fn hostname_str(&self) -> Result<String> {
+        Ok(self.hostname()?.into_string()?)
+    }
     pub fn hostid(&self) -> Result<u64> {
+        let _l = self.libzfs.lock();
+
         let s = self
             .get_config()
             .lookup_uint64(sys::zpool_config_hostid())?;
@@ -86,18 +376,35 @@ impl Zpool {
         Ok(s)
     }
     pub fn guid(&self) -> u64 {
+        let _l = self.libzfs.lock();
+
         self.prop_int(sys::zpool_prop_t::ZPOOL_PROP_GUID)
     }
     pub fn guid_hex(&self) -> String {
         format!("{:#018X}", self.guid())
     }
     pub fn size(&self) -> u64 {
+        let _l = self.libzfs.lock();
+
         self.prop_int(sys::zpool_prop_t::ZPOOL_PROP_SIZE)
     }
     pub fn read_only(&self) -> bool {
+        let _l = self.libzfs.lock();
+
         self.prop_int(sys::zpool_prop_t::ZPOOL_PROP_READONLY) != 0
     }
+    /// The on-disk version, which is `SPA_VERSION_FEATURES`
+    /// once the pool uses feature flags.
+    pub fn version(&self) -> u64 {
+        let _l = self.libzfs.lock();
+
+        self.prop_int(sys::zpool_prop_t::ZPOOL_PROP_VERSION)
+    }
+    /// The pool's config as libzfs holds it, which methods
+    /// taking `&mut self` may free and replace.
     pub fn get_config(&self) -> &nvpair::NvListRef {
+        let _l = self.libzfs.lock();
+
         unsafe {
             let x = sys::zpool_get_config(self.raw, ptr::null_mut());
             assert!(!x.is_null(), "config pointer is null");
@@ -105,13 +412,34 @@ impl Zpool {
         }
     }
     pub fn vdev_tree(&self) -> Result<VDev> {
+        let _l = self.libzfs.lock();
+
+        let config = self.get_config();
+
+        let tree = config.lookup_nv_list_ref(sys::zpool_config_vdev_tree())?;
+
+        enumerate_vdev_tree(tree)
+    }
+    /// Every vdev in the pool keyed by guid, for resolving
+    /// guids found in events without walking the tree each time.
+    pub fn vdev_map(&self) -> Result<HashMap<u64, VDevInfo>> {
+        let _l = self.libzfs.lock();
+
         let config = self.get_config();
 
-        let tree = config.lookup_nv_list(sys::zpool_config_vdev_tree())?;
+        let tree = config.lookup_nv_list_ref(sys::zpool_config_vdev_tree())?;
 
-        enumerate_vdev_tree(&tree)
+        vdev_map(tree)
+    }
+    /// Wraps dataset handles opened on this pool's libzfs handle.
+    fn own_datasets(&self, xs: Vec<*mut sys::zfs_handle_t>) -> Vec<Zfs> {
+        xs.into_iter()
+            .map(|x| Zfs::new(self.libzfs.clone(), x))
+            .collect()
     }
     pub fn datasets(&self) -> Result<Vec<Zfs>> {
+        let _l = self.libzfs.lock();
+
         let sys::zfs_type_t(zfs_type) = sys::zfs_type_dataset();
 
         let x = unsafe {
@@ -123,137 +451,1418 @@ impl Zpool {
             x
         };
 
-        let _ds = Zfs::new(x);
+        let _ds = Zfs::new(self.libzfs.clone(), x);
 
         unsafe extern "C" fn callback(handle: *mut sys::zfs_handle_t, state: *mut c_void) -> c_int {
-            let state = &mut *(state as *mut Vec<Zfs>);
+            let state = &mut *(state as *mut Vec<*mut sys::zfs_handle_t>);
 
-            state.push(Zfs::new(handle));
+            state.push(handle);
 
             0
         }
 
-        let mut state: Vec<Zfs> = Vec::new();
+        let mut state: Vec<*mut sys::zfs_handle_t> = Vec::new();
         let state_ptr: *mut c_void = &mut state as *mut _ as *mut c_void;
         let code = unsafe { sys::zfs_iter_filesystems(x, Some(callback), state_ptr) };
 
+        let xs = self.own_datasets(state);
+
         match code {
-            0 => Ok(state),
+            0 => Ok(xs),
             x => Err(LibZfsError::Io(Error::from_raw_os_error(x))),
         }
     }
-    pub fn disable_datasets(&self) -> Result<()> {
-        let code = unsafe { sys::zpool_disable_datasets(self.raw, sys::boolean::B_FALSE) };
+    /// Lists every volume in the pool, at any depth.
+    pub fn volumes(&self) -> Result<Vec<Zfs>> {
+        let _l = self.libzfs.lock();
+
+        Ok(self
+            .all_datasets()?
+            .into_iter()
+            .filter(|x| x.zfs_type() == sys::zfs_type_t::ZFS_TYPE_VOLUME)
+            .collect())
+    }
+    /// Iterates over every filesystem and volume in the pool, at any depth,
+    /// opening each one only as it is reached.
+    pub fn dataset_iter(&self) -> Result<DatasetIter> {
+        DatasetIter::new(&self.root_dataset()?)
+    }
+    /// Walks the pool's datasets like `zfs list -r -d N -t TYPES POOL`,
+    /// starting with the root filesystem.
+    ///
+    /// `max_depth` counts levels below the root, so `Some(0)` yields at most
+    /// the root itself. `types` may combine `ZFS_TYPE_FILESYSTEM`,
+    /// `ZFS_TYPE_VOLUME` and `ZFS_TYPE_SNAPSHOT`.
+    pub fn traverse(
+        &self,
+        max_depth: Option<usize>,
+        types: sys::zfs_type_t,
+    ) -> Result<DatasetIter> {
+        self.root_dataset()?.traverse(max_depth, types)
+    }
+    /// Opens the pool's root filesystem.
+    fn root_dataset(&self) -> Result<Zfs> {
+        let _l = self.libzfs.lock();
+
+        let name = self.name();
+        let h = unsafe {
+            sys::zfs_open(
+                sys::zpool_get_handle(self.raw),
+                name.as_ptr(),
+                sys::zfs_type_t::ZFS_TYPE_FILESYSTEM.0 as c_int,
+            )
+        };
+
+        if h.is_null() {
+            return Err(LibZfsError::ZfsNotFound(name.into_string()?));
+        }
+
+        Ok(Zfs::new(self.libzfs.clone(), h))
+    }
+    /// Lists every filesystem and volume in the pool, at any depth.
+    ///
+    /// Holds a handle for each one; see `dataset_iter` for large pools.
+    pub fn all_datasets(&self) -> Result<Vec<Zfs>> {
+        let _l = self.libzfs.lock();
+
+        let root = self.root_dataset()?;
+
+        unsafe extern "C" fn callback(handle: *mut sys::zfs_handle_t, state: *mut c_void) -> c_int {
+            let code = sys::zfs_iter_filesystems(handle, Some(callback), state);
+
+            let state = &mut *(state as *mut Vec<*mut sys::zfs_handle_t>);
+            state.push(handle);
+
+            code
+        }
+
+        let mut state: Vec<*mut sys::zfs_handle_t> = Vec::new();
+        let state_ptr: *mut c_void = &mut state as *mut _ as *mut c_void;
+        let code = unsafe { sys::zfs_iter_filesystems(root.raw, Some(callback), state_ptr) };
+
+        let xs = self.own_datasets(state);
 
         match code {
+            0 => Ok(xs),
+            x => Err(LibZfsError::Io(Error::from_raw_os_error(x))),
+        }
+    }
+    /// Mounts and shares every dataset in the pool.
+    pub fn enable_datasets(&self) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        match unsafe { sys::zpool_enable_datasets(self.raw, ptr::null(), 0) } {
             0 => Ok(()),
-            e => Err(LibZfsError::Io(Error::from_raw_os_error(e))),
+            _ => Err(self.last_error()),
         }
     }
-    pub fn export(&self) -> Result<()> {
-        let code = unsafe { sys::zpool_export(self.raw, sys::boolean::B_FALSE, ptr::null_mut()) };
+    pub fn disable_datasets(&self) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let code = unsafe { sys::zpool_disable_datasets(self.raw, sys::boolean::B_FALSE) };
 
         match code {
             0 => Ok(()),
             e => Err(LibZfsError::Io(Error::from_raw_os_error(e))),
         }
     }
-}
+    fn zfs_error(&self) -> ZfsError {
+        let _l = self.libzfs.lock();
 
-impl Drop for Zpool {
-    fn drop(&mut self) {
-        unsafe { sys::zpool_close(self.raw) }
+        libzfs::pool_error(unsafe { sys::zpool_get_handle(self.raw) })
     }
-}
+    /// The error for the last failed call, as `ZfsBusy`
+    /// if it failed because something was in use.
+    fn last_error(&self) -> LibZfsError {
+        let _l = self.libzfs.lock();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use libzfs::Libzfs;
+        let errno = unsafe { sys::libzfs_errno(sys::zpool_get_handle(self.raw)) } as u32;
 
-    use std::{ffi::CString, panic, path::PathBuf, str};
+        match errno {
+            sys::zfs_error_EZFS_BUSY | sys::zfs_error_EZFS_UMOUNTFAILED => {
+                LibZfsError::ZfsBusy(self.name().to_string_lossy().into_owned())
+            }
+            _ => LibZfsError::Zfs(self.zfs_error()),
+        }
+    }
+    /// Adds the vdevs in `spec` to the pool, as with `zpool add`.
+    ///
+    /// Data vdevs grow the pool; logs, cache devices and spares are added alongside.
+    pub fn add_vdevs(&self, spec: &VDevSpec) -> Result<()> {
+        let _l = self.libzfs.lock();
 
-    fn test_pools<F: Fn(&Vec<Zpool>) -> ()>(f: F) -> ()
-    where
-        F: panic::RefUnwindSafe,
-    {
-        let mut z = Libzfs::new();
+        let mut root = spec.to_nvlist()?;
 
-        let pools_to_import = z.find_importable_pools();
+        match unsafe { sys::zpool_add(self.raw, root.as_mut_ptr() as *mut _) } {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
+    }
+    /// Starts removing a top-level vdev, cache device or spare,
+    /// given as a path or a guid in decimal.
+    ///
+    /// Data is evacuated in the background; see `removal_status`.
+    pub fn remove_vdev(&self, path_or_guid: &str) -> Result<()> {
+        let _l = self.libzfs.lock();
 
-        z.import_all(&pools_to_import)
-            .expect("could not import pools");
+        let c_path = CString::new(path_or_guid).map_err(Error::from)?;
 
-        let pools = z
-            .get_imported_pools()
-            .expect("could not fetch imported pools");
+        match unsafe { sys::zpool_vdev_remove(self.raw, c_path.as_ptr()) } {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
+    }
+    /// Stops an in-progress device removal.
+    pub fn cancel_removal(&self) -> Result<()> {
+        let _l = self.libzfs.lock();
 
-        let result = panic::catch_unwind(|| {
-            f(&pools);
-        });
+        match unsafe { sys::zpool_vdev_remove_cancel(self.raw) } {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
+    }
+    /// The progress of the current or last device removal,
+    /// or `None` if no device was ever removed.
+    pub fn removal_status(&self) -> Result<Option<RemovalStatus>> {
+        let _l = self.libzfs.lock();
 
-        result.unwrap();
+        let tree = self
+            .get_config()
+            .lookup_nv_list_ref(sys::zpool_config_vdev_tree())?;
+
+        let xs = match tree.lookup_uint64_array(sys::zpool_config_removal_stats()) {
+            Ok(xs) => xs,
+            Err(_) => return Ok(None),
+        };
+
+        if xs.len() < 7 {
+            return Err(LibZfsError::Io(Error::new(
+                ErrorKind::InvalidData,
+                "removal stats are truncated",
+            )));
+        }
+
+        Ok(Some(RemovalStatus {
+            state: ScanState::from_raw(xs[0]).ok_or_else(|| {
+                Error::new(ErrorKind::InvalidData, "removal state not in enum range")
+            })?,
+            vdev: xs[1],
+            start_time: xs[2],
+            end_time: xs[3],
+            to_copy: xs[4],
+            copied: xs[5],
+            mapping_memory: xs[6],
+        }))
     }
+    /// Checkpoints the pool, so it can later be rewound
+    /// to this point on import.
+    pub fn checkpoint(&self) -> Result<()> {
+        let _l = self.libzfs.lock();
 
-    fn pool_by_name<F: Fn(&Zpool) -> ()>(name: &str, f: F) -> ()
-    where
-        F: panic::RefUnwindSafe,
-    {
-        test_pools(|xs| {
-            let x = xs
-                .iter()
-                .find(|x| x.name() == CString::new(name).unwrap())
-                .expect("did not find test pool");
+        match unsafe { sys::zpool_checkpoint(self.raw) } {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
+    }
+    /// Discards the pool's checkpoint, freeing the space it holds.
+    pub fn discard_checkpoint(&self) -> Result<()> {
+        let _l = self.libzfs.lock();
 
-            f(x);
-        });
+        match unsafe { sys::zpool_discard_checkpoint(self.raw) } {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
     }
+    /// The pool's checkpoint, or `None` if it has none.
+    pub fn checkpoint_status(&self) -> Result<Option<CheckpointStatus>> {
+        let _l = self.libzfs.lock();
 
-    #[test]
-    fn import_get_pool_len() {
-        test_pools(|xs| assert_eq!(xs.len(), 1));
+        let tree = self
+            .get_config()
+            .lookup_nv_list_ref(sys::zpool_config_vdev_tree())?;
+
+        let xs = match tree.lookup_uint64_array(sys::zpool_config_checkpoint_stats()) {
+            Ok(xs) => xs,
+            Err(_) => return Ok(None),
+        };
+
+        if xs.len() < 3 {
+            return Err(LibZfsError::Io(Error::new(
+                ErrorKind::InvalidData,
+                "checkpoint stats are truncated",
+            )));
+        }
+
+        let state = CheckpointState::from_raw(xs[0]).ok_or_else(|| {
+            Error::new(ErrorKind::InvalidData, "checkpoint state not in enum range")
+        })?;
+
+        if state == CheckpointState::None {
+            return Ok(None);
+        }
+
+        Ok(Some(CheckpointStatus {
+            state,
+            start_time: xs[1],
+            space: xs[2],
+        }))
     }
+    fn attach_vdev(&self, existing: &Path, new_device: &Path, replacing: bool) -> Result<()> {
+        let _l = self.libzfs.lock();
 
-    #[test]
-    fn get_pool_health() {
-        pool_by_name("test", |p| {
-            assert_eq!(
-                p.health().expect("could not fetch pool health"),
-                CString::new("ONLINE").unwrap()
+        let c_existing = CString::new(existing.as_os_str().as_bytes()).map_err(Error::from)?;
+        let c_new = CString::new(new_device.as_os_str().as_bytes()).map_err(Error::from)?;
+        let mut root = VDevSpec::new().device(new_device).to_nvlist()?;
+
+        let code = unsafe {
+            sys::zpool_vdev_attach(
+                self.raw,
+                c_existing.as_ptr(),
+                c_new.as_ptr(),
+                root.as_mut_ptr() as *mut _,
+                replacing as c_int,
             )
-        })
-    }
+        };
 
-    #[test]
-    fn get_pool_state() {
-        pool_by_name("test", |p| {
-            assert_eq!(p.state_name(), CString::new("ACTIVE").unwrap())
-        })
+        match code {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
     }
+    /// Attaches `new_device` as a mirror of `existing`, turning a single
+    /// device into a mirror or widening an existing one.
+    ///
+    /// The new device is resilvered; sequential rebuild needs ZFS 2.0.
+    pub fn attach<P: AsRef<Path>, Q: AsRef<Path>>(&self, existing: P, new_device: Q) -> Result<()> {
+        self.attach_vdev(existing.as_ref(), new_device.as_ref(), false)
+    }
+    /// Replaces `old_device` with `new_device`, resilvering onto it.
+    ///
+    /// A missing `old_device` fails with `ZfsErrorKind::DeviceNotFound`, and an
+    /// unsuitable `new_device` (too small, in use) with `ZfsErrorKind::InvalidDevice`.
+    pub fn replace<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        old_device: P,
+        new_device: Q,
+    ) -> Result<()> {
+        self.attach_vdev(old_device.as_ref(), new_device.as_ref(), true)
+    }
+    /// Detaches `device` from its mirror.
+    pub fn detach<P: AsRef<Path>>(&self, device: P) -> Result<()> {
+        let _l = self.libzfs.lock();
 
-    #[test]
-    fn get_pool_size() {
-        pool_by_name("test", |p| assert_eq!(p.size(), 83_886_080))
+        let c_device = CString::new(device.as_ref().as_os_str().as_bytes()).map_err(Error::from)?;
+
+        match unsafe { sys::zpool_vdev_detach(self.raw, c_device.as_ptr()) } {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
     }
+    /// Starts, pauses or stops a scrub.
+    ///
+    /// Starting a paused scrub resumes it.
+    pub fn scrub(&self, action: ScrubAction) -> Result<()> {
+        let _l = self.libzfs.lock();
 
-    #[test]
-    fn get_pool_read_only() {
-        pool_by_name("test", |p| assert_eq!(p.read_only(), false))
+        let (func, cmd) = match action {
+            ScrubAction::Start => (
+                sys::pool_scan_func_POOL_SCAN_SCRUB,
+                sys::pool_scrub_cmd_POOL_SCRUB_NORMAL,
+            ),
+            ScrubAction::Pause => (
+                sys::pool_scan_func_POOL_SCAN_SCRUB,
+                sys::pool_scrub_cmd_POOL_SCRUB_PAUSE,
+            ),
+            ScrubAction::Stop => (
+                sys::pool_scan_func_POOL_SCAN_NONE,
+                sys::pool_scrub_cmd_POOL_SCRUB_NORMAL,
+            ),
+        };
+
+        match unsafe { sys::zpool_scan(self.raw, func, cmd) } {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
     }
+    /// Restarts resilvering, picking up any deferred resilvers,
+    /// as with `zpool resilver`.
+    ///
+    /// Resilvers are only deferred on pools with the `resilver_defer`
+    /// feature: a device that needs resilvering while another resilver
+    /// is running waits for it to finish, rather than restarting it.
+    pub fn resilver(&self) -> Result<()> {
+        let _l = self.libzfs.lock();
 
-    #[test]
-    fn get_pool_hostname() {
-        pool_by_name("test", |p| {
-            assert_eq!(
-                p.hostname().expect("could not get hostname"),
-                CString::new("localhost.localdomain").unwrap()
+        let code = unsafe {
+            sys::zpool_scan(
+                self.raw,
+                sys::pool_scan_func_POOL_SCAN_RESILVER,
+                sys::pool_scrub_cmd_POOL_SCRUB_NORMAL,
             )
-        })
+        };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
     }
+    /// Guids of the leaf vdevs waiting for a deferred resilver.
+    pub fn deferred_resilvers(&self) -> Result<Vec<u64>> {
+        let _l = self.libzfs.lock();
 
-    #[test]
-    fn get_pool_hostid() {
-        pool_by_name("test", |p| assert!(p.hostid().is_ok()))
+        let tree = self
+            .get_config()
+            .lookup_nv_list_ref(sys::zpool_config_vdev_tree())?;
+
+        deferred_resilvers(tree)
+    }
+    /// The progress of the current or last scrub or resilver,
+    /// or `None` if the pool was never scanned.
+    pub fn scan_status(&self) -> Result<Option<ScanStatus>> {
+        let _l = self.libzfs.lock();
+
+        let tree = self
+            .get_config()
+            .lookup_nv_list_ref(sys::zpool_config_vdev_tree())?;
+
+        let xs = match tree.lookup_uint64_array(sys::zpool_config_scan_stats()) {
+            Ok(xs) => xs,
+            Err(_) => return Ok(None),
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        scan_status(&xs, now).map(Some)
+    }
+    /// Builds the nvlist of devices that `zpool_trim`
+    /// and `zpool_initialize` act on.
+    ///
+    /// With no `devices`, every data leaf vdev in the pool is used.
+    fn leaf_list(&self, devices: &[PathBuf]) -> Result<nvpair::NvList> {
+        let _l = self.libzfs.lock();
+
+        let mut nvl = nvpair::NvList::new()?;
+
+        let paths = if devices.is_empty() {
+            let config = self.get_config();
+
+            data_leaf_paths(config.lookup_nv_list_ref(sys::zpool_config_vdev_tree())?)?
+        } else {
+            devices.to_vec()
+        };
+
+        for x in paths {
+            nvl.add_boolean(
+                CString::new(x.as_os_str().as_bytes())
+                    .map_err(Error::from)?
+                    .as_c_str(),
+            )?;
+        }
+
+        Ok(nvl)
+    }
+    /// Starts, suspends or cancels a TRIM of `devices`, or of the whole pool.
+    ///
+    /// `rate` limits the TRIM to that many bytes per second per device,
+    /// with 0 for no limit. `secure` asks for a secure TRIM.
+    pub fn trim(
+        &self,
+        devices: &[PathBuf],
+        rate: u64,
+        secure: bool,
+        action: TrimAction,
+    ) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let mut vds = self.leaf_list(devices)?;
+
+        let mut flags = sys::trimflags_t {
+            fullpool: if devices.is_empty() {
+                sys::boolean::B_TRUE
+            } else {
+                sys::boolean::B_FALSE
+            },
+            secure: if secure {
+                sys::boolean::B_TRUE
+            } else {
+                sys::boolean::B_FALSE
+            },
+            rate,
+        };
+
+        let func = match action {
+            TrimAction::Start => sys::pool_trim_func_POOL_TRIM_START,
+            TrimAction::Suspend => sys::pool_trim_func_POOL_TRIM_SUSPEND,
+            TrimAction::Cancel => sys::pool_trim_func_POOL_TRIM_CANCEL,
+        };
+
+        let code =
+            unsafe { sys::zpool_trim(self.raw, func, vds.as_mut_ptr() as *mut _, &mut flags) };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
+    }
+    /// TRIM progress of each leaf vdev, keyed by guid.
+    pub fn trim_status(&self) -> Result<HashMap<u64, VDevProgress>> {
+        let _l = self.libzfs.lock();
+
+        let tree = self
+            .get_config()
+            .lookup_nv_list_ref(sys::zpool_config_vdev_tree())?;
+
+        leaf_progress(tree, VS_TRIM_OFFSET)
+    }
+    /// Starts, suspends or cancels initializing `devices`, or the whole pool,
+    /// which writes a pattern over their unallocated space.
+    pub fn initialize(&self, devices: &[PathBuf], action: InitializeAction) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let mut vds = self.leaf_list(devices)?;
+
+        let func = match action {
+            InitializeAction::Start => sys::pool_initialize_func_POOL_INITIALIZE_DO,
+            InitializeAction::Suspend => sys::pool_initialize_func_POOL_INITIALIZE_SUSPEND,
+            InitializeAction::Cancel => sys::pool_initialize_func_POOL_INITIALIZE_CANCEL,
+        };
+
+        let code = unsafe { sys::zpool_initialize(self.raw, func, vds.as_mut_ptr() as *mut _) };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
+    }
+    /// Initialize progress of each leaf vdev, keyed by guid.
+    pub fn initialize_status(&self) -> Result<HashMap<u64, VDevProgress>> {
+        let _l = self.libzfs.lock();
+
+        let tree = self
+            .get_config()
+            .lookup_nv_list_ref(sys::zpool_config_vdev_tree())?;
+
+        leaf_progress(tree, VS_INITIALIZE_OFFSET)
+    }
+    /// Whether `activity` is still in progress, from the last read stats.
+    fn in_progress(&self, activity: WaitActivity) -> Result<bool> {
+        let scanning = |f| -> Result<bool> {
+            Ok(self
+                .scan_status()?
+                .map(|x| x.function == f && x.state == ScanState::Scanning && !x.paused)
+                .unwrap_or(false))
+        };
+
+        let active =
+            |xs: HashMap<u64, VDevProgress>| xs.values().any(|x| x.state == VDevOpState::Active);
+
+        Ok(match activity {
+            WaitActivity::Scrub => scanning(ScanFunction::Scrub)?,
+            WaitActivity::Resilver => {
+                scanning(ScanFunction::Resilver)? || !self.deferred_resilvers()?.is_empty()
+            }
+            WaitActivity::Trim => active(self.trim_status()?),
+            WaitActivity::Initialize => active(self.initialize_status()?),
+            WaitActivity::Remove => self
+                .removal_status()?
+                .map(|x| x.state == ScanState::Scanning)
+                .unwrap_or(false),
+            WaitActivity::CheckpointDiscard => self
+                .checkpoint_status()?
+                .map(|x| x.state == CheckpointState::Discarding)
+                .unwrap_or(false),
+        })
+    }
+    /// Blocks until `activity` is no longer in progress, as with `zpool wait`.
+    ///
+    /// Returns immediately if nothing is running. Paused scrubs are not
+    /// waited for. Errors with `ErrorKind::TimedOut` if `timeout` passes first.
+    pub fn wait(&mut self, activity: WaitActivity, timeout: Option<Duration>) -> Result<()> {
+        let start = Instant::now();
+
+        loop {
+            self.refresh_stats()?;
+
+            if !self.in_progress(activity)? {
+                return Ok(());
+            }
+
+            if timeout.map(|t| start.elapsed() >= t).unwrap_or(false) {
+                return Err(LibZfsError::Io(Error::new(
+                    ErrorKind::TimedOut,
+                    format!(
+                        "{:?} on {} did not finish",
+                        activity,
+                        self.name().to_string_lossy()
+                    ),
+                )));
+            }
+
+            thread::sleep(WAIT_INTERVAL);
+        }
+    }
+    /// Rereads the pool's config and stats from the kernel.
+    ///
+    /// libzfs frees the old config, so this takes `&mut self` to
+    /// rule out any borrow from `get_config` still being held.
+    fn refresh_stats(&mut self) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let mut missing = sys::boolean::B_FALSE;
+
+        match unsafe { sys::zpool_refresh_stats(self.raw, &mut missing) } {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
+    }
+    /// Upgrades the pool, as with `zpool upgrade`.
+    ///
+    /// Upgrading to `UpgradeTarget::Features` moves a legacy pool
+    /// to feature flags and enables every feature this libzfs supports.
+    pub fn upgrade(&mut self, target: UpgradeTarget) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let version = match target {
+            UpgradeTarget::Version(x) => x,
+            UpgradeTarget::Features => u64::from(sys::SPA_VERSION_FEATURES),
+        };
+
+        let current = self.version();
+
+        if version < current || version > u64::from(sys::SPA_VERSION_FEATURES) {
+            return Err(LibZfsError::Io(Error::new(
+                ErrorKind::InvalidInput,
+                format!("cannot upgrade from version {} to {}", current, version),
+            )));
+        }
+
+        if version > current {
+            if unsafe { sys::zpool_upgrade(self.raw, version) } != 0 {
+                return Err(self.last_error());
+            }
+
+            self.refresh_stats()?;
+        }
+
+        if target == UpgradeTarget::Features {
+            for x in self.props()? {
+                if x.name.starts_with("feature@") && x.value == "disabled" {
+                    self.set_prop(&x.name, "enabled")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+    /// Gives the pool a new, random guid, so a copy of it
+    /// can be imported alongside the original.
+    pub fn reguid(&mut self) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        if unsafe { sys::zpool_reguid(self.raw) } != 0 {
+            return Err(self.last_error());
+        }
+
+        self.refresh_stats()
+    }
+    /// Reopens every device in the pool, picking up devices
+    /// that grew or came back, as with `zpool reopen`.
+    ///
+    /// An in-progress scrub restarts, as it does from the command line.
+    pub fn reopen(&mut self) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let mut scrub_restart = sys::boolean::B_TRUE;
+
+        let code =
+            unsafe { sys::zpool_reopen_one(self.raw, &mut scrub_restart as *mut _ as *mut c_void) };
+
+        if code != 0 {
+            return Err(self.last_error());
+        }
+
+        self.refresh_stats()
+    }
+    /// The pool's history, as with `zpool history -il`.
+    pub fn history(&self) -> Result<HistoryIter> {
+        let _l = self.libzfs.lock();
+
+        let mut nvl = ptr::null_mut();
+
+        if unsafe { sys::zpool_get_history(self.raw, &mut nvl) } != 0 {
+            return Err(self.last_error());
+        }
+
+        let nvl = unsafe { nvpair::NvList::from_ptr(nvl) };
+
+        Ok(HistoryIter::new(&nvl))
+    }
+    /// Lists the objects with persistent data errors, as with `zpool status -v`.
+    pub fn error_log(&self) -> Result<Vec<PersistentError>> {
+        let _l = self.libzfs.lock();
+
+        let mut nvl = ptr::null_mut();
+
+        if unsafe { sys::zpool_get_errlog(self.raw, &mut nvl) } != 0 {
+            return Err(self.last_error());
+        }
+
+        if nvl.is_null() {
+            return Ok(vec![]);
+        }
+
+        let nvl = unsafe { nvpair::NvList::from_ptr(nvl) };
+        let mut xs = vec![];
+
+        for x in nvl.iter() {
+            let entry = x.value_nv_list_ref()?;
+            let dataset = entry.lookup_uint64(sys::zpool_err_dataset())?;
+            let object = entry.lookup_uint64(sys::zpool_err_object())?;
+
+            let mut buf = vec![0u8; libc::PATH_MAX as usize * 2];
+
+            unsafe {
+                sys::zpool_obj_to_path(
+                    self.raw,
+                    dataset,
+                    object,
+                    buf.as_mut_ptr() as *mut c_char,
+                    buf.len(),
+                )
+            };
+
+            xs.push(PersistentError {
+                dataset,
+                object,
+                path: unsafe { CStr::from_ptr(buf.as_ptr() as *const c_char) }
+                    .to_string_lossy()
+                    .into_owned(),
+            });
+        }
+
+        xs.sort();
+
+        Ok(xs)
+    }
+    /// Gathers everything `zpool status` shows.
+    pub fn status(&self) -> Result<ZpoolStatus> {
+        let _l = self.libzfs.lock();
+
+        let mut msgid: *mut c_char = ptr::null_mut();
+        let mut errata = sys::zpool_errata_ZPOOL_ERRATA_NONE;
+
+        let raw = unsafe { sys::zpool_get_status(self.raw, &mut msgid, &mut errata) };
+
+        let reason = status_reason(raw);
+
+        let msgid = if msgid.is_null() {
+            None
+        } else {
+            Some(
+                unsafe { CStr::from_ptr(msgid) }
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        };
+
+        Ok(ZpoolStatus {
+            name: self.name().into_string()?,
+            guid: self.guid(),
+            state: self.state_name().into_string()?,
+            health: self.health()?.into_string()?,
+            reason,
+            msgid,
+            errata: if errata == sys::zpool_errata_ZPOOL_ERRATA_NONE {
+                None
+            } else {
+                Some(errata)
+            },
+            vdev: self.vdev_tree()?,
+            scan: self.scan_status()?,
+            removal: self.removal_status()?,
+            checkpoint: self.checkpoint_status()?,
+            error_count: self
+                .get_config()
+                .lookup_uint64(sys::zpool_config_errcount())
+                .unwrap_or(0),
+            errors: self.error_log()?,
+        })
+    }
+    /// Unmounts every dataset and destroys the pool.
+    ///
+    /// With `force`, datasets are unmounted even if in use.
+    /// Fails with `LibZfsError::ZfsBusy` if a dataset could not be unmounted.
+    pub fn destroy(&self, force: bool) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let force = if force {
+            sys::boolean::B_TRUE
+        } else {
+            sys::boolean::B_FALSE
+        };
+
+        if unsafe { sys::zpool_disable_datasets(self.raw, force) } != 0 {
+            return Err(self.last_error());
+        }
+
+        match unsafe { sys::zpool_destroy(self.raw, ptr::null()) } {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
+    }
+    pub fn export(&self) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        let code = unsafe { sys::zpool_export(self.raw, sys::boolean::B_FALSE, ptr::null_mut()) };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
+    }
+    /// Exports the pool even if its datasets are busy,
+    /// as with `zpool export -f`.
+    ///
+    /// Datasets are force-unmounted first. With `hard`, the labels on disk
+    /// are left as they are, so the pool still looks in use by this host
+    /// and another host must import it with `force`.
+    pub fn export_force(&self, hard: bool) -> Result<()> {
+        let _l = self.libzfs.lock();
+
+        if unsafe { sys::zpool_disable_datasets(self.raw, sys::boolean::B_TRUE) } != 0 {
+            return Err(self.last_error());
+        }
+
+        let code = if hard {
+            unsafe { sys::zpool_export_force(self.raw, ptr::null()) }
+        } else {
+            unsafe { sys::zpool_export(self.raw, sys::boolean::B_TRUE, ptr::null()) }
+        };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(self.last_error()),
+        }
+    }
+}
+
+/// Reads a `pool_scan_stat_t`, working out the rate and ETA
+/// of the current pass the way `zpool status` does.
+///
+/// ZFS 0.8 scans blocks ahead of issuing their I/O, so the rate and
+/// ETA follow the bytes issued. Older stats lack those, and examined
+/// bytes are used instead.
+fn scan_status(xs: &[u64], now: u64) -> Result<ScanStatus> {
+    if xs.len() < 13 {
+        return Err(LibZfsError::Io(Error::new(
+            ErrorKind::InvalidData,
+            "scan stats are truncated",
+        )));
+    }
+
+    let function = ScanFunction::from_raw(xs[0])
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "scan function not in enum range"))?;
+    let state = ScanState::from_raw(xs[1])
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "scan state not in enum range"))?;
+
+    let (to_examine, examined) = (xs[4], xs[5]);
+    let (pass_exam, pass_start, pause, spent_paused) = (xs[9], xs[10], xs[11], xs[12]);
+    let paused = pause != 0;
+
+    let (issued, pass_issued) = if xs.len() >= 15 {
+        (Some(xs[14]), xs[13])
+    } else {
+        (None, pass_exam)
+    };
+
+    let elapsed = if paused { pause } else { now }
+        .saturating_sub(pass_start)
+        .saturating_sub(spent_paused)
+        .max(1);
+
+    let rate = pass_issued / elapsed;
+
+    let eta = if state == ScanState::Scanning && rate > 0 {
+        Some(to_examine.saturating_sub(issued.unwrap_or(examined)) / rate)
+    } else {
+        None
+    };
+
+    Ok(ScanStatus {
+        function,
+        state,
+        start_time: xs[2],
+        end_time: xs[3],
+        to_examine,
+        examined,
+        issued,
+        errors: xs[8],
+        paused,
+        rate,
+        eta,
+    })
+}
+
+/// Parses the literal values of native pool properties, keyed by name.
+fn pool_props(map: &HashMap<String, String>) -> Result<PoolProps> {
+    fn get<'a>(map: &'a HashMap<String, String>, name: &str) -> Result<&'a str> {
+        map.get(name).map(|x| x.as_str()).ok_or_else(|| {
+            LibZfsError::Io(Error::new(
+                ErrorKind::NotFound,
+                format!("pool property {} not found", name),
+            ))
+        })
+    }
+
+    fn invalid(name: &str, value: &str) -> LibZfsError {
+        LibZfsError::Io(Error::new(
+            ErrorKind::InvalidData,
+            format!("pool property {} has unexpected value {}", name, value),
+        ))
+    }
+
+    fn text(map: &HashMap<String, String>, name: &str) -> Result<Option<String>> {
+        match get(map, name)? {
+            "" | "-" => Ok(None),
+            x => Ok(Some(x.to_string())),
+        }
+    }
+
+    fn opt_num(map: &HashMap<String, String>, name: &str) -> Result<Option<u64>> {
+        match get(map, name)? {
+            "" | "-" => Ok(None),
+            x => x.parse().map(Some).map_err(|_| invalid(name, x)),
+        }
+    }
+
+    fn num(map: &HashMap<String, String>, name: &str) -> Result<u64> {
+        opt_num(map, name)?.ok_or_else(|| invalid(name, "-"))
+    }
+
+    fn flag(map: &HashMap<String, String>, name: &str) -> Result<bool> {
+        match get(map, name)? {
+            "on" => Ok(true),
+            "off" => Ok(false),
+            x => Err(invalid(name, x)),
+        }
+    }
+
+    let dedupratio = get(map, "dedupratio")?;
+    let hundredths = match dedupratio
+        .trim_end_matches('x')
+        .splitn(2, '.')
+        .collect::<Vec<_>>()[..]
+    {
+        [whole, frac] => whole
+            .parse::<u64>()
+            .ok()
+            .and_then(|w| frac.parse::<u64>().ok().map(|f| w * 100 + f)),
+        [whole] => whole.parse::<u64>().ok().map(|w| w * 100),
+        _ => None,
+    }
+    .ok_or_else(|| invalid("dedupratio", dedupratio))?;
+
+    Ok(PoolProps {
+        name: get(map, "name")?.to_string(),
+        guid: num(map, "guid")?,
+        health: get(map, "health")?.to_string(),
+        version: opt_num(map, "version")?,
+        size: num(map, "size")?,
+        allocated: num(map, "allocated")?,
+        free: num(map, "free")?,
+        freeing: num(map, "freeing")?,
+        leaked: num(map, "leaked")?,
+        expandsize: opt_num(map, "expandsize")?,
+        capacity: num(map, "capacity")?,
+        fragmentation: opt_num(map, "fragmentation")?,
+        dedupratio: hundredths,
+        dedupditto: num(map, "dedupditto")?,
+        ashift: num(map, "ashift")?,
+        maxblocksize: num(map, "maxblocksize")?,
+        maxdnodesize: num(map, "maxdnodesize")?,
+        altroot: text(map, "altroot")?,
+        bootfs: text(map, "bootfs")?,
+        cachefile: text(map, "cachefile")?,
+        comment: text(map, "comment")?,
+        failmode: get(map, "failmode")?.to_string(),
+        autoexpand: flag(map, "autoexpand")?,
+        autoreplace: flag(map, "autoreplace")?,
+        delegation: flag(map, "delegation")?,
+        listsnapshots: flag(map, "listsnapshots")?,
+        multihost: flag(map, "multihost")?,
+        readonly: flag(map, "readonly")?,
+    })
+}
+
+/// What `Zpool::trim` should do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrimAction {
+    Start,
+    Suspend,
+    Cancel,
+}
+
+/// What `Zpool::initialize` should do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InitializeAction {
+    Start,
+    Suspend,
+    Cancel,
+}
+
+/// What `Zpool::wait` should wait for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WaitActivity {
+    Scrub,
+    Resilver,
+    Trim,
+    Initialize,
+    Remove,
+    CheckpointDiscard,
+}
+
+/// What `Zpool::upgrade` should upgrade to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UpgradeTarget {
+    /// A legacy version, from 1 to 28
+    Version(u64),
+    /// Feature flags, with every supported feature enabled
+    Features,
+}
+
+/// What `Zpool::scrub` should do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScrubAction {
+    Start,
+    Pause,
+    Stop,
+}
+
+impl Drop for Zpool {
+    fn drop(&mut self) {
+        let _l = self.libzfs.lock();
+
+        unsafe { sys::zpool_close(self.raw) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libzfs::Libzfs;
+
+    use std::{ffi::CString, panic, path::PathBuf, str};
+
+    fn test_pools<F: Fn(&Vec<Zpool>) -> ()>(f: F) -> ()
+    where
+        F: panic::RefUnwindSafe,
+    {
+        let mut z = Libzfs::new();
+
+        let pools_to_import = z.find_importable_pools();
+
+        z.import_all(&pools_to_import)
+            .expect("could not import pools");
+
+        let pools = z
+            .get_imported_pools()
+            .expect("could not fetch imported pools");
+
+        let result = panic::catch_unwind(|| {
+            f(&pools);
+        });
+
+        result.unwrap();
+    }
+
+    fn pool_by_name<F: Fn(&mut Zpool) -> ()>(name: &str, f: F) -> ()
+    where
+        F: panic::RefUnwindSafe,
+    {
+        test_pools(|xs| {
+            assert!(
+                xs.iter().any(|x| x.name() == CString::new(name).unwrap()),
+                "did not find test pool"
+            );
+
+            // Opened afresh, as `xs` is only lent out shared
+            let mut x = Libzfs::new()
+                .pool_by_name(name)
+                .expect("did not find test pool");
+
+            f(&mut x);
+        });
+    }
+
+    #[test]
+    fn import_get_pool_len() {
+        test_pools(|xs| assert_eq!(xs.len(), 1));
+    }
+
+    #[test]
+    fn get_pool_health() {
+        pool_by_name("test", |p| {
+            assert_eq!(
+                p.health().expect("could not fetch pool health"),
+                CString::new("ONLINE").unwrap()
+            )
+        })
+    }
+
+    #[test]
+    fn get_pool_state() {
+        pool_by_name("test", |p| {
+            assert_eq!(p.state_name(), CString::new("ACTIVE").unwrap())
+        })
+    }
+
+    #[test]
+    fn get_pool_size() {
+        pool_by_name("test", |p| assert_eq!(p.size(), 83_886_080))
+    }
+
+    #[test]
+    fn get_pool_read_only() {
+        pool_by_name("test", |p| assert_eq!(p.read_only(), false))
+    }
+
+    #[test]
+    fn get_pool_hostname() {
+        pool_by_name("test", |p| {
+            assert_eq!(
+                p.hostname().expect("could not get hostname"),
+                CString::new("localhost.localdomain").unwrap()
+            )
+        })
+    }
+
+    #[test]
+    fn get_pool_hostid() {
+        pool_by_name("test", |p| assert!(p.hostid().is_ok()))
+    }
+
+    #[test]
+    fn get_pool_props() {
+        pool_by_name("test", |p| {
+            let xs = p.props().unwrap();
+
+            assert!(xs.iter().any(|x| x.name == "health" && x.value == "ONLINE"));
+            assert!(xs.iter().any(|x| x.name.starts_with("feature@")));
+        })
+    }
+
+    #[test]
+    fn get_pool_volumes() {
+        pool_by_name("test", |p| {
+            let xs = p.all_datasets().unwrap();
+
+            assert!(xs.iter().any(|x| x.name().to_bytes() == b"test/ds"));
+            assert!(p
+                .volumes()
+                .unwrap()
+                .iter()
+                .all(|x| x.zfs_type() == sys::zfs_type_t::ZFS_TYPE_VOLUME));
+        })
+    }
+
+    #[test]
+    fn iterate_datasets() {
+        pool_by_name("test", |p| {
+            let mut xs: Vec<CString> = p.all_datasets().unwrap().iter().map(|x| x.name()).collect();
+            let mut ys: Vec<CString> = p
+                .dataset_iter()
+                .unwrap()
+                .map(|x| x.unwrap().name())
+                .collect();
+
+            xs.sort();
+            ys.sort();
+
+            assert_eq!(xs, ys);
+        })
+    }
+
+    #[test]
+    fn traverse_datasets() {
+        pool_by_name("test", |p| {
+            let fs_vol = sys::zfs_type_t::ZFS_TYPE_FILESYSTEM | sys::zfs_type_t::ZFS_TYPE_VOLUME;
+
+            let xs: Vec<CString> = p
+                .traverse(Some(1), fs_vol)
+                .unwrap()
+                .map(|x| x.unwrap().name())
+                .collect();
+
+            assert_eq!(xs[0], CString::new("test").unwrap());
+            assert!(xs.contains(&CString::new("test/ds").unwrap()));
+
+            let root: Vec<CString> = p
+                .traverse(Some(0), fs_vol)
+                .unwrap()
+                .map(|x| x.unwrap().name())
+                .collect();
+
+            assert_eq!(root, vec![CString::new("test").unwrap()]);
+
+            let snaps = p
+                .traverse(None, sys::zfs_type_t::ZFS_TYPE_SNAPSHOT)
+                .unwrap()
+                .map(|x| x.unwrap());
+
+            for x in snaps {
+                assert_eq!(x.zfs_type(), sys::zfs_type_t::ZFS_TYPE_SNAPSHOT);
+            }
+        })
+    }
+
+    #[test]
+    fn pool_info() {
+        pool_by_name("test", |p| {
+            let x = p.to_info().unwrap();
+
+            assert_eq!(x.name, "test");
+            assert!(x.props.iter().any(|x| x.name == "health"));
+            assert!(x.datasets.iter().any(|x| x.name == "test/ds"));
+        })
+    }
+
+    #[test]
+    fn get_removal_status() {
+        pool_by_name("test", |p| {
+            assert_eq!(p.removal_status().unwrap(), None);
+        })
+    }
+
+    #[test]
+    fn all_pool_props() {
+        pool_by_name("test", |p| {
+            let x = p.all_props().unwrap();
+
+            assert_eq!(x.name, "test");
+            assert!(x.maxblocksize >= 128 * 1024);
+            assert!(x.maxdnodesize >= 512);
+        })
+    }
+
+    #[test]
+    fn set_pool_prop() {
+        pool_by_name("test", |p| {
+            p.set_prop("comment", "rust-libzfs").unwrap();
+            assert_eq!(
+                p.all_props().unwrap().comment.as_deref(),
+                Some("rust-libzfs")
+            );
+
+            p.set_prop("comment", "").unwrap();
+            assert_eq!(p.all_props().unwrap().comment, None);
+
+            assert!(p.set_prop("failmode", "sometimes").is_err());
+        })
+    }
+
+    #[test]
+    fn get_pool_status() {
+        pool_by_name("test", |p| {
+            let x = p.status().unwrap();
+
+            assert_eq!(x.name, "test");
+            assert_eq!(x.health, "ONLINE");
+            assert_eq!(x.reason, StatusReason::Ok);
+            assert_eq!(x.msgid, None);
+            assert_eq!(x.error_count, 0);
+            assert_eq!(x.errors, vec![]);
+        })
+    }
+
+    #[test]
+    fn get_pool_features() {
+        pool_by_name("test", |p| {
+            let xs = p.features().unwrap();
+            let x = xs.iter().find(|x| x.name == "async_destroy").unwrap();
+
+            assert_eq!(x.guid.as_deref(), Some("com.delphix:async_destroy"));
+            assert!(x.state != FeatureState::Disabled);
+            assert!(x.refcount.is_some());
+        })
+    }
+
+    #[test]
+    fn get_checkpoint_status() {
+        pool_by_name("test", |p| {
+            assert_eq!(p.checkpoint_status().unwrap(), None);
+        })
+    }
+
+    #[test]
+    fn get_initialize_status() {
+        pool_by_name("test", |p| {
+            let xs = p.initialize_status().unwrap();
+
+            assert!(xs.keys().all(|x| p.vdev_map().unwrap().contains_key(x)));
+        })
+    }
+
+    #[test]
+    fn get_trim_status() {
+        pool_by_name("test", |p| {
+            let xs = p.trim_status().unwrap();
+
+            assert!(xs.keys().all(|x| p.vdev_map().unwrap().contains_key(x)));
+        })
+    }
+
+    #[test]
+    fn get_deferred_resilvers() {
+        pool_by_name("test", |p| {
+            assert_eq!(p.deferred_resilvers().unwrap(), Vec::<u64>::new());
+        })
+    }
+
+    #[test]
+    fn wait_for_idle_pool() {
+        let mut p = Libzfs::new().pool_by_name("test").unwrap();
+
+        p.wait(WaitActivity::Scrub, Some(Duration::from_secs(30)))
+            .unwrap();
+        p.wait(WaitActivity::Remove, Some(Duration::from_secs(30)))
+            .unwrap();
+    }
+
+    #[test]
+    fn maps_status_reasons() {
+        assert_eq!(
+            status_reason(sys::zpool_status_t_ZPOOL_STATUS_OK),
+            StatusReason::Ok
+        );
+        assert_eq!(
+            status_reason(sys::zpool_status_t_ZPOOL_STATUS_REMOVED_DEV),
+            StatusReason::RemovedDev
+        );
+        assert_eq!(status_reason(1000), StatusReason::Unknown(1000));
+    }
+
+    #[test]
+    fn parse_pool_props() {
+        let xs = [
+            ("name", "test"),
+            ("guid", "42"),
+            ("health", "ONLINE"),
+            ("version", "-"),
+            ("size", "1000"),
+            ("allocated", "100"),
+            ("free", "900"),
+            ("freeing", "0"),
+            ("leaked", "0"),
+            ("expandsize", "-"),
+            ("capacity", "10"),
+            ("fragmentation", "3"),
+            ("dedupratio", "1.25"),
+            ("dedupditto", "0"),
+            ("ashift", "12"),
+            ("maxblocksize", "1048576"),
+            ("maxdnodesize", "16384"),
+            ("altroot", "-"),
+            ("bootfs", "-"),
+            ("cachefile", "none"),
+            ("comment", ""),
+            ("failmode", "wait"),
+            ("autoexpand", "off"),
+            ("autoreplace", "on"),
+            ("delegation", "on"),
+            ("listsnapshots", "off"),
+            ("multihost", "off"),
+            ("readonly", "off"),
+        ];
+
+        let mut map: HashMap<String, String> = xs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        let x = pool_props(&map).unwrap();
+
+        assert_eq!(x.version, None);
+        assert_eq!(x.expandsize, None);
+        assert_eq!(x.fragmentation, Some(3));
+        assert_eq!(x.dedupratio, 125);
+        assert_eq!(x.cachefile.as_deref(), Some("none"));
+        assert_eq!(x.comment, None);
+        assert!(x.autoreplace);
+        assert!(!x.autoexpand);
+
+        map.insert("autoexpand".to_string(), "maybe".to_string());
+        assert!(pool_props(&map).is_err());
+
+        map.remove("autoexpand");
+        assert!(pool_props(&map).is_err());
+    }
+
+    #[test]
+    fn parse_scan_status() {
+        let xs = [1, 1, 100, 0, 1000, 900, 0, 0, 2, 900, 100, 0, 0, 200, 250];
+        let x = scan_status(&xs, 200).unwrap();
+
+        assert_eq!(x.function, ScanFunction::Scrub);
+        assert_eq!(x.state, ScanState::Scanning);
+        assert_eq!(x.errors, 2);
+        assert!(!x.paused);
+        assert_eq!(x.examined, 900);
+        assert_eq!(x.issued, Some(250));
+        assert_eq!(x.rate, 2);
+        assert_eq!(x.eta, Some(375));
+    }
+
+    #[test]
+    fn parse_scan_status_without_issued() {
+        let xs = [1, 1, 100, 0, 1000, 400, 0, 0, 2, 400, 100, 0, 0];
+        let x = scan_status(&xs, 200).unwrap();
+
+        assert_eq!(x.issued, None);
+        assert_eq!(x.rate, 4);
+        assert_eq!(x.eta, Some(150));
+    }
+
+    #[test]
+    fn test_vdev_map() {
+        pool_by_name("test", |p| {
+            let map = p.vdev_map().unwrap();
+
+            assert!(map
+                .values()
+                .any(|x| x.path == Some(PathBuf::from("/dev/sdb1")) && x.parent.is_some()));
+            assert_eq!(map.values().filter(|x| x.parent.is_none()).count(), 1);
+        })
+    }
+
+    #[test]
+    fn test_vdev_stats() {
+        pool_by_name("test", |p| {
+            let map = p.vdev_map().unwrap();
+            let root = map.values().find(|x| x.parent.is_none()).unwrap();
+
+            assert!(root.stats.space > 0);
+            assert!(root.stats.alloc <= root.stats.space);
+        })
+    }
+
+    #[test]
+    fn test_vdev_ashift() {
+        pool_by_name("test", |p| {
+            let map = p.vdev_map().unwrap();
+            let root = map.values().find(|x| x.parent.is_none()).unwrap().guid;
+
+            // Spares and cache devices hang off the root too, but have no ashift
+            let xs: Vec<&VDevInfo> = map
+                .values()
+                .filter(|x| x.parent == Some(root) && x.ashift.is_some())
+                .collect();
+
+            assert!(!xs.is_empty());
+            assert!(xs.iter().all(|x| x.ashift >= Some(9) && x.asize > Some(0)));
+        })
     }
 
     #[test]
@@ -271,6 +1880,7 @@ mod tests {
                     children,
                     cache,
                     spares,
+                    ..
                 } => (children, cache, spares),
                 _ => panic!("did not find root device"),
             };
@@ -289,10 +1899,12 @@ mod tests {
                     ref phys_path,
                     whole_disk,
                     is_log,
+                    ..
                 } => {
                     assert!(guid.is_some());
                     assert_eq!(state, "ONLINE");
                     assert_eq!(path, &create_path_buf("/dev/sdb1"));
+                    assert_eq!(mirror_vdevs[0].error_counts(), Some((0, 0, 0)));
                     assert!(dev_id.is_some());
                     assert!(phys_path.is_some());
                     assert_eq!(whole_disk, Some(true));
@@ -310,6 +1922,7 @@ mod tests {
                     ref phys_path,
                     whole_disk,
                     is_log,
+                    ..
                 } => {
                     assert!(guid.is_some());
                     assert_eq!(state, "ONLINE");
@@ -331,6 +1944,7 @@ mod tests {
                     ref phys_path,
                     whole_disk,
                     is_log,
+                    ..
                 } => {
                     assert!(guid.is_some());
                     assert_eq!(state, "ONLINE");
@@ -352,6 +1966,7 @@ mod tests {
                     ref phys_path,
                     whole_disk,
                     is_log,
+                    ..
                 } => {
                     assert!(guid.is_some());
                     assert_eq!(state, "ONLINE");
@@ -373,6 +1988,7 @@ mod tests {
                     ref phys_path,
                     whole_disk,
                     is_log,
+                    ..
                 } => {
                     assert!(guid.is_some());
                     assert_eq!(state, "ONLINE");
@@ -4,6 +4,7 @@
 
 extern crate libzfs_sys as sys;
 use std::ffi::CStr;
+use std::os::raw::c_int;
 
 pub use libzfs_types::ZProp;
 
@@ -49,6 +50,10 @@ impl ZpropItem {
     pub fn prop(&self) -> sys::zfs_prop_t {
         unsafe { sys::to_zfs_prop_t((*self.raw).pl_prop).unwrap() }
     }
+    /// The raw property number, `ZPROP_INVAL` for user and feature properties
+    pub fn raw_prop(&self) -> c_int {
+        unsafe { (*self.raw).pl_prop }
+    }
     pub fn user_prop(&self) -> &CStr {
         unsafe { CStr::from_ptr((*self.raw).pl_user_prop) }
     }
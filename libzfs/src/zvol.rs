@@ -0,0 +1,98 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Zvol device nodes
+//!
+//! udev creates `/dev/zvol/<pool>/<name>` as a link to a `/dev/zdN`
+//! node some time after a volume is created or imported. These helpers
+//! wait for the link so callers can hand the device to a block consumer.
+//!
+
+use libzfs_types::{LibZfsError, Result};
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Where udev links zvols by dataset name
+pub const ZVOL_DIR: &str = "/dev/zvol";
+
+/// How often to look for the link while waiting
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct ZvolDevice {
+    /// The stable link, such as `/dev/zvol/pool/vol`
+    pub link: PathBuf,
+    /// The device node, such as `/dev/zd0`
+    pub device: PathBuf,
+}
+
+/// The link udev creates for the volume `name`.
+pub fn zvol_link(name: &str) -> PathBuf {
+    Path::new(ZVOL_DIR).join(name)
+}
+
+/// Waits up to `timeout` for the link to `name` to appear under `dir`.
+pub fn wait_for_device_in<P: AsRef<Path>>(
+    dir: P,
+    name: &str,
+    timeout: Duration,
+) -> Result<ZvolDevice> {
+    let link = dir.as_ref().join(name);
+    let start = Instant::now();
+
+    loop {
+        if let Ok(device) = fs::canonicalize(&link) {
+            return Ok(ZvolDevice { link, device });
+        }
+
+        if start.elapsed() >= timeout {
+            return Err(LibZfsError::Io(Error::new(
+                ErrorKind::TimedOut,
+                format!("{} did not appear", link.display()),
+            )));
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Waits up to `timeout` for the device of the volume `name`.
+pub fn wait_for_device(name: &str, timeout: Duration) -> Result<ZvolDevice> {
+    wait_for_device_in(ZVOL_DIR, name, timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::File;
+    use std::os::unix::fs::symlink;
+    use std::process;
+
+    #[test]
+    fn waits_for_link() {
+        let dir = env::temp_dir().join(format!("libzfs-zvol-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("test")).unwrap();
+        File::create(dir.join("zd0")).unwrap();
+
+        assert!(wait_for_device_in(&dir, "test/vol", Duration::from_millis(0)).is_err());
+
+        let d = dir.clone();
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            symlink(d.join("zd0"), d.join("test/vol")).unwrap();
+        });
+
+        let x = wait_for_device_in(&dir, "test/vol", Duration::from_secs(5)).unwrap();
+        t.join().unwrap();
+
+        assert_eq!(x.link, dir.join("test/vol"));
+        assert_eq!(x.device, fs::canonicalize(dir.join("zd0")).unwrap());
+    }
+}
@@ -1,7 +1,7 @@
 %define base_name node-libzfs
 
 Name:       iml-%{base_name}
-Version:    0.1.20
+Version:    0.1.21
 # Release Start
 Release:    1%{?dist}
 # Release End
@@ -46,6 +46,9 @@ rm -rf %{buildroot}
 %{nodejs_sitearch}/@iml/node-libzfs/package.json
 
 %changelog
+* Fri Oct 16 2026 Iml Team <iml@whamcloud.com> - 0.1.21-1
+- Bump to ZFS 0.8.6
+
 * Sat May 04 2019 Joe Grund <jgrund@whamcloud.com> - 0.1.20-1
 - Bump to ZFS 0.7.13
 
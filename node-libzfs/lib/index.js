@@ -2,4 +2,19 @@
 // Use of this source code is governed by a MIT-style
 // license that can be found in the LICENSE file.
 
-module.exports = () => require('../native');
+// Calls `cb(err, { event, kind })` for each zevent until the returned
+// function is called. Every subscriber is called for every event.
+// A failed read ends the subscription and is the last error passed to `cb`.
+const subscribeEvents = native => cb => {
+  const id = native.subscribeEvents(cb);
+
+  return () => native.unsubscribeEvents(id);
+};
+
+module.exports = () => {
+  const native = require('../native');
+
+  return Object.assign({}, native, {
+    subscribeEvents: subscribeEvents(native)
+  });
+};
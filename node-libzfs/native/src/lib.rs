@@ -0,0 +1,326 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+#[macro_use]
+extern crate lazy_static;
+extern crate libzfs;
+#[macro_use]
+extern crate neon;
+extern crate neon_serde;
+extern crate serde_json;
+
+#[macro_use]
+extern crate serde_derive;
+
+use libzfs::events::{self, Events};
+use libzfs::{LibZfsError, Libzfs, ZEvent, ZEventKind, Zpool};
+use neon::context::TaskContext;
+use neon::event::EventHandler;
+use neon::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How long a reader waits between polls of an empty event queue.
+/// Reads do not block, so an unsubscribe is seen within this long.
+const EVENT_POLL: Duration = Duration::from_millis(250);
+
+/// A reader thread delivering zevents to one JS callback
+struct Subscription {
+    /// Dropped to stop the reader
+    stop: Sender<()>,
+    /// Cleared on the JS thread so events already queued for
+    /// the callback are not delivered after an unsubscribe.
+    active: Arc<AtomicBool>,
+}
+
+lazy_static! {
+    static ref SUBSCRIPTIONS: Mutex<HashMap<usize, Subscription>> = Mutex::new(HashMap::new());
+}
+
+static NEXT_SUBSCRIPTION: AtomicUsize = AtomicUsize::new(1);
+
+/// Throws `e` as a JS `Error`.
+fn throw<'a, C: Context<'a>, T>(cx: &mut C, e: LibZfsError) -> NeonResult<T> {
+    cx.throw_error(e.to_string())
+}
+
+/// Captures a pool for JS, giving its guid as a string,
+/// which a JS number cannot hold exactly.
+fn pool_to_json(p: &mut Zpool) -> Result<serde_json::Value, String> {
+    let x = p.to_info().map_err(|e| e.to_string())?;
+
+    let mut v = serde_json::to_value(&x).map_err(|e| e.to_string())?;
+    v["guid"] = serde_json::Value::String(x.guid.to_string());
+
+    Ok(v)
+}
+
+#[derive(Serialize, Debug, Deserialize)]
+struct JsZEvent {
+    event: ZEvent,
+    kind: ZEventKind,
+}
+
+fn read_event(events: &mut Events) -> Result<Option<JsZEvent>, LibZfsError> {
+    let nvl = match events.next_nvlist(false)? {
+        Some(x) => x,
+        None => return Ok(None),
+    };
+
+    Ok(Some(JsZEvent {
+        event: events::decode(&nvl)?,
+        kind: events::decode_kind(&nvl)?,
+    }))
+}
+
+/// Calls `f(err)` or `f(null, event)` on the JS thread.
+fn deliver(
+    cx: &mut TaskContext,
+    this: Handle<JsValue>,
+    f: Handle<JsFunction>,
+    x: Result<JsZEvent, String>,
+) -> NeonResult<()> {
+    let args: Vec<Handle<JsValue>> = match x {
+        Ok(x) => vec![cx.null().upcast(), neon_serde::to_value(cx, &x)?],
+        Err(e) => vec![cx.error(e)?.upcast()],
+    };
+
+    f.call(cx, this, args)?;
+
+    Ok(())
+}
+
+/// Reads events until `stop` is dropped or a read fails, passing each
+/// to `cb`. An event that cannot be decoded is passed as an error and
+/// reading goes on; a failed read is passed on and ends the subscription.
+fn read_events(
+    id: usize,
+    mut events: Events,
+    cb: EventHandler,
+    stop: Receiver<()>,
+    active: Arc<AtomicBool>,
+) {
+    let send = |x: Result<JsZEvent, String>| {
+        let active = Arc::clone(&active);
+
+        cb.schedule_with(move |cx, this, f| {
+            if active.load(Ordering::SeqCst) {
+                let _ = deliver(cx, this, f, x);
+            }
+        });
+    };
+
+    loop {
+        match stop.try_recv() {
+            Err(TryRecvError::Empty) => {}
+            _ => break,
+        }
+
+        match read_event(&mut events) {
+            Ok(Some(x)) => send(Ok(x)),
+            Ok(None) => match stop.recv_timeout(EVENT_POLL) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => break,
+            },
+            Err(e @ LibZfsError::Zfs(_)) => {
+                send(Err(e.to_string()));
+                break;
+            }
+            Err(e) => send(Err(e.to_string())),
+        }
+    }
+
+    SUBSCRIPTIONS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&id);
+}
+
+fn get_pool_by_name(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let mut libzfs = Libzfs::new();
+
+    let pool_name = cx.argument::<JsString>(0)?.value();
+
+    let p = libzfs.pool_by_name(&pool_name);
+
+    match p {
+        Some(mut x) => {
+            let value = match pool_to_json(&mut x) {
+                Ok(x) => x,
+                Err(e) => return cx.throw_error(e),
+            };
+
+            Ok(neon_serde::to_value(&mut cx, &value)?)
+        }
+        None => Ok(cx.null().upcast()),
+    }
+}
+
+fn get_dataset_by_name(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let ds_name = cx.argument::<JsString>(0)?.value();
+
+    match libzfs::get_dataset_by_name(&ds_name) {
+        Ok(x) => Ok(neon_serde::to_value(&mut cx, &x)?),
+        Err(LibZfsError::ZfsNotFound(_)) => Ok(cx.null().upcast()),
+        Err(e) => throw(&mut cx, e),
+    }
+}
+
+/// Reads `names` from the dataset `ds_name`, leaving out
+/// any that are not set or do not apply to it.
+fn dataset_props(ds_name: &str, names: &[String]) -> Result<BTreeMap<String, String>, LibZfsError> {
+    Libzfs::new()
+        .dataset_by_name(ds_name)
+        .ok_or_else(|| LibZfsError::ZfsNotFound(ds_name.to_string()))?
+        .get_props(names)
+}
+
+fn get_dataset_props(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let ds_name = cx.argument::<JsString>(0)?.value();
+
+    let names = cx
+        .argument::<JsArray>(1)?
+        .to_vec(&mut cx)?
+        .into_iter()
+        .map(|x| {
+            x.downcast_or_throw::<JsString, _>(&mut cx)
+                .map(|x| x.value())
+        })
+        .collect::<NeonResult<Vec<String>>>()?;
+
+    match dataset_props(&ds_name, &names) {
+        Ok(props) => Ok(neon_serde::to_value(&mut cx, &props)?),
+        Err(e) => throw(&mut cx, e),
+    }
+}
+
+/// Reads one property, returning `null` if it is not set
+/// or does not apply to the dataset.
+fn get_dataset_prop(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let ds_name = cx.argument::<JsString>(0)?.value();
+
+    let prop_name = cx.argument::<JsString>(1)?.value();
+
+    let value = match dataset_props(&ds_name, &[prop_name.clone()]) {
+        Ok(mut xs) => xs.remove(&prop_name),
+        Err(e) => return throw(&mut cx, e),
+    };
+
+    match value {
+        Some(x) => Ok(cx.string(x).upcast()),
+        None => Ok(cx.null().upcast()),
+    }
+}
+
+fn get_vdev_tree(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let mut libzfs = Libzfs::new();
+
+    let pool_name = cx.argument::<JsString>(0)?.value();
+
+    let p = libzfs.pool_by_name(&pool_name);
+
+    match p {
+        Some(x) => {
+            let vdev = match x.vdev_tree() {
+                Ok(x) => x,
+                Err(_) => return cx.throw_error("Could not enumerate vdev tree"),
+            };
+
+            let js_value = neon_serde::to_value(&mut cx, &vdev)?;
+
+            Ok(js_value)
+        }
+        None => Ok(cx.null().upcast()),
+    }
+}
+
+fn get_imported_pools(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let mut libzfs = Libzfs::new();
+
+    let pools = match libzfs.get_imported_pools() {
+        Ok(mut xs) => xs
+            .iter_mut()
+            .map(pool_to_json)
+            .collect::<Result<Vec<_>, _>>(),
+        Err(e) => Err(e.to_string()),
+    };
+
+    let pools = match pools {
+        Ok(x) => x,
+        Err(e) => return cx.throw_error(e),
+    };
+
+    let arr = neon_serde::to_value(&mut cx, &pools)?;
+
+    Ok(arr)
+}
+
+/// Starts delivering zevents to `cb(err, { event, kind })` from a
+/// reader thread of its own, returning an id for `unsubscribeEvents`.
+///
+/// Each subscription reads from its own `/dev/zfs` descriptor,
+/// so every subscriber sees every event.
+fn subscribe_events(mut cx: FunctionContext) -> JsResult<JsNumber> {
+    let f = cx.argument::<JsFunction>(0)?;
+
+    let events = match Events::open() {
+        Ok(x) => x,
+        Err(e) => return throw(&mut cx, e),
+    };
+
+    let this = cx.undefined();
+    let cb = EventHandler::new(&cx, this, f);
+
+    let (stop, stopped) = mpsc::channel();
+    let active = Arc::new(AtomicBool::new(true));
+    let id = NEXT_SUBSCRIPTION.fetch_add(1, Ordering::SeqCst);
+
+    SUBSCRIPTIONS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(
+            id,
+            Subscription {
+                stop,
+                active: Arc::clone(&active),
+            },
+        );
+
+    thread::spawn(move || read_events(id, events, cb, stopped, active));
+
+    Ok(cx.number(id as f64))
+}
+
+/// Stops a subscription. No more events reach its callback,
+/// and its reader thread exits within `EVENT_POLL`.
+fn unsubscribe_events(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let id = cx.argument::<JsNumber>(0)?.value() as usize;
+
+    let x = SUBSCRIPTIONS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&id);
+
+    if let Some(x) = x {
+        x.active.store(false, Ordering::SeqCst);
+    }
+
+    Ok(cx.undefined())
+}
+
+register_module!(mut m, {
+    m.export_function("getImportedPools", get_imported_pools)?;
+    m.export_function("getPoolByName", get_pool_by_name)?;
+    m.export_function("getDatasetByName", get_dataset_by_name)?;
+    m.export_function("getDatasetProps", get_dataset_props)?;
+    m.export_function("getDatasetProp", get_dataset_prop)?;
+    m.export_function("getVdevTree", get_vdev_tree)?;
+    m.export_function("subscribeEvents", subscribe_events)?;
+    m.export_function("unsubscribeEvents", unsubscribe_events)?;
+    Ok(())
+});